@@ -9,6 +9,7 @@ use crate::{
     name::*,
     util::{assoc::Assoc, mbe::EnvMBE},
 };
+use std::collections::HashMap;
 
 // A renaming that only affects names at the "current" quotation level
 #[derive(Clone, Debug, PartialEq)]
@@ -72,7 +73,7 @@ impl From<Assoc<Name, Ast>> for Ren {
 
 fn substitute_rec(node: &Ast, cur_node_contents: &EnvMBE<Ast>, env: &Ren) -> Ast {
     match *node {
-        Node(ref f, ref new_parts, ref export) => {
+        Node(ref f, ref new_parts, ref export, span) => {
             // let new_cnc = parts.clone();
             Node(
                 f.clone(),
@@ -80,6 +81,7 @@ fn substitute_rec(node: &Ast, cur_node_contents: &EnvMBE<Ast>, env: &Ren) -> Ast
                     substitute_rec(part, marched_parts, env)
                 }),
                 export.clone(),
+                span,
             )
         }
         VariableReference(n) => env.find(n).unwrap_or(&node.clone()).clone(),
@@ -123,11 +125,34 @@ pub fn substitute(node: &Ast, env: &Assoc<Name, Ast>) -> Ast {
     substitute_rec(node, &EnvMBE::new(), &Ren::from(env.clone()))
 }
 
+/// The names that `a` refers to without binding them.
+/// `cur_node_contents` are the parts of the node that `a` is a part of (for interpreting betas).
+pub fn free_vars(a: &Ast, cur_node_contents: &EnvMBE<Ast>) -> Vec<Name> {
+    match *a {
+        Trivial | Atom(_) => vec![],
+        VariableReference(v) => vec![v],
+        Node(_, ref parts, _, _) => parts
+            .marched_map(&mut |_, marched_parts: &EnvMBE<Ast>, part: &Ast| {
+                free_vars(part, marched_parts)
+            })
+            .map_reduce(&|vs| vs.clone(), &|l, r| [&l[..], &r[..]].concat(), vec![]),
+        ExtendEnv(ref body, ref beta) | ExtendEnvPhaseless(ref body, ref beta) => {
+            let bound = crate::beta::bound_from_beta(beta, cur_node_contents, 0);
+            free_vars(body, cur_node_contents).into_iter().filter(|v| !bound.contains(v)).collect()
+        }
+        QuoteMore(ref body, _) | QuoteLess(ref body, _) => free_vars(body, cur_node_contents),
+        IncompleteNode(ref parts) => {
+            parts.map_reduce(&|p| free_vars(p, parts), &|l, r| [&l[..], &r[..]].concat(), vec![])
+        }
+        Shape(ref subs) => subs.iter().flat_map(|s| free_vars(s, cur_node_contents)).collect(),
+    }
+}
+
 /// Like `beta::names_mentioned`, but for all the imports in `parts`
 fn mentioned_in_import(parts: &EnvMBE<Ast>) -> Vec<Name> {
     fn process_ast(a: &Ast, v: &mut Vec<Name>) {
         match *a {
-            Node(_, _, _) => {} // new scope
+            Node(_, _, _, _) => {} // new scope
             ExtendEnv(ref body, ref beta) | ExtendEnvPhaseless(ref body, ref beta) => {
                 let mut beta_mentions = beta.names_mentioned_and_bound();
                 v.append(&mut beta_mentions);
@@ -148,7 +173,7 @@ fn mentioned_in_import(parts: &EnvMBE<Ast>) -> Vec<Name> {
 fn freshen_rec(node: &Ast, renamings: &EnvMBE<(Ast, Ren)>, env: Ren) -> Ast {
     //  `env` is used to update the references to those atoms to match
     match *node {
-        Node(_, _, _) => substitute_rec(node, &EnvMBE::new(), &env),
+        Node(_, _, _, _) => substitute_rec(node, &EnvMBE::new(), &env),
         VariableReference(n) => env.find(n).unwrap_or(&node.clone()).clone(),
         ExtendEnv(ref body, ref beta) => {
             let new_env = env.set_assoc(&beta.extract_from_mbe(renamings, &|x: &(_, Ren)| &x.1));
@@ -181,7 +206,7 @@ pub fn freshen(a: &Ast) -> Ast {
     // TODO: I think this shouldn't take a reference for performance
     if freshening_enabled.with(|f| *f.borrow()) {
         match a {
-            &Node(ref f, ref p, ref export) => {
+            &Node(ref f, ref p, ref export, span) => {
                 // Every part that gets mentioned inside this node...
                 let mentioned = mentioned_in_import(p);
                 // ...needs to have its binders freshend:
@@ -195,6 +220,7 @@ pub fn freshen(a: &Ast) -> Ast {
                         },
                     ),
                     export.clone(),
+                    span,
                 )
             }
             non_node => non_node.clone(),
@@ -207,7 +233,10 @@ pub fn freshen(a: &Ast) -> Ast {
 pub fn freshen_with(lhs: &Ast, rhs: &Ast) -> (Ast, Ast) {
     if freshening_enabled.with(|f| *f.borrow()) {
         match (lhs, rhs) {
-            (&Node(ref f, ref p_lhs, ref export), &Node(ref f_rhs, ref p_rhs, ref export_rhs)) => {
+            (
+                &Node(ref f, ref p_lhs, ref export, span_lhs),
+                &Node(ref f_rhs, ref p_rhs, ref export_rhs, span_rhs),
+            ) => {
                 if f != f_rhs || export != export_rhs {
                     return (lhs.clone(), rhs.clone());
                 }
@@ -240,8 +269,8 @@ pub fn freshen_with(lhs: &Ast, rhs: &Ast) -> (Ast, Ast) {
                             },
                         );
                         (
-                            Node(f.clone(), new_p_lhs, export.clone()),
-                            Node(f.clone(), new_p_rhs, export.clone()),
+                            Node(f.clone(), new_p_lhs, export.clone(), span_lhs),
+                            Node(f.clone(), new_p_rhs, export.clone(), span_rhs),
                         )
                     }
                     None => (lhs.clone(), rhs.clone()), // No destructuring will be performed!
@@ -293,7 +322,7 @@ pub fn freshen_binders(a: &Ast) -> (Ast, Ren) {
             let new_name = old_name.freshen();
             (Atom(new_name), Ren::single(old_name, VariableReference(new_name)))
         }
-        Node(ref f, ref parts, ref export) => {
+        Node(ref f, ref parts, ref export, span) => {
             if export == &crate::beta::ExportBeta::Nothing {
                 return (a.clone(), Ren::new()); // short-circuit (should this at least warn?)
             }
@@ -303,7 +332,7 @@ pub fn freshen_binders(a: &Ast) -> (Ast, Ren) {
             let fresh_ast = fresh_pairs.map(&mut |&(ref a, _): &(Ast, _)| a.clone());
             let renaming = export.extract_from_mbe(&fresh_pairs, &|&(_, ref r): &(_, Ren)| &r);
 
-            (Node(f.clone(), fresh_ast, export.clone()), renaming)
+            (Node(f.clone(), fresh_ast, export.clone(), span), renaming)
         }
         IncompleteNode(_) | Shape(_) => icp!("didn't think this was needed"),
         QuoteMore(ref body, pos) => {
@@ -327,6 +356,99 @@ pub fn freshen_binders(a: &Ast) -> (Ast, Ren) {
     }
 }
 
+/// Rename every bound name in `a` to a canonical spelling,
+///  so that alpha-equivalent terms become `==` (useful for hashing and deduplication).
+/// The new spellings come from the binder's depth and position (not its original spelling),
+///  and aren't valid identifiers, so they can't capture free names.
+pub fn alpha_normalize(a: &Ast) -> Ast { alpha_normalize_rec(a, 0) }
+
+fn alpha_normalize_rec(a: &Ast, depth: usize) -> Ast {
+    match *a {
+        Node(ref f, ref p, ref export, span) => {
+            // Like `freshen`, but with canonical names instead of fresh ones:
+            let mentioned = mentioned_in_import(p);
+            let mut counts = HashMap::new();
+            let canon_ast_and_rens = p.named_map(&mut |part_name: &Name, part: &Ast| {
+                if mentioned.contains(part_name) {
+                    let path = format!("{}.{}", depth, part_name.sp());
+                    canonicalize_binders(part, &path, &mut counts)
+                } else {
+                    (part.clone(), Ren::new())
+                }
+            });
+            let renamed = canon_ast_and_rens.marched_map(
+                &mut |_, marched: &EnvMBE<(Ast, Ren)>, (part, _)| {
+                    freshen_rec(part, marched, Ren::new())
+                },
+            );
+            Node(
+                f.clone(),
+                renamed.map(&mut |part| alpha_normalize_rec(part, depth + 1)),
+                export.clone(),
+                span,
+            )
+        }
+        QuoteMore(ref body, pos) => QuoteMore(Box::new(alpha_normalize_rec(body, depth)), pos),
+        QuoteLess(ref body, d) => QuoteLess(Box::new(alpha_normalize_rec(body, depth)), d),
+        ExtendEnv(ref body, ref beta) => {
+            ExtendEnv(Box::new(alpha_normalize_rec(body, depth)), beta.clone())
+        }
+        ExtendEnvPhaseless(ref body, ref beta) => {
+            ExtendEnvPhaseless(Box::new(alpha_normalize_rec(body, depth)), beta.clone())
+        }
+        Atom(_) | VariableReference(_) | Trivial | IncompleteNode(_) | Shape(_) => a.clone(),
+    }
+}
+
+/// Like `freshen_binders`, but the new names are determined by `path`
+///  (and how many binders we've already seen there).
+fn canonicalize_binders(a: &Ast, path: &str, counts: &mut HashMap<String, usize>) -> (Ast, Ren) {
+    match *a {
+        Trivial | VariableReference(_) => (a.clone(), Ren::new()),
+        Atom(old_name) => {
+            let count = counts.entry(path.to_owned()).or_insert(0);
+            let new_name = n(&format!("{}#{}", path, count));
+            *count += 1;
+            (Atom(new_name), Ren::single(old_name, VariableReference(new_name)))
+        }
+        Node(ref f, ref parts, ref export, span) => {
+            if export == &crate::beta::ExportBeta::Nothing {
+                return (a.clone(), Ren::new());
+            }
+            let exported = export.names_mentioned();
+
+            let canon_pairs = parts.named_map(&mut |part_name: &Name, part: &Ast| {
+                if exported.contains(part_name) {
+                    canonicalize_binders(part, &format!("{}.{}", path, part_name.sp()), counts)
+                } else {
+                    (part.clone(), Ren::new())
+                }
+            });
+            let canon_ast = canon_pairs.map(&mut |(a, _): &(Ast, _)| a.clone());
+            let renaming = export.extract_from_mbe(&canon_pairs, &|(_, r): &(_, Ren)| r);
+
+            (Node(f.clone(), canon_ast, export.clone(), span), renaming)
+        }
+        IncompleteNode(_) | Shape(_) => icp!("didn't think this was needed"),
+        QuoteMore(ref body, pos) => {
+            let (a, r) = canonicalize_binders(body, path, counts);
+            (QuoteMore(Box::new(a), pos), r.q_less(1))
+        }
+        QuoteLess(ref body, depth) => {
+            let (a, r) = canonicalize_binders(body, path, counts);
+            (QuoteLess(Box::new(a), depth), r.q_more(depth))
+        }
+        ExtendEnv(ref sub, ref beta) => {
+            let (new_sub, subst) = canonicalize_binders(sub, path, counts);
+            (ExtendEnv(Box::new(new_sub), beta.clone()), subst)
+        }
+        ExtendEnvPhaseless(ref sub, ref beta) => {
+            let (new_sub, subst) = canonicalize_binders(sub, path, counts);
+            (ExtendEnvPhaseless(Box::new(new_sub), beta.clone()), subst.become_phaseless())
+        }
+    }
+}
+
 /// Like `freshen_binders`, but to unite two `Ast`s with identical structure (else returns `None`).
 pub fn freshen_binders_with(lhs: &Ast, rhs: &Ast) -> Option<(Ast, Ren, Ast, Ren)> {
     match (lhs, rhs) {
@@ -344,8 +466,8 @@ pub fn freshen_binders_with(lhs: &Ast, rhs: &Ast) -> Option<(Ast, Ren, Ast, Ren)
         }
         // TODO: Handle matching `'[let (a,b) = ⋯]'` against the pattern `'[let ,[p], = ⋯]'` !!
         (
-            &Node(ref f, ref parts_lhs, ref export),
-            &Node(ref f_rhs, ref parts_rhs, ref export_rhs),
+            &Node(ref f, ref parts_lhs, ref export, span_lhs),
+            &Node(ref f_rhs, ref parts_rhs, ref export_rhs, span_rhs),
         ) => {
             if f != f_rhs || export != export_rhs {
                 return None;
@@ -364,9 +486,9 @@ pub fn freshen_binders_with(lhs: &Ast, rhs: &Ast) -> Option<(Ast, Ren, Ast, Ren)
                     let ren_lhs = export.extract_from_mbe(&fresh_pairs, &|t: &(_, Ren, _, _)| &t.1);
                     let ren_rhs = export.extract_from_mbe(&fresh_pairs, &|t: &(_, _, _, Ren)| &t.3);
                     Some((
-                        Node(f.clone(), fresh_ast_lhs, export.clone()),
+                        Node(f.clone(), fresh_ast_lhs, export.clone(), span_lhs),
                         ren_lhs,
-                        Node(f.clone(), fresh_ast_rhs, export.clone()),
+                        Node(f.clone(), fresh_ast_rhs, export.clone(), span_rhs),
                         ren_rhs,
                     ))
                 }
@@ -699,3 +821,33 @@ fn alpha_quote_more_or_less() {
                                                  "rand" => [(vr "a🍅"), (vr "b🍅")]})))})
     );
 }
+
+#[test]
+fn alpha_normalization() {
+    let lam = |a: &str, b: &str, body: Ast| {
+        ast!({"Expr" "lambda" :
+            "param" => [(, Atom(n(a))), (, Atom(n(b)))],
+            "body" => (import [* ["param" : "[ignored]"]] (, body))})
+    };
+    let app = |rator: &str, rands: Vec<&str>| {
+        let rands: Vec<Ast> = rands.into_iter().map(|r| ast!((vr r))).collect();
+        ast!({"Expr" "apply" : "rator" => (vr rator), "rand" => (,seq rands)})
+    };
+
+    let ab = lam("a", "b", app("f", vec!["a", "b", "c"]));
+    let jk = lam("j", "k", app("f", vec!["j", "k", "c"]));
+    assert_eq!(alpha_normalize(&ab), alpha_normalize(&jk));
+    assert_ne!(alpha_normalize(&ab), ab);
+
+    // Swapping the order of the references makes them different...
+    let kj = lam("j", "k", app("f", vec!["k", "j", "c"]));
+    assert_ne!(alpha_normalize(&ab), alpha_normalize(&kj));
+    // ...as does changing a free variable
+    let jk_d = lam("j", "k", app("f", vec!["j", "k", "d"]));
+    assert_ne!(alpha_normalize(&ab), alpha_normalize(&jk_d));
+
+    // Shadowing:
+    let shadow_a = lam("a", "b", lam("a", "c", app("f", vec!["a", "b"])));
+    let shadow_x = lam("x", "y", lam("z", "w", app("f", vec!["z", "y"])));
+    assert_eq!(alpha_normalize(&shadow_a), alpha_normalize(&shadow_x));
+}
@@ -12,8 +12,18 @@ use crate::{
 };
 use std::{fmt, iter};
 
-// TODO: This really ought to be an `Rc` around an `enum`
-#[derive(Clone, PartialEq)]
+/// A range of bytes in the source text.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+// TODO: This really ought to be an `Rc` around an `enum`.
+// In the meantime, `Node` parts are persistent, so cloning an `Ast`
+//  (which happens *constantly* during typechecking) only copies down to the nearest `Node`s.
+// (Wrappers' bodies and `Shape`s, which are rare in types, do get copied until then.)
+#[derive(Clone)]
 pub enum Ast {
     Trivial,
     /// Typically, a binder
@@ -26,8 +36,8 @@ pub enum Ast {
     QuoteLess(Box<Ast>, u8),
 
     /// A meaningful chunk of syntax, governed by a form, containing an environment,
-    ///  potentially exporting some names.
-    Node(std::rc::Rc<Form>, EnvMBE<Ast>, ExportBeta),
+    ///  potentially exporting some names (and maybe remembering where it came from).
+    Node(std::rc::Rc<Form>, EnvMBE<Ast>, ExportBeta, Option<Span>),
 
     /// For parsing purposes.
     IncompleteNode(EnvMBE<Ast>),
@@ -41,6 +51,34 @@ pub enum Ast {
     ExtendEnvPhaseless(Box<Ast>, Beta),
 }
 
+/// One step down into an `Ast`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum PathStep {
+    /// An element of a `Shape`
+    ShapeIdx(usize),
+    /// A part of a `Node` (or `IncompleteNode`),
+    ///  with indices (outermost first) to pick out one repetition of a repeated part
+    Part(Name, Vec<usize>),
+    /// The body of an `ExtendEnv`, `QuoteMore`, etc.
+    Body,
+}
+
+/// A way to get from the top of an `Ast` to one of its subtrees.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Path(pub Vec<PathStep>);
+
+impl Path {
+    /// The path to the (non-repeated) part `part` of a `Node`.
+    pub fn part(part: Name) -> Path { Path(vec![PathStep::Part(part, vec![])]) }
+
+    /// This path, followed by `step`.
+    pub fn then(&self, step: PathStep) -> Path {
+        let mut res = self.clone();
+        res.0.push(step);
+        res
+    }
+}
+
 // Reification macros would totally work for this,
 //  but it's worth having a special case in `Value` in order to make this faster.
 impl crate::runtime::reify::Reifiable for Ast {
@@ -57,6 +95,130 @@ impl crate::runtime::reify::Reifiable for Ast {
 
 pub use self::Ast::*;
 
+/// The JSON (or whatever) shape of an `Ast`. Variants are externally tagged, like
+///  `{"Atom": "x"}`, `{"Shape": [...]}`, or `{"ExtendEnv": [<ast>, <beta>]}`;
+///  `Trivial` is just `"Trivial"`.
+/// `Node`s look like `{"Node": {"nt": "Type", "form": "fn", "parts": <EnvMBE>, "exports": ...}}`,
+///  and can only be deserialized if their form is a core form.
+/// `Span`s are dropped.
+#[derive(serde::Serialize, serde::Deserialize)]
+enum SerialAst {
+    Trivial,
+    Atom(Name),
+    VariableReference(Name),
+    QuoteMore(Box<Ast>, bool),
+    QuoteLess(Box<Ast>, u8),
+    Node { nt: Option<Name>, form: Name, parts: EnvMBE<Ast>, exports: ExportBeta },
+    IncompleteNode(EnvMBE<Ast>),
+    Shape(Vec<Ast>),
+    ExtendEnv(Box<Ast>, Beta),
+    ExtendEnvPhaseless(Box<Ast>, Beta),
+}
+
+impl serde::Serialize for Ast {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let serial = match self.clone() {
+            Trivial => SerialAst::Trivial,
+            Atom(n) => SerialAst::Atom(n),
+            VariableReference(n) => SerialAst::VariableReference(n),
+            QuoteMore(body, pos) => SerialAst::QuoteMore(body, pos),
+            QuoteLess(body, depth) => SerialAst::QuoteLess(body, depth),
+            Node(form, parts, exports, _) => SerialAst::Node {
+                nt: crate::core_forms::find_nt(&form),
+                form: form.name,
+                parts,
+                exports,
+            },
+            IncompleteNode(parts) => SerialAst::IncompleteNode(parts),
+            Shape(subs) => SerialAst::Shape(subs),
+            ExtendEnv(body, beta) => SerialAst::ExtendEnv(body, beta),
+            ExtendEnvPhaseless(body, beta) => SerialAst::ExtendEnvPhaseless(body, beta),
+        };
+        serial.serialize(s)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Ast {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Ast, D::Error> {
+        Ok(match SerialAst::deserialize(d)? {
+            SerialAst::Trivial => Trivial,
+            SerialAst::Atom(n) => Atom(n),
+            SerialAst::VariableReference(n) => VariableReference(n),
+            SerialAst::QuoteMore(body, pos) => QuoteMore(body, pos),
+            SerialAst::QuoteLess(body, depth) => QuoteLess(body, depth),
+            SerialAst::Node { nt: Some(nt), form, parts, exports } => {
+                Node(crate::core_forms::find(&nt.sp(), &form.sp()), parts, exports, None)
+            }
+            SerialAst::Node { nt: None, form, .. } => {
+                return Err(serde::de::Error::custom(format!("`{}` is not a core form", form)))
+            }
+            SerialAst::IncompleteNode(parts) => IncompleteNode(parts),
+            SerialAst::Shape(subs) => Shape(subs),
+            SerialAst::ExtendEnv(body, beta) => ExtendEnv(body, beta),
+            SerialAst::ExtendEnvPhaseless(body, beta) => ExtendEnvPhaseless(body, beta),
+        })
+    }
+}
+
+// Where syntax came from doesn't affect what it is.
+impl PartialEq for Ast {
+    fn eq(&self, other: &Ast) -> bool {
+        match (self, other) {
+            (Trivial, Trivial) => true,
+            (Atom(n_l), Atom(n_r)) => n_l == n_r,
+            (VariableReference(n_l), VariableReference(n_r)) => n_l == n_r,
+            (QuoteMore(body_l, pos_l), QuoteMore(body_r, pos_r)) => {
+                pos_l == pos_r && body_l == body_r
+            }
+            (QuoteLess(body_l, depth_l), QuoteLess(body_r, depth_r)) => {
+                depth_l == depth_r && body_l == body_r
+            }
+            (Node(f_l, parts_l, export_l, _), Node(f_r, parts_r, export_r, _)) => {
+                f_l == f_r && parts_l == parts_r && export_l == export_r
+            }
+            (IncompleteNode(parts_l), IncompleteNode(parts_r)) => parts_l == parts_r,
+            (Shape(subs_l), Shape(subs_r)) => subs_l == subs_r,
+            (ExtendEnv(body_l, beta_l), ExtendEnv(body_r, beta_r)) => {
+                beta_l == beta_r && body_l == body_r
+            }
+            (ExtendEnvPhaseless(body_l, beta_l), ExtendEnvPhaseless(body_r, beta_r)) => {
+                beta_l == beta_r && body_l == body_r
+            }
+            _ => false,
+        }
+    }
+}
+
+// Consistent with `PartialEq`; `Form`s are compared by pointer, but hashed by name.
+impl std::hash::Hash for Ast {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Trivial => {}
+            Atom(n) | VariableReference(n) => n.hash(state),
+            QuoteMore(body, pos) => {
+                body.hash(state);
+                pos.hash(state)
+            }
+            QuoteLess(body, depth) => {
+                body.hash(state);
+                depth.hash(state)
+            }
+            Node(f, parts, export, _) => {
+                f.name.hash(state);
+                parts.hash(state);
+                export.hash(state)
+            }
+            IncompleteNode(parts) => parts.hash(state),
+            Shape(subs) => subs.hash(state),
+            ExtendEnv(body, beta) | ExtendEnvPhaseless(body, beta) => {
+                body.hash(state);
+                beta.hash(state)
+            }
+        }
+    }
+}
+
 impl fmt::Debug for Ast {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -75,7 +237,7 @@ impl fmt::Debug for Ast {
                 }
                 write!(f, ")")
             }
-            Node(ref form, ref body, ref export) => {
+            Node(ref form, ref body, ref export, _) => {
                 write!(f, "{{ ({}); {:#?}", form.name.sp(), body)?;
                 match *export {
                     crate::beta::ExportBeta::Nothing => {}
@@ -104,7 +266,7 @@ impl fmt::Display for Ast {
         match *self {
             Atom(ref n) => write!(f, "{}", n.print()),
             VariableReference(ref v) => write!(f, "{}", v.print()),
-            Node(ref form, ref body, _) => {
+            Node(ref form, ref body, _, _) => {
                 let s = crate::unparse::unparse_mbe(
                     &form.grammar,
                     self,
@@ -132,6 +294,15 @@ impl fmt::Display for Ast {
 }
 
 impl Ast {
+    /// A hash that respects alpha-equivalence: alpha-equivalent terms hash the same.
+    /// (Types are `Ast`s, so this works for them, too.)
+    pub fn structural_hash(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        crate::alpha::alpha_normalize(self).hash(&mut hasher);
+        hasher.finish()
+    }
+
     // TODO: this ought to at least warn if we're losing anything other than `Shape`
     pub fn flatten(&self) -> EnvMBE<Ast> {
         match *self {
@@ -145,7 +316,7 @@ impl Ast {
                 accum
             }
             IncompleteNode(ref env) => env.clone(),
-            Node(ref _f, ref _body, ref _export) => {
+            Node(ref _f, ref _body, ref _export, _) => {
                 // TODO: think about what should happen when
                 //  `Scope` contains a `Scope` without an intervening `Named`
                 panic!("I don't know what to do here!")
@@ -161,7 +332,7 @@ impl Ast {
         &self,
         expd_form: std::rc::Rc<Form>,
     ) -> Option<crate::util::mbe::EnvMBE<Ast>> {
-        if let Node(ref f, ref parts, _) = self {
+        if let Node(ref f, ref parts, _, _) = self {
             if f == &expd_form {
                 return Some(parts.clone());
             }
@@ -171,7 +342,7 @@ impl Ast {
 
     pub fn is_node(&self) -> bool {
         match *self {
-            Node(_, _, _) => true,
+            Node(_, _, _, _) => true,
             _ => false,
         }
     }
@@ -179,13 +350,13 @@ impl Ast {
     // TODO: I think we have a lot of places where we ought to use this function:
     pub fn node_parts(&self) -> &EnvMBE<Ast> {
         match *self {
-            Node(_, ref body, _) => body,
+            Node(_, ref body, _, _) => body,
             _ => icp!(),
         }
     }
     pub fn node_form(&self) -> &Form {
         match *self {
-            Node(ref form, _, _) => form,
+            Node(ref form, _, _, _) => form,
             _ => icp!(),
         }
     }
@@ -200,7 +371,7 @@ impl Ast {
             //  (if a ∀ somehow ends up underneath a `*` in syntax.)
             // And we need to take a LazyWalkReses to do this right.
             ExtendEnv(_, _) | ExtendEnvPhaseless(_, _) => unimplemented!("TODO"),
-            Node(_, ref body, _) => body.map_reduce(
+            Node(_, ref body, _, _) => body.map_reduce(
                 &|a| a.free_vrs(),
                 &|v0, v1| {
                     let mut res = v0.clone();
@@ -212,6 +383,173 @@ impl Ast {
         }
     }
 
+    /// Where this came from in the source text (if it was parsed).
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            Node(_, _, _, span) => span,
+            ExtendEnv(ref body, _) | ExtendEnvPhaseless(ref body, _) => body.span(),
+            QuoteMore(ref body, _) | QuoteLess(ref body, _) => body.span(),
+            _ => None,
+        }
+    }
+
+    /// This, with the subtree at `path` replaced by `new`. `None` if there's nothing there.
+    pub fn replace_at(&self, path: &Path, new: Ast) -> Option<Ast> {
+        self.update_at(path, |_| Some(new))
+    }
+
+    /// This, with the subtree at `path` replaced by `f` of it.
+    /// `None` if there's nothing there (or if `f` returns `None`).
+    pub fn update_at(&self, path: &Path, f: impl FnOnce(&Ast) -> Option<Ast>) -> Option<Ast> {
+        self.update_at_steps(&path.0, f)
+    }
+
+    fn update_at_steps(
+        &self,
+        steps: &[PathStep],
+        f: impl FnOnce(&Ast) -> Option<Ast>,
+    ) -> Option<Ast> {
+        let (step, rest) = match steps.split_first() {
+            None => return f(self),
+            Some(split) => split,
+        };
+        match (step, self) {
+            (PathStep::ShapeIdx(idx), Shape(subs)) => {
+                let mut subs = subs.clone();
+                subs[*idx] = subs.get(*idx)?.update_at_steps(rest, f)?;
+                Some(Shape(subs))
+            }
+            (PathStep::Part(part, rep_idxs), Node(form, parts, export, span)) => Some(Node(
+                form.clone(),
+                parts.update_leaf_at(*part, rep_idxs, |sub| sub.update_at_steps(rest, f))?,
+                export.clone(),
+                *span,
+            )),
+            (PathStep::Part(part, rep_idxs), IncompleteNode(parts)) => Some(IncompleteNode(
+                parts.update_leaf_at(*part, rep_idxs, |sub| sub.update_at_steps(rest, f))?,
+            )),
+            (PathStep::Body, ExtendEnv(body, beta)) => {
+                Some(ExtendEnv(Box::new(body.update_at_steps(rest, f)?), beta.clone()))
+            }
+            (PathStep::Body, ExtendEnvPhaseless(body, beta)) => {
+                Some(ExtendEnvPhaseless(Box::new(body.update_at_steps(rest, f)?), beta.clone()))
+            }
+            (PathStep::Body, QuoteMore(body, pos)) => {
+                Some(QuoteMore(Box::new(body.update_at_steps(rest, f)?), *pos))
+            }
+            (PathStep::Body, QuoteLess(body, depth)) => {
+                Some(QuoteLess(Box::new(body.update_at_steps(rest, f)?), *depth))
+            }
+            _ => None,
+        }
+    }
+
+    /// Paths (for `replace_at`) to every subtree (including this one) that satisfies `p`.
+    pub fn find_all<P: Fn(&Ast) -> bool>(&self, p: P) -> Vec<Path> {
+        let mut res = vec![];
+        self.find_all_under(&p, &mut vec![], &mut res);
+        res
+    }
+
+    fn find_all_under(
+        &self,
+        p: &dyn Fn(&Ast) -> bool,
+        steps: &mut Vec<PathStep>,
+        res: &mut Vec<Path>,
+    ) {
+        if p(self) {
+            res.push(Path(steps.clone()));
+        }
+        let mut visit = |step: PathStep, sub: &Ast| {
+            steps.push(step);
+            sub.find_all_under(p, steps, res);
+            steps.pop();
+        };
+        match self {
+            Shape(subs) => {
+                for (idx, sub) in subs.iter().enumerate() {
+                    visit(PathStep::ShapeIdx(idx), sub)
+                }
+            }
+            Node(_, parts, _, _) | IncompleteNode(parts) => {
+                for (part, rep_idxs, sub) in parts.leaves_at() {
+                    visit(PathStep::Part(part, rep_idxs), sub)
+                }
+            }
+            ExtendEnv(body, _)
+            | ExtendEnvPhaseless(body, _)
+            | QuoteMore(body, _)
+            | QuoteLess(body, _) => visit(PathStep::Body, body),
+            Trivial | Atom(_) | VariableReference(_) => {}
+        }
+    }
+
+    /// Like `==`, but ignoring differences in how `Node`s group their repeated parts
+    ///  (see `EnvMBE::semantically_eq_by`).
+    pub fn semantically_eq(&self, other: &Ast) -> bool {
+        match (self, other) {
+            (Node(f_l, parts_l, export_l, _), Node(f_r, parts_r, export_r, _)) => {
+                f_l == f_r
+                    && export_l == export_r
+                    && parts_l.semantically_eq_by(parts_r, &Ast::semantically_eq)
+            }
+            (IncompleteNode(parts_l), IncompleteNode(parts_r)) => {
+                parts_l.semantically_eq_by(parts_r, &Ast::semantically_eq)
+            }
+            (Shape(subs_l), Shape(subs_r)) => {
+                subs_l.len() == subs_r.len()
+                    && subs_l.iter().zip(subs_r).all(|(l, r)| l.semantically_eq(r))
+            }
+            (QuoteMore(body_l, pos_l), QuoteMore(body_r, pos_r)) => {
+                pos_l == pos_r && body_l.semantically_eq(body_r)
+            }
+            (QuoteLess(body_l, depth_l), QuoteLess(body_r, depth_r)) => {
+                depth_l == depth_r && body_l.semantically_eq(body_r)
+            }
+            (ExtendEnv(body_l, beta_l), ExtendEnv(body_r, beta_r))
+            | (ExtendEnvPhaseless(body_l, beta_l), ExtendEnvPhaseless(body_r, beta_r)) => {
+                beta_l == beta_r && body_l.semantically_eq(body_r)
+            }
+            _ => self == other,
+        }
+    }
+
+    /// The immediate subtrees of this, in the order that `with_children` expects them.
+    pub fn children(&self) -> Vec<&Ast> {
+        match *self {
+            Trivial | Atom(_) | VariableReference(_) => vec![],
+            Shape(ref subs) => subs.iter().collect(),
+            Node(_, ref parts, _, _) | IncompleteNode(ref parts) => parts.all_leaves(),
+            QuoteMore(ref body, _)
+            | QuoteLess(ref body, _)
+            | ExtendEnv(ref body, _)
+            | ExtendEnvPhaseless(ref body, _) => vec![&**body],
+        }
+    }
+
+    /// This, with its immediate subtrees replaced by `children` (which line up with `children()`).
+    pub fn with_children(&self, children: Vec<Ast>) -> Ast {
+        if children.len() != self.children().len() {
+            icp!("{} needs {} children, not {}", self, self.children().len(), children.len())
+        }
+        let mut children = children.into_iter();
+        let mut next = || Box::new(children.next().unwrap());
+        match *self {
+            Trivial | Atom(_) | VariableReference(_) => self.clone(),
+            Shape(_) => Shape(children.collect()),
+            Node(ref f, ref parts, ref export, span) => {
+                Node(f.clone(), parts.map(&mut |_| children.next().unwrap()), export.clone(), span)
+            }
+            IncompleteNode(ref parts) => {
+                IncompleteNode(parts.map(&mut |_| children.next().unwrap()))
+            }
+            QuoteMore(_, pos) => QuoteMore(next(), pos),
+            QuoteLess(_, depth) => QuoteLess(next(), depth),
+            ExtendEnv(_, ref beta) => ExtendEnv(next(), beta.clone()),
+            ExtendEnvPhaseless(_, ref beta) => ExtendEnvPhaseless(next(), beta.clone()),
+        }
+    }
+
     pub fn to_name(&self) -> Name {
         match *self {
             Atom(n) => n,
@@ -284,3 +622,165 @@ fn mbe_r_and_r_roundtrip() {
     let mbe1 = mbe!( "a" => [@"duo" "1", "2"], "b" => [@"duo" "11", "22"]);
     assert_eq!(mbe1, EnvMBE::<Ast>::reflect(&mbe1.reify()));
 }
+
+#[test]
+fn ast_json_round_trip() {
+    let fn_ty = ast!({"Type" "fn" :
+        "param" => [{"Type" "Int" :}, (vr "T")],
+        "ret" => {"Type" "Nat" :}});
+
+    let json = serde_json::to_string(&fn_ty).unwrap();
+    assert!(json.contains(r#""form":"fn""#));
+    assert!(json.contains(r#"{"VariableReference":"T"}"#));
+    assert_eq!(serde_json::from_str::<Ast>(&json).unwrap(), fn_ty);
+
+    let imported = ast!((import [* [forall "param"]] (vr "T")));
+    let json = serde_json::to_string(&imported).unwrap();
+    assert_eq!(serde_json::from_str::<Ast>(&json).unwrap(), imported);
+
+    let not_core = r#"{"Node":{"nt":null,"form":"m","parts":{}, "exports":"Nothing"}}"#;
+    assert!(serde_json::from_str::<Ast>(not_core).is_err());
+}
+
+#[test]
+fn structural_hashing() {
+    let id_fn_ty = |param: &str| {
+        ast!({ "Type" "forall_type" :
+            "param" => [(, Atom(n(param)))],
+            "body" => (import [* [forall "param"]]
+                { "Type" "fn" : "param" => [ (vr param) ], "ret" => (vr param) })})
+    };
+    assert_eq!(id_fn_ty("T").structural_hash(), id_fn_ty("S").structural_hash());
+    assert_ne!(id_fn_ty("T"), id_fn_ty("S"));
+
+    let int_to_nat =
+        ast!({ "Type" "fn" : "param" => [{"Type" "Int" :}], "ret" => {"Type" "Nat" :}});
+    let nat_to_int =
+        ast!({ "Type" "fn" : "param" => [{"Type" "Nat" :}], "ret" => {"Type" "Int" :}});
+    assert_ne!(int_to_nat.structural_hash(), nat_to_int.structural_hash());
+    assert_ne!(int_to_nat.structural_hash(), id_fn_ty("T").structural_hash());
+    // Free variables matter:
+    assert_ne!(ast!((vr "T")).structural_hash(), ast!((vr "S")).structural_hash());
+}
+
+#[test]
+fn replace_by_path() {
+    let fn_ty = ast!({"Type" "fn" :
+        "param" => [{"Type" "Int" :}, (vr "T")],
+        "ret" => {"Type" "Nat" :}});
+
+    let ret = Path(vec![PathStep::Part(n("ret"), vec![])]);
+    assert_eq!(
+        fn_ty.replace_at(&ret, ast!({"Type" "Float" :})),
+        Some(ast!({"Type" "fn" :
+            "param" => [{"Type" "Int" :}, (vr "T")],
+            "ret" => {"Type" "Float" :}}))
+    );
+
+    let second_param = Path(vec![PathStep::Part(n("param"), vec![1])]);
+    assert_eq!(
+        fn_ty.replace_at(&second_param, ast!((vr "U"))),
+        Some(ast!({"Type" "fn" :
+            "param" => [{"Type" "Int" :}, (vr "U")],
+            "ret" => {"Type" "Nat" :}}))
+    );
+
+    // Invalid paths:
+    assert_eq!(fn_ty.replace_at(&Path(vec![PathStep::Part(n("param"), vec![2])]), Trivial), None);
+    assert_eq!(
+        fn_ty.replace_at(&Path(vec![PathStep::Part(n("nonexistent"), vec![])]), Trivial),
+        None
+    );
+    assert_eq!(fn_ty.replace_at(&Path(vec![PathStep::Body]), Trivial), None);
+    assert_eq!(
+        fn_ty.replace_at(
+            &Path(vec![PathStep::Part(n("ret"), vec![]), PathStep::ShapeIdx(0)]),
+            Trivial
+        ),
+        None
+    );
+}
+
+#[test]
+fn find_all_paths() {
+    let term = ast!({"Expr" "apply" : "rator" => (vr "x"),
+        "rand" => [(vr "y"), {"Expr" "apply" : "rator" => (vr "f"), "rand" => [(vr "x")]}]});
+    let is_x = |a: &Ast| a == &ast!((vr "x"));
+
+    let xs = term.find_all(is_x);
+    assert_eq!(xs.len(), 2);
+    assert!(xs.contains(&Path(vec![PathStep::Part(n("rator"), vec![])])));
+    // Each path leads to an `x`:
+    for path in &xs {
+        let renamed = term.replace_at(path, ast!((vr "z"))).unwrap();
+        assert_eq!(renamed.find_all(is_x).len(), 1);
+        assert_eq!(renamed.find_all(|a| a == &ast!((vr "z"))), vec![path.clone()]);
+    }
+    // ...so together they rename every `x`:
+    let renamed =
+        xs.iter().fold(term.clone(), |t, path| t.replace_at(path, ast!((vr "z"))).unwrap());
+    assert_eq!(
+        renamed,
+        ast!({"Expr" "apply" : "rator" => (vr "z"),
+            "rand" => [(vr "y"), {"Expr" "apply" : "rator" => (vr "f"), "rand" => [(vr "z")]}]})
+    );
+
+    assert_eq!(term.find_all(|_| true)[0], Path(vec![]));
+    assert_eq!(term.find_all(|a| a == &ast!((vr "w"))), vec![]);
+}
+
+#[test]
+fn children_round_trip() {
+    let lambda = ast!({"Expr" "lambda" :
+        "param" => [@"p" "x", "y"],
+        "p_t" => [@"p" {"Type" "Int" :}, (vr "T")],
+        "body" => (import [* ["param" : "p_t"]] (vr "x"))});
+    let children = lambda.children();
+    assert_eq!(children.len(), 5);
+    assert_eq!(lambda.with_children(children.into_iter().cloned().collect()), lambda);
+
+    // Rebuilding from transformed children (only the immediate ones, so `body` is unaffected):
+    let renamed = lambda.with_children(
+        lambda
+            .children()
+            .into_iter()
+            .map(|c| match *c {
+                VariableReference(_) => ast!((vr "z")),
+                _ => c.clone(),
+            })
+            .collect(),
+    );
+    assert_eq!(
+        renamed,
+        ast!({"Expr" "lambda" :
+            "param" => [@"p" "x", "y"],
+            "p_t" => [@"p" {"Type" "Int" :}, (vr "z")],
+            "body" => (import [* ["param" : "p_t"]] (vr "x"))})
+    );
+}
+
+#[cfg(test)]
+pub fn deeply_nested_type(depth: usize) -> Ast {
+    let mut t = ast!({"Type" "Int" :});
+    for _ in 0..depth {
+        t = ast!({"Type" "forall_type" : "param" => ["T"],
+            "body" => (import [* [forall "param"]]
+                {"Type" "fn" : "param" => [(vr "T")], "ret" => (, t)})});
+    }
+    t
+}
+
+#[test]
+fn clones_share_structure() {
+    let t = deeply_nested_type(10);
+    let t2 = t.clone();
+    let body = |t: &Ast| -> *const Ast {
+        match t {
+            Node(_, parts, _, _) => parts.get_leaf_or_panic(&n("body")),
+            _ => panic!(),
+        }
+    };
+    assert!(std::ptr::eq(body(&t), body(&t2)));
+    assert_eq!(t, t2);
+    assert_ne!(t, deeply_nested_type(9));
+}
@@ -59,14 +59,22 @@
 //  because all syntax should be constructable and matchable.
 
 use crate::{
-    ast::Ast::{self, *},
+    ast::{
+        Ast::{self, *},
+        Path, PathStep,
+    },
     beta::*,
     name::*,
     runtime::{eval, reify},
-    util::{assoc::Assoc, mbe::EnvMBE},
+    util::{assoc::Assoc, mbe::EnvMBE, scoped::with_scoped},
     walk_mode::{Dir, WalkElt, WalkMode},
 };
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+};
 
 /// A closed `Elt`; an `Elt` paired with an environment with which to interpret its free names.
 #[derive(Clone, Debug, PartialEq)]
@@ -104,6 +112,142 @@ thread_local! {
     // Tuple elements are (layers deep, number of steps taken).
     pub static ast_walk_layer: RefCell<(u32, u32)> = RefCell::new((0, 0));
     pub static ld_enabled: bool = std::env::var(&"UNSEEMLY_TRACE").map(|t| t == "full") == Ok(true);
+
+    // `WalkHooks<Mode>`s, by the `TypeId` of `Mode`; see `with_walk_hooks`.
+    static walk_hooks: RefCell<HashMap<TypeId, Rc<dyn Any>>> = RefCell::new(HashMap::new());
+
+    // While `with_walk_positions` runs: where each `walk` in progress is, innermost last.
+    // `None` if it's unknown (e.g. a form is walking a term that it made up).
+    static walk_positions: RefCell<Option<Vec<Option<Path>>>> = RefCell::new(None);
+    // Where the next `walk` will be, relative to the innermost one (see `walk_at`).
+    static next_walk_offset: RefCell<Option<Path>> = RefCell::new(None);
+}
+
+/// Run `f`, keeping track of where `walk` is (see `walk_position`).
+pub fn with_walk_positions<T>(f: impl FnOnce() -> T) -> T {
+    next_walk_offset.with(|o| *o.borrow_mut() = None);
+    with_scoped(&walk_positions, Some(vec![]), f)
+}
+
+/// Where the innermost `walk` is, relative to the first `Ast` walked in `with_walk_positions`.
+/// `None` if nobody's keeping track, or if it isn't known.
+pub fn walk_position() -> Option<Path> {
+    walk_positions.with(|wp| wp.borrow().as_ref()?.last().cloned()?)
+}
+
+fn keeping_walk_positions() -> bool { walk_positions.with(|wp| wp.borrow().is_some()) }
+
+/// Run `f` (a `walk`) at the position `next_walk_offset` leads to.
+fn at_next_walk_position<T>(f: impl FnOnce() -> T) -> T {
+    if !keeping_walk_positions() {
+        return f();
+    }
+    let offset = next_walk_offset.with(|o| o.borrow_mut().take());
+    walk_positions.with(|wp| {
+        let mut wp = wp.borrow_mut();
+        let positions = wp.as_mut().unwrap();
+        let position = match (positions.last(), offset) {
+            (None, _) => Some(Path::default()), // The first walk is where we start from
+            (Some(Some(outer)), Some(offset)) => {
+                let mut position = outer.clone();
+                position.0.extend(offset.0);
+                Some(position)
+            }
+            _ => None,
+        };
+        positions.push(position)
+    });
+    let res = f();
+    walk_positions.with(|wp| wp.borrow_mut().as_mut().unwrap().pop());
+    res
+}
+
+/// `walk`, where `a` is at `offset` from the term being walked (if that's known).
+/// Forms that walk their subterms directly (rather than with `get_res`) should use this.
+pub fn walk_at<Mode: WalkMode>(
+    offset: Option<Path>,
+    a: &Ast,
+    walk_ctxt: &LazyWalkReses<Mode>,
+) -> Result<<Mode::D as Dir>::Out, Mode::Err> {
+    if keeping_walk_positions() {
+        next_walk_offset.with(|o| *o.borrow_mut() = offset);
+    }
+    walk(a, walk_ctxt)
+}
+
+pub type NodeHook<Mode> = Rc<dyn Fn(&crate::form::Form, &Assoc<Name, <Mode as WalkMode>::Elt>)>;
+/// Like `NodeHook`, but also told whether the walk succeeded.
+pub type AfterNodeHook<Mode> =
+    Rc<dyn Fn(&crate::form::Form, &Assoc<Name, <Mode as WalkMode>::Elt>, bool)>;
+
+/// Callbacks for every `Node` walked in `Mode` (e.g. for timing or coverage).
+/// They get the node's form and the environment it's walked in.
+pub struct WalkHooks<Mode: WalkMode> {
+    pub before_node: Option<NodeHook<Mode>>,
+    pub after_node: Option<AfterNodeHook<Mode>>,
+}
+
+/// Like `walk`, but calling `hooks` around every `Node` walked in `Mode`
+///  (including by walks that this one starts).
+pub fn walk_with_hooks<Mode: WalkMode>(
+    a: &Ast,
+    walk_ctxt: &LazyWalkReses<Mode>,
+    hooks: WalkHooks<Mode>,
+) -> Result<<Mode::D as Dir>::Out, Mode::Err> {
+    with_walk_hooks(hooks, || walk(a, walk_ctxt))
+}
+
+/// Run `f`, calling `hooks` around every `Node` walked in `Mode`.
+/// (For hooking a whole operation, like a subtyping check, that starts its own walks.)
+/// Hooks already installed keep running, outside of the new ones.
+/// When no hooks are installed, they cost one check per `Node`.
+pub fn with_walk_hooks<Mode: WalkMode, T>(hooks: WalkHooks<Mode>, f: impl FnOnce() -> T) -> T {
+    let hooks = match current_walk_hooks::<Mode>() {
+        None => hooks,
+        Some(outer) => WalkHooks {
+            before_node: chain_before::<Mode>(outer.before_node.clone(), hooks.before_node),
+            after_node: chain_after::<Mode>(hooks.after_node, outer.after_node.clone()),
+        },
+    };
+    let mut all_hooks = walk_hooks.with(|wh| wh.borrow().clone());
+    all_hooks.insert(TypeId::of::<Mode>(), Rc::new(hooks));
+    with_scoped(&walk_hooks, all_hooks, f)
+}
+
+fn chain_before<Mode: WalkMode>(
+    first: Option<NodeHook<Mode>>,
+    second: Option<NodeHook<Mode>>,
+) -> Option<NodeHook<Mode>> {
+    match (first, second) {
+        (Some(first), Some(second)) => Some(Rc::new(move |f, env| {
+            first(f, env);
+            second(f, env)
+        })),
+        (first, second) => first.or(second),
+    }
+}
+
+fn chain_after<Mode: WalkMode>(
+    first: Option<AfterNodeHook<Mode>>,
+    second: Option<AfterNodeHook<Mode>>,
+) -> Option<AfterNodeHook<Mode>> {
+    match (first, second) {
+        (Some(first), Some(second)) => Some(Rc::new(move |f, env, succeeded| {
+            first(f, env, succeeded);
+            second(f, env, succeeded)
+        })),
+        (first, second) => first.or(second),
+    }
+}
+
+fn current_walk_hooks<Mode: WalkMode>() -> Option<Rc<WalkHooks<Mode>>> {
+    walk_hooks.with(|wh| {
+        let wh = wh.borrow();
+        if wh.is_empty() {
+            return None;
+        }
+        wh.get(&TypeId::of::<Mode>())?.clone().downcast::<WalkHooks<Mode>>().ok()
+    })
 }
 
 /// Make a `<Mode::D as Dir>::Out` by walking `node` in the environment from `walk_ctxt`.
@@ -112,20 +256,28 @@ thread_local! {
 pub fn walk<Mode: WalkMode>(
     a: &Ast,
     walk_ctxt: &LazyWalkReses<Mode>,
+) -> Result<<Mode::D as Dir>::Out, Mode::Err> {
+    // TODO: can we get rid of the & in front of our arguments and save the cloning?
+    at_next_walk_position(|| match *a {
+        // HACK: We want to process EE before pre_match before everything else.
+        // This probably means we should find a way to get rid of pre_match.
+        // But we can't just swap `a` and the ctxt when `a` is LiteralLike and the ctxt isn't.
+        ExtendEnv(_, _) => walk_prepared(a.clone(), walk_ctxt.clone()),
+        _ => Mode::D::around_walk(a, walk_ctxt, || {
+            let (a, walk_ctxt) = Mode::D::pre_walk(a.clone(), walk_ctxt.clone());
+            walk_prepared(a, walk_ctxt)
+        }),
+    })
+}
+
+/// The rest of `walk`, once `pre_walk` has had its say.
+fn walk_prepared<Mode: WalkMode>(
+    a: Ast,
+    walk_ctxt: LazyWalkReses<Mode>,
 ) -> Result<<Mode::D as Dir>::Out, Mode::Err> {
     layer_watch! { ast_walk_layer :
-        // TODO: can we get rid of the & in front of our arguments and save the cloning?
         // TODO: this has a lot of direction-specific runtime hackery.
         //  Maybe we want separate positive and negative versions?
-        let (a, walk_ctxt) = match *a {
-          // HACK: We want to process EE before pre_match before everything else.
-          // This probably means we should find a way to get rid of pre_match.
-          // But we can't just swap `a` and the ctxt when `a` is LiteralLike and the ctxt isn't.
-
-          ExtendEnv(_,_) => { (a.clone(), walk_ctxt.clone()) }
-          _ => Mode::D::pre_walk(a.clone(), walk_ctxt.clone())
-        };
-
         ld!(ast_walk_layer, ld_enabled, "{} {}", Mode::name(), a);
         // lc!(ast_walk_layer, ld_enabled, "  from: {}", walk_ctxt.this_ast);
         // match walk_ctxt.env.find(&negative_ret_val()) {
@@ -138,7 +290,7 @@ pub fn walk<Mode: WalkMode>(
                     match walk_ctxt.this_ast {
                         // `this_ast` might be `NotWalked` (and non-literal) if under `switch_mode`.
                         // It's weird, but seems to be the right thing
-                        Node(ref f, _, _) => Some(Mode::get_walk_rule(f).is_literally()),
+                        Node(ref f, _, _, _) => Some(Mode::get_walk_rule(f).is_literally()),
                         _ => None
                     }
                 }
@@ -147,21 +299,33 @@ pub fn walk<Mode: WalkMode>(
 
 
         match a {
-            Node(ref f, ref parts, _) => {
-                let mut new_walk_ctxt = walk_ctxt.switch_ast(parts, a.clone());
+            Node(ref f, ref parts, _, _) => {
+                let mut new_walk_ctxt = walk_ctxt.enter_node(parts, a.clone());
                 heal__lwr_splices(&mut new_walk_ctxt)?;
 
+                let hooks = current_walk_hooks::<Mode>();
+                if let Some(before) = hooks.as_ref().and_then(|h| h.before_node.as_ref()) {
+                    before(f, &new_walk_ctxt.env)
+                }
+                let env = new_walk_ctxt.env.clone();
+
+                Mode::entering_node(f);
                 // certain walks only work on certain kinds of AST nodes
-                match Mode::get_walk_rule(f) {
+                let res = match Mode::get_walk_rule(f) {
                     Custom(ref ts_fn) =>  ts_fn(new_walk_ctxt),
-                    Body(n) =>            walk(parts.get_leaf(n).unwrap(), &new_walk_ctxt),
+                    Body(n) =>            walk_at(Some(Path::part(n)),
+                                                  parts.get_leaf(n).unwrap(), &new_walk_ctxt),
                     LiteralLike =>        Mode::walk_quasi_literally(a.clone(), &new_walk_ctxt),
                     NotWalked =>          icp!("{:#?} should not be walked at all!", a)
+                };
+                if let Some(after) = hooks.as_ref().and_then(|h| h.after_node.as_ref()) {
+                    after(f, &env, res.is_ok())
                 }
+                res
             }
             IncompleteNode(ref parts) => { icp!("{:#?} isn't a complete node", parts)}
 
-            VariableReference(n) => { Mode::walk_var(n, &walk_ctxt) }
+            VariableReference(n) => { Mode::walk_var(n.resolve_in(&walk_ctxt.env), &walk_ctxt) }
             Atom(n) => { Mode::walk_atom(n, &walk_ctxt) }
 
             // TODO: we need to preserve these in LiteralLike contexts!!
@@ -275,6 +439,18 @@ pub fn walk<Mode: WalkMode>(
     }
 }
 
+/// Walk `a` in two different modes (with the same environment), to compare the results.
+/// This is for checking that two modes agree (e.g. #28, `Canonicalize` vs. `SynthTy` on types).
+pub fn walk_both<A: WalkMode, B: WalkMode<Elt = A::Elt>>(
+    a: &Ast,
+    env: Assoc<Name, A::Elt>,
+) -> (Result<<A::D as Dir>::Out, A::Err>, Result<<B::D as Dir>::Out, B::Err>) {
+    (
+        walk::<A>(a, &LazyWalkReses::new_wrapper(env.clone())),
+        walk::<B>(a, &LazyWalkReses::new_wrapper(env)),
+    )
+}
+
 // This fixes up `walk_ctxt` based on splice healing.
 // TODO #40: Its effects on the rest of the code are too complex:
 //  * `extra_env` needs to be used in various places, but exactly where is fuzzy
@@ -288,7 +464,7 @@ fn heal__lwr_splices<Mode: WalkMode>(walk_ctxt: &mut LazyWalkReses<Mode>) -> Res
 
     if Mode::D::is_positive() {
         walk_ctxt.parts.heal_splices::<Mode::Err>(&|lwt: &Rc<LazilyWalkedTerm<Mode>>| {
-            if let Node(ref sub_f, ref sub_parts, _) = lwt.term {
+            if let Node(ref sub_f, ref sub_parts, _, _) = lwt.term {
                 if let Some((envs, new_term)) = Mode::perform_splice_positive(
                     sub_f,
                     &orig_walk_ctxt.clone().switch_ast(&sub_parts, lwt.term.clone()),
@@ -300,6 +476,7 @@ fn heal__lwr_splices<Mode: WalkMode>(walk_ctxt: &mut LazyWalkReses<Mode>) -> Res
                                     term: new_term.clone(),
                                     res: lwt.res.clone(),
                                     extra_env: env,
+                                    step: None,
                                 })
                             })
                             .collect::<Vec<_>>(),
@@ -315,7 +492,7 @@ fn heal__lwr_splices<Mode: WalkMode>(walk_ctxt: &mut LazyWalkReses<Mode>) -> Res
         let its_a_trivial_ast = EnvMBE::new();
         let context_ast = walk_ctxt.context_elt().to_ast();
         let other_parts = match (&context_ast, &walk_ctxt.this_ast) {
-            (&Node(ref f, ref p, _), &Node(ref f_this, _, _)) => {
+            (&Node(ref f, ref p, _, _), &Node(ref f_this, _, _, _)) => {
                 if f != f_this {
                     // Mismatched ASTs; some subtyping rules allow this, but healing is nonsensical
                     return Ok(());
@@ -332,7 +509,7 @@ fn heal__lwr_splices<Mode: WalkMode>(walk_ctxt: &mut LazyWalkReses<Mode>) -> Res
         walk_ctxt.parts.heal_splices__with::<Mode::Err, Ast>(
             other_parts,
             &|lwt: &Rc<LazilyWalkedTerm<Mode>>, sub_other_thunk: &dyn Fn() -> Vec<Ast>| {
-                if let Node(ref sub_f, ref sub_parts, _) = lwt.term {
+                if let Node(ref sub_f, ref sub_parts, _, _) = lwt.term {
                     // TODO: negative
                     if let Some((envs, new_term)) = Mode::perform_splice_negative(
                         sub_f,
@@ -346,6 +523,7 @@ fn heal__lwr_splices<Mode: WalkMode>(walk_ctxt: &mut LazyWalkReses<Mode>) -> Res
                                         term: new_term.clone(),
                                         res: lwt.res.clone(),
                                         extra_env: env,
+                                        step: None,
                                     })
                                 })
                                 .collect::<Vec<_>>(),
@@ -378,7 +556,7 @@ fn maybe_literally__walk<Mode: WalkMode>(
     if literally.expect("ICP: unable to determine literalness") {
         Mode::walk_quasi_literally(a.clone(), &walk_ctxt)
     } else {
-        walk(&*body, &walk_ctxt)
+        walk_at(Some(Path(vec![PathStep::Body])), &*body, &walk_ctxt)
     }
 }
 
@@ -466,6 +644,8 @@ pub struct LazilyWalkedTerm<Mode: WalkMode> {
     /// This is a hack; it's specifically for the dotdotdot type.
     /// Maybe it needs generalization in some direction.
     pub extra_env: Assoc<Name, Mode::Elt>,
+    /// Where this is in the term being walked, if we're keeping track (see `with_walk_positions`)
+    pub step: Option<PathStep>,
 }
 
 // trait bounds on parameters are not yet supported by `Reifiable!`
@@ -485,7 +665,8 @@ impl<Mode: WalkMode> reify::Reifiable for LazilyWalkedTerm<Mode> {
             term: Ast::reflect(contents.find_or_panic(&n("term"))),
             res: RefCell::<Option<Result<<Mode::D as Dir>::Out, Mode::Err>>>::reflect(
                 contents.find_or_panic(&n("res"))),
-            extra_env: Assoc::<Name, Mode::Elt>::reflect(contents.find_or_panic(&n("extra_env")))
+            extra_env: Assoc::<Name, Mode::Elt>::reflect(contents.find_or_panic(&n("extra_env"))),
+            step: None
             })
     }
 }
@@ -497,6 +678,16 @@ impl<Mode: WalkMode> LazilyWalkedTerm<Mode> {
             term: t.clone(),
             res: RefCell::new(None),
             extra_env: Assoc::new(),
+            step: None,
+        })
+    }
+
+    fn new_at(t: &Ast, step: PathStep) -> Rc<LazilyWalkedTerm<Mode>> {
+        Rc::new(LazilyWalkedTerm {
+            term: t.clone(),
+            res: RefCell::new(None),
+            extra_env: Assoc::new(),
+            step: Some(step),
         })
     }
 
@@ -508,9 +699,10 @@ impl<Mode: WalkMode> LazilyWalkedTerm<Mode> {
         self.memoized(&|| {
             // stab-in-the-dark optimization, but this function gets called a *lot*:
             if self.extra_env.empty() {
-                walk::<Mode>(&self.term, cur_node_contents)
+                walk_at::<Mode>(self.offset(), &self.term, cur_node_contents)
             } else {
-                walk::<Mode>(
+                walk_at::<Mode>(
+                    self.offset(),
                     &self.term,
                     &cur_node_contents
                         .with_environment(cur_node_contents.env.set_assoc(&self.extra_env)),
@@ -519,6 +711,8 @@ impl<Mode: WalkMode> LazilyWalkedTerm<Mode> {
         })
     }
 
+    fn offset(&self) -> Option<Path> { self.step.clone().map(|step| Path(vec![step])) }
+
     fn memoized(
         &self,
         f: &dyn Fn() -> Result<<Mode::D as Dir>::Out, Mode::Err>,
@@ -667,9 +861,20 @@ impl<Mode: WalkMode> LazyWalkReses<Mode> {
         LazyWalkReses { parts: parts.map(&mut LazilyWalkedTerm::new), this_ast: this_ast, ..self }
     }
 
+    /// `switch_ast` to the `Node` that `walk` is at (so its parts know where they are).
+    fn enter_node(self, parts: &EnvMBE<Ast>, this_ast: Ast) -> LazyWalkReses<Mode> {
+        if !keeping_walk_positions() {
+            return self.switch_ast(parts, this_ast);
+        }
+        let parts = parts.map_at(&mut |part, rep_idxs, sub: &Ast| {
+            LazilyWalkedTerm::new_at(sub, PathStep::Part(part, rep_idxs.to_vec()))
+        });
+        LazyWalkReses { parts: parts, this_ast: this_ast, ..self }
+    }
+
     pub fn this_form(&self) -> Rc<crate::form::Form> {
         match self.this_ast {
-            Node(ref f, _, _) => f.clone(),
+            Node(ref f, _, _, _) => f.clone(),
             _ => icp!(),
         }
     }
@@ -973,3 +1178,43 @@ fn quote_more_and_less() {
     // check that we successfully squirreled it away:
     assert_eq!(*interpolation_accumulator.borrow(), assoc_n!("bind_me" => ast!({"Type" "Int" :})));
 }
+
+#[test]
+fn walk_hooks_see_nodes() {
+    use crate::ty_compare::{
+        must_subtype, must_subtype_with, NumericTower, Subtype, SubtypePolicy,
+    };
+    use std::cell::Cell;
+
+    let fns_visited = Rc::new(Cell::new(0));
+    let hooks = || {
+        let fns_visited_ = fns_visited.clone();
+        WalkHooks::<Subtype> {
+            before_node: None,
+            after_node: Some(Rc::new(move |f: &crate::form::Form, _: &Assoc<Name, Ast>, _| {
+                if f.name == n("fn") {
+                    fns_visited_.set(fns_visited_.get() + 1)
+                }
+            })),
+        }
+    };
+
+    let fn_ty = uty!({fn : [{fn : [{Int :}] {Int :}}] {Nat :}});
+    let wider_fn_ty = uty!({fn : [{fn : [{Int :}] {Int :}}] {Int :}});
+    let widening = SubtypePolicy { numeric_tower: NumericTower::Widening, ..Default::default() };
+    let widening_check = || must_subtype_with(&widening, &fn_ty, &wider_fn_ty, Assoc::new());
+    with_walk_hooks(hooks(), || assert_m!(widening_check(), Ok(_)));
+    assert_eq!(fns_visited.get(), 2);
+
+    // Reflexive checks don't walk at all:
+    with_walk_hooks(hooks(), || assert_m!(must_subtype(&fn_ty, &fn_ty, Assoc::new()), Ok(_)));
+    assert_eq!(fns_visited.get(), 2);
+
+    // The hooks are gone now:
+    assert_m!(widening_check(), Ok(_));
+    assert_eq!(fns_visited.get(), 2);
+
+    // Nested hooks both run:
+    with_walk_hooks(hooks(), || with_walk_hooks(hooks(), || assert_m!(widening_check(), Ok(_))));
+    assert_eq!(fns_visited.get(), 6);
+}
@@ -35,7 +35,8 @@ custom_derive! {
     There's probably a very elegant way to make `Beta` just another kind of `Ast`.
     Finding it might require some time in the math mines, though.
     */
-    #[derive(PartialEq, Clone, Reifiable)]
+    #[derive(PartialEq, Clone, Hash, Reifiable)]
+    #[derive(serde::Serialize, serde::Deserialize)]
     pub enum Beta {
         /// Both of these `Name`s refer to named terms in the current `Scope`
         ///  (or `ResEnv`, for `Ast`s).
@@ -286,7 +287,8 @@ pub fn env_from_beta<Mode: crate::walk_mode::WalkMode>(
 // Like `Beta`, but without type information (which gets added at the `import` stage).
 // At the moment, this seems to work better...
 custom_derive! {
-    #[derive(PartialEq, Eq, Clone, Reifiable)]
+    #[derive(PartialEq, Eq, Clone, Hash, Reifiable)]
+    #[derive(serde::Serialize, serde::Deserialize)]
     pub enum ExportBeta {
         /// Like `Basic`/`SameAs`/`Underspecified`/`Protected`, but without committing to a type
         Use(Name),
@@ -358,7 +360,7 @@ fn names_exported_by(ast: &Ast, quote_depth: i16) -> Vec<Name> {
 
     match *ast {
         Ast::Atom(n) => vec![n],
-        Ast::Node(_, ref sub_parts, ref export) => {
+        Ast::Node(_, ref sub_parts, ref export, _) => {
             if quote_depth <= 0 {
                 bound_from_export_beta(export, sub_parts, quote_depth)
             } else {
@@ -14,7 +14,7 @@ use crate::{
     ty::*,
     util::assoc::Assoc,
 };
-use std::rc::Rc; // type forms are kinda bulky
+use std::{cell::RefCell, collections::HashMap, rc::Rc}; // type forms are kinda bulky
 
 // Core forms!
 //
@@ -35,11 +35,42 @@ pub fn strip_ee(a: &Ast) -> &Ast {
 
 // lambda ==> [param: Atom  p_t: Type]*  body: Expr
 fn type_lambda(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
+    if part_types.get_rep_term(n("p_t")).contains(&get__inferred_type()) {
+        return type_lambda_inferred(part_types);
+    }
     let lambda_type: Ast = ast!({ find_type("fn") ;
          "param" => [* part_types =>("param") part_types : (, part_types.get_res(n("p_t"))? )],
          "ret" => (, part_types.get_res(n("body"))? )});
     Ok(lambda_type)
 }
+// Unannotated parameters get underdetermined types, which the body (hopefully) determines.
+fn type_lambda_inferred(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
+    let mut param_types = vec![];
+    let mut body_env = part_types.env.clone();
+    for param_parts in part_types.march_parts(&[n("param")]) {
+        let param = param_parts.get_term(n("param")).to_name();
+        let p_t = if param_parts.get_term(n("p_t")) == get__inferred_type() {
+            <crate::ty_compare::Subtype as crate::walk_mode::WalkMode>::underspecified(param)
+        } else {
+            param_parts.get_res(n("p_t"))?
+        };
+        body_env = body_env.set(param, p_t.clone());
+        param_types.push(p_t);
+    }
+
+    // This bypasses the binding in `body`, which doesn't know about the underdetermined types:
+    let body_type = crate::ast_walk::walk_at::<SynthTy>(
+        Some(Path::part(n("body")).then(PathStep::Body)),
+        strip_ee(part_types.get_term_ref(n("body"))),
+        &part_types.with_environment(body_env),
+    )?;
+
+    let lambda_type = ast!({ find_type("fn") ;
+        "param" => (,seq param_types),
+        "ret" => (, body_type)});
+    // Fill in what we learned (but if anything's still underdetermined, leave it alone):
+    Ok(crate::ty_compare::canonicalize(&lambda_type, part_types.env.clone()).unwrap_or(lambda_type))
+}
 fn eval_lambda(part_values: LazyWalkReses<Eval>) -> Result<Value, ()> {
     Ok(Function(Rc::new(Closure {
         body: strip_ee(part_values.get_term_ref(n("body"))).clone(),
@@ -53,12 +84,21 @@ fn type_apply(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
     use crate::walk_mode::WalkMode;
     let return_type = crate::ty_compare::Subtype::underspecified(n("<return_type>"));
 
+    let mut rator_type = part_types.get_res(n("rator"))?;
+    if elaborating() {
+        rator_type = instantiate_for_elaboration(rator_type, &part_types);
+    }
+    let mut rand_types = part_types.get_rep_res(n("rand"))?;
+    if inserting_coercions() {
+        rand_types = coerce_rands(&rator_type, rand_types, &part_types);
+    }
+
     // The `rator` must be a function that takes the `rand`s as arguments:
     let _ = crate::ty_compare::is_subtype(
         &ast!({ "Type" "fn" :
-            "param" => (,seq part_types.get_rep_res(n("rand"))? ),
+            "param" => (,seq rand_types),
             "ret" => (, return_type.clone() )}),
-        &part_types.get_res(n("rator"))?,
+        &rator_type,
         &part_types,
     )
     .map_err(|e| crate::util::err::sp(e, part_types.this_ast.clone()))?;
@@ -76,25 +116,157 @@ fn type_apply(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
         res.map_err(|e| crate::util::err::sp(e, part_types.this_ast.clone()))
     })
 }
-fn eval_apply(part_values: LazyWalkReses<Eval>) -> Result<Value, ()> {
-    match part_values.get_res(n("rator"))? {
-        Function(clos) => {
-            let mut new_env = clos.env.clone();
-            for (p, v) in clos.params.iter().zip(part_values.get_rep_res(n("rand"))?) {
-                new_env = new_env.set(*p, v);
+/// If `rator_type` is polymorphic, and the application doesn't say what to instantiate it at,
+///  instantiate it with underdetermined types (so `elaborate` can find out what they become).
+fn instantiate_for_elaboration(rator_type: Ast, part_types: &LazyWalkReses<SynthTy>) -> Ast {
+    use crate::walk_mode::WalkMode;
+    // (If we don't know where this application is, we can't elaborate it.)
+    let position = match crate::ast_walk::walk_position() {
+        Some(position) => position,
+        None => return rator_type,
+    };
+    let forall_type = match crate::ty_compare::canonicalize(&rator_type, part_types.env.clone()) {
+        Ok(t) => t,
+        Err(_) => return rator_type,
+    };
+    let forall_parts = match forall_type.destructure(find_type("forall_type")) {
+        Some(forall_parts) => forall_parts,
+        None => return rator_type,
+    };
+    let args: Vec<Ast> = forall_parts
+        .get_rep_leaf_or_panic(n("param"))
+        .iter()
+        .map(|p| crate::ty_compare::Subtype::underspecified(p.to_name()))
+        .collect();
+    let instantiated = match instantiate(&forall_type, &args, &part_types.this_ast) {
+        Ok(instantiated) => instantiated,
+        Err(_) => return rator_type,
+    };
+    elide_type_args(
+        position,
+        args.into_iter()
+            .map(|arg| crate::ast_walk::Clo { it: arg, env: part_types.env.clone() })
+            .collect(),
+    );
+    instantiated
+}
+
+/// Where a `rand` doesn't fit the corresponding parameter of `rator_type`,
+///  but can be coerced to it, insert the coercion and use the parameter type instead.
+fn coerce_rands(
+    rator_type: &Ast,
+    rand_types: Vec<Ast>,
+    part_types: &LazyWalkReses<SynthTy>,
+) -> Vec<Ast> {
+    use crate::ty_compare::{canonicalize, coerce, is_subtype_in, UnifCtx};
+    let position = match crate::ast_walk::walk_position() {
+        Some(position) => position,
+        None => return rand_types,
+    };
+    let fn_parts = match canonicalize(rator_type, part_types.env.clone()) {
+        Ok(rator_type) => match rator_type.destructure(find_type("fn")) {
+            Some(fn_parts) => fn_parts,
+            None => return rand_types,
+        },
+        Err(_) => return rand_types,
+    };
+    let params = fn_parts.get_rep_leaf_or_panic(n("param"));
+    if params.len() != rand_types.len() {
+        return rand_types;
+    }
+    rand_types
+        .into_iter()
+        .zip(params)
+        .enumerate()
+        .map(|(idx, (rand_type, param))| {
+            // (What's been determined so far matters, but this check shouldn't determine more.)
+            if is_subtype_in(&mut UnifCtx::snapshot(), &rand_type, param, part_types).is_ok() {
+                return rand_type;
             }
+            match coerce(&rand_type, param, part_types.env.clone()) {
+                Some(coercion) => {
+                    // (The `body` of the `coerce` gets filled in by the elaboration.)
+                    insert_coercion(
+                        position.then(PathStep::Part(n("rand"), vec![idx])),
+                        coerce_expr(&coercion, Trivial, param.clone()),
+                    );
+                    param.clone()
+                }
+                None => rand_type,
+            }
+        })
+        .collect()
+}
 
-            // TODO: this seems wrong; it discards other phase information.
-            // But would it be correct to have closures capture at all phases?
-            crate::runtime::eval::eval(&clos.body, new_env)
-        }
-        BuiltInFunction(crate::runtime::eval::BIF(f)) => Ok(f(part_values.get_rep_res(n("rand"))?)),
-        other => {
-            icp!("[type error] invoked {:#?} as if it were a function", other)
+/// An expression that applies `coercion` to `body`, producing a `t`.
+pub fn coerce_expr(coercion: &crate::ty_compare::Coercion, body: Ast, t: Ast) -> Ast {
+    let (kind, field) = coercion.to_parts();
+    match field {
+        Some(field) => ast!({"Expr" "coerce" :
+            "coercion" => (, Atom(kind)), "field" => (, Atom(field)), "body" => (, body), "t" => (, t)}),
+        None => {
+            ast!({"Expr" "coerce" : "coercion" => (, Atom(kind)), "body" => (, body), "t" => (, t)})
         }
     }
 }
 
+// let ==> name: Atom  t: Type?  rhs: Expr  body: Expr
+fn type_let(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
+    if part_types.has(n("t")) {
+        crate::ty_compare::is_subtype(
+            &part_types.get_res(n("rhs"))?,
+            &part_types.get_res(n("t"))?,
+            &part_types,
+        )
+        .map_err(|e| crate::util::err::sp(e, part_types.get_term(n("rhs"))))?;
+    }
+    part_types.get_res(n("body"))
+}
+fn eval_let(part_values: LazyWalkReses<Eval>) -> Result<Value, ()> {
+    let tail = crate::runtime::eval::in_tail_position();
+    let name = part_values.get_term(n("name")).to_name();
+    let body_env = part_values.env.set(name, part_values.get_res(n("rhs"))?);
+    // Like `let_type`, this bypasses the binding in `body` (which might be to the annotation).
+    crate::runtime::eval::eval_in_tail_position(tail, || {
+        crate::ast_walk::walk::<Eval>(
+            strip_ee(part_values.get_term_ref(n("body"))),
+            &part_values.with_environment(body_env),
+        )
+    })
+}
+
+// coerce ==> coercion: Atom  field: Atom?  body: Expr  t: Type
+fn type_coerce(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
+    let coercion = crate::ty_compare::Coercion::from_parts(
+        part_types.get_term(n("coercion")).to_name(),
+        part_types.maybe_get_term(n("field")).map(|f| f.to_name()),
+    );
+    let body_type = part_types.get_res(n("body"))?;
+    let t = part_types.get_res(n("t"))?;
+    match crate::ty_compare::coerce(&body_type, &t, part_types.env.clone()) {
+        Some(ref c) if *c == coercion => Ok(t),
+        _ => ty_err!(Mismatch(body_type, t) at part_types.get_term(n("body"))),
+    }
+}
+fn eval_coerce(part_values: LazyWalkReses<Eval>) -> Result<Value, ()> {
+    let coercion = crate::ty_compare::Coercion::from_parts(
+        part_values.get_term(n("coercion")).to_name(),
+        part_values.maybe_get_term(n("field")).map(|f| f.to_name()),
+    );
+    Ok(coercion.apply(part_values.get_res(n("body"))?))
+}
+
+fn eval_apply(part_values: LazyWalkReses<Eval>) -> Result<Value, ()> {
+    let tail = crate::runtime::eval::in_tail_position();
+    let rator = part_values.get_res(n("rator"))?;
+    let rands = part_values.get_rep_res(n("rand"))?;
+    if tail {
+        crate::runtime::eval::tail_call(rator, rands)
+    } else {
+        crate::runtime::eval::apply_function(&rator, rands)
+    }
+}
+
 // match ==> scrutinee: Expr  [p: Pat  arm: Expr]*
 fn type_match(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
     let mut res: Option<Ast> = None;
@@ -121,6 +293,29 @@ fn type_match(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
         Some(ty_res) => Ok(ty_res),
     }
 }
+// if ==> cond: Expr  then: Expr  else: Expr
+fn type_if(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
+    let cond_type = part_types.get_res(n("cond"))?;
+    crate::ty_compare::must_subtype(&cond_type, &ast!((vr "Bool")), part_types.env.clone())
+        .map_err(|e| crate::util::err::sp(e, part_types.get_term(n("cond"))))?;
+
+    crate::ty_compare::join(
+        &part_types.get_res(n("then"))?,
+        &part_types.get_res(n("else"))?,
+        part_types.env.clone(),
+    )
+    .map_err(|e| crate::util::err::sp(e, part_types.this_ast.clone()))
+}
+fn eval_if(part_values: LazyWalkReses<Eval>) -> Result<Value, ()> {
+    let tail = crate::runtime::eval::in_tail_position();
+    let branch = match part_values.get_res(n("cond"))? {
+        Enum(ref name, _) if name.is("True") => n("then"),
+        Enum(ref name, _) if name.is("False") => n("else"),
+        other => icp!("non-boolean condition {}", other),
+    };
+    crate::runtime::eval::eval_in_tail_position(tail, || part_values.get_res(branch))
+}
+
 fn eval_match(part_values: LazyWalkReses<Eval>) -> Result<Value, ()> {
     for arm_values in part_values.march_all(&[n("arm"), n("p")]) {
         // TODO: don't we need to set a context?
@@ -152,7 +347,9 @@ fn type_enum_expr(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
 
                 for (t, expected_t) in part_types.get_rep_res(n("component"))?
                         .iter().zip(component_types) {
-                    ty_exp!(t, expected_t, part_types.this_ast);
+                    // (`==` would be too strict; `enum` arms might be in a different order)
+                    crate::ty_compare::must_equal(t, expected_t, part_types.env.clone())
+                        .map_err(|e| crate::util::err::sp(e, part_types.this_ast.clone()))?;
                 }
                 return Ok(res);
             }
@@ -186,6 +383,58 @@ fn eval_struct_expr(part_values: LazyWalkReses<Eval>) -> Result<Value, ()> {
     Ok(Struct(res))
 }
 
+// struct_new ==> [component: Expr]*  t: Type
+// The components go with `t`'s component names, in the order that `t` lists them.
+// Types aren't available at runtime, so `t` must be written out as a `struct` type
+//  (a name for one won't do).
+fn type_struct_new(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
+    let t = part_types.get_term(n("t"));
+    let names = match written_component_names(&t) {
+        Some(names) => names,
+        None => ty_err!(UnableToDestructure(t.clone(), n("struct")) at t),
+    };
+    let res: Ast = part_types.get_res(n("t"))?;
+    expect_ty_node!( (res ; find_type("struct") ; &part_types.this_ast)
+        struct_type_parts;
+        {
+            let mut expected_types = Assoc::new();
+            for component_part in struct_type_parts.march_all(&[n("component_name"), n("component")]) {
+                expected_types = expected_types.set(
+                    component_part.get_leaf_or_panic(&n("component_name")).to_name(),
+                    component_part.get_leaf_or_panic(&n("component")).clone());
+            }
+
+            let component_types = part_types.get_rep_res(n("component"))?;
+            if component_types.len() != names.len() {
+                ty_err!(LengthMismatch(component_types, names.len()) at part_types.this_ast);
+            }
+            for (t, name) in component_types.iter().zip(names.iter()) {
+                let expected_t = expected_types.find_or_panic(&name.to_name());
+                crate::ty_compare::must_equal(t, expected_t, part_types.env.clone())
+                    .map_err(|e| crate::util::err::sp(e, part_types.this_ast.clone()))?;
+            }
+
+            Ok(res)
+        }
+    )
+}
+fn eval_struct_new(part_values: LazyWalkReses<Eval>) -> Result<Value, ()> {
+    let names = match written_component_names(&part_values.get_term(n("t"))) {
+        Some(names) => names,
+        None => icp!("`struct_new` with a non-`struct` type got past the typechecker"),
+    };
+    let mut res = Assoc::new();
+    for (name, value) in names.iter().zip(part_values.get_rep_res(n("component"))?) {
+        res = res.set(name.to_name(), value);
+    }
+    Ok(Struct(res))
+}
+fn written_component_names(t: &Ast) -> Option<Vec<Ast>> {
+    t.destructure(find_type("struct")).map(|parts| {
+        parts.get_rep_leaf_or_panic(n("component_name")).into_iter().cloned().collect()
+    })
+}
+
 // tuple_expr ==> [component: Expr]*
 fn type_tuple_expr(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
     Ok(uty!({tuple : [, part_types.get_rep_res(n("component"))? ]}))
@@ -196,6 +445,41 @@ fn eval_tuple_expr(part_values: LazyWalkReses<Eval>) -> Result<Value, ()> {
     ))
 }
 
+// seq_literal ==> elt: Expr*  (t: Type)?
+fn type_seq_literal(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
+    let elt_types = part_types.get_rep_res(n("elt"))?;
+    if part_types.has(n("t")) {
+        let seq_type = part_types.get_res(n("t"))?;
+        let canonical = crate::ty_compare::canonicalize(&seq_type, part_types.env.clone())
+            .map_err(|e| crate::util::err::sp(e, part_types.this_ast.clone()))?;
+        // The annotation is of the whole sequence, so `Int` is wrong, even with no elements:
+        if canonical.destructure(find_type("sequence")).is_none() {
+            ty_err!(Mismatch(seq_type.clone(), uty!({sequence : (, seq_type)}))
+                at part_types.get_term(n("t")))
+        }
+        for (elt_type, elt) in elt_types.iter().zip(part_types.get_rep_term(n("elt"))) {
+            crate::ty_compare::must_subtype(
+                &uty!({sequence : (, elt_type.clone())}),
+                &seq_type,
+                part_types.env.clone(),
+            )
+            .map_err(|e| crate::util::err::sp(e, elt))?;
+        }
+        return Ok(seq_type);
+    }
+
+    let mut elt_types = elt_types.into_iter();
+    let mut joined = match elt_types.next() {
+        Some(elt_type) => elt_type,
+        None => ty_err!(AnnotationRequired(()) at part_types.this_ast),
+    };
+    for elt_type in elt_types {
+        joined = crate::ty_compare::join(&joined, &elt_type, part_types.env.clone())
+            .map_err(|e| crate::util::err::sp(e, part_types.this_ast.clone()))?;
+    }
+    Ok(uty!({sequence : (, joined)}))
+}
+
 // unfold ==> body: Expr
 fn type_unfold(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
     // TODO: this "evaluates" types twice; once in `get_res` and once in `synth_type`
@@ -244,6 +528,66 @@ fn type__forall_expr(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
     }))
 }
 
+// type_apply_expr ==> rator: Expr  [arg: Type]*
+fn type_type_apply_expr(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
+    instantiate(
+        &part_types.get_res(n("rator"))?,
+        &part_types.get_rep_res(n("arg"))?,
+        &part_types.this_ast,
+    )
+}
+
+// fix ==> body: Expr
+// `fix : ∀T. [[T -> T] -> T]`, so instantiate `T` with whatever makes `body` fit.
+fn type_fix(part_types: LazyWalkReses<SynthTy>) -> TypeResult {
+    use crate::walk_mode::WalkMode;
+    let fixpoint_type = crate::ty_compare::Subtype::underspecified(n("<fixpoint_type>"));
+
+    let _ = crate::ty_compare::is_subtype(
+        &ast!({ "Type" "fn" :
+            "param" => [(, fixpoint_type.clone())],
+            "ret" => (, fixpoint_type.clone())}),
+        &part_types.get_res(n("body"))?,
+        &part_types,
+    )
+    .map_err(|e| crate::util::err::sp(e, part_types.this_ast.clone()))?;
+
+    crate::ty_compare::unification.with(|unif| {
+        let res = crate::ty_compare::resolve(
+            crate::ast_walk::Clo { it: fixpoint_type, env: part_types.env.clone() },
+            &unif.borrow(),
+        );
+        crate::ty_compare::canonicalize(&res.it, res.env)
+            .map_err(|e| crate::util::err::sp(e, part_types.this_ast.clone()))
+    })
+}
+
+fn eval_fix(part_values: LazyWalkReses<Eval>) -> Result<Value, ()> {
+    fixpoint(part_values.get_res(n("body"))?)
+}
+
+/// Apply `f` to a function that behaves like `f`'s fixpoint.
+/// (The fixpoint has to be a function, or else this would loop forever.)
+fn fixpoint(f: Value) -> Result<Value, ()> {
+    let f_again = f.clone();
+    // `again` is `.[arg_0 ⋯ arg_n . (fix[f] arg_0 ⋯ arg_n)].`, so errors from taking the fixpoint
+    //  anew get returned by the call. Its arity isn't known until it's called.
+    // (So that recursive calls in tail position don't grow the stack, this is a tail call.)
+    let again = BuiltInFunction(BIF(Rc::new(move |args: Vec<Value>| {
+        let params: Vec<Name> = (0..args.len()).map(|i| n(&format!("arg_{}", i))).collect();
+        let rands: Vec<Ast> = params.iter().map(|p| VariableReference(*p)).collect();
+        let reapply = Function(Rc::new(Closure {
+            body: ast!({"Expr" "apply" :
+                "rator" => {"Expr" "fix" : "body" => (vr "f")},
+                "rand" => (,seq rands)}),
+            params,
+            env: assoc_n!("f" => f_again.clone()),
+        }));
+        tail_call(reapply, args)
+    })));
+    apply_function(&f, vec![again])
+}
+
 // TODO: pull out all the other form implementations into freestanding functions.
 
 /// This is the Unseemly language.
@@ -257,8 +601,10 @@ pub fn make_core_syn_env() -> SynEnv {
     let main_expr_forms = forms_to_form_pat![
         typed_form!("lambda",
             (delim ".[", "[", [ // TODO: add comma separators to the syntax!
-                            (star [(named "param", atom), (lit ":"),
-                                    (named "p_t", (call "Type"))]), (lit "."),
+                            (star [(named "param", atom),
+                                   (biased [(lit ":"), (named "p_t", (call "Type"))],
+                                           (named "p_t", (anyways (, get__inferred_type()))))]),
+                            (lit "."),
                 (named "body",
                     (import [* ["param" : "p_t"]], (call "Expr")))]),
             cust_rc_box!(type_lambda),
@@ -268,6 +614,32 @@ pub fn make_core_syn_env() -> SynEnv {
                 (star (named "rand", (call "Expr")))]),
             cust_rc_box!(type_apply),
             cust_rc_box!(eval_apply)),
+        // The annotation is optional; without it, `name` gets the type of `rhs`.
+        typed_form!("let",
+            [(lit "let"), (named "name", atom),
+             (alt [(lit ":"), (named "t", (call "Type")), (lit "="), (named "rhs", (call "Expr")),
+                   (lit "in"), (named "body", (import ["name" : "t"], (call "Expr")))],
+                  [(lit "="), (named "rhs", (call "Expr")),
+                   (lit "in"), (named "body", (import ["name" = "rhs"], (call "Expr")))])],
+            cust_rc_box!(type_let),
+            cust_rc_box!(eval_let)),
+        // Inserted by `synth_type_inserting_coercions`; see `ty_compare::Coercion`.
+        typed_form!("coerce",
+            (delim "coerce[", "[",
+                [(named "coercion", atom), (alt [], [(lit "."), (named "field", atom)]),
+                 (named "body", (call "Expr")), (lit ":"), (named "t", (call "Type"))]),
+            cust_rc_box!(type_coerce),
+            cust_rc_box!(eval_coerce)),
+        // A placeholder for an expression that hasn't been written yet.
+        // `synth_type_with_holes` reports the type that's needed.
+        typed_form!("hole", (lit "?"),
+            cust_rc_box!(move |part_types| {
+                use crate::walk_mode::WalkMode;
+                let expected = crate::ty_compare::Subtype::underspecified(n("hole"));
+                found_hole(HoleInfo { expected: expected.clone(), env: part_types.env.clone() });
+                Ok(expected)
+            }),
+            cust_rc_box!(move |_| Err(()))),
         typed_form!("match",
             [(lit "match"), (named "scrutinee", (call "Expr")),
              (delim "{", "{",
@@ -276,6 +648,15 @@ pub fn make_core_syn_env() -> SynEnv {
             cust_rc_box!(type_match),
             cust_rc_box!(eval_match)
         ),
+        // The branches need not have the same type, as long as one type is more general.
+        typed_form!("if",
+            [(lit "if"), (named "cond", (call "Expr")),
+             (delim "{", "{", (named "then", (call "Expr"))),
+             (lit "else"),
+             (delim "{", "{", (named "else", (call "Expr")))],
+            cust_rc_box!(type_if),
+            cust_rc_box!(eval_if)
+        ),
         // Note that we inconveniently require the user to specify the type.
         // "real" languages infer the type from the (required-to-be-unique)
         // component name.
@@ -291,12 +672,27 @@ pub fn make_core_syn_env() -> SynEnv {
                     (named "component", (call "Expr"))])),
             cust_rc_box!(type_struct_expr),
             cust_rc_box!(eval_struct_expr)),
+        // Like `struct_expr`, but positional (so the type must be specified).
+        typed_form!("struct_new",
+            [(delim "*[", "[", (star (named "component", (call "Expr")))),
+             (lit ":"), (named "t", (call "Type"))],
+            cust_rc_box!(type_struct_new),
+            cust_rc_box!(eval_struct_new)),
         typed_form!(
             "tuple_expr",
             (delim "**[", "[", (star (named "component", (call "Expr")))),
             cust_rc_box!(type_tuple_expr),
             cust_rc_box!(eval_tuple_expr)
         ),
+        // The element type is the join of the elements' types, unless annotated.
+        typed_form!("seq_literal",
+            [(delim "seq[", "[", (star (named "elt", (call "Expr")))),
+             (alt [], [(lit ":"), (named "t", (call "Type"))])],
+            cust_rc_box!(type_seq_literal),
+            cust_rc_box!(move |part_values| {
+                Ok(Sequence(part_values.get_rep_res(n("elt"))?.into_iter().map(Rc::new).collect()))
+            })
+        ),
         // e.g.
         // let_type
         //   pair = mu lhs rhs. {l: lhs, r: rhs}
@@ -336,6 +732,20 @@ pub fn make_core_syn_env() -> SynEnv {
              (named "body", (import [* [forall "param"]], (call "Expr")))],
             cust_rc_box!(type__forall_expr),
             Body(n("body"))),
+        // Explicit type application, for when inference needs help (e.g. `identity[Int]`).
+        // TODO: like `type_apply`, this tokenizes ad-hoc.
+        typed_form!("type_apply_expr",
+            [(named "rator", (call "Expr")),
+             (call "DefaultSeparator"), (scan r"(\[)"),
+             (star (named "arg", (call "Type"))),
+             (call "DefaultSeparator"), (scan r"(\])")],
+            cust_rc_box!(type_type_apply_expr),
+            Body(n("rator"))),
+        // Recursion: `fix[f]` is `f` applied to (something that behaves like) `fix[f]`.
+        typed_form!("fix",
+            (delim "fix[", "[", (named "body", (call "Expr"))),
+            cust_rc_box!(type_fix),
+            cust_rc_box!(eval_fix)),
         crate::core_qq_forms::quote(/* positive= */ true),
         crate::core_macro_forms::extend_syntax()
     ];
@@ -534,38 +944,39 @@ pub fn make_core_syn_env() -> SynEnv {
     .set_assoc(&cmf) // throw in the types and macros!
 }
 
-/// Mostly for testing purposes, this looks up forms by name.
-/// In the "real world", programmers look up forms by syntax, using a parser.
-pub fn find_form(se: &SynEnv, nt: &str, form_name: &str) -> Rc<Form> {
-    fn find_form_rec(f: &FormPat, form_name: &str) -> Option<Rc<Form>> {
-        match *f {
-            Scope(ref f, _) => {
-                if f.name.is(form_name) {
-                    Some(f.clone())
-                } else {
-                    None
-                }
-            }
-            Alt(ref vf) => {
-                for f in vf {
-                    let res = find_form_rec(f, form_name);
-                    if res.is_some() {
-                        return res;
-                    }
-                }
+fn find_form_rec(f: &FormPat, form_name: &str) -> Option<Rc<Form>> {
+    match *f {
+        Scope(ref f, _) => {
+            if f.name.is(form_name) {
+                Some(f.clone())
+            } else {
                 None
             }
-            Biased(ref lhs, ref rhs) => {
-                let l_res = find_form_rec(lhs, form_name);
-                if l_res.is_some() {
-                    l_res
-                } else {
-                    find_form_rec(rhs, form_name)
+        }
+        Alt(ref vf) => {
+            for f in vf {
+                let res = find_form_rec(f, form_name);
+                if res.is_some() {
+                    return res;
                 }
             }
-            _ => None,
+            None
+        }
+        Biased(ref lhs, ref rhs) => {
+            let l_res = find_form_rec(lhs, form_name);
+            if l_res.is_some() {
+                l_res
+            } else {
+                find_form_rec(rhs, form_name)
+            }
         }
+        _ => None,
     }
+}
+
+/// Mostly for testing purposes, this looks up forms by name.
+/// In the "real world", programmers look up forms by syntax, using a parser.
+pub fn find_form(se: &SynEnv, nt: &str, form_name: &str) -> Rc<Form> {
     let pat = se.find_or_panic(&n(nt));
 
     find_form_rec(pat, form_name)
@@ -603,6 +1014,23 @@ pub fn add_form_at_the_alt(outer: Rc<FormPat>, inner: &FormPat) -> Option<FormPa
 
 thread_local! {
     pub static core_forms: SynEnv = make_core_syn_env();
+
+    // Finding a form means searching a grammar, so remember what `find` finds:
+    static found_core_forms: RefCell<HashMap<(Name, Name), Rc<Form>>> = RefCell::new(HashMap::new());
+    // How many times `find` was called, and how many times it actually had to search.
+    // (Only counted in tests, since `find` is called very often.)
+    #[cfg(test)]
+    pub static core_form_lookups: RefCell<(usize, usize)> = RefCell::new((0, 0));
+
+    // Forms that embedders have added with `register_core_form`:
+    static registered_core_forms: RefCell<HashMap<(Name, Name), Rc<Form>>> =
+        RefCell::new(HashMap::new());
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RegistrationError {
+    /// There's already a core form with this nonterminal and name.
+    AlreadyDefined(Name, Name),
 }
 
 pub fn outermost_form() -> FormPat {
@@ -610,13 +1038,55 @@ pub fn outermost_form() -> FormPat {
     form_pat!((pick [(named "program", (call "Expr")), (call "DefaultSeparator")], "program"))
 }
 
-pub fn find(nt: &str, name: &str) -> Rc<Form> { core_forms.with(|cf| find_form(cf, nt, name)) }
+pub fn find(nt: &str, name: &str) -> Rc<Form> {
+    #[cfg(test)]
+    core_form_lookups.with(|cfl| cfl.borrow_mut().0 += 1);
+    let key = (n(nt), n(name));
+    if let Some(form) = found_core_forms.with(|fcf| fcf.borrow().get(&key).cloned()) {
+        return form;
+    }
+    #[cfg(test)]
+    core_form_lookups.with(|cfl| cfl.borrow_mut().1 += 1);
+    let form = match registered_core_forms.with(|rcf| rcf.borrow().get(&key).cloned()) {
+        Some(form) => form,
+        None => core_forms.with(|cf| find_form(cf, nt, name)),
+    };
+    found_core_forms.with(|fcf| fcf.borrow_mut().insert(key, form.clone()));
+    form
+}
+
+/// Make `form` available to `find` as `name` in `nt`, for embedders with their own core forms.
+/// (This doesn't add it to the grammar; use `insert_form_pat` for that.)
+/// Existing core forms can't be redefined.
+pub fn register_core_form(nt: &str, name: &str, form: Rc<Form>) -> Result<(), RegistrationError> {
+    let key = (n(nt), n(name));
+    let built_in = core_forms.with(|cf| cf.find(&key.0).and_then(|pat| find_form_rec(pat, name)));
+    registered_core_forms.with(|rcf| {
+        let mut rcf = rcf.borrow_mut();
+        if built_in.is_some() || rcf.contains_key(&key) {
+            return Err(RegistrationError::AlreadyDefined(key.0, key.1));
+        }
+        rcf.insert(key, form);
+        Ok(())
+    })
+}
 
 // Deprecated; use `::core_forms::find` instead (keep it qualified!)
 pub fn find_core_form(nt: &str, name: &str) -> Rc<Form> { find(nt, name) }
 
 pub fn get_core_forms() -> SynEnv { core_forms.with(|cf| cf.clone()) }
 
+/// The core nonterminal that `form` belongs to, if any.
+pub fn find_nt(form: &Rc<Form>) -> Option<Name> {
+    core_forms.with(|cf| {
+        cf.iter_pairs()
+            .find(|(_, pat)| {
+                find_form_rec(pat, &form.name.sp()).is_some_and(|f| Rc::ptr_eq(&f, form))
+            })
+            .map(|(nt, _)| *nt)
+    })
+}
+
 #[test]
 fn form_grammar() {
     let cse = make_core_syn_env();
@@ -660,6 +1130,115 @@ fn form_type() {
     );
 }
 
+#[test]
+fn type_lambda_with_inference() {
+    let id_lambda = ast!({"Expr" "lambda" :
+        "param" => [@"p" "x"],
+        "p_t" => [@"p" (, get__inferred_type())],
+        "body" => (import [* ["param" : "p_t"]] (vr "x"))});
+
+    // ∀T. T ⇒ T
+    let id_ty = crate::ty_compare::generalize(
+        &synth_type(&id_lambda, Assoc::new()).unwrap(),
+        &Assoc::new(),
+    );
+    let forall_parts = id_ty.destructure(find_type("forall_type")).unwrap();
+    let params = forall_parts.get_rep_leaf_or_panic(n("param"));
+    assert_eq!(params.len(), 1);
+    let t = VariableReference(params[0].to_name());
+    assert_eq!(
+        strip_ee(forall_parts.get_leaf_or_panic(&n("body"))),
+        &ast!({"Type" "fn" : "param" => [(, t.clone())], "ret" => (, t)})
+    );
+
+    // The parameter type is determined by how it's used:
+    let ty_env = assoc_n!("nat_to_int" => uty!({fn : [{Nat :}] {Int :}}));
+    assert_eq!(
+        synth_type(
+            &ast!({"Expr" "lambda" :
+                "param" => [@"p" "x"],
+                "p_t" => [@"p" (, get__inferred_type())],
+                "body" => (import [* ["param" : "p_t"]]
+                    {"Expr" "apply" : "rator" => (vr "nat_to_int"), "rand" => [(vr "x")]})}),
+            ty_env
+        ),
+        Ok(uty!({fn : [{Nat :}] {Int :}}))
+    );
+}
+
+#[test]
+fn type_if_joins_branches() {
+    use crate::{
+        ty::synth_type_with,
+        ty_compare::{NumericTower, SubtypePolicy},
+    };
+
+    let ty_env = assoc_n!(
+        "Bool" => ast!({"Type" "enum" :
+            "name" => [@"c" "True", "False"], "component" => [@"c" [], []]}),
+        "b" => ast!((vr "Bool")),
+        "i" => uty!({Int :}),
+        "j" => uty!({Int :}),
+        "n" => uty!({Nat :}),
+        "never" => ast!({"Type" "enum" : "name" => [@"c"], "component" => [@"c"]}));
+    fn if_expr(cond: &str, then_e: &str, else_e: &str) -> Ast {
+        ast!({"Expr" "if" : "cond" => (vr cond), "then" => (vr then_e), "else" => (vr else_e)})
+    }
+
+    assert_eq!(synth_type(&if_expr("b", "i", "j"), ty_env.clone()), Ok(uty!({Int :})));
+
+    // The branches get joined:
+    assert_m!(synth_type(&if_expr("b", "n", "i"), ty_env.clone()), ty_err_p!(Mismatch(_, _)));
+    let widening = SubtypePolicy { numeric_tower: NumericTower::Widening, ..Default::default() };
+    let widening_synth = |e: &Ast| synth_type_with(&widening, e, ty_env.clone());
+    assert_eq!(widening_synth(&if_expr("b", "n", "i")), Ok(uty!({Int :})));
+    assert_eq!(widening_synth(&if_expr("b", "i", "n")), Ok(uty!({Int :})));
+
+    // A branch that can't produce a value doesn't constrain the other:
+    assert_eq!(synth_type(&if_expr("b", "never", "n"), ty_env.clone()), Ok(uty!({Nat :})));
+
+    assert_m!(synth_type(&if_expr("i", "i", "j"), ty_env.clone()), ty_err_p!(Mismatch(_, _)));
+}
+
+#[test]
+fn type_seq_literals() {
+    use crate::{
+        ty::synth_type_with,
+        ty_compare::{must_subtype_with, NumericTower, SubtypePolicy},
+    };
+
+    let ty_env = assoc_n!("i" => uty!({Int :}), "n" => uty!({Nat :}));
+    let seq_int = uty!({sequence : {Int :}});
+    let seq_nat = uty!({sequence : {Nat :}});
+
+    fn annotated(elts: Vec<Ast>, t: &Ast) -> Ast {
+        ast!({"Expr" "seq_literal" : "elt" => (,seq elts), "t" => (, t.clone())})
+    }
+
+    // An empty literal needs an annotation:
+    assert_eq!(synth_type(&annotated(vec![], &seq_int), ty_env.clone()), Ok(seq_int.clone()));
+    let empty = ast!({"Expr" "seq_literal" : "elt" => []});
+    assert_m!(synth_type(&empty, ty_env.clone()), ty_err_p!(AnnotationRequired(_)));
+    // ...and it has to be a sequence type:
+    let empty_as_int = annotated(vec![], &uty!({Int :}));
+    assert_m!(synth_type(&empty_as_int, ty_env.clone()), ty_err_p!(Mismatch(_, _)));
+
+    let mixed = ast!({"Expr" "seq_literal" : "elt" => [(vr "n"), (vr "i"), (vr "n")]});
+    assert_m!(synth_type(&mixed, ty_env.clone()), ty_err_p!(Mismatch(_, _)));
+    let widening = SubtypePolicy { numeric_tower: NumericTower::Widening, ..Default::default() };
+    assert_eq!(synth_type_with(&widening, &mixed, ty_env.clone()), Ok(seq_int.clone()));
+
+    // Subtyping is covariant:
+    assert_m!(must_subtype_with(&widening, &seq_nat, &seq_int, ty_env.clone()), Ok(_));
+    assert_m!(must_subtype_with(&widening, &seq_int, &seq_nat, ty_env.clone()), Err(_));
+
+    // Annotations are checked:
+    let n_as_int = annotated(vec![ast!((vr "n"))], &seq_int);
+    assert_eq!(synth_type_with(&widening, &n_as_int, ty_env.clone()), Ok(seq_int.clone()));
+    let i_as_nat = annotated(vec![ast!((vr "i"))], &seq_nat);
+    assert_m!(synth_type_with(&widening, &i_as_nat, ty_env.clone()), ty_err_p!(Mismatch(_, _)));
+}
+
 #[test]
 fn type_apply_with_subtype() {
     // Application can perform subtyping
@@ -676,6 +1255,90 @@ fn type_apply_with_subtype() {
     assert_eq!(synth_type(&u!({apply : forall_t_t_to_t [N]}), ty_env.clone()), Ok(nat_ty.clone()));
 }
 
+#[test]
+fn type_apply_with_coercion() {
+    let float_ty = ast!({ "Type" "Float" : });
+    let ty_env = assoc_n!("n" => uty!({Nat :}));
+    let float_to_float = u!({lambda : [x {Type Float :}] x});
+    let apply =
+        ast!({"Expr" "apply" : "rator" => (, float_to_float.clone()), "rand" => [(vr "n")]});
+
+    // A `Nat` is not a `Float`...
+    assert_m!(synth_type(&apply, ty_env.clone()), Err(_));
+
+    // ...but it can be coerced into one:
+    let coerced_n =
+        coerce_expr(&crate::ty_compare::Coercion::Widen, ast!((vr "n")), float_ty.clone());
+    let coerced_apply = ast!({"Expr" "apply" :
+        "rator" => (, float_to_float), "rand" => [(, coerced_n)]});
+    assert_eq!(
+        synth_type_inserting_coercions(&apply, ty_env.clone()),
+        Ok((float_ty.clone(), coerced_apply.clone()))
+    );
+    assert_eq!(synth_type(&coerced_apply, ty_env.clone()), Ok(float_ty));
+    assert_eq!(eval(&coerced_apply, assoc_n!("n" => val!(i 5))), Ok(val!(i 5)));
+
+    // No coercion is inserted if none is needed:
+    let ty_env = ty_env.set(n("n"), uty!({Float :}));
+    assert_eq!(
+        synth_type_inserting_coercions(&apply, ty_env),
+        Ok((ast!({ "Type" "Float" : }), apply))
+    );
+}
+
+#[test]
+fn let_typing() {
+    let ty_env = assoc_n!("n" => uty!({Nat :}), "f" => uty!({Float :}));
+    let annotated_let = |t: Ast, rhs: &str| {
+        ast!({"Expr" "let" : "name" => "x", "t" => (, t), "rhs" => (vr rhs),
+            "body" => (import ["name" : "t"] (vr "x"))})
+    };
+    let inferred_let = |rhs: &str, body: Ast| {
+        ast!({"Expr" "let" : "name" => "x", "rhs" => (vr rhs),
+            "body" => (import ["name" = "rhs"] (, body))})
+    };
+
+    assert_eq!(
+        synth_type(&annotated_let(uty!({Nat :}), "n"), ty_env.clone()),
+        Ok(ast!({"Type" "Nat" :}))
+    );
+    assert_m!(
+        synth_type(&annotated_let(uty!({Int :}), "n"), ty_env.clone()),
+        ty_err_p!(Mismatch(_, _))
+    );
+
+    assert_eq!(synth_type(&inferred_let("f", ast!((vr "x"))), ty_env.clone()), Ok(uty!({Float :})));
+    // Shadowing:
+    assert_eq!(
+        synth_type(&inferred_let("n", inferred_let("f", ast!((vr "x")))), ty_env.clone()),
+        Ok(uty!({Float :}))
+    );
+    match synth_type(&inferred_let("f", annotated_let(uty!({Nat :}), "x")), ty_env.clone()) {
+        ty_err_p!(Mismatch(got, expd)) => {
+            assert_eq!((got, expd), (uty!({Float :}), uty!({Nat :})))
+        }
+        res => panic!("{:?}", res),
+    }
+
+    assert_eq!(eval(&inferred_let("n", ast!((vr "x"))), assoc_n!("n" => val!(i 5))), Ok(val!(i 5)));
+}
+
+#[test]
+fn typed_holes() {
+    let ty_env = assoc_n!("n" => uty!({Nat :}));
+    let identity = u!({lambda : [x {Type Int :}] x});
+    let apply = ast!({"Expr" "apply" : "rator" => (, identity), "rand" => [{"Expr" "hole" :}]});
+
+    let (res_ty, holes) = synth_type_with_holes(&apply, ty_env.clone()).unwrap();
+    assert_eq!(res_ty, ast!({ "Type" "Int" : }));
+    assert_eq!(holes.len(), 1);
+    assert_eq!(holes[0].expected, ast!({ "Type" "Int" : }));
+    assert_eq!(holes[0].env, ty_env);
+
+    // Holes are found even when not being reported:
+    assert_eq!(synth_type(&apply, ty_env), Ok(ast!({ "Type" "Int" : })));
+}
+
 #[test]
 fn form_eval() {
     use num::bigint::ToBigInt;
@@ -1039,6 +1702,224 @@ fn use__let_type() {
     );
 }
 
+#[test]
+fn explicit_type_application() {
+    let identity_ty = ast!({ "Type" "forall_type" : "param" => ["T"],
+        "body" => (import [* [forall "param"]] { "Type" "fn" :
+            "param" => [(vr "T")], "ret" => (vr "T")})});
+    let env = assoc_n!("identity" => identity_ty, "three" => Ast::int_ty());
+    let tapp = |rator: &str, args: Vec<Ast>| {
+        let rator = ast!((vr rator));
+        ast!({"Expr" "type_apply_expr" : "rator" => (, rator), "arg" => (,seq args)})
+    };
+
+    assert_eq!(
+        synth_type(&tapp("identity", vec![Ast::int_ty()]), env.clone()),
+        Ok(Ast::fn_ty(vec![Ast::int_ty()], Ast::int_ty()))
+    );
+    assert_m!(
+        synth_type(&tapp("three", vec![Ast::int_ty()]), env.clone()),
+        ty_err_p!(UnableToDestructure(_, _))
+    );
+    assert_m!(
+        synth_type(&tapp("identity", vec![Ast::int_ty(), Ast::nat_ty()]), env.clone()),
+        ty_err_p!(LengthMismatch(_, 1))
+    );
+
+    // It doesn't do anything at runtime:
+    assert_eq!(
+        crate::runtime::eval::eval(
+            &tapp("three", vec![]),
+            crate::runtime::core_values::core_values()
+        ),
+        Ok(val!(i 3))
+    );
+}
+
+#[test]
+fn fix_typing() {
+    let lambda = |param: &str, p_t: Ast, body: Ast| {
+        ast!({"Expr" "lambda" : "param" => [@"p" (, Atom(n(param)))], "p_t" => [@"p" (, p_t)],
+            "body" => (import [* ["param" : "p_t"]] (, body))})
+    };
+    let int_to_int = Ast::fn_ty(vec![Ast::int_ty()], Ast::int_ty());
+    let env = assoc_n!("zero" => Ast::int_ty());
+
+    // The type of the fixpoint is the type of `again`:
+    let fix = |body: Ast| ast!({"Expr" "fix" : "body" => (, body)});
+    let recursive = lambda("again", int_to_int.clone(), lambda("n", Ast::int_ty(), ast!((vr "n"))));
+    assert_eq!(synth_type(&fix(recursive), env.clone()), Ok(int_to_int.clone()));
+
+    // ...which has to be the same as the type of the result:
+    let mismatched = lambda("again", int_to_int.clone(), ast!((vr "zero")));
+    assert_m!(synth_type(&fix(mismatched), env.clone()), Err(_));
+}
+
+#[test]
+fn tail_calls_in_constant_stack() {
+    let lambda = |param: &str, p_t: Ast, body: Ast| {
+        ast!({"Expr" "lambda" : "param" => [@"p" (, Atom(n(param)))], "p_t" => [@"p" (, p_t)],
+            "body" => (import [* ["param" : "p_t"]] (, body))})
+    };
+    let apply =
+        |rator: Ast, rand: Ast| ast!({"Expr" "apply" : "rator" => (, rator), "rand" => [(, rand)]});
+    let int_to_int = Ast::fn_ty(vec![Ast::int_ty()], Ast::int_ty());
+
+    // fix .[again : Int -> Int . .[n : Int . if (zero? n) { n } else { (again (minus n one)) }]].
+    let count_down = ast!({"Expr" "fix" : "body" => (, lambda("again", int_to_int.clone(),
+        lambda("n", Ast::int_ty(), ast!({"Expr" "if" :
+            "cond" => (, apply(ast!((vr "zero?")), ast!((vr "n")))),
+            "then" => (vr "n"),
+            "else" => (, apply(ast!((vr "again")), ast!({"Expr" "apply" :
+                "rator" => (vr "minus"), "rand" => [(vr "n"), (vr "one")]})))}))))});
+
+    // (Far more calls than fit on the stack, if each one took up some of it:
+    //  in a test thread, the non-tail version of this overflows before 1000 calls.
+    //  A million calls would make the point no better, and take minutes in a debug build.)
+    let env = crate::runtime::core_values::core_values().set(n("a_lot"), val!(i 30000));
+    assert_eq!(eval(&apply(count_down, ast!((vr "a_lot"))), env.clone()), Ok(val!(i 0)));
+
+    // Errors come back as errors, even from the recursive calls:
+    let hole = ast!({"Expr" "hole" :});
+    let broken = ast!({"Expr" "fix" : "body" => (, lambda("again", int_to_int.clone(), hole))});
+    assert_eq!(eval(&broken, env.clone()), Err(()));
+    let broken_later = ast!({"Expr" "fix" : "body" => (, lambda("again", int_to_int.clone(),
+        lambda("n", Ast::int_ty(), ast!({"Expr" "if" :
+            "cond" => (, apply(ast!((vr "zero?")), ast!((vr "n")))),
+            "then" => {"Expr" "hole" :},
+            "else" => (, apply(ast!((vr "again")), ast!({"Expr" "apply" :
+                "rator" => (vr "minus"), "rand" => [(vr "n"), (vr "one")]})))}))))});
+    assert_eq!(eval(&apply(broken_later, ast!((vr "one"))), env), Err(()));
+}
+
+#[test]
+fn register_new_core_forms() {
+    let my_type = crate::core_type_forms::type_defn("my_type", form_pat!((lit "my_type")));
+    assert_eq!(register_core_form("Type", "my_type", my_type.clone()), Ok(()));
+    assert!(Rc::ptr_eq(&find_core_form("Type", "my_type"), &my_type));
+
+    // Neither built-in nor registered forms can be redefined:
+    assert_eq!(
+        register_core_form("Type", "type_apply", my_type.clone()),
+        Err(RegistrationError::AlreadyDefined(n("Type"), n("type_apply")))
+    );
+    assert_eq!(
+        register_core_form("Type", "my_type", my_type),
+        Err(RegistrationError::AlreadyDefined(n("Type"), n("my_type")))
+    );
+    assert_eq!(find_core_form("Type", "type_apply").name, n("type_apply"));
+}
+
+#[test]
+fn coercions_go_where_they_are_needed() {
+    let float_ty = uty!({Float :});
+    let nat_to_nat = Ast::fn_ty(vec![uty!({Nat :})], uty!({Nat :}));
+    let f2_ty = Ast::fn_ty(vec![float_ty.clone(), uty!({Nat :})], float_ty.clone());
+    let ty_env = assoc_n!("n" => uty!({Nat :}), "nat_to_nat" => nat_to_nat, "f2" => f2_ty);
+    let widen = |e: Ast| coerce_expr(&crate::ty_compare::Coercion::Widen, e, float_ty.clone());
+
+    // Only the `n` that needs to be a `Float` gets coerced:
+    let apply = u!({apply : f2 [n ; {apply : nat_to_nat [n]}]});
+    let coerced_apply = ast!({"Expr" "apply" : "rator" => (vr "f2"),
+        "rand" => [(, widen(ast!((vr "n")))), (, u!({apply : nat_to_nat [n]}))]});
+    assert_eq!(
+        synth_type_inserting_coercions(&apply, ty_env.clone()),
+        Ok((float_ty.clone(), coerced_apply.clone()))
+    );
+    assert_eq!(synth_type(&coerced_apply, ty_env.clone()), Ok(float_ty.clone()));
+
+    // Coercions under binders get inserted, too:
+    let lambda = u!({lambda : [n {Type Nat :}] {apply : {lambda : [x {Type Float :}] x} [n]}});
+    let (lambda_ty, coerced_lambda) =
+        synth_type_inserting_coercions(&lambda, Assoc::new()).unwrap();
+    assert_eq!(lambda_ty, Ast::fn_ty(vec![uty!({Nat :})], float_ty.clone()));
+    assert_ne!(coerced_lambda, lambda);
+    assert_eq!(synth_type(&coerced_lambda, Assoc::new()), Ok(lambda_ty));
+}
+
+#[test]
+fn elaborate_type_application() {
+    let identity_ty = ast!({ "Type" "forall_type" : "param" => ["T"],
+        "body" => (import [* [forall "param"]] { "Type" "fn" :
+            "param" => [(vr "T")], "ret" => (vr "T")})});
+    let env = assoc_n!("identity" => identity_ty, "three" => Ast::int_ty());
+    let apply =
+        |rator: Ast| ast!({"Expr" "apply" : "rator" => (, rator), "rand" => [(vr "three")]});
+
+    // The elided type argument gets filled in:
+    let explicit = apply(ast!({"Expr" "type_apply_expr" :
+        "rator" => (vr "identity"), "arg" => [{"Type" "Int" :}]}));
+    assert_eq!(
+        elaborate(&apply(ast!((vr "identity"))), env.clone()),
+        Ok((explicit.clone(), Ast::int_ty()))
+    );
+    assert_eq!(synth_type(&explicit, env.clone()), Ok(Ast::int_ty()));
+
+    // ...even under a binder:
+    let lambda = u!({lambda : [x {Type Int :}] {apply : identity [x]}});
+    let (elaborated, lambda_ty) = elaborate(&lambda, env.clone()).unwrap();
+    assert_eq!(lambda_ty, Ast::fn_ty(vec![Ast::int_ty()], Ast::int_ty()));
+    assert_eq!(
+        elaborated,
+        u!({lambda : [x {Type Int :}] {apply : {type_apply_expr : identity [{Type Int :}]} [x]}})
+    );
+    assert_eq!(synth_type(&elaborated, env.clone()), Ok(lambda_ty));
+
+    // Monomorphic functions are left alone:
+    let int_to_int = ast!((vr "int_to_int"));
+    let env = env.set(n("int_to_int"), Ast::fn_ty(vec![Ast::int_ty()], Ast::int_ty()));
+    assert_eq!(elaborate(&apply(int_to_int.clone()), env), Ok((apply(int_to_int), Ast::int_ty())));
+}
+
+#[test]
+fn positional_struct_construction() {
+    let ab_struct = ast!({ "Type" "struct" :
+        "component_name" => [@"c" "a", "b"],
+        "component" => [@"c" {"Type" "Int" :}, {"Type" "Nat" :}]
+    });
+    let ty_env = assoc_n!("x" => uty!({Int :}), "n" => uty!({Nat :}), "AB" => ab_struct.clone());
+    let env = assoc_n!("x" => val!(i 3), "n" => val!(i 4));
+    let struct_new = |components: Vec<Ast>, t: Ast| {
+        ast!({ "Expr" "struct_new" :
+            "component" => (,seq components),
+            "t" => (, t)})
+    };
+
+    let positional = struct_new(vec![ast!((vr "x")), ast!((vr "n"))], ab_struct.clone());
+    assert_eq!(synth_type(&positional, ty_env.clone()), Ok(ab_struct.clone()));
+    assert_eq!(eval(&positional, env.clone()), Ok(val!(struct "a" => (i 3), "b" => (i 4))));
+
+    // Declaration order, not alphabetical order:
+    let ba_struct = ast!({ "Type" "struct" :
+        "component_name" => [@"c" "b", "a"],
+        "component" => [@"c" {"Type" "Nat" :}, {"Type" "Int" :}]
+    });
+    let declared = struct_new(vec![ast!((vr "n")), ast!((vr "x"))], ba_struct.clone());
+    assert_eq!(synth_type(&declared, ty_env.clone()), Ok(ba_struct));
+    assert_eq!(eval(&declared, env.clone()), Ok(val!(struct "a" => (i 3), "b" => (i 4))));
+
+    // Out of order:
+    assert_m!(
+        synth_type(
+            &struct_new(vec![ast!((vr "n")), ast!((vr "x"))], ab_struct.clone()),
+            ty_env.clone()
+        ),
+        Err(_)
+    );
+    // Wrong arity:
+    assert_m!(
+        synth_type(&struct_new(vec![ast!((vr "x"))], ab_struct.clone()), ty_env.clone()),
+        Err(crate::util::err::Spanned { body: TyErr::LengthMismatch(_, 2), .. })
+    );
+
+    // The type has to be written out, since names for types aren't available at runtime:
+    let named = struct_new(vec![ast!((vr "x")), ast!((vr "n"))], ast!((vr "AB")));
+    assert_m!(
+        synth_type(&named, ty_env),
+        Err(crate::util::err::Spanned { body: TyErr::UnableToDestructure(_, _), .. })
+    );
+}
+
 #[test]
 fn use__insert_form_pat() {
     let se = syn_env!("Pat" => (impossible),
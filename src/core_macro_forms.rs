@@ -64,6 +64,7 @@ macro_rules! syntax_syntax {
     (( $($gram:tt)* )  $syntax_name:ident  ) => {
         Rc::new(Form {
             name: n(&stringify!($syntax_name).to_lowercase()),
+            description: None,
             grammar: Rc::new(form_pat!( $($gram)* )),
             type_compare: Both(NotWalked,NotWalked), // Not a type
             // Binds nothing
@@ -79,6 +80,7 @@ macro_rules! syntax_syntax {
     (( $($gram:tt)* )  $syntax_name:ident ( $($arg:ident => $e:expr),* ) ) => {
         Rc::new(Form {
             name: n(&stringify!($syntax_name).to_lowercase()),
+            description: None,
             grammar: Rc::new(form_pat!( $($gram)* )),
             type_compare: Both(NotWalked,NotWalked), // Not a type
             synth_type: Negative(cust_rc_box!(|parts| {
@@ -100,6 +102,7 @@ macro_rules! syntax_syntax {
     (( $($gram:tt)* )  $syntax_name:ident { $type:expr } { $eval:expr }) => {
         Rc::new(Form {
             name: n(&stringify!($syntax_name).to_lowercase()),
+            description: None,
             grammar: Rc::new(form_pat!( $($gram)* )),
             type_compare: Both(NotWalked,NotWalked), // Not a type
             synth_type: Negative(cust_rc_box!( $type )), // Produces a typed value
@@ -127,6 +130,7 @@ fn macro_type(forall_ty_vars: &[Name], arguments: Vec<(Name, Ast)>, output: Ast)
         crate::core_forms::find_core_form("Type", "struct"),
         crate::util::mbe::EnvMBE::new_from_anon_repeat(components),
         ExportBeta::Nothing,
+        None,
     );
     let mac_fn = u!({Type fn : [(, argument_struct)] (, output.to_ast())});
 
@@ -202,6 +206,7 @@ pub fn macro_invocation(
     let grammar2 = grammar.clone();
     Rc::new(Form {
         name: n("macro_invocation"), // TODO: maybe generate a fresh name?
+        description: None,
         grammar: Rc::new(form_pat!([
             // `type_macro_invocation` expects "macro_name" to be set
             (named "macro_name", (anyways (,
@@ -513,6 +518,7 @@ pub fn make_core_macro_forms() -> SynEnv {
         // `Call` with a type is positive (has to be under a `Named`)
         Rc::new(Form {
             name: n("call_with_type"),
+            description: None,
             grammar: Rc::new(form_pat!(
                 (delim ",{", "{",
                     [(named "nt", atom),
@@ -535,6 +541,7 @@ pub fn make_core_macro_forms() -> SynEnv {
         // `Import` is positive (has to be under a `Named`)
         Rc::new(Form {
             name: n("import"),
+            description: None,
             grammar: Rc::new(form_pat!(
                 [(named "body", (call "Syntax")), (lit "<--"), (named "imported", (call "Beta"))])),
             type_compare: Both(NotWalked,NotWalked), // Not a type
@@ -643,13 +650,14 @@ pub fn extend_syntax() -> Rc<Form> {
             )
         }
 
-        ParseContext { grammar: syn_env, type_ctxt: pc.type_ctxt, eval_ctxt: pc.eval_ctxt }
+        ParseContext { grammar: syn_env, ..pc }
     };
 
     let trivial_type_form = crate::core_type_forms::type_defn("unused", form_pat!((impossible)));
 
     Rc::new(Form {
         name: n("extend_syntax"),
+        description: None,
         grammar: Rc::new(form_pat!(
             [(lit "extend_syntax"),
              (extend
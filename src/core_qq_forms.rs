@@ -109,14 +109,14 @@ fn change_mu_opacity(parts: crate::ast_walk::LazyWalkReses<MuProtect>) -> Result
         }
     }
     match parts.this_ast {
-        Ast::Node(f, mut mu_parts, export) => {
+        Ast::Node(f, mut mu_parts, export, span) => {
             if let Some(opacity) = opacity {
                 mu_parts.add_leaf(
                     n("opacity_for_different_phase"),
                     Ast::Atom(n(&(opacity + delta).to_string())),
                 );
             }
-            Ok(Ast::Node(f, mu_parts, export))
+            Ok(Ast::Node(f, mu_parts, export, span))
         }
         _ => icp!(),
     }
@@ -193,6 +193,7 @@ pub fn unquote_form(nt: Name, pos_quot: bool, depth: u8) -> Rc<Form> {
 
     Rc::new(Form {
         name: n("unquote"),
+        description: None,
         grammar:
             // It's a pain to determine whether type annotation is needed at syntax time,
             //  so it's optional
@@ -352,11 +353,11 @@ macro_rules! ddd_type__body {
             let mut walked_env = Assoc::new();
 
             let repeats = match ddd_parts_uq.env.find(&drivers[0]) {
-                Some(&Node(ref form, ref parts, _)) if form.name == n("tuple") => {
+                Some(&Node(ref form, ref parts, _, _)) if form.name == n("tuple") => {
                     parts.get_rep_leaf_or_panic(n("component")).len()
                 }
                 // TODO: what if some are `tuple` and others are `dotdotdot`?
-                Some(&Node(ref form, _, _)) if form.name == n("dotdotdot") => 1,
+                Some(&Node(ref form, _, _, _)) if form.name == n("dotdotdot") => 1,
                 Some(other_t) => {
                     ty_err!(UnableToDestructure(other_t.clone(), n("tuple"))
                                 at ddd_parts_uq.this_ast);
@@ -370,7 +371,7 @@ macro_rules! ddd_type__body {
                 for (name, ty) in ddd_parts_uq.env.iter_pairs() {
                     if drivers.contains(name) {
                         walked_env = walked_env.set(*name, match ty {
-                            Node(ref form, ref parts, _) if form.name == n("tuple") => {
+                            Node(ref form, ref parts, _, _) if form.name == n("tuple") => {
                                 let component
                                     = parts.get_rep_leaf_or_panic(n("component"))[i].clone();
                                 let ddd2_form = crate::core_forms::find("Type", "dotdotdot_type");
@@ -383,7 +384,7 @@ macro_rules! ddd_type__body {
                                     component
                                 }
                             }
-                            Node(ref form, ref parts, _) if form.name == n("dotdotdot") =>
+                            Node(ref form, ref parts, _, _) if form.name == n("dotdotdot") =>
                             {
                                 parts.get_leaf_or_panic(&n("body")).clone()
                             }
@@ -404,6 +405,7 @@ macro_rules! ddd_type__body {
 pub fn dotdotdot_form(nt: Name) -> Rc<Form> {
     Rc::new(Form {
         name: n("dotdotdot"),
+        description: None,
         grammar: Rc::new(form_pat!((delim "...[", "[",
             [(star [(call "DefaultSeparator"), (scan "(,)"),
              (named "driver", (-- 1 varref)),
@@ -542,6 +544,7 @@ pub fn quote(pos: bool) -> Rc<Form> {
     // What happens when more NTs are added?
     Rc::new(Form {
         name: if pos { n("quote_expr") } else { n("quote_pat") },
+        description: None,
         grammar: Rc::new(form_pat!((delim "'[", "[",
             // TODO: use `extend`, not `extend_nt`. Can it resolve the HACK above?
             [(extend_nt (named "nt", varref), "QuotationBody", perform_quotation)]))),
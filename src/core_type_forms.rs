@@ -54,9 +54,9 @@ use crate::{
     },
     name::*,
     ty::{synth_type, SynthTy, TyErr},
-    ty_compare::{Canonicalize, Subtype},
+    ty_compare::{subtype_policy_says, Canonicalize, Subtype},
     util::assoc::Assoc,
-    walk_mode::NegativeWalkMode,
+    walk_mode::{NegativeWalkMode, WalkMode},
 };
 use std::rc::Rc;
 
@@ -64,6 +64,7 @@ use std::rc::Rc;
 pub fn type_defn(form_name: &str, p: FormPat) -> Rc<Form> {
     Rc::new(Form {
         name: n(form_name),
+        description: None,
         grammar: Rc::new(p),
         type_compare: Both(LiteralLike, LiteralLike),
         synth_type: Positive(LiteralLike),
@@ -80,6 +81,7 @@ fn type_defn_complex(
 ) -> Rc<Form> {
     Rc::new(Form {
         name: n(form_name),
+        description: None,
         grammar: Rc::new(p),
         type_compare: tc,
         synth_type: Positive(sy),
@@ -88,11 +90,53 @@ fn type_defn_complex(
     })
 }
 
+// `Nat`, `Int`, and `Float` are related according to the current `NumericTower`.
+fn numeric_type_defn(form_name: &str) -> Rc<Form> {
+    type_defn_complex(
+        form_name,
+        form_pat!((name_lit form_name)),
+        LiteralLike,
+        Both(
+            LiteralLike,
+            cust_rc_box!(move |num_parts| {
+                let expd_name = num_parts.this_ast.node_form().name;
+                match *num_parts.context_elt() {
+                    Node(ref got_f, _, _, _)
+                        if subtype_policy_says(|sp| {
+                            sp.numeric_tower.is_subtype(got_f.name, expd_name)
+                        }) =>
+                    {
+                        Ok(Assoc::new())
+                    }
+                    ref got => Err(TyErr::Mismatch(got.clone(), num_parts.this_ast.clone())),
+                }
+            }),
+        ),
+    )
+}
+
+// `struct` components and `enum` arms are found by name, so their order doesn't matter.
+// Canonicalization sorts them, so that reordered types are still equal.
+fn canonicalize_sorted_by(name_part: &'static str) -> WalkRule<Canonicalize> {
+    cust_rc_box!(move |parts| {
+        match Canonicalize::walk_quasi_literally(parts.this_ast.clone(), &parts)? {
+            Node(f, sub_parts, export, span) => {
+                let sorted = sub_parts.sort_rep_by_key(n(name_part), |arm| {
+                    arm.get_leaf_or_panic(&n(name_part)).to_name()
+                });
+                Ok(Node(f, sorted, export, span))
+            }
+            other => Ok(other),
+        }
+    })
+}
+
 thread_local! {
     // Not needed by the user.
     // An internal type to keep the compiler from trying to dig into the `Expr` in `Expr<X>`.
     pub static primitive_type : Rc<Form> = Rc::new(Form {
         name: n("primitive_type"),
+        description: None,
         grammar: Rc::new(form_pat!([(named "name", atom)])),
         type_compare: Both(LiteralLike, LiteralLike),
         synth_type: Positive(LiteralLike),
@@ -101,6 +145,29 @@ thread_local! {
     })
 }
 
+thread_local! {
+    // Not needed by the user.
+    // Stands in for the type annotation of an unannotated lambda parameter.
+    pub static inferred_type : Rc<Form> = Rc::new(Form {
+        name: n("inferred_type"),
+        description: None,
+        grammar: Rc::new(form_pat!([])),
+        type_compare: Both(LiteralLike, LiteralLike),
+        synth_type: Positive(LiteralLike),
+        quasiquote: Both(LiteralLike, LiteralLike),
+        eval: Positive(NotWalked)
+    })
+}
+
+pub fn get__inferred_type() -> Ast {
+    Node(
+        inferred_type.with(|i_t| i_t.clone()),
+        crate::util::mbe::EnvMBE::new(),
+        crate::beta::ExportBeta::Nothing,
+        None,
+    )
+}
+
 pub fn get__primitive_type(called: Name) -> Ast {
     ast!({primitive_type.with(|p_t| p_t.clone()) ; "name" => (, Atom(called))})
 }
@@ -156,22 +223,72 @@ pub fn make_core_syn_env_types() -> SynEnv {
         ),
     );
 
-    let enum_type = type_defn(
+    let enum_type = type_defn_complex(
         "enum",
         form_pat!(
             (delim "{", "{", (star
                 (delim "+[", "[",
                     [(named "name", atom),(star (named "component", (call "Type")))])))),
+        LiteralLike, // synth is normal
+        Both(
+            canonicalize_sorted_by("name"),
+            cust_rc_box!(move |enum_parts| {
+                let actual_enum_parts = Subtype::context_match(
+                    &enum_parts.this_ast,
+                    enum_parts.context_elt(),
+                    enum_parts.env.clone(),
+                )?;
+                let expd_arms = enum_parts.march_parts(&[n("name"), n("component")]);
+                let actl_arms = actual_enum_parts.march_all(&[n("name"), n("component")]);
+                if expd_arms.len() != actl_arms.len() {
+                    return Err(TyErr::Mismatch(
+                        enum_parts.context_elt().clone(),
+                        enum_parts.this_ast.clone(),
+                    ));
+                }
+
+                // Arms are matched by name; their components are matched in order:
+                for actl_arm in actl_arms {
+                    let name = actl_arm.get_leaf_or_panic(&n("name")).to_name();
+                    let expd_arm = match expd_arms
+                        .iter()
+                        .find(|expd_arm| expd_arm.get_term(n("name")).to_name() == name)
+                    {
+                        Some(expd_arm) => expd_arm,
+                        None => {
+                            return Err(TyErr::NonexistentEnumArm(
+                                name,
+                                enum_parts.this_ast.clone(),
+                            ))
+                        }
+                    };
+                    let actl_components = actl_arm.get_rep_leaf_or_panic(n("component"));
+                    let expd_components = expd_arm.get_rep_term(n("component"));
+                    if expd_components.len() != actl_components.len() {
+                        return Err(TyErr::LengthMismatch(
+                            actl_components.into_iter().cloned().collect(),
+                            expd_components.len(),
+                        ));
+                    }
+                    for (expd, actl) in expd_components.iter().zip(actl_components) {
+                        let _ = walk::<Subtype>(expd, &expd_arm.with_context(actl.clone()))?;
+                    }
+                }
+
+                Ok(assoc_n!())
+            }),
+        ),
     );
 
     let struct_type = type_defn_complex(
         "struct",
         form_pat!(
-             (delim "*[", "[", (star [(named "component_name", atom), (lit ":"),
-                                     (named "component", (call "Type"))]))),
+             (delim "*[", "[", [(star [(named "component_name", atom), (lit ":"),
+                                      (named "component", (call "Type"))]),
+                                (alt [], [(lit "|"), (named "rest", (call "Type"))])])),
         LiteralLike, // synth is normal
         Both(
-            LiteralLike,
+            canonicalize_sorted_by("component_name"),
             cust_rc_box!(move |struct_parts| {
                 let actual_struct_parts = Subtype::context_match(
                     &struct_parts.this_ast,
@@ -179,6 +296,9 @@ pub fn make_core_syn_env_types() -> SynEnv {
                     struct_parts.env.clone(),
                 )?;
 
+                // With a "rest" row, fields we don't expect are fine; they go to the row instead:
+                let mut leftover_names = vec![];
+                let mut leftover_components = vec![];
                 for (got_name, got_ty) in actual_struct_parts
                     .get_rep_leaf_or_panic(n("component_name"))
                     .iter()
@@ -198,11 +318,64 @@ pub fn make_core_syn_env_types() -> SynEnv {
                             walk::<Subtype>(&got_ty, &struct_parts.with_context(exp_ty.clone()))?;
                     }
                     if !found {
-                        return Err(TyErr::NonexistentStructField(
-                            got_name.to_name(),
+                        if !struct_parts.has(n("rest")) {
+                            return Err(TyErr::NonexistentStructField(
+                                got_name.to_name(),
+                                struct_parts.context_elt().clone(),
+                            ));
+                        }
+                        leftover_names.push((*got_name).clone());
+                        leftover_components.push(got_ty.clone());
+                    }
+                }
+
+                // Without width subtyping, the expected struct can't have extra fields, either:
+                if !subtype_policy_says(|sp| sp.struct_width_subtyping) {
+                    let got_names: Vec<Name> = actual_struct_parts
+                        .get_rep_leaf_or_panic(n("component_name"))
+                        .iter()
+                        .map(|got_name| got_name.to_name())
+                        .collect();
+                    for exp_name in struct_parts.get_rep_term(n("component_name")) {
+                        if !got_names.contains(&exp_name.to_name()) {
+                            return Err(TyErr::NonexistentStructField(
+                                exp_name.to_name(),
+                                struct_parts.context_elt().clone(),
+                            ));
+                        }
+                    }
+                }
+
+                let got_rest = actual_struct_parts.get_leaf(n("rest")).cloned();
+                match struct_parts.maybe_get_term(n("rest")) {
+                    Some(rest) => {
+                        let leftover = match got_rest {
+                            Some(got_rest) if leftover_names.is_empty() => got_rest,
+                            got_rest => {
+                                let mut leftover_parts = mbe!(
+                                    "component_name" => (@"c" ,seq leftover_names),
+                                    "component" => (@"c" ,seq leftover_components));
+                                if let Some(got_rest) = got_rest {
+                                    leftover_parts.add_leaf(n("rest"), got_rest);
+                                }
+                                Node(
+                                    crate::core_forms::find("Type", "struct"),
+                                    leftover_parts,
+                                    crate::beta::ExportBeta::Nothing,
+                                    None,
+                                )
+                            }
+                        };
+                        let _ = walk::<Subtype>(&rest, &struct_parts.with_context(leftover))?;
+                    }
+                    // An open row might have any fields at all:
+                    None if got_rest.is_some() => {
+                        return Err(TyErr::Mismatch(
                             struct_parts.context_elt().clone(),
+                            struct_parts.this_ast.clone(),
                         ));
                     }
+                    None => {}
                 }
 
                 Ok(assoc_n!())
@@ -217,6 +390,14 @@ pub fn make_core_syn_env_types() -> SynEnv {
         Both(LiteralLike, LiteralLike),
     );
 
+    // Homogeneous sequences. Subtyping is covariant, thanks to `LiteralLike`.
+    let sequence_type = type_defn_complex(
+        "sequence",
+        form_pat!((delim "Seq[", "[", (named "elt", (call "Type")))),
+        LiteralLike,
+        Both(LiteralLike, LiteralLike),
+    );
+
     let forall_type = type_defn_complex(
         "forall_type",
         form_pat!([(lit "forall"), (star (named "param", atom)), (lit "."),
@@ -230,13 +411,23 @@ pub fn make_core_syn_env_types() -> SynEnv {
                     forall_parts.context_elt(),
                     forall_parts.env.clone(),
                 ) {
-                    // ∀ X. ⋯ <: ∀ Y. ⋯ ? (so force X=Y)
+                    // ∀ X. ⋯ <: ∀ Y. ⋯ ? (so try to specialize X, treating Y as abstract)
+                    // The parameters needn't line up; they're matched up by how they're used
+                    //  (e.g. ∀ A B. Pair<B A> specializes to ∀ X Y. Pair<X Y>, with A=Y, B=X).
                     Ok(actual_forall_parts) => {
                         let actl_inner_body = actual_forall_parts.get_leaf_or_panic(&n("body"));
+                        let mut env = forall_parts.env.clone();
+                        for actl_param in actual_forall_parts.get_rep_leaf_or_panic(n("param")) {
+                            let actl_param = actl_param.to_name();
+                            // Like a `mu`-protected name, it only matches itself:
+                            env = env.set(actl_param, VariableReference(actl_param));
+                        }
 
                         walk::<Subtype>(
                             &forall_parts.get_term(n("body")),
-                            &forall_parts.with_context(actl_inner_body.clone()),
+                            &forall_parts
+                                .with_environment(env)
+                                .with_context(actl_inner_body.clone()),
                         )
                     }
                     // ∀ X. ⋯ <: ⋯ ?  (so try to specialize X)
@@ -257,7 +448,10 @@ pub fn make_core_syn_env_types() -> SynEnv {
     //  to prevent the attempted generation of an infinite type.
     let mu_type = type_defn_complex(
         "mu_type",
-        form_pat!([(lit "mu_type"), (star (named "param", (import [prot "param"], varref))),
+        // `nominal_mu_type` is like `mu_type`, but see `with_nominal_subtyping`.
+        form_pat!([(alt (lit "mu_type"),
+                        [(lit "nominal_mu_type"), (named "nominal", (anyways "nominal"))]),
+             (star (named "param", (import [prot "param"], varref))),
              (lit "."), (named "body", (import [* [prot "param"]], (call "Type")))]),
         LiteralLike,
         Both(
@@ -279,6 +473,9 @@ pub fn make_core_syn_env_types() -> SynEnv {
                         l_params.len(),
                     ));
                 }
+                let nominal = subtype_policy_says(|sp| sp.nominal_subtyping)
+                    && (mu_parts.has(n("nominal"))
+                        || rhs_mu_parts.get_leaf(n("nominal")).is_some());
                 // Apply the Amber rule; assume the `mu`ed names are subtypes to subtype the bodies
                 let mut amber_environment = mu_parts.env.clone();
                 for (&ee_r, ee_l) in r_params.iter().zip(l_params.iter()) {
@@ -293,6 +490,20 @@ pub fn make_core_syn_env_types() -> SynEnv {
                     {
                         continue;
                     }
+                    if nominal {
+                        // Nominal types only match if they were declared with the same name
+                        //  (and both are nominal):
+                        if !mu_parts.has(n("nominal"))
+                            || rhs_mu_parts.get_leaf(n("nominal")).is_none()
+                            || p_r.vr_to_name().unhygienic_orig()
+                                != p_l.vr_to_name().unhygienic_orig()
+                        {
+                            return Err(TyErr::Mismatch(
+                                mu_parts.context_elt().clone(),
+                                mu_parts.this_ast.clone(),
+                            ));
+                        }
+                    }
 
                     amber_environment = amber_environment.set(p_r.vr_to_name(), p_l.clone());
                 }
@@ -358,16 +569,16 @@ pub fn make_core_syn_env_types() -> SynEnv {
                     }
                     new__tapp_parts.add_anon_repeat(args);
 
-                    if let Node(ref f, _, ref exp) = tapp_parts.this_ast {
-                        Ok(Node(/* forall */ f.clone(), new__tapp_parts, exp.clone()))
+                    if let Node(ref f, _, ref exp, span) = tapp_parts.this_ast {
+                        Ok(Node(/* forall */ f.clone(), new__tapp_parts, exp.clone(), span))
                     } else {
                         icp!()
                     }
                 }
-                Node(ref got_f, ref lhs_parts, ref exports) if is_primitive(got_f) => {
+                Node(ref got_f, ref lhs_parts, ref exports, got_span) if is_primitive(got_f) => {
                     // Like the above; don't descend into `Expr`
                     let mut new__tapp_parts = EnvMBE::new_from_leaves(assoc_n!("type_rator" =>
-                            Node(got_f.clone(), lhs_parts.clone(), exports.clone())));
+                            Node(got_f.clone(), lhs_parts.clone(), exports.clone(), got_span)));
                     let mut args = vec![];
                     for individual__arg_res in arg_res {
                         args.push(EnvMBE::new_from_leaves(
@@ -376,13 +587,13 @@ pub fn make_core_syn_env_types() -> SynEnv {
                     }
                     new__tapp_parts.add_anon_repeat(args);
 
-                    if let Node(ref f, _, ref exp) = tapp_parts.this_ast {
-                        Ok(Node(/* forall */ f.clone(), new__tapp_parts, exp.clone()))
+                    if let Node(ref f, _, ref exp, span) = tapp_parts.this_ast {
+                        Ok(Node(/* forall */ f.clone(), new__tapp_parts, exp.clone(), span))
                     } else {
                         icp!()
                     }
                 }
-                Node(ref got_f, ref forall_type__parts, _) if got_f == &forall_type_0 => {
+                Node(ref got_f, ref forall_type__parts, _, _) if got_f == &forall_type_0 => {
                     // This might ought to be done by a specialized `beta`...
                     let params = forall_type__parts.get_rep_leaf_or_panic(n("param"));
                     if params.len() != arg_res.len() {
@@ -410,6 +621,45 @@ pub fn make_core_syn_env_types() -> SynEnv {
         Both(LiteralLike, LiteralLike),
     );
 
+    // A type-level conditional, for types computed by macros: `type_if( A == B , T , U )type_if`.
+    // (`resolve` reduces these, too.)
+    // If the comparison can't be decided yet (say, it mentions an abstract type),
+    //  the conditional is stuck, and acts like an opaque type.
+    let type_if = type_defn_complex(
+        "type_if",
+        form_pat!((delim "type_if(", "(",
+            [(named "lhs", (call "Type")), (lit "=="), (named "rhs", (call "Type")), (lit ","),
+             (named "then", (call "Type")), (lit ","), (named "else", (call "Type"))])),
+        cust_rc_box!(move |if_parts| {
+            let (lhs, rhs) = (if_parts.get_res(n("lhs"))?, if_parts.get_res(n("rhs"))?);
+            match type_if_branch(&lhs, &rhs, &if_parts.env) {
+                Some(branch) => if_parts.get_res(branch),
+                None => SynthTy::walk_quasi_literally(if_parts.this_ast.clone(), &if_parts),
+            }
+        }),
+        Both(
+            cust_rc_box!(move |if_parts| {
+                let (lhs, rhs) = (if_parts.get_res(n("lhs"))?, if_parts.get_res(n("rhs"))?);
+                match type_if_branch(&lhs, &rhs, &if_parts.env) {
+                    Some(branch) => if_parts.get_res(branch),
+                    None => {
+                        Canonicalize::walk_quasi_literally(if_parts.this_ast.clone(), &if_parts)
+                    }
+                }
+            }),
+            cust_rc_box!(move |if_parts| {
+                match type_if_branch(
+                    &if_parts.get_term(n("lhs")),
+                    &if_parts.get_term(n("rhs")),
+                    &if_parts.env,
+                ) {
+                    Some(branch) => walk::<Subtype>(&if_parts.get_term(branch), &if_parts),
+                    None => Subtype::walk_quasi_literally(if_parts.this_ast.clone(), &if_parts),
+                }
+            }),
+        ),
+    );
+
     assoc_n!("Type" => Rc::new(Biased(Rc::new(forms_to_form_pat![
         fn_type,
         // TODO: these should turn into `primitive_type`s in the core type environment.
@@ -417,16 +667,18 @@ pub fn make_core_syn_env_types() -> SynEnv {
         //  and then to change all the `uty!({Type Int :})`s into `uty!(Int)`s
         //  (and `ast!({"Type" "Int" :})`s into `ast!((vr "Int"))`).
         type_defn("Ident", form_pat!((name_lit "Ident"))),
-        type_defn("Int", form_pat!((name_lit "Int"))),
-        type_defn("Nat", form_pat!((name_lit "Nat"))),
-        type_defn("Float", form_pat!((name_lit "Float"))),
+        numeric_type_defn("Int"),
+        numeric_type_defn("Nat"),
+        numeric_type_defn("Float"),
         enum_type,
         struct_type,
         tuple_type,
+        sequence_type,
         forall_type,
         dotdotdot_type,
         mu_type,
-        type_apply
+        type_apply,
+        type_if
         ]), Rc::new(VarRef(Rc::new(Call(n("DefaultAtom"))))))))
 }
 
@@ -440,6 +692,32 @@ pub fn find_type(form_name: &str) -> Rc<Form> {
     core_type_forms.with(|ctf| crate::core_forms::find_form(ctf, "Type", form_name))
 }
 
+thread_local! {
+    // Built once, since they're needed constantly (`Ast` clones are shallow).
+    static common_types: [Ast; 4] = [
+        ast!({"Type" "Int" :}),
+        ast!({"Type" "Nat" :}),
+        ast!({"Type" "Float" :}),
+        ast!((vr "Bool")),
+    ];
+}
+
+/// Shortcuts for common types, so hot code doesn't need to look up forms every time.
+impl Ast {
+    pub fn int_ty() -> Ast { common_types.with(|ct| ct[0].clone()) }
+    pub fn nat_ty() -> Ast { common_types.with(|ct| ct[1].clone()) }
+    pub fn float_ty() -> Ast { common_types.with(|ct| ct[2].clone()) }
+    pub fn bool_ty() -> Ast { common_types.with(|ct| ct[3].clone()) }
+
+    pub fn fn_ty(params: Vec<Ast>, ret: Ast) -> Ast {
+        ast!({find_type("fn") ; "param" => (,seq params), "ret" => (, ret)})
+    }
+
+    pub fn tuple_ty(components: Vec<Ast>) -> Ast {
+        ast!({find_type("tuple") ; "component" => (,seq components)})
+    }
+}
+
 // TODO #4: this should be extensible for when the syntax environment is extended...
 //  or just automatically have one type per NT. Probably the latter.
 pub fn nt_to_type(nt: Name) -> Ast {
@@ -489,6 +767,46 @@ pub fn less_quoted_ty(t: &Ast, nt: Option<Name>, loc: &Ast) -> Result<Ast, crate
     )
 }
 
+/// Which part of a `type_if` comparing `lhs` and `rhs` is selected?
+/// They're equal if they canonicalize to the same type. (Unlike `must_equal`,
+///  this never determines underdetermined types, so it's safe to use inside `resolve`.)
+/// `None` if that can't be known yet: if they differ, but mention abstract or underdetermined
+///  types (which might turn out to be equal), or if either one fails to canonicalize.
+pub fn type_if_branch(lhs: &Ast, rhs: &Ast, env: &Assoc<Name, Ast>) -> Option<Name> {
+    use crate::{
+        alpha::{alpha_normalize, free_vars},
+        ty::is_determined,
+        ty_compare::{canonicalize, same_type},
+        util::mbe::EnvMBE,
+    };
+    let (l, r) = (canonicalize(lhs, env.clone()).ok()?, canonicalize(rhs, env.clone()).ok()?);
+    if same_type(&alpha_normalize(&l), &alpha_normalize(&r)) {
+        return Some(n("then"));
+    }
+    let concrete = |t: &Ast| free_vars(t, &EnvMBE::new()).is_empty() && is_determined(t);
+    if concrete(&l) && concrete(&r) {
+        Some(n("else"))
+    } else {
+        None
+    }
+}
+
+/// Instantiate the `forall_type` `t` at `args` (e.g. `forall T . [T -> T]` at `Int`).
+pub fn instantiate(t: &Ast, args: &[Ast], loc: &Ast) -> Result<Ast, crate::ty::TypeError> {
+    let forall_parts = t.ty_destructure(find_type("forall_type"), loc)?;
+    let params = forall_parts.get_rep_leaf_or_panic(n("param"));
+    if params.len() != args.len() {
+        ty_err!(LengthMismatch(args.to_vec(), params.len()) at loc);
+    }
+    let actuals: Assoc<Name, Ast> =
+        params.iter().map(|p| p.to_name()).zip(args.iter().cloned()).collect();
+    // This bypasses the binding in the type, which is what we want:
+    Ok(crate::alpha::substitute(
+        crate::core_forms::strip_ee(forall_parts.get_leaf_or_panic(&n("body"))),
+        &actuals,
+    ))
+}
+
 pub fn more_quoted_ty(t: &Ast, nt: Name) -> Ast {
     ast!({"Type" "type_apply" :
         "type_rator" => (, get__primitive_type(nt)),
@@ -564,3 +882,27 @@ fn parametric_types() {
             "ret" => (, ident_ty.clone())}))
     );
 }
+
+#[test]
+fn common_type_shortcuts() {
+    assert_eq!(Ast::int_ty(), ast!({"Type" "Int" :}));
+    assert_eq!(Ast::int_ty(), Ast::int_ty());
+    assert_ne!(Ast::int_ty(), Ast::nat_ty());
+    assert_eq!(Ast::bool_ty(), ast!((vr "Bool")));
+
+    // They're the same as what the type syntax means:
+    let parsed = |src| crate::grammar::parse_type(src, &crate::core_forms::get_core_forms());
+    assert_eq!(parsed("Int"), Ok(Ast::int_ty()));
+    assert_eq!(parsed("Nat"), Ok(Ast::nat_ty()));
+    assert_eq!(parsed("Float"), Ok(Ast::float_ty()));
+    assert_eq!(parsed("Bool"), Ok(Ast::bool_ty()));
+
+    assert_eq!(
+        Ast::fn_ty(vec![Ast::int_ty(), Ast::nat_ty()], Ast::bool_ty()),
+        ast!({"Type" "fn" : "param" => [{"Type" "Int" :}, {"Type" "Nat" :}], "ret" => (vr "Bool")})
+    );
+    assert_eq!(
+        Ast::tuple_ty(vec![Ast::float_ty()]),
+        ast!({"Type" "tuple" : "component" => [{"Type" "Float" :}]})
+    );
+}
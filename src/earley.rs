@@ -17,16 +17,27 @@
 // Also, it turns out that implementing an Earley parser goes pretty smoothly. Yay!
 
 use crate::{
-    ast::Ast,
+    ast::{Ast, Span},
     ast_walk::LazyWalkReses,
+    beta::ExportBeta,
+    form::Form,
     grammar::{
+        Associativity,
         FormPat::{self, *},
-        SynEnv,
+        OpPrec, SynEnv,
     },
     name::*,
-    util::{assoc::Assoc, mbe::EnvMBE},
+    util::{
+        assoc::Assoc,
+        mbe::EnvMBE,
+        scoped::{with_scoped, Scoped},
+    },
+};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
 };
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 // TODO: This UniqueId stuff is great, but we could make things faster
 //  by storing array indices instead
@@ -39,9 +50,80 @@ thread_local! {
     static all_parse_contexts: RefCell<HashMap<UniqueIdRef, ParseContext>>
         = RefCell::new(HashMap::new());
 
-    // For parse error reporting: how far have we gotten?
-    static best_token: RefCell<(usize, Rc<FormPat>, usize)>
-        = RefCell::new((0, Rc::new(Impossible), 0));
+    // For parse error reporting: how far have we gotten? (And in which `Biased` branches?)
+    static best_token: RefCell<(usize, Rc<FormPat>, usize, Rc<Vec<BiasedBranch>>)>
+        = RefCell::new((0, Rc::new(Impossible), 0, Rc::new(vec![])));
+
+    // For grammar debugging; see `with_parse_tracer`.
+    static parse_tracer: RefCell<Option<Tracer>> = RefCell::new(None);
+
+    // While `parse_all` builds its chart: keep every justification, even `Biased` plan Bs.
+    static finding_all_parses: RefCell<bool> = RefCell::new(false);
+    // While `parse_all` reads a parse out of its chart: at each ambiguity encountered (in order),
+    //  which alternative to take, and how many there were. Also, how many we've encountered.
+    static parse_choices: RefCell<Option<ParseChoices>> = RefCell::new(None);
+}
+
+type Tracer = Rc<dyn Fn(&ParseEvent)>;
+type ParseChoices = (Vec<(usize, usize)>, usize);
+
+/// Something the parser did with a grammar node, for grammar debugging.
+/// `rule` describes the node (see `describe_rule`); positions are byte indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseEvent {
+    /// Started trying to match `rule` at `pos`.
+    Enter { rule: String, pos: usize },
+    /// Matched `rule` from `start` to `end`. (It can match more than one way!)
+    Exit { rule: String, start: usize, end: usize },
+    /// Never matched `rule` starting from `pos`. Reported after the whole input is examined.
+    Fail { rule: String, pos: usize },
+}
+
+/// Run `f`, reporting everything the parser does to `tracer`.
+/// When no tracer is installed, tracing costs one check per new `Item`.
+pub fn with_parse_tracer<T>(tracer: impl Fn(&ParseEvent) + 'static, f: impl FnOnce() -> T) -> T {
+    with_scoped(&parse_tracer, Some(Rc::new(tracer)), f)
+}
+
+fn current_tracer() -> Option<Tracer> { parse_tracer.with(|pt| pt.borrow().clone()) }
+
+/// A short description of a grammar node (but not its children).
+pub fn describe_rule(rule: &FormPat) -> String {
+    match *rule {
+        Anyways(_) => "Anyways".to_owned(),
+        Impossible => "Impossible".to_owned(),
+        Scan(crate::grammar::Scanner(ref regex)) => {
+            format!("Scan({})", regex.as_str().trim_start_matches('^'))
+        }
+        AnyGroup(d) => format!("AnyGroup({})", d.open()),
+        Custom(_) => "Custom".to_owned(),
+        SkipTo(sync) => format!("SkipTo({})", sync),
+        RestOfInput => "RestOfInput".to_owned(),
+        Common(_) => "Common".to_owned(),
+        Reserved(_, _) => "Reserved".to_owned(),
+        Satisfy(_, _) => "Satisfy".to_owned(),
+        Literal(_, name) => format!("Literal({})", name),
+        VarRef(_) => "VarRef".to_owned(),
+        Seq(_) => "Seq".to_owned(),
+        Star(_) => "Star".to_owned(),
+        Plus(_) => "Plus".to_owned(),
+        Alt(_) => "Alt".to_owned(),
+        Biased(_, _) => "Biased".to_owned(),
+        Switch(ref cases, _) => format!(
+            "Switch({})",
+            cases.iter().map(|(kw, _)| kw.orig_sp()).collect::<Vec<_>>().join(", ")
+        ),
+        PrattExpr(_, _, _) => "PrattExpr".to_owned(),
+        Call(name) => format!("Call({})", name),
+        SynImport(_, _, _) => "SynImport".to_owned(),
+        Scope(ref form, _) => format!("Scope({})", form.name),
+        Named(name, _) => format!("Named({})", name),
+        Pick(_, name) => format!("Pick({})", name),
+        NameImport(_, _) => "NameImport".to_owned(),
+        NameImportPhaseless(_, _) => "NameImportPhaseless".to_owned(),
+        QuoteDeepen(_, _) => "QuoteDeepen".to_owned(),
+        QuoteEscape(_, _) => "QuoteEscape".to_owned(),
+    }
 }
 
 fn get_next_id() -> UniqueId {
@@ -90,22 +172,34 @@ custom_derive! {
     pub struct ParseContext {
         pub grammar: SynEnv,
         pub type_ctxt: LazyWalkReses<crate::ty::SynthTy>,
-        pub eval_ctxt: LazyWalkReses<crate::runtime::eval::Eval>
+        pub eval_ctxt: LazyWalkReses<crate::runtime::eval::Eval>,
+        /// How many `SynImport`s deep we are. A syntax extension gets the phase of its body
+        ///  (so, the extension at the top level sees 1).
+        pub phase: u8,
+        /// Names that `VarRef`s won't match (e.g. `and`, in a grammar that uses it as an operator)
+        pub keywords: Vec<Name>
     }
 }
 
 impl ParseContext {
     pub fn new(se: SynEnv, ce: CodeEnvs) -> ParseContext {
-        ParseContext { grammar: se, type_ctxt: ce.0, eval_ctxt: ce.1 }
+        ParseContext { grammar: se, type_ctxt: ce.0, eval_ctxt: ce.1, phase: 0, keywords: vec![] }
     }
     pub fn new_from_grammar(se: SynEnv) -> ParseContext {
         ParseContext {
             grammar: se,
             type_ctxt: LazyWalkReses::<crate::ty::SynthTy>::new_empty(),
             eval_ctxt: LazyWalkReses::<crate::runtime::eval::Eval>::new_empty(),
+            phase: 0,
+            keywords: vec![],
         }
     }
     pub fn with_grammar(self, se: SynEnv) -> ParseContext { ParseContext { grammar: se, ..self } }
+    /// Add to the names that `VarRef`s won't match.
+    pub fn with_keywords(mut self, new_keywords: &[Name]) -> ParseContext {
+        self.keywords.extend_from_slice(new_keywords);
+        self
+    }
 }
 
 // Hey, this doesn't need to be Reifiable!
@@ -122,9 +216,18 @@ pub struct Item {
     /// Environments, for typing/evaluating syntax extensions
     envs: Rc<CodeEnvs>,
 
+    /// How many `SynImport` bodies this is inside of
+    phase: u8,
+
+    /// Names that `VarRef`s can't match (from the `ParseContext`)
+    keywords: Rc<HashSet<Name>>,
+
     // -- Just for error messages --
     /// This rule is too commonplace to be informative in a parse error
     common: bool,
+    /// The `Biased` branches this item is (outermost first) trying to match on behalf of.
+    /// If it's wanted by more than one, this is the one picked by `prefer_ancestry`.
+    biased_branches: RefCell<Rc<Vec<BiasedBranch>>>,
 
     // -- Everything after this line is nonstandard, and is just here as an optimization--
     /// Identity for the purposes of `wanted_by` and `local_parse`
@@ -155,6 +258,42 @@ pub struct Item {
     wanted_by: Rc<RefCell<Vec<UniqueIdRef>>>,
 }
 
+/// One step of an item's `Biased` ancestry: where the `Biased` item is, and which plan.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+struct BiasedBranch {
+    start_idx: usize,
+    /// The `Biased` item's index in the state set at `start_idx`
+    ///  (unlike the address of its rule, this doesn't vary from run to run)
+    biased: usize,
+    plan_b: bool,
+}
+
+/// Should the error from progress on `old` (which reached `old_idx`) beat progress on `new`,
+///  even though `new` is more recent?
+/// Only if, within the same `Biased`, `old` is in plan A (and consumed input)
+///  and `new` is in plan B.
+/// (So, when a whole chain of `Biased`s fails, we blame the first branch that got anywhere.)
+fn outranks(old_idx: usize, old: &[BiasedBranch], new: &[BiasedBranch]) -> bool {
+    match old.iter().zip(new.iter()).find(|(o, n)| o != n) {
+        Some((o, n)) => {
+            o.start_idx == n.start_idx
+                && o.biased == n.biased
+                && !o.plan_b
+                && n.plan_b
+                && old_idx > o.start_idx
+        }
+        None => false,
+    }
+}
+
+/// When one item is wanted on behalf of different `Biased` branches, should `new` replace `old`?
+/// Plan A wins where they first differ (anything else is an arbitrary, but fixed, choice),
+///  so that blame doesn't depend on which one the parser happened to get to first.
+fn prefer_ancestry(new: &[BiasedBranch], old: &[BiasedBranch]) -> bool {
+    let key = |b: &BiasedBranch| (b.plan_b, b.start_idx, b.biased);
+    new.iter().map(key).lt(old.iter().map(key))
+}
+
 /// Information for parsing. It's not a parse tree, but it tells you the next step to get one.
 /// (Hence "local")
 #[derive(PartialEq, Debug, Clone)]
@@ -169,6 +308,34 @@ enum LocalParse {
 }
 use self::LocalParse::*;
 
+impl LocalParse {
+    fn is_justification(&self) -> bool {
+        matches!(*self, JustifiedByItemPlanB(_) | JustifiedByItem(_) | Ambiguous(_, _))
+    }
+
+    /// The justifications that this represents (more than one, if it's `Ambiguous`).
+    fn alternatives(&self) -> Vec<LocalParse> {
+        match *self {
+            Ambiguous(ref l, ref r) => [l.alternatives(), r.alternatives()].concat(),
+            _ => vec![self.clone()],
+        }
+    }
+
+    /// Does `self` already account for `other`?
+    /// (Normally, that's `<=`, but when finding all parses, every justification matters.)
+    fn subsumes(&self, other: &LocalParse) -> bool {
+        if finding_all_parses.with(|fap| *fap.borrow())
+            && self.is_justification()
+            && other.is_justification()
+        {
+            let alts = self.alternatives();
+            other.alternatives().iter().all(|alt| alts.contains(alt))
+        } else {
+            other <= self
+        }
+    }
+}
+
 impl PartialOrd for LocalParse {
     /// Establish a lattice for `LocalParse`; some parses are better than others.
     /// `Biased` allows one to find a "Plan B" parse that gets overwritten by "Plan A".
@@ -199,7 +366,10 @@ impl Clone for Item {
             pos: self.pos,
             grammar: self.grammar.clone(),
             envs: self.envs.clone(),
+            phase: self.phase,
+            keywords: self.keywords.clone(),
             common: self.common,
+            biased_branches: RefCell::new(self.biased_branches.borrow().clone()),
             id: get_next_id(),
             done: self.done.clone(),
             local_parse: RefCell::new(LocalParse::NothingYet),
@@ -210,46 +380,77 @@ impl Clone for Item {
 
 /// Progress through the state sets
 // TODO: this ought to produce an Option<ParseError>, not a bool!
-fn create_chart(
-    rule: Rc<FormPat>,
-    grammar: SynEnv,
-    envs: CodeEnvs,
-    toks: &str,
-) -> (UniqueId, Vec<Vec<Item>>) {
+fn create_chart(rule: Rc<FormPat>, ctxt: ParseContext, toks: &str) -> (UniqueId, Vec<Vec<Item>>) {
     let toks = toks.trim(); // HACK: tokens don't consume trailing whitespace
-    let mut chart: Vec<Vec<Item>> = vec![];
-    chart.resize_with(toks.len() + 1, std::default::Default::default);
+    let (start_but_startier, mut chart) = start_chart(rule, ctxt);
+    extend_chart(&mut chart, toks);
+
+    if let Some(tracer) = current_tracer() {
+        trace_failures(&chart, &*tracer);
+    }
+
+    (start_but_startier, chart)
+}
 
+/// A chart that has seen no input yet.
+fn start_chart(rule: Rc<FormPat>, ctxt: ParseContext) -> (UniqueId, Vec<Vec<Item>>) {
     let start_but_startier = get_next_id();
 
     let start_item = Item {
         start_idx: 0,
         rule: rule,
         pos: 0,
-        grammar: grammar,
-        envs: Rc::new(envs),
+        grammar: ctxt.grammar,
+        envs: Rc::new((ctxt.type_ctxt, ctxt.eval_ctxt)),
+        phase: 0,
+        keywords: Rc::new(ctxt.keywords.into_iter().collect()),
         common: false,
+        biased_branches: RefCell::new(Rc::new(vec![])),
         id: get_next_id(),
         done: RefCell::new(false),
         local_parse: RefCell::new(LocalParse::NothingYet),
         wanted_by: Rc::new(RefCell::new(vec![start_but_startier.get_ref()])),
     };
 
-    chart[0].push(start_item);
+    if let Some(tracer) = current_tracer() {
+        tracer(&ParseEvent::Enter { rule: describe_rule(&start_item.rule), pos: 0 });
+    }
+
+    (start_but_startier, vec![vec![start_item]])
+}
 
-    for cur_tok in 0..toks.len() {
-        walk_tt(&mut chart, &toks, cur_tok)
+/// Progress `chart` through `toks`, which extends the input that it has already seen.
+fn extend_chart(chart: &mut Vec<Vec<Item>>, toks: &str) {
+    let old_end = chart.len() - 1;
+    chart.resize_with(toks.len() + 1, std::default::Default::default);
+
+    // Terminals that read past their own token might match differently with more input.
+    // Anything new they produce before `old_end` needs to be walked again, too:
+    let mut from = old_end;
+    for cur_tok in 0..old_end {
+        for idx in 0..chart[cur_tok].len() {
+            if !chart[cur_tok][idx].reads_ahead() {
+                continue;
+            }
+            for (new_item, adv) in chart[cur_tok][idx].examine(toks, cur_tok, chart) {
+                if merge_into_state_set(new_item, &mut chart[cur_tok + adv]) {
+                    from = from.min(cur_tok + adv)
+                }
+            }
+        }
     }
 
-    examine_state_set(&mut chart, &toks, toks.len()); // One last time, for nullable rules at the end
+    for cur_tok in from..toks.len() {
+        walk_tt(chart, toks, cur_tok)
+    }
 
-    (start_but_startier, chart)
+    examine_state_set(chart, toks, toks.len()); // One last time, for nullable rules at the end
 }
 
 /// Recognize `rule` in `grammar` (but assume no code will need to be executed)
 fn recognize(rule: &FormPat, grammar: &SynEnv, toks: &str) -> bool {
     let (start_but_startier, chart) =
-        create_chart(Rc::new(rule.clone()), grammar.clone(), empty__code_envs(), toks);
+        create_chart(Rc::new(rule.clone()), ParseContext::new_from_grammar(grammar.clone()), toks);
 
     chart[chart.len() - 1].iter().any(|item| {
         (*item.wanted_by.borrow()).iter().any(|idr| start_but_startier.is(*idr))
@@ -271,15 +472,53 @@ fn examine_state_set(chart: &mut Vec<Vec<Item>>, toks: &str, cur_tok: usize) {
 }
 
 fn new_items_from_state_set(chart: &mut Vec<Vec<Item>>, toks: &str, cur_tok: usize) -> bool {
+    let tracer = current_tracer();
     let mut effect = false;
     for idx in 0..chart[cur_tok].len() {
         for (new_item, adv) in chart[cur_tok][idx].examine(toks, cur_tok, chart) {
+            let event = match tracer {
+                Some(_) => trace_event(&new_item, &chart[cur_tok + adv], cur_tok + adv),
+                None => None,
+            };
             effect = merge_into_state_set(new_item, &mut chart[cur_tok + adv]) || effect;
+            if let (Some(ref tracer), Some(ref event)) = (&tracer, event) {
+                tracer(event)
+            }
         }
     }
     effect
 }
 
+/// Is `item` news (to `items`, the state set at `here`)?
+fn trace_event(item: &Item, items: &[Item], here: usize) -> Option<ParseEvent> {
+    let existing = items.iter().find(|i| i.similar(item));
+    let rule = describe_rule(&item.rule);
+    if *item.done.borrow() && existing.is_none_or(|e| !*e.done.borrow()) {
+        Some(ParseEvent::Exit { rule, start: item.start_idx, end: here })
+    } else if existing.is_none() && item.pos == 0 {
+        Some(ParseEvent::Enter { rule, pos: here })
+    } else {
+        None
+    }
+}
+
+/// Report every item that was started but never finished.
+fn trace_failures(chart: &[Vec<Item>], tracer: &dyn Fn(&ParseEvent)) {
+    for (pos, items) in chart.iter().enumerate() {
+        for item in items.iter().filter(|i| i.pos == 0 && !*i.done.borrow()) {
+            let finished = chart[pos..].iter().flatten().any(|other| {
+                *other.done.borrow()
+                    && other.start_idx == pos
+                    && Rc::ptr_eq(&other.rule, &item.rule)
+                    && other.grammar.almost_ptr_eq(&item.grammar)
+            });
+            if !finished {
+                tracer(&ParseEvent::Fail { rule: describe_rule(&item.rule), pos })
+            }
+        }
+    }
+}
+
 // Returns whether anything happened
 fn merge_into_state_set(item: Item, items: &mut Vec<Item>) -> bool {
     for i in items.iter() {
@@ -323,6 +562,13 @@ impl Item {
             && &*self.rule as *const FormPat == &*other.rule as *const FormPat
             && self.pos == other.pos
             && self.grammar.almost_ptr_eq(&other.grammar)
+            && self.phase == other.phase
+            && (Rc::ptr_eq(&self.keywords, &other.keywords) || self.keywords == other.keywords)
+    }
+
+    /// Is this a terminal that looks beyond one token (e.g. to find the end of a group)?
+    fn reads_ahead(&self) -> bool {
+        self.pos == 0 && matches!(*self.rule, AnyGroup(_) | SkipTo(_) | RestOfInput | Custom(_))
     }
 
     /// `false` if `other` might provide new information
@@ -331,16 +577,20 @@ impl Item {
     fn as_good_as<'f>(&'f self, other: &'f Item) -> bool {
         assert!(self.similar(other));
         (*self.done.borrow() == *other.done.borrow() || !*other.done.borrow()) // no more done?
-        && (*other.local_parse.borrow() <= *self.local_parse.borrow() ) // no "better" parse?
+        && self.local_parse.borrow().subsumes(&other.local_parse.borrow()) // no "better" parse?
         && (other.wanted_by.borrow().len() == 0 // no more wanted?
             || (other.wanted_by.borrow().iter().all(
                    |w| self.wanted_by.borrow().iter().any(|s_w| w == s_w))))
+        && !prefer_ancestry(&other.biased_branches.borrow(), &self.biased_branches.borrow())
     }
 
     fn merge(&self, other: &Item) {
         if *other.done.borrow() {
             *self.done.borrow_mut() = true;
         }
+        if prefer_ancestry(&other.biased_branches.borrow(), &self.biased_branches.borrow()) {
+            *self.biased_branches.borrow_mut() = other.biased_branches.borrow().clone();
+        }
 
         use std::cmp::Ordering::*;
         let comparison = other.local_parse.borrow().partial_cmp(&*self.local_parse.borrow());
@@ -350,7 +600,25 @@ impl Item {
             *other.local_parse.borrow(),
             *self.local_parse.borrow()
         );
+        let self_lp = self.local_parse.borrow().clone();
+        let other_lp = other.local_parse.borrow().clone();
         match comparison {
+            // Keep all of the justifications, not just the "best" one:
+            _ if finding_all_parses.with(|fap| *fap.borrow())
+                && self_lp.is_justification()
+                && other_lp.is_justification() =>
+            {
+                let mut alts = self_lp.alternatives();
+                for alt in other_lp.alternatives() {
+                    if !alts.contains(&alt) {
+                        alts.push(alt)
+                    }
+                }
+                let first = alts.remove(0);
+                *self.local_parse.borrow_mut() = alts
+                    .into_iter()
+                    .fold(first, |amb, alt| LocalParse::Ambiguous(Box::new(amb), Box::new(alt)));
+            }
             Some(Greater) => {
                 *self.local_parse.borrow_mut() = other.local_parse.borrow().clone();
             }
@@ -408,7 +676,10 @@ impl Item {
                 done: RefCell::new(false),
                 grammar: self.grammar.clone(),
                 envs: self.envs.clone(),
+                phase: self.phase,
+                keywords: self.keywords.clone(),
                 common: self.common,
+                biased_branches: RefCell::new(self.biased_branches.borrow().clone()),
                 local_parse: RefCell::new(LocalParse::NothingYet),
                 id: get_next_id(),
                 wanted_by: Rc::new(RefCell::new(vec![self.id.get_ref()])),
@@ -417,6 +688,25 @@ impl Item {
         )]
     }
 
+    /// Like `start`, but for one of the plans of `self` (which is a `Biased`)
+    fn start_plan(
+        &self,
+        rule: &Rc<FormPat>,
+        cur_idx: usize,
+        chart: &[Vec<Item>],
+        plan_b: bool,
+    ) -> Vec<(Item, usize)> {
+        let mut res = self.start(rule, cur_idx);
+        let biased = chart[cur_idx]
+            .iter()
+            .position(|item| item.similar(self))
+            .unwrap_or_else(|| icp!("`Biased` item not in its own state set"));
+        let mut branches = (**self.biased_branches.borrow()).clone();
+        branches.push(BiasedBranch { start_idx: cur_idx, biased, plan_b });
+        res[0].0.biased_branches = RefCell::new(Rc::new(branches));
+        res
+    }
+
     // -----------------------------------------------------------
 
     /// See what new items this item justifies
@@ -438,7 +728,8 @@ impl Item {
                     //  in `shift_or_predict` for leaves.
                     // Except for `Seq`. TODO: why?
                     let mut more = match *waiting_item.rule {
-                        Anyways(_) | Impossible | Scan(_) => {
+                        Anyways(_) | Impossible | Scan(_) | AnyGroup(_) | Custom(_) | SkipTo(_)
+                        | RestOfInput => {
                             icp!("{:#?} should not be waiting for anything!", waiting_item)
                         }
                         Seq(ref subs) => {
@@ -468,6 +759,15 @@ impl Item {
                             },
                             0,
                         )],
+                        // Keywords can't be variables:
+                        VarRef(_)
+                            if !waiting_item.keywords.is_empty()
+                                && waiting_item
+                                    .keywords
+                                    .contains(&n(toks[self.start_idx..cur_idx].trim())) =>
+                        {
+                            vec![]
+                        }
                         VarRef(_)
                         | Alt(_)
                         | Call(_)
@@ -479,6 +779,8 @@ impl Item {
                         | NameImportPhaseless(_, _)
                         | QuoteDeepen(_, _)
                         | QuoteEscape(_, _)
+                        | PrattExpr(_, _, _)
+                        | Switch(_, _)
                         | Common(_) => waiting_item.finish_with(me_justif, 0),
                         // Using `c_parse` instead of `local_parse` here is weird,
                         //  but probably necessary to allow `Call` under `Reserved`.
@@ -495,6 +797,19 @@ impl Item {
                                 vec![]
                             }
                         },
+                        Satisfy(_, ref pred) => match self.c_parse(chart, cur_idx) {
+                            Ok(Ast::Atom(name)) | Ok(Ast::VariableReference(name)) => {
+                                if (pred.0)(&name.orig_sp()) {
+                                    waiting_item.finish_with(me_justif, 0)
+                                } else {
+                                    vec![]
+                                }
+                            }
+                            _ => {
+                                log!("found something unusual {:?}", other);
+                                vec![]
+                            }
+                        },
                         Literal(_, expected) => match self.c_parse(chart, cur_idx) {
                             Ok(Ast::Atom(name)) => {
                                 if name == expected {
@@ -509,7 +824,9 @@ impl Item {
                             }
                         },
                         Biased(ref _plan_a, ref plan_b) => {
-                            if &*self.rule as *const FormPat == &**plan_b as *const FormPat {
+                            if &*self.rule as *const FormPat == &**plan_b as *const FormPat
+                                && !finding_all_parses.with(|fap| *fap.borrow())
+                            {
                                 waiting_item.finish_with(JustifiedByItemPlanB(self.id.get_ref()), 0)
                             } else {
                                 waiting_item.finish_with(me_justif, 0)
@@ -529,8 +846,18 @@ impl Item {
             if let Call(_) = *res[0].0.rule {
                 // HACK: I think that `Call` is uninformative
             } else if !self.common {
-                best_token
-                    .with(|bt| *bt.borrow_mut() = (cur_idx, res[0].0.rule.clone(), res[0].0.pos));
+                best_token.with(|bt| {
+                    let progress = &res[0].0;
+                    let mut bt = bt.borrow_mut();
+                    if !outranks(bt.0, &bt.3, &progress.biased_branches.borrow()) {
+                        *bt = (
+                            cur_idx,
+                            progress.rule.clone(),
+                            progress.pos,
+                            progress.biased_branches.borrow().clone(),
+                        );
+                    }
+                })
             }
         }
 
@@ -568,6 +895,61 @@ impl Item {
                     vec![]
                 }
             }
+            (0, &Custom(crate::grammar::CustomParser(ref parser))) => {
+                let rest = &toks[cur_idx..];
+                match parser(rest) {
+                    // A length past the end (or into a character) can't be a real match:
+                    Some((ast, len)) if rest.is_char_boundary(len) => {
+                        self.finish_with(ParsedAtom(ast), len)
+                    }
+                    _ => vec![],
+                }
+            }
+            (0, &SkipTo(sync)) => match toks[cur_idx..]
+                .match_indices(&sync.orig_sp())
+                .map(|(len, _)| len)
+                .find(|len| token_at(toks, cur_idx + len, &sync.orig_sp()))
+            {
+                Some(len) if !toks[cur_idx..cur_idx + len].trim().is_empty() => {
+                    // Stop where a successful parse would have, so that Plan A can replace us:
+                    let len = toks[cur_idx..cur_idx + len].trim_end().len();
+                    let skipped = Ast::Atom(n(toks[cur_idx..cur_idx + len].trim()));
+                    let placeholder = Ast::Node(
+                        crate::grammar::get_parse_error_form(),
+                        crate::util::mbe::EnvMBE::new_from_leaves(assoc_n!("text" => skipped)),
+                        crate::beta::ExportBeta::Nothing,
+                        None,
+                    );
+                    self.finish_with(ParsedAtom(placeholder), len)
+                }
+                _ => vec![],
+            },
+            (0, &RestOfInput) => {
+                let contents = toks[skip_whitespace(toks, cur_idx)..].trim_end();
+                self.finish_with(ParsedAtom(Ast::Atom(n(contents))), toks.len() - cur_idx)
+            }
+            (0, &AnyGroup(d)) => {
+                let group_start = skip_whitespace(toks, cur_idx);
+                if !toks[group_start..].starts_with(d.open()) {
+                    return vec![];
+                }
+                let mut depth = 0;
+                for (i, c) in toks[group_start..].char_indices() {
+                    if c == d.open() {
+                        depth += 1;
+                    } else if c == d.close() {
+                        depth -= 1;
+                        if depth == 0 {
+                            let contents = &toks[group_start + 1..group_start + i];
+                            return self.finish_with(
+                                ParsedAtom(Ast::Atom(n(contents))),
+                                group_start + i + 1 - cur_idx,
+                            );
+                        }
+                    }
+                }
+                vec![] // Unbalanced
+            }
             (0, &VarRef(ref sub)) => self.start(sub, cur_idx),
             (pos, &Seq(ref subs)) => {
                 if pos < subs.len() {
@@ -600,10 +982,17 @@ impl Item {
             }
             // Needs special handling elsewhere!
             (0, &Biased(ref plan_a, ref plan_b)) => {
-                let mut res = self.start(&plan_a, cur_idx);
-                res.append(&mut self.start(&plan_b, cur_idx));
+                let mut res = self.start_plan(&plan_a, cur_idx, chart, false);
+                res.append(&mut self.start_plan(&plan_b, cur_idx, chart, true));
                 res
             }
+            // No backtracking: the next token picks the only case we try.
+            (0, &Switch(ref cases, ref default)) => {
+                self.start(switch_case(toks, cur_idx, cases).unwrap_or(default), cur_idx)
+            }
+            // This is already memoized: `merge_into_state_set` folds together items with the same
+            //  rule and start, so each nonterminal is only parsed once per position,
+            //  however many places want it.
             (0, &Call(n)) => self.start(&self.grammar.find_or_panic(&n), cur_idx),
             (0, &Scope(ref f, _)) => {
                 // form.grammar is a FormPat. Confusing!
@@ -631,8 +1020,11 @@ impl Item {
                     mut_grammars
                         .entry(self.id.get_ref()) // memoize
                         .or_insert_with(||
-                            f.0(ParseContext::new(
-                                self.grammar.clone(), (*self.envs).clone()), partial_parse))
+                            f.0(ParseContext {
+                                phase: self.phase + 1,
+                                keywords: self.keywords.iter().cloned().collect(),
+                                ..ParseContext::new(self.grammar.clone(), (*self.envs).clone())
+                            }, partial_parse))
                         .clone()
                 });
 
@@ -644,7 +1036,10 @@ impl Item {
                         done: RefCell::new(false),
                         grammar: new_ctxt.grammar.clone(),
                         envs: Rc::new((new_ctxt.type_ctxt.clone(), new_ctxt.eval_ctxt.clone())),
+                        phase: self.phase + 1,
+                        keywords: Rc::new(new_ctxt.keywords.iter().cloned().collect()),
                         common: false,
+                        biased_branches: RefCell::new(self.biased_branches.borrow().clone()),
                         local_parse: RefCell::new(LocalParse::NothingYet),
                         id: get_next_id(),
                         wanted_by: Rc::new(RefCell::new(vec![self.id.get_ref()])),
@@ -657,7 +1052,9 @@ impl Item {
             | (0, &NameImportPhaseless(ref body, _))
             | (0, &QuoteDeepen(ref body, _))
             | (0, &QuoteEscape(ref body, _))
-            | (0, &Reserved(ref body, _)) => self.start(&body, cur_idx),
+            | (0, &Reserved(ref body, _))
+            | (0, &Satisfy(ref body, _))
+            | (0, &PrattExpr(ref body, _, _)) => self.start(&body, cur_idx),
             (0, &Common(ref body)) => {
                 let mut res = self.start(&body, cur_idx);
                 res[0].0.common = true; // Only has one element
@@ -675,6 +1072,13 @@ impl Item {
         let local_parse = self.local_parse.borrow().clone();
         let desired_id = match local_parse {
             JustifiedByItem(id) | JustifiedByItemPlanB(id) => id,
+            Ambiguous(_, _) if parse_choices.with(|pc| pc.borrow().is_some()) => {
+                let alts = local_parse.alternatives();
+                match alts[choose_alternative(alts.len())] {
+                    JustifiedByItem(id) | JustifiedByItemPlanB(id) => id,
+                    ref alt => icp!("ambiguity between non-justifications: {:#?}", alt),
+                }
+            }
             Ambiguous(ref l, ref r) => {
                 // HACK: this is quite ugly!
                 let l = *l.clone();
@@ -715,18 +1119,25 @@ impl Item {
         let res = match *self.rule {
             Anyways(ref a) => Ok(a.clone()),
             Impossible => icp!("Parser parsed the impossible!"),
-            Scan(_) => match self.local_parse.borrow().clone() {
-                ParsedAtom(a) => Ok(a),
-                NothingYet => Ok(Ast::Trivial),
-                _ => icp!(),
-            },
+            Scan(_) | AnyGroup(_) | Custom(_) | SkipTo(_) | RestOfInput => {
+                match self.local_parse.borrow().clone() {
+                    ParsedAtom(a) => Ok(a),
+                    NothingYet => Ok(Ast::Trivial),
+                    _ => icp!(),
+                }
+            }
             VarRef(_) => match self.find_wanted(chart, done_tok).c_parse(chart, done_tok)? {
                 Ast::Atom(a) => Ok(Ast::VariableReference(a)),
                 _ => icp!("no atom saved"),
             },
-            Literal(_, _) | Alt(_) | Biased(_, _) | Call(_) | Reserved(_, _) | Common(_) => {
-                self.find_wanted(chart, done_tok).c_parse(chart, done_tok)
-            }
+            Literal(_, _)
+            | Alt(_)
+            | Biased(_, _)
+            | Switch(_, _)
+            | Call(_)
+            | Reserved(_, _)
+            | Satisfy(_, _)
+            | Common(_) => self.find_wanted(chart, done_tok).c_parse(chart, done_tok),
             Seq(_) | Star(_) | Plus(_) | SynImport(_, _, _) => {
                 let mut step = self;
                 let mut subtrees: Vec<Ast> = vec![];
@@ -784,7 +1195,13 @@ impl Item {
             Scope(ref form, ref export) => {
                 let sub_parsed = self.find_wanted(chart, done_tok).c_parse(chart, done_tok)?;
                 // TODO #14: We should add zero-length repeats of missing `Named`s,
-                Ok(Ast::Node(form.clone(), sub_parsed.flatten(), export.clone()))
+                // (This span includes leading whitespace; `parse` trims it.)
+                let span = Span { start: self.start_idx, end: done_tok };
+                Ok(Ast::Node(form.clone(), sub_parsed.flatten(), export.clone(), Some(span)))
+            }
+            PrattExpr(_, ref table, ref form) => {
+                let sub_parsed = self.find_wanted(chart, done_tok).c_parse(chart, done_tok)?;
+                Ok(climb_precedence(&sub_parsed, table, form))
             }
             Pick(_, name) => {
                 let sub_parsed = self.find_wanted(chart, done_tok).c_parse(chart, done_tok)?;
@@ -819,6 +1236,117 @@ impl Item {
     }
 }
 
+/// The case of a `Switch` whose keyword is the next token in `toks[idx..]`, if any.
+fn switch_case<'p>(
+    toks: &str,
+    idx: usize,
+    cases: &'p [(Name, Rc<FormPat>)],
+) -> Option<&'p Rc<FormPat>> {
+    let idx = skip_whitespace(toks, idx);
+    cases.iter().find(|(kw, _)| token_at(toks, idx, &kw.orig_sp())).map(|(_, case)| case)
+}
+
+/// Is `kw` at `toks[idx..]`, and not just part of a longer word?
+/// (`let` shouldn't pick out `letter` or `outlet`.)
+fn token_at(toks: &str, idx: usize, kw: &str) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    toks[idx..].starts_with(kw)
+        && !(kw.starts_with(is_word_char) && toks[..idx].ends_with(is_word_char))
+        && !(kw.ends_with(is_word_char) && toks[idx + kw.len()..].starts_with(is_word_char))
+}
+
+/// While `parse_all` is reading out a parse: which of the `alternatives` should we use?
+fn choose_alternative(alternatives: usize) -> usize {
+    parse_choices.with(|pc| match *pc.borrow_mut() {
+        Some((ref mut script, ref mut next)) => {
+            if *next == script.len() {
+                script.push((0, alternatives));
+            }
+            *next += 1;
+            script[*next - 1].0
+        }
+        None => icp!("not reading out all parses"),
+    })
+}
+
+enum PrattTok {
+    Prefix(Name),
+    Infix(Name),
+    Operand(Ast),
+}
+
+/// Group the flat parse of a `PrattExpr` body (see `grammar::pratt_expr`) into `form` nodes.
+fn climb_precedence(flat: &Ast, table: &[OpPrec], form: &Rc<Form>) -> Ast {
+    fn add_unit(unit: &Ast, toks: &mut Vec<PrattTok>) {
+        match *unit {
+            Ast::Shape(ref parts) => {
+                for prefix in parts[0].flatten().get_rep_leaf(n("prefix")).unwrap_or_default() {
+                    toks.push(PrattTok::Prefix(prefix.to_name()));
+                }
+                toks.push(PrattTok::Operand(parts[1].clone()));
+            }
+            _ => icp!("malformed operand {:?}", unit),
+        }
+    }
+
+    let mut toks = vec![];
+    match *flat {
+        Ast::Shape(ref parts) => {
+            add_unit(&parts[0], &mut toks);
+            for link in parts[1].flatten().get_rep_leaf(n("link")).unwrap_or_default() {
+                match *link {
+                    Ast::Shape(ref link_parts) => {
+                        toks.push(PrattTok::Infix(link_parts[0].to_name()));
+                        add_unit(&link_parts[1], &mut toks);
+                    }
+                    _ => icp!("malformed operator {:?}", link),
+                }
+            }
+        }
+        _ => icp!("malformed operator expression {:?}", flat),
+    }
+
+    let mut toks = toks.into_iter().peekable();
+    climb(&mut toks, 0, table, form)
+}
+
+fn climb(
+    toks: &mut std::iter::Peekable<std::vec::IntoIter<PrattTok>>,
+    min_prec: u8,
+    table: &[OpPrec],
+    form: &Rc<Form>,
+) -> Ast {
+    let entry = |op: Name, prefix: bool| {
+        table
+            .iter()
+            .find(|entry| entry.op == op && (entry.assoc == Associativity::Prefix) == prefix)
+            .unwrap_or_else(|| icp!("{} is not in the precedence table", op))
+    };
+    let node = |parts: Assoc<Name, Ast>| {
+        Ast::Node(form.clone(), EnvMBE::new_from_leaves(parts), ExportBeta::Nothing, None)
+    };
+
+    let mut lhs = match toks.next() {
+        Some(PrattTok::Operand(a)) => a,
+        Some(PrattTok::Prefix(op)) => {
+            let rhs = climb(toks, entry(op, true).prec, table, form);
+            node(assoc_n!("op" => Ast::Atom(op), "rhs" => rhs))
+        }
+        _ => icp!("expected an operand"),
+    };
+    while let Some(&PrattTok::Infix(op)) = toks.peek() {
+        let OpPrec { prec, assoc, .. } = *entry(op, false);
+        if prec < min_prec {
+            break;
+        }
+        toks.next();
+        let rhs =
+            climb(toks, if assoc == Associativity::Left { prec + 1 } else { prec }, table, form);
+        lhs = node(assoc_n!("lhs" => lhs, "op" => Ast::Atom(op), "rhs" => rhs));
+    }
+    lhs
+}
+
 type ParseResult = Result<Ast, ParseError>;
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -827,35 +1355,241 @@ pub struct ParseError {
 }
 
 pub fn parse(rule: &FormPat, grammar: &SynEnv, envs: CodeEnvs, toks: &str) -> ParseResult {
-    best_token.with(|bt| *bt.borrow_mut() = (0, Rc::new(rule.clone()), 0));
+    parse_with(rule, grammar, envs, toks, false)
+}
 
-    let (start_but_startier, chart) =
-        create_chart(Rc::new(rule.clone()), grammar.clone(), envs, toks);
+/// Like `parse`, but if `flatten` is set, applies `flatten_shapes` to the result.
+pub fn parse_with(
+    rule: &FormPat,
+    grammar: &SynEnv,
+    envs: CodeEnvs,
+    toks: &str,
+    flatten: bool,
+) -> ParseResult {
+    parse_in_context(rule, ParseContext::new(grammar.clone(), envs), toks, flatten)
+}
+
+/// Like `parse_with`, but takes everything (including keywords) from `ctxt`.
+pub fn parse_in_context(
+    rule: &FormPat,
+    ctxt: ParseContext,
+    toks: &str,
+    flatten: bool,
+) -> ParseResult {
+    best_token.with(|bt| *bt.borrow_mut() = (0, Rc::new(rule.clone()), 0, Rc::new(vec![])));
+
+    let (start_but_startier, chart) = create_chart(Rc::new(rule.clone()), ctxt, toks);
     let final_item = chart[chart.len() - 1].iter().find(|item| {
         (*item.wanted_by.borrow()).iter().any(|idr| start_but_startier.is(*idr))
             && *item.done.borrow()
     });
     log!("-------\n");
     match final_item {
-        Some(i) => i.c_parse(&chart, chart.len() - 1),
-        None => best_token.with(|bt| {
-            let (idx, ref grammar, pos) = *bt.borrow();
-
-            let line_begin = toks[0..idx].rfind('\n').map(|n| n + 1).unwrap_or(0);
-            let line_end = toks[idx..toks.len()].find('\n').map(|n| n + idx).unwrap_or(toks.len());
-            let line_number = toks[0..idx].matches('\n').count() + 1;
-
-            Err(ParseError {
-                msg: format!(
-                    "Could not parse past “{}•{}” (on line {}) \nin rule {:?} at {}",
-                    &toks[line_begin..idx],
-                    &toks[idx..line_end],
-                    line_number,
-                    grammar,
-                    pos
-                ),
-            })
-        }),
+        Some(i) => Ok(finish_parse(&i.c_parse(&chart, chart.len() - 1)?, toks, flatten)),
+        None => Err(best_token_error(toks, 0)),
+    }
+}
+
+/// Like `parse`, but instead of committing to one way to parse `toks`, find all of them
+///  (including the ones that `Biased` would rule out), without duplicates.
+/// This can be very slow; it's for finding unintended ambiguity in grammars.
+pub fn parse_all(
+    rule: &FormPat,
+    grammar: &SynEnv,
+    envs: CodeEnvs,
+    toks: &str,
+) -> Result<Vec<Ast>, ParseError> {
+    best_token.with(|bt| *bt.borrow_mut() = (0, Rc::new(rule.clone()), 0, Rc::new(vec![])));
+
+    let (start_but_startier, chart) = with_scoped(&finding_all_parses, true, || {
+        create_chart(Rc::new(rule.clone()), ParseContext::new(grammar.clone(), envs), toks)
+    });
+    let final_item = chart[chart.len() - 1].iter().find(|item| {
+        (*item.wanted_by.borrow()).iter().any(|idr| start_but_startier.is(*idr))
+            && *item.done.borrow()
+    });
+    let final_item = match final_item {
+        Some(i) => i,
+        None => return Err(best_token_error(toks, 0)),
+    };
+
+    // Read out one parse for each combination of choices at the ambiguities we encounter:
+    let mut parses = vec![];
+    let mut script = vec![];
+    loop {
+        let choices = Scoped::set(&parse_choices, Some((script, 0)));
+        let res = final_item.c_parse(&chart, chart.len() - 1);
+        script = choices.restore().unwrap().0;
+        let res = finish_parse(&res?, toks, false);
+        if !parses.contains(&res) {
+            parses.push(res);
+        }
+
+        // Take the next alternative at the last ambiguity that has any left:
+        while let Some((choice, alternatives)) = script.pop() {
+            if choice + 1 < alternatives {
+                script.push((choice + 1, alternatives));
+                break;
+            }
+        }
+        if script.is_empty() {
+            return Ok(parses);
+        }
+    }
+}
+
+/// Turn the result of `c_parse` into the result of `parse`.
+fn finish_parse(res: &Ast, toks: &str, flatten: bool) -> Ast {
+    // `create_chart` trimmed `toks`, so positions in the chart are off by this much:
+    let leading_ws = toks.len() - toks.trim_start().len();
+    let res = trim_spans(res, toks, leading_ws);
+    if flatten {
+        flatten_shapes(&res)
+    } else {
+        res
+    }
+}
+
+/// Make the spans in `a` point into `toks` (instead of the chart), without leading whitespace.
+fn trim_spans(a: &Ast, toks: &str, offset: usize) -> Ast {
+    use crate::ast::*;
+    match *a {
+        Node(ref f, ref parts, ref export, span) => Node(
+            f.clone(),
+            parts.map(&mut |sub| trim_spans(sub, toks, offset)),
+            export.clone(),
+            span.map(|span| Span {
+                start: skip_whitespace(toks, span.start + offset).min(span.end + offset),
+                end: span.end + offset,
+            }),
+        ),
+        IncompleteNode(ref parts) => {
+            IncompleteNode(parts.map(&mut |sub| trim_spans(sub, toks, offset)))
+        }
+        Shape(ref subs) => Shape(subs.iter().map(|sub| trim_spans(sub, toks, offset)).collect()),
+        QuoteMore(ref body, pos) => QuoteMore(Box::new(trim_spans(body, toks, offset)), pos),
+        QuoteLess(ref body, depth) => QuoteLess(Box::new(trim_spans(body, toks, offset)), depth),
+        ExtendEnv(ref body, ref beta) => {
+            ExtendEnv(Box::new(trim_spans(body, toks, offset)), beta.clone())
+        }
+        ExtendEnvPhaseless(ref body, ref beta) => {
+            ExtendEnvPhaseless(Box::new(trim_spans(body, toks, offset)), beta.clone())
+        }
+        Trivial | Atom(_) | VariableReference(_) => a.clone(),
+    }
+}
+
+/// Remove `Shape` structure that doesn't say anything:
+///  `Shape`s directly inside `Shape`s are spliced into them,
+///  and then one-element `Shape`s are replaced by their element.
+/// `Node`s are left alone (except that their parts are flattened),
+///  since their parts are found by name, not position.
+pub fn flatten_shapes(a: &Ast) -> Ast {
+    use crate::ast::*;
+    match *a {
+        Shape(ref subs) => {
+            let mut flat_subs = vec![];
+            for sub in subs {
+                match flatten_shapes(sub) {
+                    Shape(sub_subs) => flat_subs.extend(sub_subs),
+                    flat_sub => flat_subs.push(flat_sub),
+                }
+            }
+            if flat_subs.len() == 1 {
+                flat_subs.pop().unwrap()
+            } else {
+                Shape(flat_subs)
+            }
+        }
+        Node(ref f, ref parts, ref export, span) => {
+            Node(f.clone(), parts.map(&mut flatten_shapes), export.clone(), span)
+        }
+        IncompleteNode(ref parts) => IncompleteNode(parts.map(&mut flatten_shapes)),
+        QuoteMore(ref body, pos) => QuoteMore(Box::new(flatten_shapes(body)), pos),
+        QuoteLess(ref body, depth) => QuoteLess(Box::new(flatten_shapes(body)), depth),
+        ExtendEnv(ref body, ref beta) => ExtendEnv(Box::new(flatten_shapes(body)), beta.clone()),
+        ExtendEnvPhaseless(ref body, ref beta) => {
+            ExtendEnvPhaseless(Box::new(flatten_shapes(body)), beta.clone())
+        }
+        Trivial | Atom(_) | VariableReference(_) => a.clone(),
+    }
+}
+
+/// Parse the contents of a group captured (uninterpreted) by `AnyGroup`,
+///  presumably now that we know what grammar they should be parsed with.
+pub fn parse_group(rule: &FormPat, grammar: &SynEnv, envs: CodeEnvs, group: &Ast) -> ParseResult {
+    parse(rule, grammar, envs, &group.to_name().sp())
+}
+
+/// Describe how far the last parse got. `offset` is where (in `toks`) that parse started.
+fn best_token_error(toks: &str, offset: usize) -> ParseError {
+    best_token.with(|bt| {
+        let (idx, ref grammar, pos, _) = *bt.borrow();
+        let idx = idx + offset;
+
+        let line_begin = toks[0..idx].rfind('\n').map(|n| n + 1).unwrap_or(0);
+        let line_end = toks[idx..toks.len()].find('\n').map(|n| n + idx).unwrap_or(toks.len());
+        let line_number = toks[0..idx].matches('\n').count() + 1;
+
+        let expectation = match describe_expectation(grammar, pos) {
+            Some(expected) => {
+                let found = match toks[idx..].split_whitespace().next() {
+                    Some(tok) => format!("`{}`", tok),
+                    None => "the end of the input".to_owned(),
+                };
+                format!(": expected {}, found {}", expected, found)
+            }
+            None => String::new(),
+        };
+
+        ParseError {
+            msg: format!(
+                "Could not parse past “{}•{}” (on line {}){} \nin rule {:?} at {}",
+                &toks[line_begin..idx],
+                &toks[idx..line_end],
+                line_number,
+                expectation,
+                grammar,
+                pos
+            ),
+        }
+    })
+}
+
+/// What `rule` wants next, having gotten `pos` subparts, in terms the user might recognize.
+fn describe_expectation(rule: &FormPat, pos: usize) -> Option<String> {
+    match *rule {
+        Seq(ref subs) => subs.get(pos).and_then(|sub| describe_expected(sub)),
+        _ if pos == 0 => describe_expected(rule),
+        _ => None,
+    }
+}
+
+fn describe_expected(rule: &FormPat) -> Option<String> {
+    match *rule {
+        AnyGroup(d) => Some(format!("a `{}...{}` group", d.open(), d.close())),
+        Literal(_, name) => Some(format!("`{}`", name)),
+        Alt(ref subs) if subs.is_empty() => Some(
+            "an alternative, but there are none (an empty alternation never matches)".to_owned(),
+        ),
+        VarRef(_) => Some("a variable reference".to_owned()),
+        Call(nt) => Some(format!("a `{}`", nt)),
+        Scope(ref form, _) => Some(form.display_name()),
+        Scan(crate::grammar::Scanner(ref regex)) => {
+            Some(format!("text matching `{}`", regex.as_str().trim_start_matches('^')))
+        }
+        Seq(ref subs) => subs.first().and_then(|sub| describe_expected(sub)),
+        Named(_, ref body)
+        | Common(ref body)
+        | Reserved(ref body, _)
+        | Satisfy(ref body, _)
+        | PrattExpr(ref body, _, _)
+        | Pick(ref body, _)
+        | NameImport(ref body, _)
+        | NameImportPhaseless(ref body, _)
+        | QuoteDeepen(ref body, _)
+        | QuoteEscape(ref body, _) => describe_expected(body),
+        _ => None,
     }
 }
 
@@ -863,6 +1597,390 @@ fn parse_top(rule: &FormPat, toks: &str) -> ParseResult {
     parse(rule, &Assoc::new(), empty__code_envs(), toks)
 }
 
+/// A parse of one top-level form, and the part of the input (byte indices) it came from.
+/// Spans inside `ast` are relative to `range.start` (so that the form survives being moved).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopLevelForm {
+    pub range: std::ops::Range<usize>,
+    pub ast: Rc<Ast>,
+}
+
+/// Replace the bytes in `range` with `new_text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub range: std::ops::Range<usize>,
+    pub new_text: String,
+}
+
+impl Edit {
+    pub fn apply(&self, toks: &str) -> String {
+        format!("{}{}{}", &toks[0..self.range.start], self.new_text, &toks[self.range.end..])
+    }
+
+    /// How far the edit moves everything after it.
+    fn shift(&self, idx: usize) -> usize { idx + self.new_text.len() - self.range.len() }
+}
+
+/// Parse the longest prefix of `toks` that matches `rule`. Returns the parse and its length.
+fn parse_longest_prefix(
+    rule: &FormPat,
+    grammar: &SynEnv,
+    envs: CodeEnvs,
+    toks: &str,
+) -> Result<(Ast, usize), ParseError> {
+    best_token.with(|bt| *bt.borrow_mut() = (0, Rc::new(rule.clone()), 0, Rc::new(vec![])));
+
+    let (start_but_startier, chart) =
+        create_chart(Rc::new(rule.clone()), ParseContext::new(grammar.clone(), envs), toks);
+    for done_tok in (1..chart.len()).rev() {
+        let final_item = chart[done_tok].iter().find(|item| {
+            (*item.wanted_by.borrow()).iter().any(|idr| start_but_startier.is(*idr))
+                && *item.done.borrow()
+        });
+        if let Some(i) = final_item {
+            return Ok((trim_spans(&i.c_parse(&chart, done_tok)?, toks, 0), done_tok));
+        }
+    }
+    Err(ParseError { msg: String::new() }) // The caller knows where we were
+}
+
+fn skip_whitespace(toks: &str, idx: usize) -> usize {
+    toks[idx..].find(|c: char| !c.is_whitespace()).map(|n| n + idx).unwrap_or(toks.len())
+}
+
+/// Parse the longest prefix of `toks` that matches `rule`, leaving the rest for someone else.
+/// Returns the parse and how many bytes of `toks` it consumed (including leading whitespace).
+pub fn parse_prefix(
+    rule: &FormPat,
+    grammar: &SynEnv,
+    envs: CodeEnvs,
+    toks: &str,
+) -> Result<(Ast, usize), ParseError> {
+    let start = skip_whitespace(toks, 0);
+    parse_longest_prefix(rule, grammar, envs, &toks[start..])
+        .map(|(ast, len)| (ast, start + len))
+        .map_err(|_| best_token_error(toks, start))
+}
+
+/// Parse `toks[idx..]` as a sequence of `rule`s, greedily.
+/// If `resume` says that some old form starts at the current position, stop and let it take over.
+fn parse_forms_from(
+    rule: &FormPat,
+    grammar: &SynEnv,
+    envs: &CodeEnvs,
+    toks: &str,
+    mut idx: usize,
+    resume: &dyn Fn(usize) -> bool,
+) -> Result<(Vec<TopLevelForm>, usize), ParseError> {
+    let mut res = vec![];
+    loop {
+        idx = skip_whitespace(toks, idx);
+        if idx == toks.len() || resume(idx) {
+            return Ok((res, idx));
+        }
+        let (ast, len) = parse_longest_prefix(rule, grammar, envs.clone(), &toks[idx..])
+            .map_err(|_| best_token_error(toks, idx))?;
+        res.push(TopLevelForm { range: idx..idx + len, ast: Rc::new(ast) });
+        idx += len;
+    }
+}
+
+/// Parse `toks` as a sequence of top-level `rule`s, remembering where each one came from.
+pub fn parse_forms(
+    rule: &FormPat,
+    grammar: &SynEnv,
+    envs: CodeEnvs,
+    toks: &str,
+) -> Result<Vec<TopLevelForm>, ParseError> {
+    parse_forms_from(rule, grammar, &envs, toks, 0, &|_| false).map(|(forms, _)| forms)
+}
+
+/// Like `parse_forms`, but a broken form doesn't stop the parse; its error is collected,
+///  and parsing resumes at the next synchronization point (see `sync_point`).
+pub fn parse_forms_recovering(
+    rule: &FormPat,
+    grammar: &SynEnv,
+    envs: CodeEnvs,
+    toks: &str,
+    recovery: Option<&str>,
+) -> (Vec<TopLevelForm>, Vec<ParseError>) {
+    let mut forms = vec![];
+    let mut errs = vec![];
+    let mut idx = skip_whitespace(toks, 0);
+    while idx < toks.len() {
+        match parse_longest_prefix(rule, grammar, envs.clone(), &toks[idx..]) {
+            Ok((ast, len)) => {
+                forms.push(TopLevelForm { range: idx..idx + len, ast: Rc::new(ast) });
+                idx += len;
+            }
+            Err(_) => {
+                errs.push(best_token_error(toks, idx));
+                idx = sync_point(toks, idx, recovery);
+            }
+        }
+        idx = skip_whitespace(toks, idx);
+    }
+    (forms, errs)
+}
+
+/// Where to pick up after a broken form starting at `idx`:
+///  just after the closing delimiter that balances out its first opening delimiter,
+///  or just after the `recovery` literal, if that appears first (even inside delimiters).
+/// (An empty `recovery` literal would match without moving forward, so it's ignored.)
+fn sync_point(toks: &str, idx: usize, recovery: Option<&str>) -> usize {
+    let mut depth = 0;
+    for (i, c) in toks[idx..].char_indices() {
+        let here = idx + i;
+        if let Some(recovery) = recovery.filter(|r| !r.is_empty()) {
+            if toks[here..].starts_with(recovery) {
+                return here + recovery.len();
+            }
+        }
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth <= 0 {
+                    return here + c.len_utf8();
+                }
+            }
+            _ => {}
+        }
+    }
+    toks.len()
+}
+
+/// Top-level forms, parsed from text that arrives in chunks (e.g. lines of standard input).
+/// Chunks are only pulled from `source` when the text buffered so far doesn't parse.
+/// So, a form ends at the end of a chunk if it's complete there,
+///  even if the next chunk could have extended it.
+pub struct FormStream<I: Iterator<Item = String>> {
+    parser: IncrementalParser,
+    source: I,
+}
+
+impl<I: Iterator<Item = String>> Iterator for FormStream<I> {
+    type Item = ParseResult;
+
+    fn next(&mut self) -> Option<ParseResult> {
+        let mut chunk = String::new();
+        loop {
+            match self.parser.feed(&chunk) {
+                IncrementalParse::Done(ast) => return Some(Ok(ast)),
+                IncrementalParse::Error(err) => return Some(Err(err)),
+                IncrementalParse::NeedMore => match self.source.next() {
+                    Some(next_chunk) => chunk = next_chunk,
+                    None => return self.parser.finish(),
+                },
+            }
+        }
+    }
+}
+
+/// Parse a sequence of top-level `rule`s from `source`, without reading it all up front.
+pub fn parse_stream<I: Iterator<Item = String>>(
+    rule: &FormPat,
+    grammar: &SynEnv,
+    envs: CodeEnvs,
+    source: I,
+) -> FormStream<I> {
+    FormStream { parser: parse_incremental(rule, grammar, envs), source }
+}
+
+/// The state of an `IncrementalParser` after it's been fed a chunk.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IncrementalParse {
+    /// What's been fed so far could be the beginning of a `rule`, but isn't one yet.
+    NeedMore,
+    Done(Ast),
+    /// No amount of further input would help.
+    Error(ParseError),
+}
+
+/// Parses `rule`s from text that's fed to it a chunk at a time (e.g. by a REPL).
+/// Chunks are assumed to end at token boundaries.
+/// Each chunk only advances the parse that's in progress, rather than starting it over.
+/// Spans (and line numbers in errors) count from the beginning of the first chunk.
+pub struct IncrementalParser {
+    rule: Rc<FormPat>,
+    grammar: SynEnv,
+    envs: CodeEnvs,
+    /// Everything fed so far
+    buffer: String,
+    /// Where the current `rule` starts in `buffer`
+    start: usize,
+    /// The parse of the current `rule`, as far as `buffer` goes
+    chart: Option<(UniqueId, Vec<Vec<Item>>)>,
+    /// `best_token` for `chart` (other parses may happen between chunks)
+    best: (usize, Rc<FormPat>, usize, Rc<Vec<BiasedBranch>>),
+}
+
+impl IncrementalParser {
+    /// After a `Done`, the next `rule` starts where that one stopped;
+    ///  after an `Error`, it starts after everything fed so far.
+    /// A `rule` is `Done` as soon as it's complete at the end of a chunk,
+    ///  or when the text after it can't extend it.
+    pub fn feed(&mut self, chunk: &str) -> IncrementalParse {
+        self.buffer.push_str(chunk);
+        self.start = skip_whitespace(&self.buffer, self.start);
+        if self.start == self.buffer.len() {
+            return IncrementalParse::NeedMore;
+        }
+        let end = self.buffer.trim_end().len(); // HACK: tokens don't consume trailing whitespace
+
+        let outer_best = best_token.with(|bt| bt.replace(self.best.clone()));
+        let res = self.advance_to(end);
+        self.best = best_token.with(|bt| bt.replace(outer_best));
+        res
+    }
+
+    /// There's no more input. Returns the last `rule` (or the error), if anything was left.
+    pub fn finish(&mut self) -> Option<ParseResult> {
+        let res = match self.feed("") {
+            IncrementalParse::NeedMore if self.start == self.buffer.len() => return None,
+            IncrementalParse::NeedMore => {
+                let outer_best = best_token.with(|bt| bt.replace(self.best.clone()));
+                let res = self.take_longest().unwrap_or_else(|| Err(self.give_up()));
+                best_token.with(|bt| *bt.borrow_mut() = outer_best);
+                res
+            }
+            IncrementalParse::Done(ast) => Ok(ast),
+            IncrementalParse::Error(err) => Err(err),
+        };
+        Some(res)
+    }
+
+    fn advance_to(&mut self, end: usize) -> IncrementalParse {
+        let (rule, grammar, envs) = (&self.rule, &self.grammar, &self.envs);
+        let (start_but_startier, chart) = self.chart.get_or_insert_with(|| {
+            best_token.with(|bt| *bt.borrow_mut() = (0, rule.clone(), 0, Rc::new(vec![])));
+            start_chart(rule.clone(), ParseContext::new(grammar.clone(), envs.clone()))
+        });
+        let toks = &self.buffer[self.start..end];
+        extend_chart(chart, toks);
+        let len = end - self.start;
+
+        if finished_at(chart, start_but_startier, len).is_some() {
+            self.take(len)
+        } else if !chart[len].is_empty() || waiting_to_read_ahead(chart, toks) {
+            // Something is still waiting for input, so more input could finish it:
+            IncrementalParse::NeedMore
+        } else {
+            match self.take_longest() {
+                Some(Ok(ast)) => IncrementalParse::Done(ast),
+                Some(Err(err)) => IncrementalParse::Error(err),
+                None => IncrementalParse::Error(self.give_up()),
+            }
+        }
+    }
+
+    /// Finish the longest `rule` that's complete so far, if there is one.
+    fn take_longest(&mut self) -> Option<ParseResult> {
+        let (start_but_startier, chart) = self.chart.as_ref().unwrap();
+        let len = (1..chart.len())
+            .rev()
+            .find(|pos| finished_at(chart, start_but_startier, *pos).is_some())?;
+        Some(match self.take(len) {
+            IncrementalParse::Done(ast) => Ok(ast),
+            IncrementalParse::Error(err) => Err(err),
+            IncrementalParse::NeedMore => icp!("`take` always finishes"),
+        })
+    }
+
+    /// Finish the `rule` that's complete `len` bytes after `start`.
+    fn take(&mut self, len: usize) -> IncrementalParse {
+        let (start_but_startier, chart) = self.chart.take().unwrap();
+        let final_item = finished_at(&chart, &start_but_startier, len).unwrap();
+        let res = match final_item.c_parse(&chart, len) {
+            Ok(ast) => IncrementalParse::Done(trim_spans(&ast, &self.buffer, self.start)),
+            Err(e) => IncrementalParse::Error(e),
+        };
+        self.start += len;
+        res
+    }
+
+    /// Report the error in the current `rule`, and skip everything fed so far.
+    fn give_up(&mut self) -> ParseError {
+        let err = best_token_error(&self.buffer, self.start);
+        self.chart = None;
+        self.start = self.buffer.len();
+        err
+    }
+}
+
+/// Is there a terminal that reads ahead (e.g. to the end of a group) that more input could match?
+fn waiting_to_read_ahead(chart: &[Vec<Item>], toks: &str) -> bool {
+    chart.iter().enumerate().any(|(pos, items)| {
+        items.iter().any(|item| item.reads_ahead() && item.examine(toks, pos, chart).is_empty())
+    })
+}
+
+/// The item (if any) that finishes the whole parse at `pos`.
+fn finished_at<'a>(
+    chart: &'a [Vec<Item>],
+    start_but_startier: &UniqueId,
+    pos: usize,
+) -> Option<&'a Item> {
+    chart[pos].iter().find(|item| {
+        (*item.wanted_by.borrow()).iter().any(|idr| start_but_startier.is(*idr))
+            && *item.done.borrow()
+    })
+}
+
+/// Parse `rule`s from text that isn't available all at once; see `IncrementalParser::feed`.
+pub fn parse_incremental(rule: &FormPat, grammar: &SynEnv, envs: CodeEnvs) -> IncrementalParser {
+    IncrementalParser {
+        rule: Rc::new(rule.clone()),
+        grammar: grammar.clone(),
+        envs,
+        buffer: String::new(),
+        start: 0,
+        chart: None,
+        best: (0, Rc::new(Impossible), 0, Rc::new(vec![])),
+    }
+}
+
+/// Re-parse after `edit` is applied to `old_toks` (which parsed as `old_forms`).
+/// Only the top-level forms that the edit touches get re-parsed;
+///  the others keep their (shared) `Ast`s, though forms after the edit get their ranges shifted.
+/// Assumes that a form can't be extended by text that isn't adjacent to it.
+pub fn reparse(
+    rule: &FormPat,
+    grammar: &SynEnv,
+    envs: CodeEnvs,
+    old_toks: &str,
+    old_forms: &[TopLevelForm],
+    edit: &Edit,
+) -> Result<Vec<TopLevelForm>, ParseError> {
+    let toks = edit.apply(old_toks);
+
+    // Forms strictly before the edit are untouched...
+    let mut res: Vec<TopLevelForm> =
+        old_forms.iter().take_while(|f| f.range.end < edit.range.start).cloned().collect();
+    let restart = res.last().map(|f| f.range.end).unwrap_or(0);
+
+    // ...and so are forms after it, as long as re-parsing brings us to one of their boundaries.
+    let after: Vec<&TopLevelForm> =
+        old_forms.iter().filter(|f| f.range.start >= edit.range.end).collect();
+    let edit_end = edit.shift(edit.range.end);
+    let resumes_at =
+        |idx: usize| after.iter().position(|f| idx >= edit_end && edit.shift(f.range.start) == idx);
+
+    let (mut reparsed, stop) =
+        parse_forms_from(rule, grammar, &envs, &toks, restart, &|idx| resumes_at(idx).is_some())?;
+    res.append(&mut reparsed);
+
+    if let Some(first_reused) = resumes_at(stop) {
+        for f in &after[first_reused..] {
+            res.push(TopLevelForm {
+                range: edit.shift(f.range.start)..edit.shift(f.range.end),
+                ast: f.ast.clone(),
+            });
+        }
+    }
+    Ok(res)
+}
+
 #[test]
 fn earley_merging() {
     let one_rule = crate::grammar::new_scan("whatever");
@@ -877,7 +1995,10 @@ fn earley_merging() {
         pos: 0,
         grammar: main_grammar.clone(),
         envs: Rc::new((LazyWalkReses::new_empty(), LazyWalkReses::new_empty())),
+        phase: 0,
+        keywords: Rc::new(HashSet::new()),
         common: false,
+        biased_branches: RefCell::new(Rc::new(vec![])),
         id: get_next_id(),
         done: RefCell::new(false),
         local_parse: RefCell::new(LocalParse::NothingYet),
@@ -1332,3 +2453,547 @@ fn basic_parsing_e() {
         Ok(ast!("Moon"))
     );
 }
+
+#[test]
+fn incremental_reparsing() {
+    let list = form_pat!([(scan r"\s*(\()"), (star (named "elt", (scan r"\s*([a-z]+)"))),
+                          (scan r"\s*(\))")]);
+    let toks = "(a b) (c d)\n(e f)";
+
+    let forms = parse_forms(&list, &Assoc::new(), empty__code_envs(), toks).unwrap();
+    assert_eq!(forms.len(), 3);
+    assert_eq!(forms[1].range, 6..11);
+    assert_eq!(*forms[2].ast, parse_top(&list, "(e f)").unwrap());
+
+    // Edit inside the second form:
+    let edit = Edit { range: 9..10, new_text: "xyz".to_owned() };
+    let new_toks = edit.apply(toks);
+    assert_eq!(new_toks, "(a b) (c xyz)\n(e f)");
+    let new_forms = reparse(&list, &Assoc::new(), empty__code_envs(), toks, &forms, &edit).unwrap();
+    assert_eq!(new_forms.len(), 3);
+    assert!(Rc::ptr_eq(&new_forms[0].ast, &forms[0].ast));
+    assert!(!Rc::ptr_eq(&new_forms[1].ast, &forms[1].ast));
+    assert!(Rc::ptr_eq(&new_forms[2].ast, &forms[2].ast));
+    assert_eq!(*new_forms[1].ast, parse_top(&list, "(c xyz)").unwrap());
+    assert_eq!(new_forms[2].range, 14..19);
+    // Same as starting from scratch:
+    assert_eq!(
+        new_forms,
+        parse_forms(&list, &Assoc::new(), empty__code_envs(), &new_toks).unwrap()
+    );
+
+    // An edit that merges two forms can't reuse either of them:
+    let edit = Edit { range: 10..13, new_text: " ".to_owned() };
+    let merged = reparse(&list, &Assoc::new(), empty__code_envs(), toks, &forms, &edit).unwrap();
+    assert_eq!(merged.len(), 2);
+    assert_eq!(*merged[1].ast, parse_top(&list, "(c d e f)").unwrap());
+
+    // Errors are reported in terms of the whole input:
+    let edit = Edit { range: 11..11, new_text: " (".to_owned() };
+    assert_m!(reparse(&list, &Assoc::new(), empty__code_envs(), toks, &forms, &edit), Err(_));
+}
+
+#[test]
+fn parse_any_group() {
+    let quote = form_pat!((pick [(lit_aat "quote"), (named "body", (any_group "{"))], "body"));
+
+    let group = parse_top(&quote, "quote { a {b c} d }").unwrap();
+    assert_eq!(group, ast!(" a {b c} d "));
+    // Unbalanced groups don't parse:
+    assert_m!(parse_top(&quote, "quote { a {b c d }"), Err(_));
+    assert_m!(parse_top(&quote, "quote a b"), Err(_));
+    // ...and the error says what was expected:
+    let err = parse_top(&quote, "quote asdf").unwrap_err();
+    assert!(err.msg.contains("expected a `{...}` group, found `asdf`"));
+
+    // Forms can describe themselves:
+    let app = crate::form::described_form(
+        "app",
+        "a function application",
+        form_pat!((named "args", (any_group "("))),
+    );
+    let go_app = form_pat!([(lit_aat "go"), (scope app)]);
+    let err = parse_top(&go_app, "go home").unwrap_err();
+    assert!(err.msg.contains("expected a function application, found `home`"));
+
+    // Later, we can parse the contents with some other grammar:
+    let words = form_pat!((star (named "elt", (alt (scan r"\s*([a-z]+)"), (any_group "{")))));
+    assert_eq!(
+        parse_group(&words, &Assoc::new(), empty__code_envs(), &group).unwrap(),
+        ast!({ - "elt" => ["a", "b c", "d"]})
+    );
+}
+
+#[test]
+fn parse_empty_seq_and_alt() {
+    // An empty sequence matches nothing, successfully:
+    assert_eq!(parse_top(&Seq(vec![]), ""), Ok(ast!(())));
+    assert_eq!(
+        parse_top(&form_pat!([(lit_aat "a"), [], (lit_aat "b")]), "a b"),
+        Ok(ast!(("a" () "b")))
+    );
+    assert_m!(parse_top(&Seq(vec![]), "a"), Err(_));
+
+    // An empty alternation never matches, and says why:
+    for toks in &["", "a"] {
+        let err = parse_top(&Alt(vec![]), toks).unwrap_err();
+        assert!(err.msg.contains("an empty alternation never matches"));
+    }
+    let err = parse_top(&form_pat!([(lit_aat "a"), (alt)]), "a b").unwrap_err();
+    assert!(err.msg.contains("an empty alternation never matches"));
+}
+
+#[test]
+fn parse_biased_chains() {
+    let chain = form_pat!((biased_alt
+        [(named "a", (lit_aat "a")), (lit_aat "b"), (lit_aat "c")],
+        (named "x", (scan r"\s*([a-z]+)")),
+        [(named "q", (lit_aat "a")), (lit_aat "q"), (lit_aat "r")]));
+
+    // Each branch is tried in order:
+    assert_eq!(parse_top(&chain, "a b c"), Ok(ast!(({ - "a" => "a"} "b" "c"))));
+    assert_eq!(parse_top(&chain, "a"), Ok(ast!({ - "x" => "a"})));
+    assert_eq!(parse_top(&chain, "a q r"), Ok(ast!(({ - "q" => "a"} "q" "r"))));
+    assert_m!(parse_top(&form_pat!((biased_alt)), "a"), Err(_));
+
+    // The third branch gets the furthest, but the first is the one we blame:
+    let err = parse_top(&chain, "a q").unwrap_err();
+    assert!(err.msg.contains("expected `b`, found `q`"), "{}", err.msg);
+    let err = parse_top(&chain, "a q s").unwrap_err();
+    assert!(err.msg.contains("expected `b`, found `q`"), "{}", err.msg);
+
+    // ...but only if it consumed some input:
+    let err = parse_top(&chain, "z q").unwrap_err();
+    assert!(!err.msg.contains("expected `b`"), "{}", err.msg);
+    // ...even when plan A shares an item with plan B, which happened to predict it first:
+    let se = assoc_n!(
+        "AB" => Rc::new(form_pat!([(lit_aat "a"), (lit_aat "b")])),
+        "Pre" => Rc::new(form_pat!((call "Nothing"))),
+        "Nothing" => Rc::new(form_pat!((anyways ""))));
+    let sharing = form_pat!((biased [(call "Pre"), (call "AB"), (lit_aat "c")],
+        (alt (call "AB"), [(lit_aat "a"), (lit_aat "q"), (lit_aat "r")])));
+    let err = parse(&sharing, &se, empty__code_envs(), "a q").unwrap_err();
+    assert!(err.msg.contains("expected `b`, found `q`"), "{}", err.msg);
+    // ...and, once the chain succeeds, later errors are reported as usual:
+    let err = parse_top(&form_pat!([(named "c", (anyways "")), (lit_aat "y")]), "z").unwrap_err();
+    assert!(err.msg.contains("expected `y`"), "{}", err.msg);
+}
+
+#[test]
+fn parse_forests() {
+    let word = r"\s*([a-z]+)";
+    let either = form_pat!((alt (named "a", (scan word)), (named "b", (scan word))));
+    let all = parse_all(&either, &Assoc::new(), empty__code_envs(), "x").unwrap();
+    assert_eq!(all.len(), 2);
+    assert!(all.contains(&ast!({ - "a" => "x"})));
+    assert!(all.contains(&ast!({ - "b" => "x"})));
+
+    // `Biased` normally rules out plan B:
+    let preferring_a = form_pat!((biased (named "a", (scan word)), (named "b", (scan word))));
+    assert_eq!(parse_top(&preferring_a, "x"), Ok(ast!({ - "a" => "x"})));
+    let all = parse_all(&preferring_a, &Assoc::new(), empty__code_envs(), "x").unwrap();
+    assert_eq!(all.len(), 2);
+
+    // Ambiguities multiply:
+    let pair = form_pat!([(call "Either"), (call "Either")]);
+    let grammar = Assoc::new().set(n("Either"), Rc::new(either));
+    assert_eq!(parse_all(&pair, &grammar, empty__code_envs(), "x y").unwrap().len(), 4);
+
+    // Unambiguous grammars have just the one parse:
+    let just_a = form_pat!((named "a", (scan word)));
+    assert_eq!(
+        parse_all(&just_a, &Assoc::new(), empty__code_envs(), "x"),
+        Ok(vec![ast!({ - "a" => "x"})])
+    );
+    assert_m!(parse_all(&just_a, &Assoc::new(), empty__code_envs(), "x y"), Err(_));
+}
+
+#[test]
+fn switch_on_keywords() {
+    let word = r"\s*([a-z]+)";
+    let stmt = form_pat!((switch [
+        "let" => [(lit_aat "let"), (named "let_name", (scan word))],
+        "fn" => [(lit_aat "fn"), (named "fn_name", (scan word))]],
+        (named "expr", (scan word))));
+
+    assert_eq!(parse_top(&stmt, "let x"), Ok(ast_shape!("let" {- "let_name" => "x"})));
+    assert_eq!(parse_top(&stmt, "  fn f"), Ok(ast_shape!("fn" {- "fn_name" => "f"})));
+    // The keyword has to be the whole token:
+    assert_eq!(parse_top(&stmt, "lettuce"), Ok(ast!({ - "expr" => "lettuce"})));
+    // ...and once it is, there's no backtracking to the default:
+    assert_m!(parse_top(&stmt, "let"), Err(_));
+    assert_eq!(
+        parse_top(&form_pat!((named "expr", (scan word))), "let"),
+        Ok(ast!({ - "expr" => "let"}))
+    );
+}
+
+#[test]
+fn parse_rest_of_input() {
+    let verbatim =
+        form_pat!((pick [(lit_aat "verbatim"), (named "rest", (rest_of_input))], "rest"));
+
+    // Anything goes, even unbalanced delimiters:
+    let rest = parse_top(&verbatim, "verbatim  a (b] c ").unwrap();
+    assert_eq!(rest, ast!("a (b] c"));
+    assert_eq!(parse_top(&verbatim, "verbatim"), Ok(ast!("")));
+    assert_m!(parse_top(&verbatim, "verbose a b c"), Err(_));
+
+    // Earlier parts of the form still need to match up:
+    let two_words = form_pat!([(named "first", (scan r"\s*([a-z]+)")), (lit_aat ":"),
+                               (named "rest", (rest_of_input))]);
+    let parts = parse_top(&two_words, "key: value (with parens)").unwrap().flatten();
+    assert_eq!(parts.get_leaf_or_panic(&n("first")), &ast!("key"));
+    assert_eq!(parts.get_leaf_or_panic(&n("rest")), &ast!("value (with parens)"));
+
+    // Later, we can parse the contents with some other grammar:
+    let words = form_pat!((star (named "elt", (scan r"\s*([a-z]+)"))));
+    assert_m!(parse_group(&words, &Assoc::new(), empty__code_envs(), &rest), Err(_));
+    let rest = parse_top(&verbatim, "verbatim a b c").unwrap();
+    assert_eq!(
+        parse_group(&words, &Assoc::new(), empty__code_envs(), &rest).unwrap(),
+        ast!({ - "elt" => ["a", "b", "c"]})
+    );
+}
+
+#[test]
+fn parse_satisfy() {
+    let shouting = form_pat!((star (named "word",
+        (satisfy (scan r"\s*([a-zA-Z]+)"), |s: &str| s.chars().all(char::is_uppercase)))));
+
+    assert_eq!(parse_top(&shouting, "FOO BAR").unwrap(), ast!({ - "word" => ["FOO", "BAR"]}));
+    assert_m!(parse_top(&shouting, "FOO Bar"), Err(_));
+    assert_m!(parse_top(&shouting, "foo"), Err(_));
+}
+
+#[test]
+fn parse_keywords() {
+    let refs = form_pat!((star (named "ref", varref_aat)));
+    let ctxt = ParseContext::new_from_grammar(Assoc::new()).with_keywords(&[n("and"), n("or")]);
+    let parse_refs =
+        |ctxt: &ParseContext, toks: &str| parse_in_context(&refs, ctxt.clone(), toks, false);
+
+    assert_eq!(parse_refs(&ctxt, "foo bar").unwrap(), ast!({ - "ref" => [(vr "foo"), (vr "bar")]}));
+    assert_m!(parse_refs(&ctxt, "foo and bar"), Err(_));
+    assert_m!(parse_refs(&ctxt, "or"), Err(_));
+    // Without the keywords, they're ordinary names:
+    assert_eq!(
+        parse_refs(&ParseContext::new_from_grammar(Assoc::new()), "and").unwrap(),
+        ast!({ - "ref" => [(vr "and")]})
+    );
+    // Keywords accumulate:
+    let ctxt = ctxt.with_keywords(&[n("not")]);
+    assert_m!(parse_refs(&ctxt, "not"), Err(_));
+    assert_m!(parse_refs(&ctxt, "and"), Err(_));
+}
+
+#[test]
+fn parse_custom() {
+    // Digit groups, like "1 000 000", are one number:
+    let digit_groups = form_pat!((custom |toks: &str| {
+        let pair = regex::Regex::new(r"^\s*([0-9]+)\s+([0-9]+)").unwrap();
+        pair.captures(toks)
+            .map(|caps| (Ast::Atom(n(&format!("{}{}", &caps[1], &caps[2]))), caps[0].len()))
+    }));
+    assert_eq!(parse_top(&digit_groups, " 12 345"), Ok(ast!("12345")));
+    assert_m!(parse_top(&digit_groups, "12"), Err(_));
+
+    // It backtracks like anything else:
+    let num = form_pat!((alt (named "big", (, digit_groups.clone())),
+                             (named "small", (scan r"\s*([0-9]+)"))));
+    assert_eq!(parse_top(&num, "7"), Ok(ast!({ - "small" => "7"})));
+    assert_eq!(parse_top(&num, "7 8"), Ok(ast!({ - "big" => "78"})));
+    let seq = form_pat!([(named "num", (, digit_groups)), (lit_aat "!")]);
+    assert_m!(parse_top(&seq, "6 7 !"), Ok(_));
+    assert_m!(parse_top(&seq, "6 !"), Err(_));
+
+    // Bad lengths are just non-matches:
+    let overlong = form_pat!((custom |toks: &str| Some((ast!("x"), toks.len() + 1))));
+    assert_m!(parse_top(&overlong, "abc"), Err(_));
+    let mid_char = form_pat!((custom |_: &str| Some((ast!("x"), 1))));
+    assert_m!(parse_top(&mid_char, "é"), Err(_));
+}
+
+#[test]
+fn parse_recover() {
+    let stmts = form_pat!((star [(named "stmt", (recover
+        [(named "lhs", (scan r"\s*([a-z]+)")), (lit_aat "="), (named "rhs", (scan r"\s*([0-9]+)"))],
+        ";")), (lit_aat ";")]));
+    let parse_error = |text: &str| {
+        let text = Ast::Atom(n(text));
+        ast!({ crate::grammar::get_parse_error_form() ; "text" => (, text)})
+    };
+
+    let parts = parse_top(&stmts, "a = 1 ; b = = ; c = 3 ;").unwrap().flatten();
+    let stmts = parts.get_rep_leaf_or_panic(n("stmt"));
+    assert_eq!(stmts.len(), 3);
+    assert_eq!(stmts[1], &parse_error("b = ="));
+    // Good statements aren't affected:
+    assert_eq!(stmts[0].flatten().get_leaf_or_panic(&n("lhs")), &ast!("a"));
+    assert_eq!(stmts[2].flatten().get_leaf_or_panic(&n("rhs")), &ast!("3"));
+
+    // Recovery still needs the synchronization point:
+    assert_m!(parse_top(&form_pat!((recover (lit_aat "x"), ";")), "y"), Err(_));
+
+    // ...as a whole word:
+    let blocks = form_pat!([(named "block", (recover (lit_aat "ok"), "end")), (lit_aat "end")]);
+    let parts = parse_top(&blocks, "spend it end").unwrap().flatten();
+    assert_eq!(parts.get_leaf_or_panic(&n("block")), &parse_error("spend it"));
+}
+
+#[test]
+fn parse_pratt_expr() {
+    use crate::grammar::{pratt_expr, Associativity::*};
+    let op = |op: &str, prec: u8, assoc: Associativity| OpPrec { op: n(op), prec, assoc };
+    let binop = crate::form::simple_form(
+        "binop",
+        form_pat!([(named "lhs", (call "Expr")), (named "op", atom), (named "rhs", (call "Expr"))]),
+    );
+    let arith = pratt_expr(
+        Rc::new(form_pat!((scan r"\s*(\d+)"))),
+        vec![
+            op("+", 1, Left),
+            op("-", 1, Left),
+            op("*", 2, Left),
+            op("^", 3, Right),
+            op("-", 4, Prefix),
+        ],
+        binop.clone(),
+    );
+    let se = assoc_n!("DefaultToken" => Rc::new(crate::grammar::new_scan(r"\s*(\S+)")));
+    let parse_arith = |toks: &str| parse(&arith, &se, empty__code_envs(), toks).unwrap();
+
+    assert_eq!(parse_arith("7"), ast!("7"));
+    // `*` binds more tightly than `+`:
+    assert_eq!(
+        parse_arith("1 + 2 * 3"),
+        ast!({ binop.clone() ; "lhs" => "1", "op" => "+",
+            "rhs" => { binop.clone() ; "lhs" => "2", "op" => "*", "rhs" => "3" }})
+    );
+    // `-` is left-associative...
+    assert_eq!(
+        parse_arith("2 - 1 - 1"),
+        ast!({ binop.clone() ; "lhs" => { binop.clone() ; "lhs" => "2", "op" => "-", "rhs" => "1" },
+            "op" => "-", "rhs" => "1" })
+    );
+    // ...and `^` is right-associative:
+    assert_eq!(
+        parse_arith("2 ^ 3 ^ 2"),
+        ast!({ binop.clone() ; "lhs" => "2", "op" => "^",
+            "rhs" => { binop.clone() ; "lhs" => "3", "op" => "^", "rhs" => "2" }})
+    );
+    // Prefix operators work, too:
+    assert_eq!(
+        parse_arith("- 2 * 3"),
+        ast!({ binop.clone() ; "lhs" => { binop.clone() ; "op" => "-", "rhs" => "2" },
+            "op" => "*", "rhs" => "3" })
+    );
+    assert_m!(parse(&arith, &se, empty__code_envs(), "1 + * 2"), Err(_));
+}
+
+#[test]
+fn error_recovery() {
+    let list = form_pat!([(scan r"\s*(\()"), (star (named "elt", (scan r"\s*([a-z]+)"))),
+                          (scan r"\s*(\))")]);
+
+    let (forms, errs) = parse_forms_recovering(
+        &list,
+        &Assoc::new(),
+        empty__code_envs(),
+        "(a b) (c 1 (d)) (e f)",
+        None,
+    );
+    assert_eq!(forms.len(), 2);
+    assert_eq!(*forms[0].ast, parse_top(&list, "(a b)").unwrap());
+    assert_eq!(*forms[1].ast, parse_top(&list, "(e f)").unwrap());
+    assert_eq!(forms[1].range, 16..21);
+    assert_eq!(errs.len(), 1);
+    assert!(errs[0].msg.contains("(c• 1"));
+
+    // A recovery literal can cut a broken form short:
+    let (forms, errs) = parse_forms_recovering(
+        &list,
+        &Assoc::new(),
+        empty__code_envs(),
+        "(a b) (c 1 ; (e f)",
+        Some(";"),
+    );
+    assert_eq!(forms.len(), 2);
+    assert_eq!(*forms[1].ast, parse_top(&list, "(e f)").unwrap());
+    assert_eq!(errs.len(), 1);
+
+    // ...but an empty one can't:
+    let (forms, errs) = parse_forms_recovering(
+        &list,
+        &Assoc::new(),
+        empty__code_envs(),
+        "(a b) (c 1) (e f)",
+        Some(""),
+    );
+    assert_eq!(forms.len(), 2);
+    assert_eq!(errs.len(), 1);
+
+    // Without errors, it's just `parse_forms`:
+    let (forms, errs) =
+        parse_forms_recovering(&list, &Assoc::new(), empty__code_envs(), "(a) (b)", None);
+    assert_eq!(forms, parse_forms(&list, &Assoc::new(), empty__code_envs(), "(a) (b)").unwrap());
+    assert!(errs.is_empty());
+}
+
+#[test]
+fn parse_from_stream() {
+    use std::cell::Cell;
+
+    let list = form_pat!([(scan r"\s*(\()"), (star (named "elt", (scan r"\s*([a-z]+)"))),
+                          (scan r"\s*(\))")]);
+    let pulled = Cell::new(0);
+    let chunks = vec!["(a b) (c", " d)\n", "", "(e f)"].into_iter().map(|chunk| {
+        pulled.set(pulled.get() + 1);
+        chunk.to_owned()
+    });
+    let mut forms = parse_stream(&list, &Assoc::new(), empty__code_envs(), chunks);
+
+    assert_eq!(forms.next(), Some(parse_top(&list, "(a b)")));
+    assert_eq!(pulled.get(), 1); // Nothing more was needed
+    assert_eq!(forms.next(), Some(parse_top(&list, "(c d)")));
+    assert_eq!(pulled.get(), 2);
+    assert_eq!(forms.next(), Some(parse_top(&list, "(e f)")));
+    assert_eq!(forms.next(), None);
+
+    // A form that's never finished is an error:
+    let chunks = vec!["(a) (b".to_owned()].into_iter();
+    let mut forms = parse_stream(&list, &Assoc::new(), empty__code_envs(), chunks);
+    assert_eq!(forms.next(), Some(parse_top(&list, "(a)")));
+    assert_m!(forms.next(), Some(Err(_)));
+    assert_eq!(forms.next(), None);
+
+    // Spans count from the beginning of the stream:
+    let list_form = crate::form::simple_form("list", list.clone());
+    let lists = form_pat!((scope list_form));
+    let chunks = vec!["(a b) (c", " d)\n", "(e f)"].into_iter().map(|chunk| chunk.to_owned());
+    let spans: Vec<_> = parse_stream(&lists, &Assoc::new(), empty__code_envs(), chunks)
+        .map(|form| form.unwrap().span().map(|span| (span.start, span.end)))
+        .collect();
+    assert_eq!(spans, vec![Some((0, 5)), Some((6, 11)), Some((12, 17))]);
+}
+
+#[test]
+fn parse_prefixes() {
+    let pair = form_pat!([(named "a", (scan r"\s*([a-z]+)")), (lit_aat "="),
+                          (named "b", (scan r"\s*([a-z]+)"))]);
+    let parse_prefix_top = |toks| parse_prefix(&pair, &Assoc::new(), empty__code_envs(), toks);
+
+    let toks = "  x = y; more stuff";
+    let (ast, consumed) = parse_prefix_top(toks).unwrap();
+    assert_eq!(ast, parse_top(&pair, "x = y").unwrap());
+    assert_eq!(consumed, 7);
+    assert_eq!(&toks[consumed..], "; more stuff");
+
+    assert_eq!(parse_prefix_top("x = y"), Ok((parse_top(&pair, "x = y").unwrap(), 5)));
+    let err = parse_prefix_top("x y = z").unwrap_err();
+    assert!(err.msg.contains("expected `=`"), "{}", err.msg);
+}
+
+#[test]
+fn parse_incrementally() {
+    let pair = form_pat!([(named "a", (scan r"\s*([a-z]+)")), (named "b", (scan r"\s*([a-z]+)"))]);
+    let mut parser = parse_incremental(&pair, &Assoc::new(), empty__code_envs());
+
+    assert_eq!(parser.feed("x"), IncrementalParse::NeedMore);
+    assert_eq!(parser.feed(" y"), IncrementalParse::Done(parse_top(&pair, "x y").unwrap()));
+
+    // The parser starts over, and whitespace alone doesn't count as a start:
+    assert_eq!(parser.feed("  "), IncrementalParse::NeedMore);
+    assert_eq!(parser.feed("z"), IncrementalParse::NeedMore);
+    assert_eq!(parser.feed(" w"), IncrementalParse::Done(parse_top(&pair, "z w").unwrap()));
+
+    // Input that can't be the start of a `pair`:
+    assert_m!(parser.feed("!"), IncrementalParse::Error(_));
+    assert_eq!(parser.feed("p"), IncrementalParse::NeedMore);
+    assert_m!(parser.finish(), Some(Err(_)));
+    assert_eq!(parser.finish(), None);
+
+    // One chunk can hold more than one `pair`:
+    assert_eq!(parser.feed("a b c d e"), IncrementalParse::Done(parse_top(&pair, "a b").unwrap()));
+    assert_eq!(parser.feed(""), IncrementalParse::Done(parse_top(&pair, "c d").unwrap()));
+    assert_eq!(parser.feed(" f"), IncrementalParse::Done(parse_top(&pair, "e f").unwrap()));
+
+    // Groups can span chunks:
+    let group = form_pat!((named "body", (any_group "(")));
+    let mut parser = parse_incremental(&group, &Assoc::new(), empty__code_envs());
+    assert_eq!(parser.feed("(a"), IncrementalParse::NeedMore);
+    assert_eq!(parser.feed(" b)"), IncrementalParse::Done(parse_top(&group, "(a b)").unwrap()));
+}
+
+#[test]
+fn parse_tracing() {
+    let ab = form_pat!((alt [(scan "(a)"), (scan "(c)")], [(scan "(a)"), (scan "(b)")]));
+
+    let events = Rc::new(RefCell::new(vec![]));
+    let events_ = events.clone();
+    let res =
+        with_parse_tracer(move |e| events_.borrow_mut().push(e.clone()), || parse_top(&ab, "ab"));
+    assert_m!(res, Ok(_));
+
+    use self::ParseEvent::*;
+    fn enter(rule: &str, pos: usize) -> ParseEvent { Enter { rule: rule.to_owned(), pos } }
+    fn exit(rule: &str, start: usize, end: usize) -> ParseEvent {
+        Exit { rule: rule.to_owned(), start, end }
+    }
+    fn fail(rule: &str, pos: usize) -> ParseEvent { Fail { rule: rule.to_owned(), pos } }
+
+    assert_eq!(*events.borrow(), vec![
+        enter("Alt", 0),
+        enter("Seq", 0),
+        enter("Seq", 0),
+        enter("Scan((a))", 0),
+        enter("Scan((a))", 0),
+        exit("Scan((a))", 0, 1),
+        exit("Scan((a))", 0, 1),
+        enter("Scan((c))", 1),
+        enter("Scan((b))", 1),
+        exit("Scan((b))", 1, 2),
+        exit("Seq", 0, 2),
+        exit("Alt", 0, 2),
+        // Failures are reported at the end:
+        fail("Seq", 0),
+        fail("Scan((c))", 1),
+    ]);
+
+    // No tracer, no events:
+    events.borrow_mut().clear();
+    assert_m!(parse_top(&ab, "ab"), Ok(_));
+    assert_eq!(events.borrow().len(), 0);
+}
+
+#[test]
+fn parse_ambiguous_calls_once_per_position() {
+    let expr = Rc::new(form_pat!((alt
+        [(call "expr"), (lit_aat "+"), (call "expr")],
+        [(call "expr"), (lit_aat "+"), (call "expr"), (lit_aat "+"), (call "expr")],
+        (scan r"\s*([a-z])"))));
+    let grammar = assoc_n!("expr" => expr.clone());
+    let toks = vec!["a"; 40].join(" + ");
+
+    let entered = Rc::new(RefCell::new(vec![]));
+    let entered_ = entered.clone();
+    let res = with_parse_tracer(
+        move |e| {
+            if let ParseEvent::Enter { ref rule, pos } = *e {
+                if rule == "Alt" {
+                    entered_.borrow_mut().push(pos)
+                }
+            }
+        },
+        || recognize(&Call(n("expr")), &grammar, &toks),
+    );
+    assert!(res);
+
+    // Exponentially many parses (`parse` would complain),
+    //  but `expr` was started at most once at each position:
+    let mut positions = entered.borrow().clone();
+    positions.sort();
+    positions.dedup();
+    assert_eq!(positions.len(), entered.borrow().len());
+    assert_eq!(positions.len(), 40);
+}
@@ -19,6 +19,8 @@ custom_derive! {
     pub struct Form {
         /// The name of the form. Mainly for internal use.
         pub name: Name,
+        /// What to call the form in diagnostics (e.g. "function application"), if not `name`.
+        pub description: Option<String>,
         /// The grammar the programmer should use to invoke this form.
         /// This contains information about bindings and syntax extension:
         pub grammar: Rc<FormPat>,
@@ -78,6 +80,16 @@ impl PartialEq for Form {
     fn eq(&self, other: &Form) -> bool { self as *const Form == other as *const Form }
 }
 
+impl Form {
+    /// How diagnostics should refer to this form.
+    pub fn display_name(&self) -> String {
+        match self.description {
+            Some(ref description) => description.clone(),
+            None => format!("`{}`", self.name),
+        }
+    }
+}
+
 impl Debug for Form {
     fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
         formatter.write_str(format!("[FORM {:#?}]", self.name).as_str())
@@ -85,13 +97,23 @@ impl Debug for Form {
 }
 
 pub fn simple_form(form_name: &str, p: FormPat) -> Rc<Form> {
+    Rc::new(inert_form(form_name, None, p))
+}
+
+/// Like `simple_form`, but diagnostics will call it `description`.
+pub fn described_form(form_name: &str, description: &str, p: FormPat) -> Rc<Form> {
+    Rc::new(inert_form(form_name, Some(description.to_owned()), p))
+}
+
+fn inert_form(form_name: &str, description: Option<String>, p: FormPat) -> Form {
     use WalkRule::*;
-    Rc::new(Form {
+    Form {
         name: n(form_name),
+        description,
         grammar: Rc::new(p),
         type_compare: Both(NotWalked, NotWalked),
         synth_type: Positive(NotWalked),
         eval: Positive(NotWalked),
         quasiquote: Both(LiteralLike, LiteralLike),
-    })
+    }
 }
@@ -5,6 +5,7 @@ use crate::{
     beta::{Beta, ExportBeta},
     form::Form,
     name::*,
+    read::DelimChar,
     runtime::{eval::Value, reify},
     util::assoc::Assoc,
 };
@@ -28,6 +29,19 @@ custom_derive! {
         /// The regex must have a single capturing group.
         /// The contents of the capturing group are
         Scan(Scanner),
+        /// Matches a whole group delimited by `DelimChar` (balanced, but otherwise uninterpreted).
+        /// Produces an `Atom` of the text between the delimiters, to be parsed later.
+        AnyGroup(DelimChar),
+        /// Matches whatever a Rust function says it does,
+        ///  for syntax that the other patterns can't express.
+        /// Given the rest of the input, the function produces a parse and how many bytes it used.
+        Custom(CustomParser),
+        /// Gives up: matches all the (nonblank) text before the next occurrence of the name,
+        ///  producing a `parse_error` node. (Construct this with `recover`.)
+        SkipTo(Name),
+        /// Matches everything up to the end of the input, producing an `Atom` of it
+        ///  (like `AnyGroup`, to be parsed later). Possibly empty.
+        RestOfInput,
 
         /// Marks this rule as too commonly-used to be informative;
         ///  prevents display of this rule in parse errors,
@@ -37,6 +51,8 @@ custom_derive! {
         Reserved(Rc<FormPat>, Vec<Name>),
         /// Matches if the sub-pattern equals the given name
         Literal(Rc<FormPat>, Name),
+        /// Matches an atom or varref, but only if its spelling satisfies the predicate
+        Satisfy(Rc<FormPat>, Predicate),
 
         /// Matches an atom, turns it into a `VariableReference`
         VarRef(Rc<FormPat>),
@@ -51,6 +67,13 @@ custom_derive! {
         Alt(Vec<Rc<FormPat>>),
         /// Matches the LHS pattern, or, failing that, the RHS pattern.
         Biased(Rc<FormPat>, Rc<FormPat>),
+        /// Looks at the next token and matches the pattern for the keyword it is
+        ///  (or, if it's none of them, the default pattern), without trying the others.
+        /// The patterns must match their keywords themselves.
+        Switch(Vec<(Name, Rc<FormPat>)>, Rc<FormPat>),
+        /// Matches operands separated by operators (construct this with `pratt_expr`),
+        ///  and groups them into `Form` nodes according to the precedence table.
+        PrattExpr(Rc<FormPat>, Vec<OpPrec>, Rc<Form>),
 
         /// Lookup a nonterminal in the current syntactic environment.
         Call(Name),
@@ -92,7 +115,8 @@ impl FormPat {
                 }
                 res
             }
-            Scope(_, _) | Pick(_, _) => vec![], // No more bindings in this scope
+            // No more bindings in this scope:
+            Scope(_, _) | Pick(_, _) | PrattExpr(_, _, _) => vec![],
             Star(ref body) | Plus(ref body) => {
                 body.binders().into_iter().map(|(n, depth)| (n, depth + 1)).collect()
             }
@@ -103,11 +127,28 @@ impl FormPat {
             | QuoteDeepen(ref body, _)
             | QuoteEscape(ref body, _)
             | Common(ref body)
-            | Reserved(ref body, _) => body.binders(),
+            | Reserved(ref body, _)
+            | Satisfy(ref body, _) => body.binders(),
             Biased(ref body_a, ref body_b) => {
                 body_a.binders().tap_mut(|v| v.append(&mut body_b.binders()))
             }
-            Anyways(_) | Impossible | Literal(_, _) | Scan(_) | VarRef(_) | Call(_) => vec![],
+            Switch(ref cases, ref default) => {
+                let mut res = vec![];
+                for (_, case) in cases {
+                    res.append(&mut case.binders());
+                }
+                res.tap_mut(|v| v.append(&mut default.binders()))
+            }
+            Anyways(_)
+            | Impossible
+            | Literal(_, _)
+            | Scan(_)
+            | AnyGroup(_)
+            | Custom(_)
+            | SkipTo(_)
+            | RestOfInput
+            | VarRef(_)
+            | Call(_) => vec![],
         }
     }
 
@@ -127,8 +168,10 @@ impl FormPat {
             }
             Named(_, _) => None, // Otherwise, skip
             Call(_) => None,
-            Scope(_, _) | Pick(_, _) => None, // Only look in the current scope
-            Anyways(_) | Impossible | Scan(_) => None,
+            // Only look in the current scope:
+            Scope(_, _) | Pick(_, _) | PrattExpr(_, _, _) => None,
+            Anyways(_) | Impossible | Scan(_) | AnyGroup(_) | Custom(_) | SkipTo(_)
+            | RestOfInput => None,
             Star(ref body)
             | Plus(ref body)
             | SynImport(ref body, _, _)
@@ -139,7 +182,8 @@ impl FormPat {
             | QuoteDeepen(ref body, _)
             | QuoteEscape(ref body, _)
             | Common(ref body)
-            | Reserved(ref body, _) => body.find_named_call(n),
+            | Reserved(ref body, _)
+            | Satisfy(ref body, _) => body.find_named_call(n),
             Seq(ref bodies) | Alt(ref bodies) => {
                 for body in bodies {
                     let sub_fnc = body.find_named_call(n);
@@ -152,10 +196,121 @@ impl FormPat {
             Biased(ref body_a, ref body_b) => {
                 body_a.find_named_call(n).or_else(|| body_b.find_named_call(n))
             }
+            Switch(ref cases, ref default) => cases
+                .iter()
+                .find_map(|(_, case)| case.find_named_call(n))
+                .or_else(|| default.find_named_call(n)),
+        }
+    }
+
+    /// Look for mistakes in a hand-built grammar (to be used in `se`).
+    /// `Call`s under a `SynImport` aren't checked, since the extension might define them.
+    pub fn validate(&self, se: &SynEnv) -> Result<(), Vec<GrammarErr>> {
+        let mut errs = vec![];
+        self.validate_under(se, false, true, &mut errs);
+        if errs.is_empty() {
+            Ok(())
+        } else {
+            Err(errs)
+        }
+    }
+
+    fn validate_under(
+        &self,
+        se: &SynEnv,
+        in_scope: bool,
+        check_calls: bool,
+        errs: &mut Vec<GrammarErr>,
+    ) {
+        match *self {
+            Named(name, ref body) => {
+                if !in_scope {
+                    errs.push(GrammarErr::NamedOutsideScope(name));
+                }
+                body.validate_under(se, in_scope, check_calls, errs)
+            }
+            Call(nt) => {
+                if check_calls && se.find(&nt).is_none() {
+                    errs.push(GrammarErr::UnknownNonterminal(nt));
+                }
+            }
+            Alt(ref bodies) if bodies.is_empty() => errs.push(GrammarErr::EmptyAlt),
+            Scope(ref f, _) => f.grammar.validate_under(se, true, check_calls, errs),
+            Pick(ref body, _) | PrattExpr(ref body, _, _) => {
+                body.validate_under(se, true, check_calls, errs)
+            }
+            SynImport(ref lhs, ref body, _) => {
+                lhs.validate_under(se, in_scope, check_calls, errs);
+                body.validate_under(se, in_scope, false, errs);
+            }
+            Seq(ref bodies) | Alt(ref bodies) => {
+                for body in bodies {
+                    body.validate_under(se, in_scope, check_calls, errs);
+                }
+            }
+            Biased(ref body_a, ref body_b) => {
+                body_a.validate_under(se, in_scope, check_calls, errs);
+                body_b.validate_under(se, in_scope, check_calls, errs);
+            }
+            Switch(ref cases, ref default) => {
+                for (_, case) in cases {
+                    case.validate_under(se, in_scope, check_calls, errs);
+                }
+                default.validate_under(se, in_scope, check_calls, errs);
+            }
+            Star(ref body)
+            | Plus(ref body)
+            | Common(ref body)
+            | Reserved(ref body, _)
+            | Satisfy(ref body, _)
+            | Literal(ref body, _)
+            | VarRef(ref body)
+            | NameImport(ref body, _)
+            | NameImportPhaseless(ref body, _)
+            | QuoteDeepen(ref body, _)
+            | QuoteEscape(ref body, _) => body.validate_under(se, in_scope, check_calls, errs),
+            Anyways(_) | Impossible | Scan(_) | AnyGroup(_) | Custom(_) | SkipTo(_)
+            | RestOfInput => {}
+        }
+    }
+}
+
+/// A mistake in the construction of a grammar (see `FormPat::validate`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrammarErr {
+    /// Nothing would keep what this captures.
+    NamedOutsideScope(Name),
+    UnknownNonterminal(Name),
+    /// An `Alt` with no alternatives can never match (use `Impossible` to mean that).
+    EmptyAlt,
+}
+
+impl std::fmt::Display for GrammarErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            GrammarErr::NamedOutsideScope(name) => {
+                write!(f, "`{}` is captured outside of any `Scope`", name)
+            }
+            GrammarErr::UnknownNonterminal(nt) => {
+                write!(f, "the nonterminal `{}` is undefined", nt)
+            }
+            GrammarErr::EmptyAlt => write!(f, "an `Alt` has no alternatives"),
         }
     }
 }
 
+custom_derive! {
+    /// How an operator in a `PrattExpr` groups with its neighbors.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, Reifiable)]
+    pub enum Associativity { Left, Right, Prefix }
+}
+
+custom_derive! {
+    /// An entry in a `PrattExpr`'s precedence table. Higher `prec`s bind more tightly.
+    #[derive(Debug, PartialEq, Eq, Clone, Reifiable)]
+    pub struct OpPrec { pub op: Name, pub prec: u8, pub assoc: Associativity }
+}
+
 #[derive(Clone)]
 pub struct SyntaxExtension(
     pub Rc<Box<(dyn Fn(crate::earley::ParseContext, Ast) -> crate::earley::ParseContext)>>,
@@ -184,10 +339,123 @@ impl std::fmt::Debug for SyntaxExtension {
     }
 }
 
+#[derive(Clone)]
+pub struct Predicate(pub Rc<dyn Fn(&str) -> bool>);
+
+impl PartialEq for Predicate {
+    /// pointer equality! (for testing)
+    fn eq(&self, other: &Predicate) -> bool { Rc::ptr_eq(&self.0, &other.0) }
+}
+
+impl reify::Reifiable for Predicate {
+    fn ty_name() -> Name { n("Predicate") }
+
+    fn reify(&self) -> Value {
+        let pred = self.0.clone();
+        reify::reify_1ary_function(Rc::new(Box::new(move |s: String| pred(&s))))
+    }
+
+    fn reflect(v: &Value) -> Self {
+        let pred = reify::reflect_1ary_function::<String, bool>(v.clone());
+        Predicate(Rc::new(move |s: &str| pred(s.to_owned())))
+    }
+}
+
+impl std::fmt::Debug for Predicate {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.write_str("[predicate]")
+    }
+}
+
+/// Given the rest of the input, a parse and its length (in bytes).
+pub type CustomParseFn = dyn Fn(&str) -> Option<(Ast, usize)>;
+
+#[derive(Clone)]
+pub struct CustomParser(pub Rc<CustomParseFn>);
+
+impl PartialEq for CustomParser {
+    /// pointer equality! (for testing)
+    fn eq(&self, other: &CustomParser) -> bool { Rc::ptr_eq(&self.0, &other.0) }
+}
+
+impl reify::Reifiable for CustomParser {
+    fn ty_name() -> Name { n("CustomParser") }
+
+    fn reify(&self) -> Value {
+        let parser = self.0.clone();
+        reify::reify_1ary_function(Rc::new(Box::new(move |s: String| parser(&s))))
+    }
+
+    fn reflect(v: &Value) -> Self {
+        let parser = reify::reflect_1ary_function::<String, Option<(Ast, usize)>>(v.clone());
+        CustomParser(Rc::new(move |s: &str| parser(s.to_owned())))
+    }
+}
+
+impl std::fmt::Debug for CustomParser {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.write_str("[custom parser]")
+    }
+}
+
 pub fn new_scan(regex: &str) -> FormPat {
     Scan(Scanner(regex::Regex::new(&format!("^{}", regex)).unwrap()))
 }
 
+/// Expressions made of `operand`s and the operators in `table`,
+///  grouped by precedence climbing (instead of by a nonterminal per precedence level).
+/// Binary operators make `form` nodes with "lhs", "op", and "rhs" parts;
+///  prefix operators make `form` nodes with just "op" and "rhs".
+pub fn pratt_expr(operand: Rc<FormPat>, table: Vec<OpPrec>, form: Rc<Form>) -> FormPat {
+    let ops = |prefix: bool| {
+        Rc::new(Alt(table
+            .iter()
+            .filter(|entry| (entry.assoc == Associativity::Prefix) == prefix)
+            .map(|entry| Rc::new(Literal(Rc::new(Call(n("DefaultToken"))), entry.op)))
+            .collect()))
+    };
+    let unit = Rc::new(Seq(vec![Rc::new(Star(Rc::new(Named(n("prefix"), ops(true))))), operand]));
+    let links = Star(Rc::new(Named(n("link"), Rc::new(Seq(vec![ops(false), unit.clone()])))));
+    PrattExpr(Rc::new(Seq(vec![unit, Rc::new(links)])), table, form)
+}
+
+thread_local! {
+    static parse_error_form: Rc<Form> = crate::form::simple_form("parse_error", Impossible);
+}
+
+/// The form of the placeholder nodes that `SkipTo` produces. Their "text" part is what was skipped.
+pub fn get_parse_error_form() -> Rc<Form> { parse_error_form.with(|pef| pef.clone()) }
+
+/// Matches `body`, or, if it can't, skips to the next `sync` (e.g. the `;` after a statement),
+///  leaving a `parse_error` node in its place. (`sync` itself still needs to be matched.)
+pub fn recover(body: Rc<FormPat>, sync: Name) -> FormPat { Biased(body, Rc::new(SkipTo(sync))) }
+
+/// Matches `body` (which should have a part named `name`), or, if it can't, nothing.
+/// Either way, there's a `name` part (`default`, if `body` didn't match),
+///  so that every element of a repetition has one.
+pub fn optional_named(body: Rc<FormPat>, name: Name, default: Ast) -> FormPat {
+    Biased(body, Rc::new(Named(name, Rc::new(Anyways(default)))))
+}
+
+/// Tries each of `alts` in turn, like a chain of `Biased`s: the first one that matches wins.
+/// If none of them match, the parse error comes from the first one that got anywhere.
+pub fn biased_alt(mut alts: Vec<Rc<FormPat>>) -> FormPat {
+    let last = match alts.pop() {
+        Some(last) => last,
+        None => return Impossible,
+    };
+    (*alts.into_iter().rev().fold(last, |plan_b, plan_a| Rc::new(Biased(plan_a, plan_b)))).clone()
+}
+
+/// An indented block (see `read::layout`) containing `body`.
+pub fn block(body: Rc<FormPat>) -> FormPat {
+    Seq(vec![
+        Rc::new(new_scan(&format!(r"\s*({})", crate::read::INDENT))),
+        body,
+        Rc::new(new_scan(&format!(r"\s*({})", crate::read::DEDENT))),
+    ])
+}
+
 #[derive(Clone)]
 pub struct Scanner(pub regex::Regex);
 
@@ -221,6 +489,238 @@ pub fn parse_top(f: &FormPat, toks: &str) -> Result<Ast, crate::earley::ParseErr
     parse(f, &Assoc::new(), crate::earley::empty__code_envs(), toks)
 }
 
+/// Parse `src` as a `Type` in `se` (e.g. `core_forms::get_core_forms()`).
+/// This only parses; `ty::synth_type` checks the result (and resolves any names in it).
+pub fn parse_type(src: &str, se: &SynEnv) -> Result<Ast, crate::earley::ParseError> {
+    parse(&Call(n("Type")), se, crate::runtime::core_values::get_core_envs(), src)
+}
+
+/// A readable, EBNF-ish rendering of `se` (e.g. for reference documentation),
+///  with one `nt ::= ...` line per nonterminal, sorted by name.
+/// Names, scopes, imports, and the like are left out; only what the text looks like remains.
+pub fn render_grammar(se: &SynEnv) -> String {
+    let mut nts: Vec<(&Name, &Rc<FormPat>)> = se.iter_pairs().collect();
+    nts.sort_by_key(|(nt, _)| **nt);
+    let mut res = String::new();
+    for (nt, pat) in nts {
+        res.push_str(&format!("{} ::= {}\n", nt, or_epsilon(render_pat(pat, 0))));
+    }
+    res
+}
+
+fn or_epsilon(rendered: String) -> String {
+    if rendered.is_empty() {
+        "ε".to_owned()
+    } else {
+        rendered
+    }
+}
+
+/// `binding` is 0 at the top, 1 inside a sequence (so choices need parentheses),
+///  and 2 under a `*` or `+` (so sequences do, too).
+fn render_pat(pat: &FormPat, binding: u8) -> String {
+    let parenthesize = |rendered: String, needed: bool| {
+        if needed {
+            format!("({})", rendered)
+        } else {
+            rendered
+        }
+    };
+    let choice = |choices: Vec<&Rc<FormPat>>, sep: &str| {
+        let rendered: Vec<String> =
+            choices.iter().map(|choice| or_epsilon(render_pat(choice, 1))).collect();
+        parenthesize(rendered.join(sep), binding >= 1 && rendered.len() > 1)
+    };
+    let seq = |subs: Vec<&Rc<FormPat>>| {
+        let rendered: Vec<String> =
+            subs.iter().map(|sub| render_pat(sub, 1)).filter(|r| !r.is_empty()).collect();
+        parenthesize(rendered.join(" "), binding >= 2 && rendered.len() > 1)
+    };
+    match *pat {
+        Anyways(_) => "".to_owned(),
+        Impossible => "∅".to_owned(),
+        Scan(Scanner(ref regex)) => format!("/{}/", regex.as_str().trim_start_matches('^')),
+        AnyGroup(d) => format!("{} ... {}", d.open(), d.close()),
+        Custom(_) => "<custom>".to_owned(),
+        SkipTo(sync) => format!("<anything up to `{}`>", sync),
+        RestOfInput => "<rest of input>".to_owned(),
+        Literal(_, name) => format!("'{}'", name),
+        Call(nt) => format!("{}", nt),
+        Seq(ref subs) => seq(subs.iter().collect()),
+        SynImport(ref lhs, ref body, _) => seq(vec![lhs, body]),
+        Star(ref body) => format!("{}*", render_pat(body, 2)),
+        Plus(ref body) => format!("{}+", render_pat(body, 2)),
+        Alt(ref subs) => choice(subs.iter().collect(), " | "),
+        // Like PEG's ordered choice:
+        Biased(ref plan_a, ref plan_b) => choice(vec![plan_a, plan_b], " / "),
+        Switch(ref cases, ref default) => {
+            choice(cases.iter().map(|(_, case)| case).chain(Some(default)).collect(), " | ")
+        }
+        Scope(ref f, _) => render_pat(&f.grammar, binding),
+        Common(ref body)
+        | Reserved(ref body, _)
+        | Satisfy(ref body, _)
+        | VarRef(ref body)
+        | PrattExpr(ref body, _, _)
+        | Named(_, ref body)
+        | Pick(ref body, _)
+        | NameImport(ref body, _)
+        | NameImportPhaseless(ref body, _)
+        | QuoteDeepen(ref body, _)
+        | QuoteEscape(ref body, _) => render_pat(body, binding),
+    }
+}
+
+/// Every form reachable from `se`, with the nonterminal it's reachable from,
+///  sorted by nonterminal and then form name (e.g. for autocompletion).
+/// A syntax extension is evaluated once (but only if its LHS can match nothing;
+///  otherwise, what it does depends on the text being parsed).
+pub fn all_forms(se: &SynEnv) -> Vec<(Name, Rc<Form>)> {
+    let mut res = vec![];
+    all_forms_in_env(se, true, &mut res);
+    res.sort_by(|(nt_l, f_l), (nt_r, f_r)| (nt_l, f_l.name).cmp(&(nt_r, f_r.name)));
+    res
+}
+
+fn all_forms_in_env(se: &SynEnv, extend: bool, res: &mut Vec<(Name, Rc<Form>)>) {
+    for (nt, pat) in se.iter_pairs() {
+        all_forms_in_pat(*nt, pat, se, extend, res);
+    }
+}
+
+fn all_forms_in_pat(
+    nt: Name,
+    pat: &FormPat,
+    se: &SynEnv,
+    extend: bool,
+    res: &mut Vec<(Name, Rc<Form>)>,
+) {
+    match *pat {
+        Scope(ref f, _) => {
+            if !res.iter().any(|(res_nt, res_f)| *res_nt == nt && Rc::ptr_eq(res_f, f)) {
+                res.push((nt, f.clone()));
+                all_forms_in_pat(nt, &f.grammar, se, extend, res);
+            }
+        }
+        PrattExpr(ref body, _, ref f) => {
+            if !res.iter().any(|(res_nt, res_f)| *res_nt == nt && Rc::ptr_eq(res_f, f)) {
+                res.push((nt, f.clone()));
+            }
+            all_forms_in_pat(nt, body, se, extend, res);
+        }
+        SynImport(ref lhs, ref body, ref f) => {
+            all_forms_in_pat(nt, lhs, se, extend, res);
+            let lhs_res = parse(lhs, se, crate::earley::empty__code_envs(), "");
+            match lhs_res {
+                Ok(lhs_ast) if extend => {
+                    let empty_ctxt = crate::earley::ParseContext::new(
+                        se.clone(),
+                        crate::earley::empty__code_envs(),
+                    );
+                    let new_se = f.0(empty_ctxt, lhs_ast).grammar;
+                    // Don't evaluate any more extensions; they might go on forever.
+                    all_forms_in_pat(nt, body, &new_se, false, res);
+                    all_forms_in_env(&new_se, false, res);
+                }
+                _ => all_forms_in_pat(nt, body, se, extend, res),
+            }
+        }
+        Seq(ref bodies) | Alt(ref bodies) => {
+            for body in bodies {
+                all_forms_in_pat(nt, body, se, extend, res);
+            }
+        }
+        Biased(ref body_a, ref body_b) => {
+            all_forms_in_pat(nt, body_a, se, extend, res);
+            all_forms_in_pat(nt, body_b, se, extend, res);
+        }
+        Switch(ref cases, ref default) => {
+            for (_, case) in cases {
+                all_forms_in_pat(nt, case, se, extend, res);
+            }
+            all_forms_in_pat(nt, default, se, extend, res);
+        }
+        Star(ref body)
+        | Plus(ref body)
+        | Common(ref body)
+        | Reserved(ref body, _)
+        | Satisfy(ref body, _)
+        | Literal(ref body, _)
+        | VarRef(ref body)
+        | Named(_, ref body)
+        | Pick(ref body, _)
+        | NameImport(ref body, _)
+        | NameImportPhaseless(ref body, _)
+        | QuoteDeepen(ref body, _)
+        | QuoteEscape(ref body, _) => all_forms_in_pat(nt, body, se, extend, res),
+        Anyways(_) | Impossible | Scan(_) | AnyGroup(_) | Custom(_) | SkipTo(_) | RestOfInput
+        | Call(_) => {}
+    }
+}
+
+/// Finds `Named` patterns that re-use the name of a `Named` pattern they're inside of
+///  (this doesn't look through `Call`s, so it only sees the grammar as written).
+/// Each result is the shadowed name, and the form whose `Scope` re-binds it
+///  (`None` means that no `Scope` intervenes, so the two captures collide).
+pub fn find_shadowing(pat: &FormPat) -> Vec<(Name, Option<Name>)> {
+    let mut res = vec![];
+    find_shadowing_under(pat, &mut vec![], None, &mut res);
+    res
+}
+
+fn find_shadowing_under(
+    pat: &FormPat,
+    outer: &mut Vec<Name>,
+    scope: Option<Name>,
+    res: &mut Vec<(Name, Option<Name>)>,
+) {
+    match *pat {
+        Named(name, ref body) => {
+            if outer.contains(&name) {
+                res.push((name, scope));
+            }
+            outer.push(name);
+            find_shadowing_under(body, outer, scope, res);
+            outer.pop();
+        }
+        Scope(ref f, _) => find_shadowing_under(&f.grammar, outer, Some(f.name), res),
+        PrattExpr(ref body, _, ref f) => find_shadowing_under(body, outer, Some(f.name), res),
+        SynImport(ref lhs, ref body, _) => {
+            find_shadowing_under(lhs, outer, scope, res);
+            find_shadowing_under(body, outer, scope, res);
+        }
+        Seq(ref bodies) | Alt(ref bodies) => {
+            for body in bodies {
+                find_shadowing_under(body, outer, scope, res);
+            }
+        }
+        Biased(ref body_a, ref body_b) => {
+            find_shadowing_under(body_a, outer, scope, res);
+            find_shadowing_under(body_b, outer, scope, res);
+        }
+        Switch(ref cases, ref default) => {
+            for (_, case) in cases {
+                find_shadowing_under(case, outer, scope, res);
+            }
+            find_shadowing_under(default, outer, scope, res);
+        }
+        Star(ref body)
+        | Plus(ref body)
+        | Common(ref body)
+        | Reserved(ref body, _)
+        | Satisfy(ref body, _)
+        | Literal(ref body, _)
+        | VarRef(ref body)
+        | Pick(ref body, _)
+        | NameImport(ref body, _)
+        | NameImportPhaseless(ref body, _)
+        | QuoteDeepen(ref body, _)
+        | QuoteEscape(ref body, _) => find_shadowing_under(body, outer, scope, res),
+        Anyways(_) | Impossible | Scan(_) | AnyGroup(_) | Custom(_) | SkipTo(_) | RestOfInput
+        | Call(_) => {}
+    }
+}
+
 use self::FormPat::*;
 
 #[test]
@@ -300,6 +800,62 @@ fn alternation() {
     );
 }
 
+#[test]
+fn named_call_shorthand() {
+    let se = assoc_n!("expr" => Rc::new(form_pat!((scan r"\s*(\S+)"))));
+    let short = form_pat!([(named_call "x", "expr"), (lit_aat "+"), (named_call "y", "expr")]);
+    let long = form_pat!([(named "x", (call "expr")), (lit_aat "+"), (named "y", (call "expr"))]);
+
+    assert_eq!(short, long);
+    let parse_with = |pat: &FormPat| parse(pat, &se, crate::earley::empty__code_envs(), "a + b");
+    assert_eq!(parse_with(&short), parse_with(&long));
+    assert_eq!(parse_with(&short), Ok(ast!(({- "x" => "a"} "+" {- "y" => "b"}))));
+}
+
+#[test]
+fn optional_parts_with_defaults() {
+    let se = assoc_n!("word" => Rc::new(form_pat!((scan r"\s*([a-z]+)"))));
+    let pat = form_pat!((star [(named_call "k", "word"),
+        (optional_named "v" => "none", [(lit_aat "="), (named_call "v", "word")])]));
+    let parts = match parse(&pat, &se, crate::earley::empty__code_envs(), "a = x b c = y") {
+        Ok(Ast::IncompleteNode(parts)) => parts,
+        res => panic!("unexpected parse: {:?}", res),
+    };
+    // Every element has a `v`, so the repetition is rectangular:
+    assert_eq!(parts.get_rep_leaf_or_panic(n("k")), vec![&ast!("a"), &ast!("b"), &ast!("c")]);
+    assert_eq!(parts.get_rep_leaf_or_panic(n("v")), vec![&ast!("x"), &ast!("none"), &ast!("y")]);
+}
+
+#[test]
+fn indented_blocks() {
+    use crate::read::{layout, LayoutError, DEDENT, INDENT};
+
+    let src = "loop\n    a\n\n    b\ndone\n";
+    assert_eq!(layout(src), Ok(format!("loop\n    {}a\n\n    b\n{}done\n", INDENT, DEDENT)));
+    // End of file closes open blocks:
+    assert_eq!(
+        layout("x\n\ty\n\t\tz"),
+        Ok(format!("x\n\t{}y\n\t\t{}z{}{}", INDENT, INDENT, DEDENT, DEDENT))
+    );
+    // Tabs and spaces don't mix:
+    assert_eq!(layout("x\n\ty\n    z\n"), Err(LayoutError::InconsistentIndentation(2)));
+    assert_eq!(layout("x\n    y\n  z\n"), Err(LayoutError::InconsistentIndentation(2)));
+
+    let word = || form_pat!((scan r"\s*([a-z]+)"));
+    let looper = form_pat!([(named "head", (, word())),
+                            (block (star (named "stmt", (, word())))),
+                            (named "tail", (, word()))]);
+    // The markers themselves are atoms:
+    let indent = Ast::Atom(n(&INDENT.to_string()));
+    let dedent = Ast::Atom(n(&DEDENT.to_string()));
+    assert_eq!(
+        parse_top(&looper, &layout(src).unwrap()),
+        Ok(ast!(({- "head" => "loop"} ((, indent) {- "stmt" => ["a", "b"]} (, dedent))
+                 {- "tail" => "done"})))
+    );
+    assert_m!(parse_top(&looper, src), Err(_));
+}
+
 #[test]
 fn advanced_parsing() {
     use crate::form::simple_form;
@@ -421,6 +977,24 @@ fn extensible_parsing() {
         ast!({- "c" => ["O", "O", ("Extend" (() {- "c" => ["AA", "AA", ("Back" {- "c" => ["O"]} "#"), "AA"]}) "#"), "O"]})
     );
 
+    // Without the `Shape`s that don't mean anything:
+    let flattened = ast!({- "c" => ["O", "O",
+        ("Extend" {- "c" => ["AA", "AA", ("Back" {- "c" => ["O"]} "#"), "AA"]} "#"), "O"]});
+    let parse_extend_back = |flatten| {
+        crate::earley::parse_with(
+            &form_pat!((call "o")),
+            &orig,
+            crate::earley::empty__code_envs(),
+            tokens_s!("O" "O" "Extend" "AA" "AA" "Back" "O" "#" "AA" "#" "O"),
+            flatten,
+        )
+    };
+    assert_eq!(
+        parse_extend_back(false).map(|a| crate::earley::flatten_shapes(&a)),
+        Ok(flattened.clone())
+    );
+    assert_eq!(parse_extend_back(true), Ok(flattened));
+
     assert_eq!(
         parse(
             &form_pat!((call "o")),
@@ -488,6 +1062,19 @@ fn extensible_parsing() {
         ),
         Err(_)
     );
+
+    // The extended grammar, for documentation:
+    let extended = static_synex(
+        ParseContext::new((*orig).clone(), crate::earley::empty__code_envs()),
+        Ast::Trivial,
+    );
+    assert_eq!(
+        render_grammar(&extended.grammar),
+        "a ::= ('AA' | 'Back' o '#')*\nb ::= 'BB'\no ::= ('O' | 'Extend' a '#')*\n"
+    );
+    let core = render_grammar(&crate::core_forms::get_core_forms());
+    assert!(core.contains("\nExpr ::= "));
+    assert!(core.contains("'match'"));
 }
 
 // #[test]
@@ -496,3 +1083,132 @@ fn extensible_parsing() {
 // se = se.set(n("xes"), Box::new(Form { grammar: form_pat!((star (lit_aat "X")),
 // relative_phase)}))
 // }
+
+#[test]
+fn syntax_extension_phases() {
+    use crate::earley::ParseContext;
+    use std::cell::RefCell;
+
+    let phases_seen = Rc::new(RefCell::new(vec![]));
+    let record_phase = |label: &'static str| {
+        let phases_seen = phases_seen.clone();
+        move |pc: ParseContext, _: Ast| -> ParseContext {
+            phases_seen.borrow_mut().push((label, pc.phase));
+            pc
+        }
+    };
+
+    let nested = form_pat!(
+        (extend [], [(lit_aat "outer"),
+                     (extend [], (lit_aat "inner"), record_phase("inner"))],
+            record_phase("outer")));
+    assert_m!(parse_top(&nested, tokens_s!("outer" "inner")), Ok(_));
+    assert_eq!(*phases_seen.borrow(), vec![("outer", 1), ("inner", 2)]);
+}
+
+#[test]
+fn form_enumeration() {
+    use crate::{earley::ParseContext, form::simple_form};
+
+    let pair_form = simple_form(
+        "pair",
+        form_pat!([(named "lhs", (lit_aat "a")), (named "rhs", (lit_aat "b"))]),
+    );
+    let other_1_form = simple_form("o", form_pat!((lit_aat "other")));
+    let other_2_form = simple_form("o", form_pat!((lit_aat "otherother")));
+    let se = syn_env!(
+        "other_1" => (scope other_1_form.clone()),
+        "Expr" => (scope pair_form.clone()),
+        "other_2" => (scope other_2_form.clone())
+    );
+
+    let forms = all_forms(&se);
+    let forms: Vec<(Name, *const Form)> =
+        forms.iter().map(|(nt, f)| (*nt, Rc::as_ptr(f))).collect();
+    assert_eq!(forms, vec![
+        (n("Expr"), Rc::as_ptr(&pair_form)),
+        (n("other_1"), Rc::as_ptr(&other_1_form)),
+        (n("other_2"), Rc::as_ptr(&other_2_form))
+    ]);
+
+    // Forms introduced by a syntax extension are found, too:
+    fn add_b(pc: ParseContext, _: Ast) -> ParseContext {
+        let b_form = simple_form("b_form", form_pat!((lit_aat "BB")));
+        ParseContext {
+            grammar: assoc_n!("b" => Rc::new(form_pat!((scope b_form)))).set_assoc(&pc.grammar),
+            ..pc
+        }
+    }
+    let se = syn_env!("a" => (alt (scope pair_form), (extend_nt [], "b", add_b)));
+    let form_names: Vec<(Name, Name)> =
+        all_forms(&se).iter().map(|(nt, f)| (*nt, f.name)).collect();
+    assert_eq!(form_names, vec![(n("a"), n("pair")), (n("b"), n("b_form"))]);
+
+    let core_forms = all_forms(&crate::core_forms::get_core_forms());
+    assert!(core_forms.iter().any(|(nt, f)| *nt == n("Expr") && f.name == n("lambda")));
+}
+
+#[test]
+fn shadowing_detection() {
+    use crate::form::simple_form;
+
+    let clean = form_pat!([(named "lhs", (call "Expr")), (named "rhs", (call "Expr"))]);
+    assert_eq!(find_shadowing(&clean), vec![]);
+    // Re-binding in a different `Scope` doesn't collide, but it's still reported:
+    let inner_form = simple_form("inner", form_pat!((named "body", (call "Expr"))));
+    let across_scope = form_pat!((named "body", (scope inner_form)));
+    assert_eq!(find_shadowing(&across_scope), vec![(n("body"), Some(n("inner")))]);
+    // Siblings with the same name aren't shadowing (though they're probably a mistake, too):
+    let siblings = form_pat!([(named "x", (call "Expr")), (named "x", (call "Expr"))]);
+    assert_eq!(find_shadowing(&siblings), vec![]);
+
+    let shadowed = form_pat!((named "x", [(lit "("), (star (named "x", (call "Expr")))]));
+    assert_eq!(find_shadowing(&shadowed), vec![(n("x"), None)]);
+}
+
+#[test]
+fn grammar_validation() {
+    use crate::form::simple_form;
+
+    let pair_form = simple_form(
+        "pair",
+        form_pat!([(named "lhs", (call "Expr")), (lit ","), (named "rhs", (call "Expr"))]),
+    );
+    let se = syn_env!(
+        "DefaultToken" => (scan r"\s*(\S+)"),
+        "Expr" => (alt (scope pair_form.clone()), (lit_aat "x"))
+    );
+
+    assert_eq!(se.find_or_panic(&n("Expr")).validate(&se), Ok(()));
+
+    assert_eq!(
+        form_pat!([(named "lhs", (call "Expr")), (call "Stmt")]).validate(&se),
+        Err(vec![
+            GrammarErr::NamedOutsideScope(n("lhs")),
+            GrammarErr::UnknownNonterminal(n("Stmt"))
+        ])
+    );
+    // `Pick` counts as a scope:
+    assert_eq!(form_pat!((pick (named "lhs", (call "Expr")), "lhs")).validate(&se), Ok(()));
+    assert_eq!(Alt(vec![]).validate(&se), Err(vec![GrammarErr::EmptyAlt]));
+
+    let core_se = crate::core_forms::get_core_forms();
+    for (nt, pat) in core_se.iter_pairs() {
+        assert_eq!(pat.validate(&core_se), Ok(()), "in {}", nt);
+    }
+}
+
+#[test]
+fn parse_types() {
+    let cse = crate::core_forms::get_core_forms();
+    assert_eq!(
+        parse_type("[Int -> Int]", &cse),
+        Ok(Ast::fn_ty(vec![Ast::int_ty()], Ast::int_ty()))
+    );
+    assert_eq!(
+        parse_type("[Int Nat -> T]", &cse),
+        Ok(Ast::fn_ty(vec![Ast::int_ty(), ast!({"Type" "Nat" :})], ast!((vr "T"))))
+    );
+    assert_m!(parse_type("[Int ->", &cse), Err(_));
+    assert_m!(parse_type(".[x : Int . x].", &cse), Err(_));
+}
@@ -0,0 +1,124 @@
+//! Warnings about programs that are valid, but probably not what was meant.
+
+use crate::{
+    alpha::free_vars,
+    ast::{Ast, Ast::*, Span},
+    beta::bound_from_beta,
+    name::Name,
+    util::mbe::EnvMBE,
+};
+use std::{collections::HashSet, fmt};
+
+/// Something suspicious (but not wrong) about a program.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// A name that its scope never mentions, and the node that binds it.
+    UnusedBinding(Name, Option<Span>),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Warning::UnusedBinding(name, _) => write!(f, "`{}` is never used", name),
+        }
+    }
+}
+
+/// Warn about every name bound in `expr` that's never referred to.
+pub fn find_unused_bindings(expr: &Ast) -> Vec<Warning> {
+    let mut warnings = vec![];
+    find_unused_rec(expr, &mut warnings);
+    warnings
+}
+
+/// Checks the names bound by each node's parts against the free variables of
+///  the parts that they're bound in.
+fn find_unused_rec(a: &Ast, warnings: &mut Vec<Warning>) {
+    match *a {
+        Node(_, ref parts, _, span) => {
+            parts.marched_map(&mut |_, marched_parts: &EnvMBE<Ast>, part: &Ast| {
+                if let ExtendEnv(ref body, ref beta) | ExtendEnvPhaseless(ref body, ref beta) =
+                    *part
+                {
+                    let mentioned = free_vars(body, marched_parts);
+                    for bound in bound_from_beta(beta, marched_parts, 0) {
+                        if !bound.orig_sp().starts_with('_') && !mentioned.contains(&bound) {
+                            warnings.push(Warning::UnusedBinding(bound, span));
+                        }
+                    }
+                }
+                find_unused_rec(part, warnings)
+            });
+        }
+        IncompleteNode(ref parts) => {
+            parts.map(&mut |part: &Ast| find_unused_rec(part, warnings));
+        }
+        ExtendEnv(ref body, _) | ExtendEnvPhaseless(ref body, _) => find_unused_rec(body, warnings),
+        Shape(ref subs) => subs.iter().for_each(|sub| find_unused_rec(sub, warnings)),
+        // Quoted syntax isn't run here, so it's left alone.
+        QuoteMore(_, _) | QuoteLess(_, _) => {}
+        Trivial | Atom(_) | VariableReference(_) => {}
+    }
+}
+
+/// The names of the forms that `a` uses, and the names that it refers to without binding them.
+/// (For tracking what a module depends on.)
+pub fn dependencies(a: &Ast) -> (HashSet<Name>, HashSet<Name>) {
+    fn forms_used(a: &Ast, res: &mut HashSet<Name>) {
+        match *a {
+            Node(ref form, ref parts, _, _) => {
+                res.insert(form.name);
+                parts.map(&mut |part: &Ast| forms_used(part, res));
+            }
+            IncompleteNode(ref parts) => {
+                parts.map(&mut |part: &Ast| forms_used(part, res));
+            }
+            ExtendEnv(ref body, _)
+            | ExtendEnvPhaseless(ref body, _)
+            | QuoteMore(ref body, _)
+            | QuoteLess(ref body, _) => forms_used(body, res),
+            Shape(ref subs) => subs.iter().for_each(|sub| forms_used(sub, res)),
+            Trivial | Atom(_) | VariableReference(_) => {}
+        }
+    }
+    let mut forms = HashSet::new();
+    forms_used(a, &mut forms);
+    (forms, free_vars(a, &EnvMBE::new()).into_iter().collect())
+}
+
+#[test]
+fn unused_bindings() {
+    use crate::name::n;
+
+    let let_x = |body: Ast| {
+        ast!({"Expr" "let" : "name" => "x", "rhs" => (vr "three"),
+            "body" => (import ["name" = "rhs"] (, body))})
+    };
+    let unused_x = vec![Warning::UnusedBinding(n("x"), None)];
+    assert_eq!(find_unused_bindings(&let_x(ast!((vr "four")))), unused_x);
+    assert_eq!(find_unused_bindings(&let_x(ast!((vr "x")))), vec![]);
+
+    // A use in a nested scope counts, unless it's shadowed:
+    let lambda = |param: &str, body: Ast| {
+        ast!({"Expr" "lambda" : "param" => [@"p" (, crate::ast::Atom(n(param)))],
+            "p_t" => [@"p" {"Type" "Int" :}], "body" => (import [* ["param" : "p_t"]] (, body))})
+    };
+    assert_eq!(find_unused_bindings(&let_x(lambda("y", ast!((vr "x"))))).len(), 1);
+    assert_eq!(find_unused_bindings(&let_x(lambda("x", ast!((vr "x"))))), unused_x);
+    assert_eq!(find_unused_bindings(&lambda("_y", ast!((vr "four")))), vec![]);
+}
+
+#[test]
+fn dependencies_of_a_function() {
+    use crate::name::n;
+
+    // .[f : [Int -> Int] . (f x)].
+    let fn_ty = ast!({"Type" "fn" : "param" => [{"Type" "Int" :}], "ret" => {"Type" "Int" :}});
+    let body = ast!({"Expr" "apply" : "rator" => (vr "f"), "rand" => [(vr "x")]});
+    let lambda = ast!({"Expr" "lambda" : "param" => [@"p" "f"], "p_t" => [@"p" (, fn_ty)],
+        "body" => (import [* ["param" : "p_t"]] (, body))});
+
+    let (forms, free) = dependencies(&lambda);
+    assert_eq!(forms, vec![n("lambda"), n("fn"), n("Int"), n("apply")].into_iter().collect());
+    assert_eq!(free, vec![n("x")].into_iter().collect());
+}
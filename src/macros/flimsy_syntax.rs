@@ -80,7 +80,7 @@ macro_rules! u {
             crate::ast::Node(f.clone(),
                 crate::macros::flimsy_syntax::parse_flimsy_mbe(&u!( (~ $($ts)* ) ), &f.grammar)
                     .unwrap_or_else(crate::util::mbe::EnvMBE::new),
-                crate::beta::ExportBeta::Nothing)
+                crate::beta::ExportBeta::Nothing, None)
         }
     };
     ( { $nt:ident $form:ident : $( $ts:tt )*} ) => {
@@ -96,7 +96,7 @@ macro_rules! u {
             let res = crate::ast::Node(f.clone(),
                 crate::macros::flimsy_syntax::parse_flimsy_mbe(&u!( (~ $($ts)* ) ), &f.grammar)
                     .unwrap_or_else(crate::util::mbe::EnvMBE::new),
-                crate::beta::ExportBeta::Nothing);
+                crate::beta::ExportBeta::Nothing, None);
             crate::macros::flimsy_syntax::default_nt.with(|def_nt| {
                 *def_nt.borrow_mut() = old_default_nt;
             });
@@ -113,7 +113,7 @@ macro_rules! u {
             crate::ast::Node(f.clone(),
                 crate::macros::flimsy_syntax::parse_flimsy_mbe(&u!( (~ $($ts)* ) ), &f.grammar)
                     .unwrap_or_else(crate::util::mbe::EnvMBE::new),
-                ebeta!($ebeta))
+                ebeta!($ebeta), None)
         }
     };
     ( { $nt:ident $form:ident => $ebeta:tt : $( $ts:tt )*} ) => {
@@ -131,7 +131,7 @@ macro_rules! u {
             let res =crate::ast::Node(f.clone(),
                     crate::macros::flimsy_syntax::parse_flimsy_mbe(&u!( (~ $($ts)* ) ), &f.grammar)
                         .unwrap_or_else(crate::util::mbe::EnvMBE::new),
-                    ebeta!($ebeta));
+                    ebeta!($ebeta), None);
             crate::macros::flimsy_syntax::default_nt.with(|def_nt| {
                 *def_nt.borrow_mut() = old_default_nt;
             });
@@ -144,7 +144,7 @@ macro_rules! u {
             crate::ast::Node(f.clone(),
                 crate::macros::flimsy_syntax::parse_flimsy_mbe(&u!( (~ $($ts)* ) ), &f.grammar)
                     .unwrap_or_else(crate::util::mbe::EnvMBE::new),
-                crate::beta::ExportBeta::Nothing)
+                crate::beta::ExportBeta::Nothing, None)
         }
     };
     ({ $( $anything:tt )* }) => {
@@ -206,6 +206,8 @@ where I: Iterator<Item = &'a Ast> {
             }
             result
         }
+        // Flimsy syntax always takes the first option (e.g. an annotated lambda parameter):
+        Biased(ref plan_a, _) => parse_flimsy_seq(flimsy_seq, plan_a),
         _ => {
             let flimsy = *match flimsy_seq.peek() {
                 None => return EnvMBE::new(), // Or is this an error?
@@ -238,7 +240,7 @@ pub fn parse_flimsy_mbe(flimsy: &Ast, grammar: &FormPat) -> Option<EnvMBE<Ast>>
     match grammar {
         Literal(_, _) => None,
         Call(_) => None,
-        Scan(_) => None,
+        Scan(_) | AnyGroup(_) | Custom(_) | SkipTo(_) | RestOfInput => None,
         Seq(_) => match flimsy {
             Shape(flimsy_parts) => {
                 if flimsy_parts[0] != Atom(n("SEQ")) {
@@ -284,7 +286,7 @@ fn parse_flimsy_ast(flimsy: &Ast, grammar: &FormPat) -> Ast {
     match grammar {
         Anyways(ref a) => a.clone(),
         Impossible => unimplemented!(),
-        Scan(_) => flimsy.clone(),
+        Scan(_) | AnyGroup(_) | Custom(_) | SkipTo(_) | RestOfInput => flimsy.clone(),
         Literal(_, _) => Trivial,
         VarRef(_) => match flimsy {
             VariableReference(a) => VariableReference(*a),
@@ -117,32 +117,23 @@ macro_rules! tokens_s {
     }
 }
 
+macro_rules! tokens {
+    ($($contents:tt)*) => {
+        crate::read::TokenTree { t: vec![ $( t_elt!($contents) ),* ] }
+    }
+}
+
 macro_rules! t_elt {
-    ( [ $e:expr ;  $( $list:tt )* ] ) => {
-        {
-            let mut toks = vec![::name::n(concat!($e,"["))];
-            toks.append(&mut tokens!($($list)*).t);
-            toks.push(::name::n(concat!("]", $e)));
-            toks
-        }
+    ( ( $( $list:tt )* ) ) => {
+        crate::read::Token::Group(crate::read::DelimChar::Paren, tokens!($($list)*))
     };
-    ( { $e:expr ;  $( $list:tt )* } ) => {
-        {
-            let mut toks = vec![::name::n(concat!($e,"{"))];
-            toks.append(&mut tokens!($($list)*).t);
-            toks.push(::name::n(concat!("}", $e)));
-            toks
-        }
+    ( [ $( $list:tt )* ] ) => {
+        crate::read::Token::Group(crate::read::DelimChar::SquareBracket, tokens!($($list)*))
     };
-    ( ( $e:expr ;  $( $list:tt )* ) ) => {
-        {
-            let mut toks = vec![::name::n(concat!($e,"("))];
-            toks.append(&mut tokens!($($list)*).t);
-            toks.push(::name::n(concat!(")", $e)));
-            toks
-        }
+    ( { $( $list:tt )* } ) => {
+        crate::read::Token::Group(crate::read::DelimChar::CurlyBracket, tokens!($($list)*))
     };
-    ($e:expr) => { vec![::name::n(& $e.replace(" ", "_"))] }
+    ($e:expr) => { crate::read::Token::Simple(crate::name::n($e)) }
 }
 
 // Ast
@@ -186,20 +177,20 @@ macro_rules! ast {
     };
     ( { $nt:tt $form:tt => $beta:tt : $($mbe_arg:tt)*} ) => {
         crate::ast::Node(crate::core_forms::find($nt, $form), mbe!( $($mbe_arg)* ),
-                    ebeta!($beta))
+                    ebeta!($beta), None)
     };
     ( { $form:expr => $beta:tt ; $($mbe_arg:tt)*} ) => {
-        crate::ast::Node($form, mbe!( $($mbe_arg)* ), ebeta!($beta))
+        crate::ast::Node($form, mbe!( $($mbe_arg)* ), ebeta!($beta), None)
     };
     ( { $form:expr; [ $($mbe_arg:tt)* ] }) => {
         ast!( { $form ; $($mbe_arg)* } )
     };
     ( { $form:expr; $($mbe_arg:tt)* }) => {
-        crate::ast::Node($form, mbe!( $($mbe_arg)* ), crate::beta::ExportBeta::Nothing)
+        crate::ast::Node($form, mbe!( $($mbe_arg)* ), crate::beta::ExportBeta::Nothing, None)
     };
     ( { $nt:tt $form:tt : $($mbe_arg:tt)* }) => {
         crate::ast::Node(crate::core_forms::find($nt, $form), mbe!( $($mbe_arg)* ),
-                    crate::beta::ExportBeta::Nothing)
+                    crate::beta::ExportBeta::Nothing, None)
     };
     ($e:expr) => { crate::ast::Atom(crate::name::n($e))}
 }
@@ -339,12 +330,31 @@ macro_rules! form_pat {
     ((scan $e:expr)) => {
         crate::grammar::new_scan($e)
     };
+    ((optional_named $n:expr => $default:tt, $body:tt)) => {
+        crate::grammar::optional_named(
+            std::rc::Rc::new(form_pat!($body)), crate::name::n($n), ast!($default))
+    };
+    ((block $body:tt)) => { crate::grammar::block(std::rc::Rc::new(form_pat!($body))) };
+    ((any_group $d:expr)) => {
+        crate::grammar::FormPat::AnyGroup(crate::read::delim($d))
+    };
+    ((rest_of_input)) => { crate::grammar::FormPat::RestOfInput };
+    ((recover $body:tt, $sync:expr)) => {
+        crate::grammar::recover(std::rc::Rc::new(form_pat!($body)), crate::name::n($sync))
+    };
+    ((custom $parser:expr)) => {
+        crate::grammar::FormPat::Custom(crate::grammar::CustomParser(std::rc::Rc::new($parser)))
+    };
     ((reserved $body:tt, $( $res:tt )*)) => {
         crate::grammar::FormPat::Reserved(std::rc::Rc::new(form_pat!($body)), vec![$( n($res) ),*])
     };
     ((reserved_by_name_vec $body:tt, $names:expr)) => {
         crate::grammar::FormPat::Reserved(std::rc::Rc::new(form_pat!($body)), $names)
     };
+    ((satisfy $body:tt, $pred:expr)) => {
+        crate::grammar::FormPat::Satisfy(
+            std::rc::Rc::new(form_pat!($body)), crate::grammar::Predicate(std::rc::Rc::new($pred)))
+    };
     ((common $body:tt)) => {
         crate::grammar::FormPat::Common(std::rc::Rc::new(form_pat!($body)))
     };
@@ -381,6 +391,12 @@ macro_rules! form_pat {
     ((biased $lhs:tt, $rhs:tt)) => {
         crate::grammar::FormPat::Biased(std::rc::Rc::new(form_pat!($lhs)),
                                  std::rc::Rc::new(form_pat!($rhs))) };
+    ((biased_alt $($body:tt),*)) => {
+        crate::grammar::biased_alt(vec![ $( std::rc::Rc::new(form_pat!($body)) ),* ]) };
+    ((switch [$($kw:tt => $case:tt),*], $default:tt)) => {
+        crate::grammar::FormPat::Switch(
+            vec![$( (crate::name::n($kw), std::rc::Rc::new(form_pat!($case))) ),*],
+            std::rc::Rc::new(form_pat!($default))) };
     ((call $n:expr)) => { crate::grammar::FormPat::Call(crate::name::n($n)) };
     ((call_by_name $n:expr)) => { crate::grammar::FormPat::Call($n) };
     ((scope $f:expr)) => { crate::grammar::FormPat::Scope($f, crate::beta::ExportBeta::Nothing) };
@@ -391,6 +407,7 @@ macro_rules! form_pat {
     ((named $n:expr, $body:tt)) => {
         crate::grammar::FormPat::Named(crate::name::n($n), std::rc::Rc::new(form_pat!($body)))
     };
+    ((named_call $n:expr, $nt:expr)) => { form_pat!((named $n, (call $nt))) };
     ((import $beta:tt, $body:tt)) => {
         crate::grammar::FormPat::NameImport(std::rc::Rc::new(form_pat!($body)), beta!($beta))
     };
@@ -443,6 +460,7 @@ macro_rules! basic_typed_form {
     ( $p:tt, $gen_type:expr, $eval:expr ) => {
         Rc::new(Form {
             name: crate::name::n("unnamed form"),
+            description: None,
             grammar: Rc::new(form_pat!($p)),
             type_compare: crate::form::Positive(crate::ast_walk::WalkRule::NotWalked),
             synth_type: crate::form::Positive($gen_type),
@@ -459,6 +477,7 @@ macro_rules! typed_form {
     ( $name:expr, $p:tt, $gen_type:expr, $eval:expr ) => {
         Rc::new(Form {
             name: crate::name::n($name),
+            description: None,
             grammar: Rc::new(form_pat!($p)),
             type_compare: crate::form::Positive(crate::ast_walk::WalkRule::NotWalked),
             synth_type: crate::form::Positive($gen_type),
@@ -475,6 +494,7 @@ macro_rules! negative_typed_form {
     ( $name:expr, $p:tt, $gen_type:expr, $eval:expr ) => {
         Rc::new(Form {
             name: crate::name::n($name),
+            description: None,
             grammar: Rc::new(form_pat!($p)),
             type_compare: crate::form::Positive(crate::ast_walk::WalkRule::NotWalked),
             synth_type: crate::form::Negative($gen_type),
@@ -514,7 +534,7 @@ macro_rules! val {
         crate::runtime::eval::Value::Enum(crate::name::n($nm), vec![ $( val! $v ),* ])
     };
     (seq $($v:tt)*) => {
-        crate::runtime::eval::Value::Sequence(vec![ $( Rc::new(val! $v) ),* ])
+        crate::runtime::eval::Value::Sequence(vec![ $( std::rc::Rc::new(val! $v) ),* ])
     };
     (, $interpolate:expr) => { $interpolate }
 }
@@ -573,7 +593,7 @@ macro_rules! core_fn {
         BuiltInFunction(BIF(Rc::new(
             move | args | {
                 let mut argi = args.into_iter();
-                bind_patterns!(argi; ($( $p, )*) => $body )
+                Ok(bind_patterns!(argi; ($( $p, )*) => $body ))
             }
         )))
     }
@@ -600,7 +620,7 @@ macro_rules! without_freshening {
 macro_rules! expect_node {
     ( ($node:expr ; $form:expr) $env:ident ; $body:expr ) => {
         // This is tied to the signature of `Custom`
-        if let Node(ref f, ref $env, _) = $node {
+        if let Node(ref f, ref $env, _, _) = $node {
             if *f == $form {
                 $body
             } else {
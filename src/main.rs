@@ -38,6 +38,7 @@ mod form;
 
 mod ast_walk;
 mod expand;
+mod lint;
 mod ty;
 mod ty_compare;
 mod walk_mode;
@@ -304,13 +305,7 @@ fn assign_variable(name: &str, expr: &str) -> Result<Value, String> {
 }
 
 fn assign_t_var(name: &str, t: &str) -> Result<Ast, String> {
-    let ast = grammar::parse(
-        &grammar::FormPat::Call(n("Type")),
-        &core_forms::get_core_forms(),
-        runtime::core_values::get_core_envs(),
-        t,
-    )
-    .map_err(|e| e.msg)?;
+    let ast = grammar::parse_type(t, &core_forms::get_core_forms()).map_err(|e| e.msg)?;
 
     let res =
         ty_env.with(|tys| ty::synth_type(&ast, tys.borrow().clone()).map_err(|e| format!("{}", e)));
@@ -326,13 +321,7 @@ fn assign_t_var(name: &str, t: &str) -> Result<Ast, String> {
 }
 
 fn canonicalize_type(t: &str) -> Result<Ast, String> {
-    let ast = grammar::parse(
-        &grammar::FormPat::Call(n("Type")),
-        &core_forms::get_core_forms(),
-        runtime::core_values::get_core_envs(),
-        t,
-    )
-    .map_err(|e| e.msg)?;
+    let ast = grammar::parse_type(t, &core_forms::get_core_forms()).map_err(|e| e.msg)?;
 
     ty_env.with(|tys| ty::synth_type(&ast, tys.borrow().clone()).map_err(|e| format!("{}", e)))
 }
@@ -419,6 +408,40 @@ fn simple_end_to_end_eval() {
 
     assert_eq!(eval_unseemly_program("(.[x : Int  y : Int . (plus x y)]. one one)"), Ok(val!(i 2)));
 
+    assert_eq!(eval_unseemly_program("(.[x  y : Int . (plus x y)]. one one)"), Ok(val!(i 2)));
+
+    assert_eq!(eval_unseemly_program("if (zero? one) { one } else { zero }"), Ok(val!(i 0)));
+
+    assert_eq!(eval_unseemly_program("let x : Int = five in (plus x one)"), Ok(val!(i 6)));
+
+    assert_eq!(eval_unseemly_program("let x = one in let x = five in x"), Ok(val!(i 5)));
+
+    assert_eq!(
+        eval_unseemly_program(
+            "((fix[[Int -> Int]] .[again : [ -> [Int -> Int]] . .[n : Int . n]. ].) five)"
+        ),
+        Ok(val!(i 5))
+    );
+
+    assert_eq!(
+        eval_unseemly_program(
+            "(fix[ .[again : [Int -> Int] . .[n : Int . match (zero? n) {
+                +[True]+ => one  +[False]+ => (times n (again (minus n one))) } ]. ]. ]fix five)"
+        ),
+        Ok(val!(i 120))
+    );
+
+    assert_eq!(
+        eval_unseemly_program(
+            "(.[x : type_if( Int == Int , Int , Float )type_if . (plus x one)]. two)"
+        ),
+        Ok(val!(i 3))
+    );
+
+    assert_eq!(eval_unseemly_program("seq[ one (plus one one) ]seq"), Ok(val!(seq (i 1) (i 2))));
+
+    assert_eq!(eval_unseemly_program("seq[ ]seq : Seq[ Int ]Seq"), Ok(val!(seq)));
+
     assert_eq!(
         eval_unseemly_program(
             "((fix .[ again : [ -> [ Int -> Int ]] .
@@ -431,6 +454,23 @@ fn simple_end_to_end_eval() {
     );
 }
 
+#[test]
+fn type_errors_have_spans() {
+    let program = "  (zero? (plus one (zero? one)))";
+    let ast = grammar::parse(
+        &core_forms::outermost_form(),
+        &core_forms::get_core_forms(),
+        runtime::core_values::get_core_envs(),
+        program,
+    )
+    .unwrap();
+    assert_eq!(ast.span(), Some(ast::Span { start: 2, end: 32 }));
+
+    let err = ty_env.with(|tys| ty::synth_type(&ast, tys.borrow().clone())).unwrap_err();
+    let span = err.loc.span().unwrap();
+    assert_eq!(&program[span.start..span.end], "(plus one (zero? one))");
+}
+
 #[test]
 fn end_to_end_int_list_tools() {
     assert_m!(assign_t_var("IntList", "mu_type IntList . { +[Nil]+ +[Cons Int IntList]+ }"), Ok(_));
@@ -1,5 +1,6 @@
 #![macro_use]
 
+use crate::util::assoc::Assoc;
 use std::{
     cell::RefCell,
     collections::{HashMap, HashSet},
@@ -38,6 +39,12 @@ thread_local! {
 
     // Should we do "naive" freshening for testing purposes?
     static fake_freshness: RefCell<bool> = RefCell::new(false);
+
+    // Unqualified variable references prefer names from this module:
+    static current_module: RefCell<Option<Name>> = RefCell::new(None);
+
+    // Printable names for `with_portable_printing`, if it's active:
+    static portable_printables: RefCell<Option<HashMap<usize, String>>> = RefCell::new(None);
 }
 
 impl crate::runtime::reify::Reifiable for Name {
@@ -53,6 +60,19 @@ impl crate::runtime::reify::Reifiable for Name {
     }
 }
 
+/// `Name`s are serialized as their spelling; freshness doesn't survive the trip.
+impl serde::Serialize for Name {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&self.sp())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Name {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Name, D::Error> {
+        Ok(n(&String::deserialize(d)?))
+    }
+}
+
 impl std::cmp::PartialOrd for Name {
     fn partial_cmp(&self, other: &Name) -> Option<std::cmp::Ordering> {
         Some(self.orig_sp().cmp(&other.orig_sp()))
@@ -88,6 +108,9 @@ impl Name {
     // This way if the compiler freshens some name a bunch of times, producing a tomato-filled mess,
     // but only prints one version of the name, it gets to print an unadorned name.
     pub fn print(self) -> String {
+        if let Some(res) = self.portable_print() {
+            return res;
+        }
         printables.with(|printables_| {
             printables_used.with(|printables_used_| {
                 printables_
@@ -107,6 +130,71 @@ impl Name {
         })
     }
 
+    // Like `print`, but only produces spellings the parser will accept,
+    //  and doesn't depend on what's been printed previously.
+    fn portable_print(self) -> Option<String> {
+        portable_printables.with(|pp| {
+            let mut pp = pp.borrow_mut();
+            let pp = pp.as_mut()?;
+            if let Some(res) = pp.get(&self.id) {
+                return Some(res.clone());
+            }
+            let orig = self.orig_sp();
+            let mut print_version = orig.clone();
+            let mut suffix = 1;
+            // Unfreshened names must keep their spelling, or they'd refer to something else:
+            while self.sp() != orig && pp.values().any(|used| used == &print_version) {
+                suffix += 1;
+                print_version = format!("{}{}", orig, suffix);
+            }
+            pp.insert(self.id, print_version.clone());
+            Some(print_version)
+        })
+    }
+
+    /// Like `print`, but quoted if it wouldn't read back as a single token:
+    ///  names containing whitespace, delimiters, `` ` ``, or `\` are wrapped in backticks,
+    ///  with those characters written as `\<hex>;`.
+    pub fn to_source_string(self) -> String {
+        let printed = self.print();
+        let needs_escape = |c: char| {
+            c.is_whitespace()
+                || "()[]{}`\\".contains(c)
+                || [crate::read::INDENT, crate::read::DEDENT].contains(&c)
+        };
+        if !printed.is_empty() && !printed.contains(needs_escape) {
+            return printed;
+        }
+        let mut res = "`".to_owned();
+        for c in printed.chars() {
+            if needs_escape(c) {
+                res.push_str(&format!("\\{:x};", c as u32));
+            } else {
+                res.push(c);
+            }
+        }
+        res.push('`');
+        res
+    }
+
+    /// The inverse of `to_source_string`. (Freshness doesn't survive the trip.)
+    pub fn from_source_string(s: &str) -> Option<Name> {
+        let quoted = match s.strip_prefix('`') {
+            None => return if s.is_empty() { None } else { Some(n(s)) },
+            Some(quoted) => quoted.strip_suffix('`')?,
+        };
+        let mut res = String::new();
+        let mut rest = quoted;
+        while let Some(idx) = rest.find('\\') {
+            res.push_str(&rest[..idx]);
+            let end = rest[idx..].find(';')? + idx;
+            res.push(std::char::from_u32(u32::from_str_radix(&rest[idx + 1..end], 16).ok()?)?);
+            rest = &rest[end + 1..];
+        }
+        res.push_str(rest);
+        Some(n(&res))
+    }
+
     pub fn global(s: &str) -> Name { Name::new(s, false) }
     pub fn gensym(s: &str) -> Name { Name::new(s, true) }
     pub fn freshen(self) -> Name { Name::new(&self.orig_sp(), true) }
@@ -147,11 +235,61 @@ impl Name {
             Name { id: id }
         })
     }
+    /// A name inside `module`, printed as `module::base`.
+    /// The qualification is just part of the spelling,
+    ///  so qualified names are interned, freshened, and alpha-renamed like any other.
+    pub fn qualified(module: Name, base: Name) -> Name {
+        Name::global(&format!("{}::{}", module.sp(), base.sp()))
+    }
+
+    /// The module that qualifies this name, if any.
+    pub fn module(self) -> Option<Name> {
+        let orig = self.orig_sp();
+        orig.rfind("::").map(|idx| Name::global(&orig[..idx]))
+    }
+
+    /// If this name is unqualified, and `env` has a version of it
+    ///  in the current module (see `with_current_module`), use that instead.
+    pub fn resolve_in<V: Clone>(self, env: &Assoc<Name, V>) -> Name {
+        match current_module.with(|cm| *cm.borrow()) {
+            Some(module) if self.module().is_none() => {
+                let qualified = Name::qualified(module, self);
+                if env.find(&qualified).is_some() {
+                    qualified
+                } else {
+                    self
+                }
+            }
+            _ => self,
+        }
+    }
+
     pub fn is(self, s: &str) -> bool { self.sp() == s }
 
     pub fn is_name(self, n: Name) -> bool { self.sp() == n.sp() }
 }
 
+/// Run `f`, resolving unqualified variable references in `module` where possible.
+pub fn with_current_module<T>(module: Name, f: impl FnOnce() -> T) -> T {
+    crate::util::scoped::with_scoped(&current_module, Some(module), f)
+}
+
+/// Run `f`, printing names in a way that can be parsed back in a later session:
+///  instead of adding carrots, distinct names with the same spelling get numbered.
+/// Unfreshened names keep their spelling, so `f` must only print names from `mentioned`
+///  (otherwise, a freshened name might be numbered into the spelling of an unfreshened one).
+pub fn with_portable_printing<T>(mentioned: &[Name], f: impl FnOnce() -> T) -> T {
+    let reserved = mentioned
+        .iter()
+        .filter(|name| name.sp() == name.orig_sp())
+        .map(|name| (name.id, name.orig_sp()))
+        .collect();
+    crate::util::scoped::with_scoped(&portable_printables, Some(reserved), f)
+}
+
+/// Is `with_portable_printing` active?
+pub fn printing_portably() -> bool { portable_printables.with(|pp| pp.borrow().is_some()) }
+
 // TODO: move to `ast_walk`
 // TODO: using `lazy_static!` (with or without gensym) makes some tests fail. Why?
 /// Special name for negative `ast_walk`ing
@@ -196,3 +334,30 @@ fn name_interning() {
     assert_eq!(a.freshen().print(), "a");
     assert_eq!(a.print(), "a🥕");
 }
+
+#[test]
+fn name_qualification() {
+    let foo = n("foo");
+    let a_foo = Name::qualified(n("a"), foo);
+    let b_foo = Name::qualified(n("b"), foo);
+
+    assert_ne!(a_foo, b_foo);
+    assert_ne!(a_foo, foo);
+    assert_eq!(a_foo, Name::qualified(n("a"), n("foo")));
+    assert_eq!(a_foo.sp(), "a::foo");
+    assert_eq!(a_foo.module(), Some(n("a")));
+    assert_eq!(foo.module(), None);
+
+    // Freshening keeps the qualification:
+    assert_ne!(a_foo.freshen(), a_foo);
+    assert_eq!(a_foo.freshen().module(), Some(n("a")));
+
+    let env = Assoc::new().set(a_foo, 1).set(b_foo, 2).set(n("bar"), 3);
+    assert_eq!(foo.resolve_in(&env), foo); // no current module
+    with_current_module(n("a"), || {
+        assert_eq!(foo.resolve_in(&env), a_foo);
+        assert_eq!(b_foo.resolve_in(&env), b_foo); // already qualified
+        assert_eq!(n("bar").resolve_in(&env), n("bar")); // not in this module
+    });
+    with_current_module(n("b"), || assert_eq!(foo.resolve_in(&env), b_foo));
+}
@@ -24,6 +24,71 @@ impl DelimChar {
 
 use self::DelimChar::*;
 
+/// Source code as tokens and delimited groups, but no further structure
+///  (e.g. for constructing the output of a macro).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TokenTree {
+    pub t: Vec<Token>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Token {
+    Simple(crate::name::Name),
+    Group(DelimChar, TokenTree),
+}
+
+/// Turn `tt` back into source text, with a space between adjacent tokens.
+pub fn render_tokens(tt: &TokenTree) -> String {
+    tt.t.iter()
+        .map(|tok| match *tok {
+            Token::Simple(name) => name.to_source_string(),
+            Token::Group(d, ref body) => {
+                format!("{}{}{}", d.open(), render_tokens(body), d.close())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The inverse of `render_tokens`: split `src` at whitespace and delimiters.
+/// Returns `None` if the delimiters don't match up (or a quoted name is malformed).
+pub fn tokenize(src: &str) -> Option<TokenTree> {
+    fn tokenize_rec(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<TokenTree> {
+        let mut t = vec![];
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if "([{".contains(c) {
+                chars.next();
+                let body = tokenize_rec(chars)?;
+                if chars.next() != Some(delim(&c.to_string()).close()) {
+                    return None;
+                }
+                t.push(Token::Group(delim(&c.to_string()), body));
+            } else if ")]}".contains(c) {
+                break; // Let the caller check that it's the right one
+            } else {
+                let mut tok = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()[]{}".contains(c) {
+                        break;
+                    }
+                    tok.push(c);
+                    chars.next();
+                }
+                t.push(Token::Simple(crate::name::Name::from_source_string(&tok)?));
+            }
+        }
+        Some(TokenTree { t })
+    }
+    let mut chars = src.chars().peekable();
+    let res = tokenize_rec(&mut chars)?;
+    if chars.next().is_some() {
+        return None; // unmatched closing delimiter
+    }
+    Some(res)
+}
+
 pub fn delim(s: &str) -> DelimChar {
     match s {
         "(" | ")" => Paren,
@@ -32,3 +97,80 @@ pub fn delim(s: &str) -> DelimChar {
         _ => icp!("not a delimiter!"),
     }
 }
+
+// For indentation-sensitive syntax, `layout` marks where indentation changes.
+// These are private-use characters, so they won't collide with anything the programmer wrote,
+//  and they aren't whitespace, so `\s*` won't skip them.
+pub const INDENT: char = '\u{E000}';
+pub const DEDENT: char = '\u{E001}';
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LayoutError {
+    /// On this (0-indexed) line, the indentation doesn't extend or return to an enclosing level.
+    /// (This includes mixing tabs and spaces inconsistently.)
+    InconsistentIndentation(usize),
+}
+
+/// Insert `INDENT` before the first line of each more-indented block,
+///  and `DEDENT` before the first line after it (or at the end, for blocks still open at EOF).
+/// Blank lines don't count. Tabs and spaces are never equated; an indentation level
+///  must literally start with the indentation of the level that encloses it.
+pub fn layout(src: &str) -> Result<String, LayoutError> {
+    let mut levels: Vec<&str> = vec![""];
+    let mut res = String::new();
+
+    for (line_no, line) in src.split_inclusive('\n').enumerate() {
+        let content = line.trim_start_matches([' ', '\t']);
+        let indentation = &line[..line.len() - content.len()];
+        if content.trim().is_empty() {
+            res.push_str(line);
+            continue;
+        }
+        res.push_str(indentation);
+
+        let cur = *levels.last().unwrap();
+        if indentation.len() > cur.len() && indentation.starts_with(cur) {
+            levels.push(indentation);
+            res.push(INDENT);
+        } else {
+            while indentation != *levels.last().unwrap() {
+                levels.pop();
+                if levels.is_empty() || !indentation.starts_with(levels.last().unwrap()) {
+                    return Err(LayoutError::InconsistentIndentation(line_no));
+                }
+                res.push(DEDENT);
+            }
+        }
+        res.push_str(content);
+    }
+    for _ in 1..levels.len() {
+        res.push(DEDENT);
+    }
+    Ok(res)
+}
+
+#[test]
+fn token_rendering() {
+    assert_eq!(render_tokens(&tokens!()), "");
+    assert_eq!(render_tokens(&tokens!("a" ("b" "c"))), "a (b c)");
+    assert_eq!(render_tokens(&tokens!("f" ["x" {"y" ()}] "z")), "f [x {y ()}] z");
+}
+
+#[test]
+fn token_round_trip() {
+    use crate::name::Name;
+
+    let tt = tokens!("f" ["x" {"y" ()}] "z");
+    assert_eq!(tokenize(&render_tokens(&tt)), Some(tt));
+    assert_eq!(tokenize("f (x]"), None);
+    assert_eq!(tokenize("f x)"), None);
+
+    // A gensym'd name with awkward characters in it:
+    let awkward = Name::gensym("tmp (for `x`)");
+    let rendered = render_tokens(&TokenTree { t: vec![Token::Simple(awkward)] });
+    assert_eq!(rendered.split_whitespace().count(), 1);
+    match tokenize(&rendered).unwrap().t[..] {
+        [Token::Simple(read_back)] => assert_eq!(read_back.sp(), awkward.print()),
+        _ => panic!("{} didn't re-tokenize into one name", rendered),
+    }
+}
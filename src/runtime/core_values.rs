@@ -8,7 +8,7 @@ use crate::{
     },
     util::assoc::Assoc,
 };
-use std::rc::Rc;
+use std::{cell::RefCell, rc::Rc};
 
 use num::BigInt;
 
@@ -80,6 +80,26 @@ pub fn core_typed_values() -> Assoc<Name, TypedValue> {
 
 pub fn core_values() -> Assoc<Name, Value> { core_typed_values().map(&erase_type) }
 
+thread_local! {
+    // Primitive operations that embedders have added with `register_primitive`:
+    static registered_primitives: RefCell<Assoc<Name, TypedValue>> = RefCell::new(Assoc::new());
+}
+
+/// Make `prim` available to every program as `name`, for embedders with their own primitives.
+/// `synth_type` and `eval` fall back to these for names that aren't in their environments.
+/// (Registering the same name again replaces the old primitive.)
+pub fn register_primitive(name: &str, prim: TypedValue) {
+    registered_primitives.with(|rp| {
+        let with_prim = rp.borrow().set(n(name), prim);
+        *rp.borrow_mut() = with_prim
+    })
+}
+
+/// The primitive registered (with `register_primitive`) as `name`, if any.
+pub fn find_primitive(name: Name) -> Option<TypedValue> {
+    registered_primitives.with(|rp| rp.borrow().find(&name).cloned())
+}
+
 // Helper for building an environment by reifying a bunch of Rust types
 macro_rules! reified_ty_env {
     ( $($t:ty),* ) => {
@@ -112,6 +132,8 @@ pub fn core_types() -> Assoc<Name, Ast> {
             crate::util::mbe::EnvMBE<Irr>,
             Name, crate::ast::Ast, crate::beta::Beta, crate::beta::ExportBeta,
             crate::grammar::FormPat, crate::grammar::SyntaxExtension, crate::grammar::Scanner,
+            crate::grammar::Predicate, crate::grammar::CustomParser,
+            crate::grammar::OpPrec, crate::grammar::Associativity,
             crate::form::Form, crate::form::EitherPN<Irr, Irr>, crate::ast_walk::WalkRule<Irr>,
             crate::runtime::eval::QQuote, crate::runtime::eval::QQuoteDestr,
             crate::runtime::eval::Eval, crate::runtime::eval::Destructure,
@@ -146,6 +168,31 @@ fn basic_core_value_evaluation() {
     );
 }
 
+#[test]
+fn register_and_use_primitives() {
+    use crate::ty::synth_type;
+
+    register_primitive("plus", tf!([( "Int", "Int" ) -> "Int"], ( Int(a), Int(b) ) => Int(a + b)));
+    let add =
+        |l: Ast, r: Ast| ast!({"Expr" "apply" : "rator" => (vr "plus"), "rand" => [(, l), (, r)]});
+    let one_plus_two = add(ast!((vr "one")), ast!((vr "two")));
+
+    // `plus` isn't in the environment, but the other names need to be:
+    let int_env = assoc_n!("one" => ast!({"Type" "Int" :}), "two" => ast!({"Type" "Int" :}));
+    assert_eq!(synth_type(&one_plus_two, int_env.clone()), Ok(ast!({"Type" "Int" :})));
+    assert_m!(
+        synth_type(
+            &add(ast!((vr "one")), ast!((vr "half"))),
+            int_env.set(n("half"), ast!({"Type" "Float" :}))
+        ),
+        Err(_)
+    );
+    let val_env = assoc_n!("one" => val!(i 1), "two" => val!(i 2));
+    assert_eq!(eval(&one_plus_two, val_env), Ok(val!(i 3)));
+
+    assert_eq!(find_primitive(n("not_a_primitive")), None);
+}
+
 #[test]
 fn fixpoint_evaluation() {
     assert_eq!(
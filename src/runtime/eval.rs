@@ -9,7 +9,7 @@ use crate::{
     walk_mode::{NegativeWalkMode, WalkMode},
 };
 use num::bigint::BigInt;
-use std::{self, rc::Rc};
+use std::{self, cell::RefCell, rc::Rc};
 
 /// Values in Unseemly.
 
@@ -34,7 +34,7 @@ pub struct Closure {
 }
 
 // Built-in function
-pub struct BIF(pub Rc<(dyn Fn(Vec<Value>) -> Value)>);
+pub struct BIF(pub Rc<(dyn Fn(Vec<Value>) -> Result<Value, ()>)>);
 
 impl PartialEq for BIF {
     fn eq(&self, other: &BIF) -> bool { self as *const BIF == other as *const BIF }
@@ -118,12 +118,22 @@ impl WalkMode for Eval {
         }
         f.eval.pos().clone()
     }
+    fn entering_node(_: &Form) {
+        // This is the node that's about to be evaluated; see `in_tail_position`.
+        tail_position.with(|tp| {
+            let this_node_is_tail = tp.replace(false);
+            node_in_tail_position.with(|nitp| *nitp.borrow_mut() = this_node_is_tail)
+        });
+    }
     fn automatically_extend_env() -> bool { true }
 
     fn walk_var(n: Name, cnc: &LazyWalkReses<Eval>) -> Result<Value, ()> {
         match cnc.env.find(&n) {
             Some(v) => Ok(v.clone()),
-            None => panic!("Undefined var `{}` in {}", n, cnc.env),
+            None => match crate::runtime::core_values::find_primitive(n) {
+                Some(prim) => Ok(prim.val),
+                None => panic!("Undefined var `{}` in {}", n, cnc.env),
+            },
         }
     }
 
@@ -160,6 +170,70 @@ impl crate::walk_mode::WalkElt for Ast {
 
 pub fn eval_top(expr: &Ast) -> Result<Value, ()> { eval(expr, Assoc::new()) }
 
+thread_local! {
+    // Is the next `Node` to be evaluated in tail position (of the body of an `apply_function`)?
+    static tail_position: RefCell<bool> = RefCell::new(false);
+    // Is the `Node` whose eval rule is running in tail position? (See `in_tail_position`.)
+    static node_in_tail_position: RefCell<bool> = RefCell::new(false);
+    // A call that `tail_call` left for the innermost `apply_function` to make.
+    // (While this is set, the `Err(())` being returned is just a signal to make the call.)
+    static pending_tail_call: RefCell<Option<(Value, Vec<Value>)>> = RefCell::new(None);
+}
+
+/// Apply a function to `args`.
+/// Calls that closures make in tail position don't grow the Rust stack;
+///  instead, they come back here to be made in a loop.
+pub fn apply_function(f: &Value, args: Vec<Value>) -> Result<Value, ()> {
+    let (mut f, mut args) = (f.clone(), args);
+    // (Don't leave a call pending if something panics:)
+    let _pending = crate::util::scoped::Scoped::set(&pending_tail_call, None);
+    loop {
+        let res = match f {
+            Function(ref clos) => {
+                let mut new_env = clos.env.clone();
+                for (p, v) in clos.params.iter().zip(args) {
+                    new_env = new_env.set(*p, v);
+                }
+                // TODO: this seems wrong; it discards other phase information.
+                // But would it be correct to have closures capture at all phases?
+                eval_in_tail_position(true, || eval(&clos.body, new_env))
+            }
+            BuiltInFunction(BIF(ref bif)) => bif(args),
+            ref other => icp!("[type error] invoked {:#?} as if it were a function", other),
+        };
+        match pending_tail_call.with(|ptc| ptc.borrow_mut().take()) {
+            Some((next_f, next_args)) => {
+                f = next_f;
+                args = next_args;
+            }
+            None => return res,
+        }
+    }
+}
+
+/// Instead of applying `f` to `args`, have the `apply_function` that's running us do it.
+/// Only use this in tail position, and return its result right away:
+///  the `Err` is a signal to `apply_function`, not a value or a real error.
+/// (Eval rules can check `in_tail_position`, and built-in functions are always in tail position.)
+pub fn tail_call(f: Value, args: Vec<Value>) -> Result<Value, ()> {
+    pending_tail_call.with(|ptc| *ptc.borrow_mut() = Some((f, args)));
+    Err(())
+}
+
+/// Is the node being evaluated in tail position? An eval rule that wants to know must
+///  call this before evaluating any of its subterms (and it can only be called once).
+pub fn in_tail_position() -> bool { node_in_tail_position.with(|nitp| nitp.replace(false)) }
+
+/// Evaluate a subterm (with `eval_subterm`) that is in tail position if `tail` is.
+/// (e.g. the branches of an `if` are in tail position if the `if` is.)
+pub fn eval_in_tail_position(
+    tail: bool,
+    eval_subterm: impl FnOnce() -> Result<Value, ()>,
+) -> Result<Value, ()> {
+    // (If the subterm isn't a `Node`, nothing will reset `tail_position`; this will.)
+    crate::util::scoped::with_scoped(&tail_position, tail, eval_subterm)
+}
+
 pub fn eval(expr: &Ast, env: Assoc<Name, Value>) -> Result<Value, ()> {
     walk::<Eval>(expr, &LazyWalkReses::new_wrapper(env))
 }
@@ -1,3 +1,5 @@
 pub mod core_values;
 pub mod eval;
+pub mod partial_eval;
 pub mod reify;
+pub mod type_erase;
@@ -0,0 +1,277 @@
+use crate::{
+    ast::{Ast, Ast::*},
+    ast_walk::{LazyWalkReses, WalkRule, WalkRule::*},
+    form::{Both, Form, Negative, Positive},
+    name::{n, Name},
+    runtime::{eval, eval::Value, reify::Reifiable},
+    util::{assoc::Assoc, mbe::EnvMBE},
+    walk_mode::{Dir, NegativeWalkMode, WalkMode},
+};
+use num::bigint::BigInt;
+use std::rc::Rc;
+
+custom_derive! {
+    #[derive(Copy, Clone, Debug, Reifiable)]
+    pub struct PartialEval {}
+}
+custom_derive! {
+    #[derive(Copy, Clone, Debug, Reifiable)]
+    pub struct UnusedNegativePartialEval {}
+}
+
+/// Evaluates the subexpressions that have no free variables (other than ones with known values),
+///  and rebuilds everything else (like `ExpandMacros`, the `Elt`s are reified `Ast`s).
+/// Parts of the program that might never run (like `lambda` bodies) aren't touched.
+/// A result makes it back into the program as a literal or a quotation;
+///  if it can't (e.g. it's a function), the subexpression is left alone.
+impl WalkMode for PartialEval {
+    fn name() -> &'static str { "PEval" }
+    type Elt = Value;
+    type Negated = UnusedNegativePartialEval;
+    type AsPositive = PartialEval;
+    type AsNegative = UnusedNegativePartialEval;
+    type Err = <eval::Eval as WalkMode>::Err;
+    type D = crate::walk_mode::Positive<PartialEval>;
+    type ExtraInfo = ();
+
+    fn get_walk_rule(_: &Form) -> WalkRule<PartialEval> { LiteralLike }
+
+    fn walk_quasi_literally(node: Ast, cnc: &LazyWalkReses<PartialEval>) -> Result<Value, ()> {
+        if let Node(ref f, ref parts, ref exports, span) = node {
+            // Quoted syntax is already as simple as it gets (and its unquotes might not run):
+            if f.name == n("quote_expr") {
+                return Ok(node.reify());
+            }
+            if is_evaluable(f) && is_closed(&node, &cnc.env) {
+                // This would get evaluated (in this environment) whenever the program got here.
+                if let Some(res) =
+                    eval::eval(&node, cnc.env.clone()).ok().and_then(|v| residualize(&v))
+                {
+                    return Ok(res.reify());
+                }
+            }
+            if let Some(run_parts) = always_run_parts(f) {
+                let mut parts = parts.clone();
+                for part in run_parts {
+                    parts.add_leaf(part, Ast::reflect(&cnc.get_res(part)?));
+                }
+                return Ok(Node(f.clone(), parts, exports.clone(), span).reify());
+            }
+        }
+        Self::D::walk_quasi_literally(node, cnc)
+    }
+    // Binders don't get values, so names they bind are never known.
+    // (Positive walks freshen binders, so they can't be confused with names in the environment.)
+    fn automatically_extend_env() -> bool { false }
+
+    fn walk_var(name: Name, _: &LazyWalkReses<PartialEval>) -> Result<Value, Self::Err> {
+        Ok(VariableReference(name).reify())
+    }
+}
+impl WalkMode for UnusedNegativePartialEval {
+    fn name() -> &'static str { "XXXXX" }
+    type Elt = Value;
+    type Negated = PartialEval;
+    type AsPositive = PartialEval;
+    type AsNegative = UnusedNegativePartialEval;
+    type Err = <eval::Eval as WalkMode>::Err;
+    type D = crate::walk_mode::Negative<UnusedNegativePartialEval>;
+    type ExtraInfo = ();
+    fn get_walk_rule(_: &Form) -> WalkRule<UnusedNegativePartialEval> { icp!() }
+    fn automatically_extend_env() -> bool { icp!() }
+}
+
+impl NegativeWalkMode for UnusedNegativePartialEval {
+    fn needs_pre_match() -> bool { panic!() }
+}
+
+/// Does every variable that `a` refers to (without binding it) have a value in `env`?
+fn is_closed(a: &Ast, env: &Assoc<Name, Value>) -> bool {
+    free_vars_at_runtime(a, &EnvMBE::new(), 0).iter().all(|v| env.find(v).is_some())
+}
+
+/// Like `alpha::free_vars`, but quoted syntax only refers to things inside unquotations.
+/// `depth` is how many quotations deep we are.
+fn free_vars_at_runtime(a: &Ast, cur_node_contents: &EnvMBE<Ast>, depth: u32) -> Vec<Name> {
+    match *a {
+        VariableReference(v) if depth == 0 => vec![v],
+        Node(ref f, ref parts, _, _) => parts
+            .marched_map(&mut |name, marched_parts: &EnvMBE<Ast>, part: &Ast| {
+                // A quotation's nonterminal isn't a variable:
+                if depth == 0 && f.name == n("quote_expr") && name == n("nt") {
+                    return vec![];
+                }
+                free_vars_at_runtime(part, marched_parts, depth)
+            })
+            .map_reduce(&|vs| vs.clone(), &|l, r| [&l[..], &r[..]].concat(), vec![]),
+        ExtendEnv(ref body, ref beta) | ExtendEnvPhaseless(ref body, ref beta) => {
+            let bound = crate::beta::bound_from_beta(beta, cur_node_contents, 0);
+            free_vars_at_runtime(body, cur_node_contents, depth)
+                .into_iter()
+                .filter(|v| !bound.contains(v))
+                .collect()
+        }
+        QuoteMore(ref body, _) => free_vars_at_runtime(body, cur_node_contents, depth + 1),
+        QuoteLess(ref body, levels) => {
+            free_vars_at_runtime(body, cur_node_contents, depth.saturating_sub(levels as u32))
+        }
+        _ => vec![],
+    }
+}
+
+/// Is `f` an expression (as opposed to a type, pattern, etc.)?
+fn is_evaluable(f: &Form) -> bool {
+    match *f.eval.pos() {
+        Custom(_) | Body(_) => f.name != n("macro_invocation"),
+        _ => false,
+    }
+}
+
+/// If only some parts of an `f` node run whenever the node does, which ones?
+/// (e.g. an `if`'s branches are conditional, and a `lambda`'s body might never run.)
+/// The rest are left alone, since evaluating them might not terminate.
+fn always_run_parts(f: &Form) -> Option<Vec<Name>> {
+    if f.name == n("lambda") || f.name == n("fix") {
+        Some(vec![])
+    } else if f.name == n("if") {
+        Some(vec![n("cond")])
+    } else if f.name == n("match") {
+        Some(vec![n("scrutinee")])
+    } else {
+        None
+    }
+}
+
+/// An expression for `val`, if the language has syntax for it.
+fn residualize(val: &Value) -> Option<Ast> {
+    match *val {
+        Value::Int(ref i) => Some(int_literal(i)),
+        Value::Struct(ref components) => {
+            let mut names = vec![];
+            let mut exprs = vec![];
+            for (name, component) in components.iter_pairs() {
+                names.push(Atom(*name));
+                exprs.push(residualize(component)?);
+            }
+            Some(ast!({"Expr" "struct_expr" :
+                "component_name" => (@"c" ,seq names), "component" => (@"c" ,seq exprs)}))
+        }
+        Value::AbstractSyntax(ref a) => {
+            let nt = match *a {
+                Node(ref f, _, _, _) => nt_of(f)?,
+                _ => return None, // Could be in any nonterminal.
+            };
+            Some(ast!({"Expr" "quote_expr" :
+                "nt" => (, VariableReference(nt)), "body" => (++ true (, a.clone()))}))
+        }
+        // `Sequence`s could come from a tuple or a `seq` literal, and `Enum`s need a type.
+        _ => None,
+    }
+}
+
+/// Which nonterminal does syntax headed by `f` come from? (Judging from how `f` gets walked.)
+fn nt_of(f: &Form) -> Option<Name> {
+    match (&f.eval, &f.type_compare) {
+        (Negative(_), _) => Some(n("Pat")),
+        (Positive(Custom(_)), _) | (Positive(Body(_)), _) => Some(n("Expr")),
+        (_, Both(LiteralLike, _)) | (_, Both(Custom(_), _)) => Some(n("Type")),
+        _ => None,
+    }
+}
+
+thread_local! {
+    static int_literal_form: Rc<Form> = Rc::new(Form {
+        name: n("int_literal"),
+        description: Some("integer literal".to_owned()),
+        // Only partial evaluation makes these; there's no syntax for them (yet).
+        grammar: Rc::new(crate::grammar::FormPat::Impossible),
+        type_compare: Positive(NotWalked),
+        synth_type: Positive(cust_rc_box!(|_| Ok(Ast::int_ty()))),
+        quasiquote: Both(LiteralLike, LiteralLike),
+        eval: Positive(cust_rc_box!(|part_values| {
+            let digits = part_values.get_term(n("value")).to_name().orig_sp();
+            match digits.parse::<BigInt>() {
+                Ok(i) => Ok(Value::Int(i)),
+                Err(_) => icp!("malformed integer literal {}", digits),
+            }
+        })),
+    });
+}
+
+/// An expression for the integer `i`.
+pub fn int_literal(i: &BigInt) -> Ast {
+    ast!({int_literal_form.with(|f| f.clone()) ; "value" => (, Atom(n(&i.to_string())))})
+}
+
+/// Simplify `expr`, given the values in `env`.
+pub fn partial_eval(expr: &Ast, env: Assoc<Name, Value>) -> Result<Ast, ()> {
+    Ok(Ast::reflect(&crate::ast_walk::walk::<PartialEval>(expr, &LazyWalkReses::new_wrapper(env))?))
+}
+
+#[test]
+fn partial_eval_basics() {
+    use crate::alpha::alpha_normalize;
+
+    let lambda = |param: &str, body: Ast| {
+        ast!({"Expr" "lambda" : "param" => [@"p" (, Atom(n(param)))],
+            "p_t" => [@"p" {"Type" "Int" :}], "body" => (import [* ["param" : "p_t"]] (, body))})
+    };
+    let apply = |rator: &str, rands: Vec<Ast>| {
+        ast!({"Expr" "apply" :
+            "rator" => (vr rator), "rand" => (,seq rands)})
+    };
+    let lit = |i: i32| int_literal(&BigInt::from(i));
+
+    let identity = eval::eval_top(&lambda("y", ast!((vr "y")))).unwrap();
+    let env = crate::runtime::core_values::core_values().set(n("identity"), identity);
+    let pe = |a: &Ast| partial_eval(a, env.clone()).map(|res| alpha_normalize(&res));
+
+    assert_eq!(pe(&apply("identity", vec![lit(3)])), Ok(lit(3)));
+    assert_eq!(eval::eval(&lit(3), Assoc::new()), Ok(Value::Int(BigInt::from(3))));
+    // `x` is unknown:
+    let unknown = apply("identity", vec![ast!((vr "x"))]);
+    assert_eq!(pe(&unknown), Ok(unknown));
+    // ...but its neighbors can still be simplified:
+    let sum = apply("plus", vec![ast!((vr "one")), ast!((vr "two"))]);
+    assert_eq!(
+        pe(&apply("plus", vec![sum.clone(), ast!((vr "x"))])),
+        Ok(apply("plus", vec![lit(3), ast!((vr "x"))]))
+    );
+
+    // Nothing under a `lambda` is touched, since it might never run:
+    let under_lambda = alpha_normalize(&lambda("z", sum.clone()));
+    assert_eq!(pe(&under_lambda), Ok(under_lambda.clone()));
+    // Likewise for the branches of an `if` (only the condition is sure to run):
+    let if_ = |cond: Ast, then: Ast, else_: Ast| {
+        ast!({"Expr" "if" :
+            "cond" => (, cond), "then" => (, then), "else" => (, else_)})
+    };
+    // (`zero?`'s result is a `Bool`, which has no syntax without a type annotation.)
+    let zero_x = apply("zero?", vec![apply("plus", vec![sum.clone(), ast!((vr "x"))])]);
+    assert_eq!(
+        pe(&if_(zero_x, sum.clone(), ast!((vr "x")))),
+        Ok(if_(
+            apply("zero?", vec![apply("plus", vec![lit(3), ast!((vr "x"))])]),
+            sum.clone(),
+            ast!((vr "x"))
+        ))
+    );
+
+    // Binders don't get confused by shadowing:
+    let let_ = |name: &str, rhs: Ast, body: Ast| {
+        ast!({"Expr" "let" : "name" => (, Atom(n(name))), "rhs" => (, rhs),
+            "body" => (import ["name" = "rhs"] (, body))})
+    };
+    let shadowed = alpha_normalize(&let_("one", ast!((vr "x")), sum.clone()));
+    assert_eq!(pe(&shadowed), Ok(shadowed.clone()));
+    // ...but if the whole thing is closed, it's evaluated:
+    assert_eq!(pe(&let_("one", ast!((vr "two")), sum)), Ok(lit(4)));
+
+    // Syntax becomes a quotation:
+    let quoted = ast!({"Expr" "quote_expr" : "nt" => (vr "Expr"),
+        "body" => (++ true (, apply("plus", vec![ast!((vr "one")), ast!((vr "two"))])))});
+    assert_eq!(pe(&apply("identity", vec![quoted.clone()])), Ok(quoted));
+    // Functions have no syntax:
+    let fn_of_fn = apply("identity", vec![ast!((vr "identity"))]);
+    assert_eq!(pe(&fn_of_fn), Ok(fn_of_fn));
+}
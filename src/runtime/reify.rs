@@ -244,21 +244,14 @@ pub fn reify_1ary_function<A: Reifiable + 'static, R: Reifiable + 'static>(
     f: Rc<Box<(dyn Fn(A) -> R)>>,
 ) -> Value {
     Value::BuiltInFunction(eval::BIF(Rc::new(move |args: Vec<Value>| {
-        ((*f)(A::reflect(&args[0]))).reify()
+        Ok(((*f)(A::reflect(&args[0]))).reify())
     })))
 }
 
 pub fn reflect_1ary_function<A: Reifiable + 'static, R: Reifiable + 'static>(
     f_v: Value,
 ) -> Rc<Box<(dyn Fn(A) -> R)>> {
-    Rc::new(Box::new(move |a: A| {
-        extract!((&f_v)
-        Value::BuiltInFunction = (ref bif) => R::reflect(&(*bif.0)(vec![a.reify()]));
-        Value::Function = (ref closure) => {
-            R::reflect(&eval::eval(&closure.body,
-                closure.env.clone().set(closure.params[0], a.reify())).unwrap())
-        })
-    }))
+    Rc::new(Box::new(move |a: A| R::reflect(&eval::apply_function(&f_v, vec![a.reify()]).unwrap())))
 }
 
 // I bet there's more of a need for reification than reflection for functions....
@@ -270,7 +263,7 @@ pub fn reify_2ary_function<
     f: Rc<Box<(dyn Fn(A, B) -> R)>>,
 ) -> Value {
     Value::BuiltInFunction(eval::BIF(Rc::new(move |args: Vec<Value>| {
-        ((*f)(A::reflect(&args[0]), B::reflect(&args[1]))).reify()
+        Ok(((*f)(A::reflect(&args[0]), B::reflect(&args[1]))).reify())
     })))
 }
 
@@ -282,14 +275,7 @@ pub fn reflect_2ary_function<
     f_v: Value,
 ) -> Rc<Box<(dyn Fn(A, B) -> R)>> {
     Rc::new(Box::new(move |a: A, b: B| {
-        extract!((&f_v)
-        Value::BuiltInFunction = (ref bif) =>
-            R::reflect(&(*bif.0)(vec![a.reify(), b.reify()]));
-        Value::Function = (ref closure) => {
-            R::reflect(&eval::eval(&closure.body,
-                closure.env.clone().set(closure.params[0], a.reify())
-                                   .set(closure.params[1], b.reify())).unwrap())
-        })
+        R::reflect(&eval::apply_function(&f_v, vec![a.reify(), b.reify()]).unwrap())
     }))
 }
 
@@ -0,0 +1,90 @@
+use crate::{
+    ast::{Ast, Ast::*},
+    core_type_forms::get__inferred_type,
+    form::Form,
+    name::{n, Name},
+    ty_compare::Coercion,
+    util::mbe::EnvMBE,
+};
+
+/// Strip the type information out of `expr`, leaving something that evaluates the same way:
+///  `fold`, `unfold`, `forall`, `let_type`, explicit type applications, and no-op coercions
+///  are replaced by their bodies,
+///  and `lambda` parameter annotations are replaced by `inferred_type`.
+/// Everything else is rebuilt, so evaluation order and binding structure are unchanged.
+pub fn erase_types(expr: &Ast) -> Ast {
+    match *expr {
+        Node(ref f, ref parts, ref exports, span) => {
+            if let Some(body_name) = type_only_body(f, parts) {
+                // The only binding these forms do is type-level:
+                return match *parts.get_leaf_or_panic(&body_name) {
+                    ExtendEnv(ref body, _) | ExtendEnvPhaseless(ref body, _) => erase_types(body),
+                    ref body => erase_types(body),
+                };
+            }
+            let parts = parts.named_map(&mut |name: &Name, part: &Ast| {
+                if f.name == n("lambda") && *name == n("p_t") {
+                    get__inferred_type()
+                } else {
+                    erase_types(part)
+                }
+            });
+            Node(f.clone(), parts, exports.clone(), span)
+        }
+        IncompleteNode(ref parts) => IncompleteNode(parts.map(&mut |part| erase_types(part))),
+        Shape(ref subs) => Shape(subs.iter().map(erase_types).collect()),
+        ExtendEnv(ref body, ref beta) => ExtendEnv(Box::new(erase_types(body)), beta.clone()),
+        ExtendEnvPhaseless(ref body, ref beta) => {
+            ExtendEnvPhaseless(Box::new(erase_types(body)), beta.clone())
+        }
+        // Quoted syntax is left alone:
+        QuoteMore(_, _) | QuoteLess(_, _) => expr.clone(),
+        Trivial | Atom(_) | VariableReference(_) => expr.clone(),
+    }
+}
+
+/// If `f` evaluates to one of its parts, with nothing but type information around it,
+///  which part is it?
+fn type_only_body(f: &Form, parts: &EnvMBE<Ast>) -> Option<Name> {
+    if f.name == n("coerce") {
+        let field = parts.get_leaf(n("field")).map(Ast::to_name);
+        let coercion =
+            Coercion::from_parts(parts.get_leaf_or_panic(&n("coercion")).to_name(), field);
+        return if coercion == Coercion::Widen { Some(n("body")) } else { None };
+    }
+    if f.name == n("type_apply_expr") {
+        return Some(n("rator"));
+    }
+    if [n("fold"), n("unfold"), n("forall_expr"), n("let_type")].contains(&f.name) {
+        Some(n("body"))
+    } else {
+        None
+    }
+}
+
+#[test]
+fn type_erasure() {
+    use crate::alpha::alpha_normalize;
+
+    let lambda = |p_t: Ast, body: Ast| {
+        ast!({"Expr" "lambda" : "param" => [@"p" "x"], "p_t" => [@"p" (, p_t)],
+            "body" => (import [* ["param" : "p_t"]] (, body))})
+    };
+    let annotated = ast!({"Expr" "forall_expr" : "param" => ["T"],
+        "body" => (import [* [forall "param"]] (, lambda(ast!({"Type" "Int" :}),
+            ast!({"Expr" "coerce" : "coercion" => (, Atom(n("widen"))), "body" => (vr "x"),
+                "t" => {"Type" "Float" :}}))))});
+    let erased = erase_types(&annotated);
+    assert_eq!(
+        alpha_normalize(&erased),
+        alpha_normalize(&lambda(get__inferred_type(), ast!((vr "x"))))
+    );
+
+    let apply_to_three =
+        |rator: Ast| ast!({"Expr" "apply" : "rator" => (, rator), "rand" => [(vr "three")]});
+    let env = crate::runtime::core_values::core_values();
+    assert_eq!(
+        crate::runtime::eval::eval(&apply_to_three(erased), env.clone()),
+        crate::runtime::eval::eval(&apply_to_three(annotated), env)
+    );
+}
@@ -9,15 +9,16 @@
 use crate::{
     ast::*,
     ast_walk::{
-        walk, LazyWalkReses,
+        walk, with_walk_positions, LazyWalkReses,
         WalkRule::{self},
     },
     form::Form,
     name::*,
-    util::assoc::Assoc,
+    runtime::{eval::Value, reify::Reifiable},
+    util::{assoc::Assoc, mbe::EnvMBE, scoped::Scoped},
     walk_mode::WalkMode,
 };
-use std::{fmt, rc::Rc};
+use std::{cell::RefCell, fmt, rc::Rc};
 
 impl Ast {
     // TODO: use this more
@@ -59,7 +60,10 @@ impl WalkMode for SynthTy {
         parts: &crate::ast_walk::LazyWalkReses<SynthTy>,
     ) -> Result<Ast, TypeError> {
         match parts.env.find(&name) {
-            None => Err(crate::util::err::sp(TyErr::UnboundName(name), parts.this_ast.clone())),
+            None => match crate::runtime::core_values::find_primitive(name) {
+                Some(prim) => synth_type(&prim.ty, parts.env.clone()),
+                None => Err(crate::util::err::sp(TyErr::UnboundName(name), parts.this_ast.clone())),
+            },
             // If name is protected, stop:
             Some(ty) if &VariableReference(name) == ty => Ok(ty.clone()),
             Some(ref ty) => synth_type(ty, parts.env.clone()),
@@ -98,26 +102,287 @@ pub fn synth_type(expr: &Ast, env: Assoc<Name, Ast>) -> TypeResult {
     walk::<SynthTy>(expr, &LazyWalkReses::new_wrapper(env))
 }
 
+/// Like `synth_type`, but subtyping according to `policy`.
+pub fn synth_type_with(
+    policy: &crate::ty_compare::SubtypePolicy,
+    expr: &Ast,
+    env: Assoc<Name, Ast>,
+) -> TypeResult {
+    crate::ty_compare::with_subtype_policy(policy, || synth_type(expr, env))
+}
+
+/// Check that `expr` has the type `expected`, pushing `expected` inward where that helps:
+///  a `lambda` checked against `[A -> B]` gives its unannotated parameters the type `A`,
+///  and its body gets checked against `B`.
+/// Everything else is synthesized, and must be a subtype of `expected`.
+pub fn check_type(expr: &Ast, expected: &Ast, env: Assoc<Name, Ast>) -> Result<(), TypeError> {
+    let at_expr = |body: TyErr| crate::util::err::Spanned { loc: expr.clone(), body };
+    let resolved = crate::ty_compare::canonicalize(expected, env.clone()).map_err(at_expr)?;
+    if let (Node(ref f, ref parts, _, _), Node(ref t_f, ref t_parts, _, _)) = (expr, &resolved) {
+        let expd_params = t_parts.get_rep_leaf(n("param")).unwrap_or_default();
+        if f.name == n("lambda")
+            && t_f.name == n("fn")
+            && parts.get_rep_leaf_or_panic(n("param")).len() == expd_params.len()
+        {
+            let mut body_env = env.clone();
+            for (param_parts, expd_p) in parts.march_all(&[n("param")]).iter().zip(expd_params) {
+                let p_t = param_parts.get_leaf_or_panic(&n("p_t"));
+                let p_t = if *p_t == crate::core_type_forms::get__inferred_type() {
+                    expd_p.clone()
+                } else {
+                    // Parameters are contravariant:
+                    let p_t = synth_type(p_t, env.clone())?;
+                    crate::ty_compare::must_subtype(expd_p, &p_t, env.clone()).map_err(at_expr)?;
+                    p_t
+                };
+                body_env = body_env.set(param_parts.get_leaf_or_panic(&n("param")).to_name(), p_t);
+            }
+            return check_type(
+                crate::core_forms::strip_ee(parts.get_leaf_or_panic(&n("body"))),
+                t_parts.get_leaf_or_panic(&n("ret")),
+                body_env,
+            );
+        }
+    }
+    let got = synth_type(expr, env.clone())?;
+    crate::ty_compare::must_subtype(&got, expected, env).map_err(at_expr)?;
+    Ok(())
+}
+
 pub fn neg_synth_type(pat: &Ast, env: Assoc<Name, Ast>) -> Result<Assoc<Name, Ast>, TypeError> {
     walk::<UnpackTy>(pat, &LazyWalkReses::new_wrapper(env))
 }
 
+thread_local! {
+    // While `synth_type_inserting_coercions` runs: where expressions need coercing,
+    //  and the `coerce` expressions to wrap them in.
+    static inserted_coercions: RefCell<Option<Vec<(Path, Ast)>>> = RefCell::new(None);
+}
+
+/// Like `synth_type`, but where an argument doesn't fit a function's parameter type,
+///  try to coerce it (see `ty_compare::coerce`).
+/// Returns the type and `expr` with any needed `coerce` expressions inserted.
+pub fn synth_type_inserting_coercions(
+    expr: &Ast,
+    env: Assoc<Name, Ast>,
+) -> Result<(Ast, Ast), TypeError> {
+    let coercions = Scoped::set(&inserted_coercions, Some(vec![]));
+    let res = with_walk_positions(|| synth_type(expr, env));
+    let coercions = coercions.restore().unwrap();
+    let res = res?;
+    let elaborations = coercions.into_iter().map(|(path, coerced)| (path, Coerce(coerced)));
+    Ok((res, elaborate_at(expr, elaborations.collect())))
+}
+
+/// A `?` in an expression, as seen by the type checker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoleInfo {
+    /// The type that the context needs the hole to have.
+    pub expected: Ast,
+    /// The types of the variables in scope at the hole.
+    pub env: Assoc<Name, Ast>,
+}
+
+thread_local! {
+    // While `synth_type_with_holes` runs: the holes found so far.
+    static found_holes: RefCell<Option<Vec<HoleInfo>>> = RefCell::new(None);
+}
+
+/// Like `synth_type`, but also reports the `hole`s in `expr`, in the order they were typechecked.
+pub fn synth_type_with_holes(
+    expr: &Ast,
+    env: Assoc<Name, Ast>,
+) -> Result<(Ast, Vec<HoleInfo>), TypeError> {
+    use crate::ty_compare::{canonicalize, resolve, unification};
+    let holes = Scoped::set(&found_holes, Some(vec![]));
+    let res = synth_type(expr, env);
+    let holes = holes.restore().unwrap();
+
+    // Now that the whole expression has been checked, we know as much as we're going to:
+    let holes = holes
+        .into_iter()
+        .map(|hole| {
+            let expected = crate::ast_walk::Clo { it: hole.expected, env: hole.env.clone() };
+            let expected = unification.with(|unif| resolve(expected, &unif.borrow()));
+            HoleInfo {
+                expected: canonicalize(&expected.it, expected.env).unwrap_or(expected.it),
+                env: hole.env,
+            }
+        })
+        .collect();
+    Ok((res?, holes))
+}
+
+/// Record a `hole` (if anyone's looking for them).
+pub fn found_hole(hole: HoleInfo) {
+    found_holes.with(|fh| {
+        if let Some(ref mut holes) = *fh.borrow_mut() {
+            holes.push(hole)
+        }
+    })
+}
+
+/// Are we in `synth_type_inserting_coercions`?
+pub fn inserting_coercions() -> bool { inserted_coercions.with(|ic| ic.borrow().is_some()) }
+
+/// Record that the expression at `path` (see `ast_walk::walk_position`)
+///  should be wrapped in `coerced` (a `coerce` expression). Only valid if `inserting_coercions()`.
+pub fn insert_coercion(path: Path, coerced: Ast) {
+    inserted_coercions.with(|ic| match *ic.borrow_mut() {
+        Some(ref mut coercions) => coercions.push((path, coerced)),
+        None => icp!("not inserting coercions"),
+    })
+}
+
+// Where a polymorphic function is applied, and what its type parameters were instantiated at.
+type TypeArgs = (Path, Vec<crate::ast_walk::Clo<Ast>>);
+
+thread_local! {
+    // While `elaborate` runs: the applications whose type arguments might need making explicit.
+    // (The type arguments might be underdetermined until the whole expression is checked.)
+    static elided_type_args: RefCell<Option<Vec<TypeArgs>>> = RefCell::new(None);
+}
+
+/// Typecheck `expr`, and also produce an elaborated version of it:
+///  the type arguments of polymorphic functions are made explicit (with `type_apply_expr`),
+///  and coercions are inserted (as in `synth_type_inserting_coercions`).
+/// Returns the elaborated expression and its type.
+pub fn elaborate(expr: &Ast, env: Assoc<Name, Ast>) -> Result<(Ast, Ast), TypeError> {
+    use crate::ty_compare::{canonicalize, resolve, unification};
+    let coercions = Scoped::set(&inserted_coercions, Some(vec![]));
+    let type_args = Scoped::set(&elided_type_args, Some(vec![]));
+    let res = with_walk_positions(|| synth_type(expr, env));
+    let type_args = type_args.restore().unwrap();
+    let coercions = coercions.restore().unwrap();
+    let ty = res?;
+
+    // Now that the whole expression has been checked, we know as much as we're going to.
+    // (If a type argument is still underdetermined, leave that application alone.)
+    let type_args = type_args.into_iter().filter_map(|(path, args)| {
+        let args: Option<Vec<Ast>> = args
+            .into_iter()
+            .map(|arg| {
+                let arg = unification.with(|unif| resolve(arg, &unif.borrow()));
+                canonicalize(&arg.it, arg.env).ok().filter(is_determined)
+            })
+            .collect();
+        args.map(|args| (path, TypeArgs(args)))
+    });
+    let coercions = coercions.into_iter().map(|(path, coerced)| (path, Coerce(coerced)));
+    Ok((elaborate_at(expr, type_args.chain(coercions).collect()), ty))
+}
+
+/// Are we in `elaborate`?
+pub fn elaborating() -> bool { elided_type_args.with(|eta| eta.borrow().is_some()) }
+
+/// Record that the polymorphic rator of the application at `path` was instantiated at `args`.
+/// Only valid if `elaborating()`.
+pub fn elide_type_args(path: Path, args: Vec<crate::ast_walk::Clo<Ast>>) {
+    elided_type_args.with(|eta| match *eta.borrow_mut() {
+        Some(ref mut type_args) => type_args.push((path, args)),
+        None => icp!("not elaborating"),
+    })
+}
+
+/// Does `t` contain no underdetermined types?
+pub fn is_determined(t: &Ast) -> bool {
+    let u_f = crate::ty_compare::underdetermined_form.with(|u_f| u_f.clone());
+    match *t {
+        Node(ref f, _, _, _) if f == &u_f => false,
+        Node(_, ref parts, _, _) => parts.map_reduce(&is_determined, &|l, r| *l && *r, true),
+        ExtendEnv(ref body, _)
+        | ExtendEnvPhaseless(ref body, _)
+        | QuoteMore(ref body, _)
+        | QuoteLess(ref body, _) => is_determined(body),
+        _ => true,
+    }
+}
+
+/// Something `elaborate` does to the subterm at a particular `Path`.
+enum Elaboration {
+    /// Make the type arguments of an application explicit (with `type_apply_expr`).
+    TypeArgs(Vec<Ast>),
+    /// Wrap the subterm in a `coerce` expression (this one, but with the subterm as its `body`).
+    Coerce(Ast),
+}
+use self::Elaboration::*;
+
+fn elaborate_at(expr: &Ast, mut elaborations: Vec<(Path, Elaboration)>) -> Ast {
+    // Deeper subterms go first, so that the paths to shallower ones stay valid.
+    // (At the same path, the coercion goes around the application.)
+    elaborations
+        .sort_by_key(|(path, elab)| (std::cmp::Reverse(path.0.len()), matches!(elab, Coerce(_))));
+    // A subterm might be checked more than once:
+    let mut seen = std::collections::HashSet::new();
+    elaborations.retain(|(path, elab)| seen.insert((path.clone(), matches!(elab, Coerce(_)))));
+
+    elaborations.into_iter().fold(expr.clone(), |expr, (path, elab)| {
+        let elaborated = expr.update_at(&path, |sub| match (elab, sub) {
+            (TypeArgs(args), Node(f, parts, export, span)) => {
+                let mut parts = parts.clone();
+                let rator = parts.get_leaf_or_panic(&n("rator")).clone();
+                parts.add_leaf(
+                    n("rator"),
+                    ast!({"Expr" "type_apply_expr" : "rator" => (, rator), "arg" => (,seq args)}),
+                );
+                Some(Node(f.clone(), parts, export.clone(), *span))
+            }
+            (Coerce(Node(f, mut parts, export, span)), _) => {
+                parts.add_leaf(n("body"), sub.clone());
+                Some(Node(f, parts, export, span))
+            }
+            _ => None,
+        });
+        elaborated.unwrap_or_else(|| icp!("nothing to elaborate at {:?} in {}", path, expr))
+    })
+}
+
 custom_derive! {
     #[derive(Reifiable, Clone, PartialEq)]
     pub enum TyErr {
         Mismatch(Ast, Ast), // got, expected
         LengthMismatch(Vec<Ast>, usize),
+        DriverMismatch(Vec<Ast>, Vec<Ast>), // got, expected
         NtInterpMismatch(Name, Name),
         NonexistentEnumArm(Name, Ast),
         NonexistentStructField(Name, Ast),
         NonExhaustiveMatch(Ast),
         UnableToDestructure(Ast, Name),
         UnboundName(Name),
+        UnboundTypeVar(Name),
         // TODO: the reification macros can't handle empty `enum` cases. Fix that!
         AnnotationRequired(()),
         NeedsDriver(()),
         // TODO: replace all uses of `Other` with more specific errors:
-        Other(String)
+        Other(String),
+        /// For type rules from outside the core language (e.g. an embedder's own forms)
+        Custom(CustomTyErr)
+    }
+}
+
+impl TyErr {
+    pub fn custom(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> TyErr {
+        TyErr::Custom(CustomTyErr(Rc::from(err.into())))
+    }
+}
+
+/// An arbitrary error, wrapped so that `TyErr` can stay `Clone` and `PartialEq`.
+/// Two `CustomTyErr`s are equal if their messages are.
+#[derive(Clone)]
+pub struct CustomTyErr(pub Rc<dyn std::error::Error + Send + Sync>);
+
+impl PartialEq for CustomTyErr {
+    fn eq(&self, other: &CustomTyErr) -> bool { self.0.to_string() == other.0.to_string() }
+}
+
+// Only the message survives reification.
+impl Reifiable for CustomTyErr {
+    fn ty_name() -> Name { n("CustomTyErr") }
+
+    fn reify(&self) -> Value { self.0.to_string().reify() }
+
+    fn reflect(v: &Value) -> Self {
+        CustomTyErr(Rc::from(Box::<dyn std::error::Error + Send + Sync>::from(String::reflect(v))))
     }
 }
 
@@ -126,7 +391,12 @@ impl fmt::Display for TyErr {
         use self::TyErr::*;
         match *self {
             Mismatch(ref got, ref exp) => {
-                write!(f, "[Mismatch] got:\n  `{}`\n   expected:\n  `{}`\n", got, exp)
+                write!(f, "[Mismatch] got:\n  `{}`\n   expected:\n  `{}`\n", got, exp)?;
+                // If the types are similar, point out where they differ:
+                match type_diff(got, exp) {
+                    diff @ TyDiff::Within(_, _) => write!(f, "{}", diff),
+                    _ => Ok(()),
+                }
             }
             LengthMismatch(ref got, exp_len) => {
                 write!(f, "[LengthMismatch] got:\n  ")?;
@@ -135,6 +405,16 @@ impl fmt::Display for TyErr {
                 }
                 write!(f, "\n  expected {} arguments.\n", exp_len)
             }
+            DriverMismatch(ref got, ref exp) => {
+                let list = |ds: &[Ast]| ds.iter().map(|d| format!("{}", d)).collect::<Vec<_>>();
+                write!(
+                    f,
+                    "[DriverMismatch] a repetition driven by `{}` where one driven by `{}` was \
+                     expected",
+                    list(got).join(" "),
+                    list(exp).join(" ")
+                )
+            }
             NtInterpMismatch(got, exp) => write!(
                 f,
                 "[NtInterpMismatch] expected the nonterminal `{}`, but `{}` was interpolated",
@@ -157,6 +437,9 @@ impl fmt::Display for TyErr {
                 write!(f, "[UnableToDestructure] expected a `{}` type, got `{}`", expected_name, ty)
             }
             UnboundName(name) => write!(f, "[UnboundName] `{}` is not defined", name),
+            UnboundTypeVar(name) => {
+                write!(f, "[UnboundTypeVar] the type `{}` is not defined", name)
+            }
             AnnotationRequired(()) => write!(
                 f,
                 "[AnnotationRequired] Negative syntax (e.g. a pattern) inside positive syntax \
@@ -164,10 +447,168 @@ impl fmt::Display for TyErr {
             ),
             NeedsDriver(()) => write!(f, "[NeedsDriver] Repetition needs a driver"),
             Other(ref s) => write!(f, "[Other] {}", s),
+            Custom(ref err) => write!(f, "[Custom] {}", err.0),
+        }
+    }
+}
+
+/// A structural comparison of two types, for explaining `Mismatch`es.
+#[derive(Clone, PartialEq, Debug)]
+pub enum TyDiff {
+    Same(Ast),
+    /// got, expected
+    Differ(Ast, Ast),
+    /// The same form with the same shape, but something inside differs.
+    Within(Rc<Form>, EnvMBE<TyDiff>),
+}
+
+/// Find the subtrees where `got` and `expd` differ.
+pub fn type_diff(got: &Ast, expd: &Ast) -> TyDiff {
+    if got == expd {
+        return TyDiff::Same(got.clone());
+    }
+    match (got, expd) {
+        (Node(f_got, parts_got, _, _), Node(f_expd, parts_expd, _, _))
+            if f_got == f_expd && parts_got.can_map_with(parts_expd) =>
+        {
+            TyDiff::Within(f_got.clone(), parts_got.map_with(parts_expd, &type_diff))
+        }
+        // Binding is invisible in the diff (but, e.g., `forall` parameters aren't)
+        (ExtendEnv(body_got, beta_got), ExtendEnv(body_expd, beta_expd))
+            if beta_got == beta_expd =>
+        {
+            type_diff(body_got, body_expd)
         }
+        _ => TyDiff::Differ(got.clone(), expd.clone()),
     }
 }
 
+impl TyDiff {
+    pub fn is_same(&self) -> bool { matches!(*self, TyDiff::Same(_)) }
+
+    /// The differing subtrees (got, expected), each with the part names that lead to it.
+    pub fn differences(&self) -> Vec<(Vec<Name>, Ast, Ast)> {
+        let mut res = vec![];
+        self.collect_differences(&mut vec![], &mut res);
+        res
+    }
+
+    fn collect_differences(&self, path: &mut Vec<Name>, res: &mut Vec<(Vec<Name>, Ast, Ast)>) {
+        match *self {
+            TyDiff::Same(_) => {}
+            TyDiff::Differ(ref got, ref expd) => {
+                res.push((path.clone(), got.clone(), expd.clone()))
+            }
+            TyDiff::Within(_, ref parts) => {
+                parts.named_map(&mut |part_name: &Name, sub: &TyDiff| {
+                    path.push(*part_name);
+                    sub.collect_differences(path, res);
+                    path.pop();
+                });
+            }
+        }
+    }
+}
+
+// Like a unified diff, with one hunk per differing subtree:
+impl fmt::Display for TyDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (path, got, expd) in self.differences() {
+            write!(f, "@@ type")?;
+            for part_name in path {
+                write!(f, ".{}", part_name)?;
+            }
+            write!(f, " @@\n- {}\n+ {}\n", got, expd)?;
+        }
+        Ok(())
+    }
+}
+
+/// A type-level rewrite: whatever matches `pattern` becomes `replacement`.
+/// References to the `metavars` in `pattern` match anything (consistently),
+///  and are substituted into `replacement`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RewriteRule {
+    pub metavars: Vec<Name>,
+    pub pattern: Ast,
+    pub replacement: Ast,
+}
+
+impl RewriteRule {
+    /// What the metavariables have to be for `t` to match, if it does.
+    fn match_against(&self, pat: &Ast, t: &Ast) -> Option<Assoc<Name, Ast>> {
+        match (pat, t) {
+            (VariableReference(mv), _) if self.metavars.contains(mv) => {
+                Some(Assoc::new().set(*mv, t.clone()))
+            }
+            (Node(f_pat, parts_pat, _, _), Node(f_t, parts_t, _, _))
+                if f_pat == f_t && parts_pat.can_map_with(parts_t) =>
+            {
+                parts_pat.map_reduce_with(
+                    parts_t,
+                    &|sub_pat, sub_t| self.match_against(sub_pat, sub_t),
+                    &|l, r| {
+                        let (l, r) = (l?, r?);
+                        // Metavariables that appear twice have to match the same thing:
+                        for (mv, l_t) in l.iter_pairs() {
+                            if matches!(r.find(mv), Some(r_t) if r_t != l_t) {
+                                return None;
+                            }
+                        }
+                        Some(l.set_assoc(&r))
+                    },
+                    Some(Assoc::new()),
+                )
+            }
+            (ExtendEnv(body_pat, beta_pat), ExtendEnv(body_t, beta_t))
+            | (ExtendEnvPhaseless(body_pat, beta_pat), ExtendEnvPhaseless(body_t, beta_t))
+                if beta_pat == beta_t =>
+            {
+                self.match_against(body_pat, body_t)
+            }
+            _ if pat == t => Some(Assoc::new()),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, t: &Ast) -> Option<Ast> {
+        let metavar_values = self.match_against(&self.pattern, t)?;
+        Some(crate::alpha::substitute(&self.replacement, &metavar_values))
+    }
+}
+
+/// Rewrite `t` with `rules` (innermost first), until none of them apply anywhere.
+/// (So the rules had better not be able to rewrite forever.)
+pub fn rewrite(t: &Ast, rules: &[RewriteRule]) -> Ast {
+    let mut t = t.clone();
+    loop {
+        let rewritten = rewrite_once(&t, rules);
+        if rewritten == t {
+            return t;
+        }
+        t = rewritten;
+    }
+}
+
+fn rewrite_once(t: &Ast, rules: &[RewriteRule]) -> Ast {
+    let t = match *t {
+        Node(ref f, ref parts, ref export, span) => Node(
+            f.clone(),
+            parts.map(&mut |sub: &Ast| rewrite_once(sub, rules)),
+            export.clone(),
+            span,
+        ),
+        ExtendEnv(ref body, ref beta) => {
+            ExtendEnv(Box::new(rewrite_once(body, rules)), beta.clone())
+        }
+        ExtendEnvPhaseless(ref body, ref beta) => {
+            ExtendEnvPhaseless(Box::new(rewrite_once(body, rules)), beta.clone())
+        }
+        _ => t.clone(),
+    };
+    rules.iter().find_map(|rule| rule.apply(&t)).unwrap_or(t)
+}
+
 // temporary, until we get rid of `Debug` as the way of outputting errors
 impl fmt::Debug for TyErr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { fmt::Display::fmt(self, f) }
@@ -195,6 +636,30 @@ pub fn expect_type(expected: &Ast, got: &Ast, loc: &Ast) -> Result<(), TypeError
     }
 }
 
+/// Print a type in a form that `from_portable_string` can read back,
+///  e.g. for storing it in a file.
+pub fn to_portable_string(t: &Ast) -> String {
+    let canon = crate::ty_compare::canonicalize(t, Assoc::new()).unwrap_or_else(|_| t.clone());
+    fn mentioned_names(a: &Ast, names: &mut Vec<Name>) {
+        match *a {
+            Atom(name) | VariableReference(name) => names.push(name),
+            _ => a.children().into_iter().for_each(|sub| mentioned_names(sub, names)),
+        }
+    }
+    let mut names = vec![];
+    mentioned_names(&canon, &mut names);
+    with_portable_printing(&names, || format!("{}", canon))
+}
+
+pub fn from_portable_string(s: &str) -> Result<Ast, crate::earley::ParseError> {
+    crate::grammar::parse(
+        &form_pat!((call "Type")),
+        &crate::core_forms::get_core_forms(),
+        crate::earley::empty__code_envs(),
+        s,
+    )
+}
+
 #[test]
 fn basic_type_synth() {
     use crate::ast_walk::WalkRule::*;
@@ -258,6 +723,66 @@ fn basic_type_synth() {
     assert_eq!(synth_type(&ast!((vr "a")), chained_ty_env), Ok(ast!({"Type" "Int":})));
 }
 
+#[test]
+fn bidirectional_checking() {
+    let int_to_int = Ast::fn_ty(vec![Ast::int_ty()], Ast::int_ty());
+    let id = ast!({"Expr" "lambda" : "param" => [@"p" "x"],
+        "p_t" => [@"p" (, crate::core_type_forms::get__inferred_type())],
+        "body" => (import [* ["param" : "p_t"]] (vr "x"))});
+    let env = Assoc::new().set(n("three"), Ast::int_ty());
+
+    // No annotation needed:
+    assert_eq!(check_type(&id, &int_to_int, env.clone()), Ok(()));
+    assert_m!(
+        check_type(&id, &Ast::fn_ty(vec![Ast::int_ty()], Ast::nat_ty()), env.clone()),
+        ty_err_p!(Mismatch(_, _))
+    );
+    // Annotated parameters must accept what the expected type says they'll get:
+    let annotated = |p_t: Ast| {
+        ast!({"Expr" "lambda" : "param" => [@"p" "x"], "p_t" => [@"p" (, p_t)],
+            "body" => (import [* ["param" : "p_t"]] (vr "three"))})
+    };
+    assert_eq!(check_type(&annotated(Ast::int_ty()), &int_to_int, env.clone()), Ok(()));
+    assert_m!(
+        check_type(&annotated(Ast::nat_ty()), &int_to_int, env.clone()),
+        ty_err_p!(Mismatch(_, _))
+    );
+
+    // Terms that synthesize just get compared:
+    assert_eq!(check_type(&ast!((vr "three")), &Ast::int_ty(), env.clone()), Ok(()));
+    assert_m!(check_type(&ast!((vr "three")), &Ast::nat_ty(), env), ty_err_p!(Mismatch(_, _)));
+}
+
+#[test]
+fn custom_type_errors() {
+    use crate::ast_walk::WalkRule::*;
+
+    #[derive(Debug)]
+    struct Overheated(u32);
+    impl fmt::Display for Overheated {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "too hot: {} degrees", self.0)
+        }
+    }
+    impl std::error::Error for Overheated {}
+
+    let thermostat = basic_typed_form!(
+        atom,
+        Custom(Rc::new(Box::new(|parts| {
+            Err(crate::util::err::sp(TyErr::custom(Overheated(451)), parts.this_ast.clone()))
+        }))),
+        NotWalked
+    );
+    let body = basic_typed_form!(atom, Body(n("body")), NotWalked);
+
+    let res = synth_type(&ast!({body ; "body" => {thermostat ; []}}), Assoc::new());
+    let err = res.unwrap_err().body;
+    // Equality is by message:
+    assert_eq!(err, TyErr::custom("too hot: 451 degrees"));
+    assert_ne!(err, TyErr::custom("too cold"));
+    assert_eq!(format!("{}", err), "[Custom] too hot: 451 degrees");
+}
+
 #[test]
 fn type_specialization() {
     let nat_ty = ast!( { "Type" "Nat" : });
@@ -291,3 +816,102 @@ fn type_specialization() {
     // TODO: test that ∀ X. ∀ Y. [ X → Y ] is a (sortof) sensible type (for transmogrify)
     //        and that ∀ X. [ X → ∀ Y . Y ] is ridiculously permissive
 }
+
+#[test]
+fn type_diffing() {
+    let int_to_int = uty!({fn : [{Int :}] {Int :}});
+    let int_to_nat = uty!({fn : [{Int :}] {Nat :}});
+
+    assert!(type_diff(&int_to_int, &int_to_int).is_same());
+
+    let diff = type_diff(&int_to_int, &int_to_nat);
+    assert_eq!(diff.differences(), vec![(vec![n("ret")], uty!({Int :}), uty!({Nat :}))]);
+    assert_eq!(format!("{}", diff), "@@ type.ret @@\n- Int\n+ Nat\n");
+
+    // Different shapes are just different:
+    let int_int_to_int = ast!({"Type" "fn" :
+        "param" => [{"Type" "Int" :}, {"Type" "Int" :}],
+        "ret" => {"Type" "Int" :}});
+    assert_eq!(
+        type_diff(&int_to_int, &int_int_to_int),
+        TyDiff::Differ(int_to_int.clone(), int_int_to_int.clone())
+    );
+}
+
+#[test]
+fn type_synth_in_modules() {
+    let env = assoc_n!("a::foo" => uty!({Int :}), "b::foo" => uty!({Nat :}));
+    let foo = ast!((vr "foo"));
+
+    // Unqualified references resolve in the current module:
+    assert_eq!(
+        crate::name::with_current_module(n("a"), || synth_type(&foo, env.clone())),
+        Ok(uty!({Int :}))
+    );
+    assert_eq!(
+        crate::name::with_current_module(n("b"), || synth_type(&foo, env.clone())),
+        Ok(uty!({Nat :}))
+    );
+    // ...but qualification can reach into other modules:
+    let b_foo = ast!((vr "b::foo"));
+    assert_eq!(
+        crate::name::with_current_module(n("a"), || synth_type(&b_foo, env.clone())),
+        Ok(uty!({Nat :}))
+    );
+    // Outside of any module, `foo` is unbound:
+    assert_m!(synth_type(&foo, env), Err(_));
+}
+
+#[test]
+fn portable_type_strings() {
+    let id_fn_ty = ast!({ "Type" "forall_type" :
+        "param" => ["t"],
+        "body" => (import [* [forall "param"]]
+            { "Type" "fn" : "param" => [ (vr "t") ], "ret" => (vr "t") })});
+
+    let printed = to_portable_string(&id_fn_ty);
+    let parsed = from_portable_string(&printed).unwrap();
+    assert_eq!(crate::ty_compare::must_equal(&parsed, &id_fn_ty, Assoc::new()), Ok(()));
+    // Printing is stable:
+    assert_eq!(to_portable_string(&parsed), printed);
+
+    assert_m!(from_portable_string("[Int ->"), Err(_));
+
+    // A freshened name can't take the spelling of an unfreshened one, even one printed later:
+    let fresh_t = n("t").freshen();
+    let capturable_ty = ast!({ "Type" "forall_type" :
+        "param" => [(, Atom(fresh_t))],
+        "body" => (import [* [forall "param"]]
+            { "Type" "fn" : "param" => [ (, VariableReference(fresh_t)) ], "ret" => (vr "t") })});
+    let printed = to_portable_string(&capturable_ty);
+    assert_ne!(printed, to_portable_string(&id_fn_ty), "{}", printed);
+    assert_eq!(to_portable_string(&from_portable_string(&printed).unwrap()), printed);
+}
+
+#[test]
+fn type_rewriting() {
+    let nat_to_int =
+        RewriteRule { metavars: vec![], pattern: uty!({Nat :}), replacement: uty!({Int :}) };
+    // A pair of the same type is just that type:
+    let collapse_pairs = RewriteRule {
+        metavars: vec![n("T")],
+        pattern: uty!({tuple : [T; T]}),
+        replacement: uty!(T),
+    };
+
+    let rules = [nat_to_int, collapse_pairs];
+
+    let nested = uty!({fn : [{tuple : [{Nat :}; {Float :}]}] {fn : [{Nat :}] {Int :}}});
+    assert_eq!(
+        rewrite(&nested, &rules[..1]),
+        uty!({fn : [{tuple : [{Int :}; {Float :}]}] {fn : [{Int :}] {Int :}}})
+    );
+
+    // Rewriting continues until nothing changes:
+    assert_eq!(
+        rewrite(&uty!({tuple : [{tuple : [{Nat :}; {Int :}]}; {Int :}]}), &rules),
+        uty!({Int :})
+    );
+    let mismatched = uty!({tuple : [{Float :}; {Int :}]});
+    assert_eq!(rewrite(&mismatched, &rules), mismatched);
+}
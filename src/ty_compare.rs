@@ -7,8 +7,12 @@ use crate::{
     core_forms::find_core_form,
     form::{Both, Form},
     name::*,
+    runtime::eval::Value,
     ty::TyErr,
-    util::assoc::Assoc,
+    util::{
+        assoc::Assoc,
+        scoped::{with_scoped, Scoped},
+    },
     walk_mode::WalkMode,
 };
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
@@ -106,7 +110,53 @@ use std::{cell::RefCell, collections::HashMap, rc::Rc};
 ///  until we hit something that can't move further.
 /// TODO #28: could this be replaced by `SynthTy`?
 /// TODO: This doesn't change `env`, and none of its clients care. It should just return `Ast`.
-pub fn resolve(Clo { it: t, env }: Clo<Ast>, unif: &HashMap<Name, Clo<Ast>>) -> Clo<Ast> {
+pub fn resolve(clo: Clo<Ast>, unif: &HashMap<Name, Clo<Ast>>) -> Clo<Ast> {
+    use std::hash::{Hash, Hasher};
+    // Only `unification` itself is cached, because we know when it changes:
+    let is_unification = unification.with(|u| match u.try_borrow() {
+        Ok(u) => std::ptr::eq(&*u, unif),
+        Err(_) => false,
+    });
+    if !is_unification || resolve_cache.with(|rc| rc.borrow().is_none()) {
+        return resolve_uncached(clo, unif);
+    }
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    clo.it.hash(&mut hasher);
+    let key = hasher.finish();
+    let unif_version = unification_generation.with(|g| *g.borrow());
+
+    let cached = resolve_cache.with(|rc| {
+        let mut rc = rc.borrow_mut();
+        let cache = rc.as_mut()?;
+        if cache.unif_version != unif_version {
+            cache.results.clear();
+            cache.unif_version = unif_version;
+            return None;
+        }
+        cache.results.get(&key)?.iter().find_map(|(orig, res)| {
+            if orig.it == clo.it && orig.env.almost_ptr_eq(&clo.env) {
+                Some(res.clone())
+            } else {
+                None
+            }
+        })
+    });
+    if let Some(res) = cached {
+        return res;
+    }
+
+    let res = resolve_uncached(clo.clone(), unif);
+    resolve_cache.with(|rc| {
+        if let Some(ref mut cache) = *rc.borrow_mut() {
+            if cache.unif_version == unif_version {
+                cache.results.entry(key).or_default().push((clo, res.clone()));
+            }
+        }
+    });
+    res
+}
+
+fn resolve_uncached(Clo { it: t, env }: Clo<Ast>, unif: &HashMap<Name, Clo<Ast>>) -> Clo<Ast> {
     let u_f = underdetermined_form.with(|u_f| u_f.clone());
 
     let resolved = match t {
@@ -118,7 +168,7 @@ pub fn resolve(Clo { it: t, env }: Clo<Ast>, unif: &HashMap<Name, Clo<Ast>>) ->
                 None => None,
             }
         }
-        Node(ref form, ref parts, _) if form == &find_core_form("Type", "type_apply") => {
+        Node(ref form, ref parts, _, _) if form == &find_core_form("Type", "type_apply") => {
             // Expand defined type applications.
             // This is sorta similar to the type synthesis for "type_apply",
             //  but it does not recursively process the arguments (which may be underdetermined!).
@@ -155,6 +205,7 @@ pub fn resolve(Clo { it: t, env }: Clo<Ast>, unif: &HashMap<Name, Clo<Ast>>) ->
                         find_core_form("Type", "type_apply"),
                         new__tapp_parts,
                         crate::beta::ExportBeta::Nothing,
+                        None,
                     );
 
                     if res != t {
@@ -180,6 +231,7 @@ pub fn resolve(Clo { it: t, env }: Clo<Ast>, unif: &HashMap<Name, Clo<Ast>>) ->
                                 actual_params = actual_params.set(name.to_name(), arg_term.clone());
                             }
 
+                            resolve_expansions.with(|re| *re.borrow_mut() += 1);
                             Some(Clo {
                                 it: crate::alpha::substitute(
                                     crate::core_forms::strip_ee(
@@ -194,10 +246,19 @@ pub fn resolve(Clo { it: t, env }: Clo<Ast>, unif: &HashMap<Name, Clo<Ast>>) ->
                 }
             }
         }
+        Node(ref form, ref parts, _, _) if form == &find_core_form("Type", "type_if") => {
+            // (If it's stuck, it stays as it is.)
+            crate::core_type_forms::type_if_branch(
+                parts.get_leaf_or_panic(&n("lhs")),
+                parts.get_leaf_or_panic(&n("rhs")),
+                &env,
+            )
+            .map(|branch| Clo { it: parts.get_leaf_or_panic(&branch).clone(), env: env.clone() })
+        }
         // TODO: This needs to be implemented (unless issue #28 obviates it)
-        // Ast(Node(ref form, ref parts, _)) if form == &find_core_form("Type", "dotdotdot") => {
+        // Ast(Node(ref form, ref parts, _, _)) if form == &find_core_form("Type", "dotdotdot") => {
         // }
-        Node(ref form, ref parts, _) if form == &u_f => {
+        Node(ref form, ref parts, _, _) if form == &u_f => {
             // underdetermined
             unif.get(&parts.get_leaf_or_panic(&n("id")).to_name()).cloned()
         }
@@ -207,12 +268,312 @@ pub fn resolve(Clo { it: t, env }: Clo<Ast>, unif: &HashMap<Name, Clo<Ast>>) ->
     resolved.map(|clo: Clo<Ast>| resolve(clo, unif)).unwrap_or(Clo { it: t, env: env })
 }
 
+/// `clo.it`, made to mean the same thing in `env` as it does in `clo.env`:
+///  type variables that `clo.env` defines differently are replaced by their definitions.
+/// (A variable whose definition mentions it again is only expanded once.)
+fn close_over(clo: &Clo<Ast>, env: &Assoc<Name, Ast>) -> Ast {
+    let mut res = clo.it.clone();
+    let mut expanded = std::collections::HashSet::new();
+    loop {
+        let mut defs = Assoc::new();
+        for v in crate::alpha::free_vars(&res, &crate::util::mbe::EnvMBE::new()) {
+            match clo.env.find(&v) {
+                Some(&VariableReference(abstract_v)) if abstract_v == v => {}
+                Some(def) if env.find(&v) != Some(def) && !expanded.contains(&v) => {
+                    defs = defs.set(v, def.clone())
+                }
+                _ => {}
+            }
+        }
+        if defs.empty() {
+            return res;
+        }
+        expanded.extend(defs.iter_keys().cloned());
+        res = crate::alpha::substitute(&res, &defs);
+    }
+}
+
+/// Replace the underdetermined types in `t` (which is in `env`) that have been determined
+///  in `unif` with their determinations (recursively). Any others are left alone.
+pub fn apply_unification(t: &Ast, env: &Assoc<Name, Ast>, unif: &HashMap<Name, Clo<Ast>>) -> Ast {
+    let u_f = underdetermined_form.with(|u_f| u_f.clone());
+    match *t {
+        Node(ref form, ref parts, _, _) if form == &u_f => {
+            match unif.get(&parts.get_leaf_or_panic(&n("id")).to_name()) {
+                Some(clo) => apply_unification(&close_over(clo, env), env, unif),
+                None => t.clone(),
+            }
+        }
+        Node(ref form, ref parts, ref export, span) => Node(
+            form.clone(),
+            parts.map(&mut |sub: &Ast| apply_unification(sub, env, unif)),
+            export.clone(),
+            span,
+        ),
+        ExtendEnv(ref body, ref beta) => {
+            ExtendEnv(Box::new(apply_unification(body, env, unif)), beta.clone())
+        }
+        ExtendEnvPhaseless(ref body, ref beta) => {
+            ExtendEnvPhaseless(Box::new(apply_unification(body, env, unif)), beta.clone())
+        }
+        _ => t.clone(),
+    }
+}
+
+/// Turn the underdetermined types left in `t` (which is in `env`) into `forall`ed type variables
+///  (after replacing the ones that have been determined with their determinations).
+pub fn generalize(t: &Ast, env: &Assoc<Name, Ast>) -> Ast {
+    let mut params = vec![];
+    let body = unification.with(|unif| generalize_rec(t, env, &unif.borrow(), &mut params));
+    if params.is_empty() {
+        return body;
+    }
+    ast!({"Type" "forall_type" :
+        "param" => (,seq params.into_iter().map(Atom).collect::<Vec<_>>()),
+        "body" => (import [* [forall "param"]] (, body))})
+}
+
+fn generalize_rec(
+    t: &Ast,
+    env: &Assoc<Name, Ast>,
+    unif: &HashMap<Name, Clo<Ast>>,
+    params: &mut Vec<Name>,
+) -> Ast {
+    let u_f = underdetermined_form.with(|u_f| u_f.clone());
+    match *t {
+        Node(ref form, ref parts, _, _) if form == &u_f => {
+            let id = parts.get_leaf_or_panic(&n("id")).to_name();
+            match unif.get(&id) {
+                Some(clo) => generalize_rec(&close_over(clo, env), env, unif, params),
+                None => {
+                    if !params.contains(&id) {
+                        params.push(id);
+                    }
+                    VariableReference(id)
+                }
+            }
+        }
+        Node(ref form, ref parts, ref export, span) => Node(
+            form.clone(),
+            parts.map(&mut |sub: &Ast| generalize_rec(sub, env, unif, params)),
+            export.clone(),
+            span,
+        ),
+        ExtendEnv(ref body, ref beta) => {
+            ExtendEnv(Box::new(generalize_rec(body, env, unif, params)), beta.clone())
+        }
+        ExtendEnvPhaseless(ref body, ref beta) => {
+            ExtendEnvPhaseless(Box::new(generalize_rec(body, env, unif, params)), beta.clone())
+        }
+        _ => t.clone(),
+    }
+}
+
+/// Does `var` (a type variable or the `id` of an underdetermined type) appear free in `t`,
+///  after following variable references in `env` and determinations in `unification`?
+pub fn occurs(var: Name, t: &Ast, env: &Assoc<Name, Ast>) -> bool {
+    unification
+        .with(|unif| occurs_rec(var, Clo { it: t.clone(), env: env.clone() }, &unif.borrow()))
+}
+
+fn occurs_rec(var: Name, t: Clo<Ast>, unif: &HashMap<Name, Clo<Ast>>) -> bool {
+    occurs_under(var, t, unif, None, &mut vec![])
+}
+
+/// `parts` is the innermost enclosing node (for binders); `expanding` prevents infinite regress.
+fn occurs_under(
+    var: Name,
+    Clo { it: t, env }: Clo<Ast>,
+    unif: &HashMap<Name, Clo<Ast>>,
+    parts: Option<&crate::util::mbe::EnvMBE<Ast>>,
+    expanding: &mut Vec<Name>,
+) -> bool {
+    match t {
+        VariableReference(vr) if vr == var => return true,
+        VariableReference(vr) if expanding.contains(&vr) => return false,
+        VariableReference(vr) => expanding.push(vr),
+        _ => {}
+    }
+    let Clo { it: t, env } = resolve(Clo { it: t, env }, unif);
+    let u_f = underdetermined_form.with(|u_f| u_f.clone());
+
+    match t {
+        VariableReference(vr) => vr == var,
+        Node(ref form, ref parts, _, _) if form == &u_f => {
+            parts.get_leaf_or_panic(&n("id")).to_name() == var
+        }
+        Node(_, ref parts, _, _) => parts.map_reduce(
+            &|sub| {
+                occurs_under(
+                    var,
+                    Clo { it: sub.clone(), env: env.clone() },
+                    unif,
+                    Some(parts),
+                    &mut expanding.clone(),
+                )
+            },
+            &|l, r| *l || *r,
+            false,
+        ),
+        ExtendEnv(ref body, ref beta) | ExtendEnvPhaseless(ref body, ref beta) => {
+            // Both binding (e.g. `forall`) and protecting (e.g. `mu`) hide names from outside:
+            let binder_leaves = beta.names_mentioned();
+            let binders = parts.map(|parts| {
+                parts
+                    .named_map(&mut |leaf_name: &Name, a: &Ast| {
+                        let mut a = a;
+                        while let ExtendEnv(ref sub, _) | ExtendEnvPhaseless(ref sub, _) = *a {
+                            a = &**sub; // e.g. `mu` params are imported into themselves
+                        }
+                        match *a {
+                            Atom(b) | VariableReference(b) if binder_leaves.contains(leaf_name) => {
+                                vec![b]
+                            }
+                            _ => vec![],
+                        }
+                    })
+                    .map_reduce(&|b| b.clone(), &|l, r| [&l[..], &r[..]].concat(), vec![])
+            });
+            let mut env = env;
+            for b in binders.unwrap_or_default() {
+                if b == var {
+                    return false;
+                }
+                env = env.set(b, VariableReference(b)); // protect it, as `mu` does
+            }
+            occurs_under(var, Clo { it: (**body).clone(), env }, unif, parts, expanding)
+        }
+        QuoteMore(ref body, _) | QuoteLess(ref body, _) => {
+            occurs_under(var, Clo { it: (**body).clone(), env }, unif, parts, expanding)
+        }
+        Shape(ref subs) => subs.iter().any(|sub| {
+            occurs_under(var, Clo { it: sub.clone(), env: env.clone() }, unif, parts, expanding)
+        }),
+        IncompleteNode(ref sub_parts) => sub_parts.map_reduce(
+            &|sub| {
+                occurs_under(
+                    var,
+                    Clo { it: sub.clone(), env: env.clone() },
+                    unif,
+                    Some(sub_parts),
+                    &mut expanding.clone(),
+                )
+            },
+            &|l, r| *l || *r,
+            false,
+        ),
+        Trivial | Atom(_) => false,
+    }
+}
+
+/// Panics if the `underdetermined_form`s in `unif` form a cycle (which would hang `resolve`).
+/// Only checks in debug builds; it's run after every new determination.
+pub fn check_unif_acyclic(unif: &HashMap<Name, Clo<Ast>>) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    fn mentioned_udets(t: &Ast, u_f: &Rc<Form>) -> Vec<Name> {
+        match t {
+            Node(ref form, ref parts, _, _) if form == u_f => {
+                vec![parts.get_leaf_or_panic(&n("id")).to_name()]
+            }
+            Node(_, ref parts, _, _) | IncompleteNode(ref parts) => parts.map_reduce(
+                &|sub| mentioned_udets(sub, u_f),
+                &|l, r| [&l[..], &r[..]].concat(),
+                vec![],
+            ),
+            Shape(ref subs) => subs.iter().flat_map(|sub| mentioned_udets(sub, u_f)).collect(),
+            ExtendEnv(ref body, _)
+            | ExtendEnvPhaseless(ref body, _)
+            | QuoteMore(ref body, _)
+            | QuoteLess(ref body, _) => mentioned_udets(body, u_f),
+            Trivial | Atom(_) | VariableReference(_) => vec![],
+        }
+    }
+    // `chain` is the sequence of determinations that led us to `id`:
+    fn visit(
+        id: Name,
+        unif: &HashMap<Name, Clo<Ast>>,
+        u_f: &Rc<Form>,
+        chain: &mut Vec<Name>,
+        finished: &mut std::collections::HashSet<Name>,
+    ) {
+        if finished.contains(&id) {
+            return;
+        }
+        if let Some(start) = chain.iter().position(|link| *link == id) {
+            let cycle: Vec<String> =
+                chain[start..].iter().chain(Some(&id)).map(|link| format!("{}", link)).collect();
+            icp!("cycle in unification: {}", cycle.join(" → "))
+        }
+        if let Some(clo) = unif.get(&id) {
+            chain.push(id);
+            for next in mentioned_udets(&clo.it, u_f) {
+                visit(next, unif, u_f, chain, finished);
+            }
+            chain.pop();
+        }
+        finished.insert(id);
+    }
+
+    let u_f = underdetermined_form.with(|u_f| u_f.clone());
+    let mut finished = std::collections::HashSet::new();
+    for id in unif.keys() {
+        visit(*id, unif, &u_f, &mut vec![], &mut finished);
+    }
+}
+
+type UnderspecifiedRenderer = Rc<dyn Fn(Name, usize) -> String>;
+
+// The type to resolve, and what it resolved to:
+type Resolution = (Clo<Ast>, Clo<Ast>);
+
+/// What `resolve` has already worked out, keyed by the hash of the type being resolved.
+/// Only valid for the `unification` it was computed against.
+#[derive(Default)]
+struct ResolveCache {
+    unif_version: u64, // see `unification_generation`
+    results: HashMap<u64, Vec<Resolution>>,
+}
+
 thread_local! {
+    // Active during a subtyping query (see `with_resolve_cache`):
+    static resolve_cache: RefCell<Option<ResolveCache>> = RefCell::new(None);
+    // How many `type_apply`s `resolve` has expanded (to see whether caching is doing its job):
+    pub static resolve_expansions: RefCell<usize> = RefCell::new(0);
+
+    // How to spell fresh underdetermined types (see `with_underspecified_renderer`):
+    static underspecified_renderer: RefCell<Option<UnderspecifiedRenderer>> = RefCell::new(None);
+    static underspecified_count: RefCell<usize> = RefCell::new(0);
+    // Every underdetermined type made so far in this session or `UnifCtx`, if anyone's asking
+    //  (see `with_unsolved_variables`):
+    static underspecified_ids: RefCell<Option<Vec<Name>>> = RefCell::new(None);
+
+    // Should `Canonicalize` reject unbound type variables? (see `with_strict_type_vars`)
+    static strict_type_vars: RefCell<bool> = RefCell::new(false);
+
+    // While `with_failed_subtypes` runs: the subtyping checks that have failed so far.
+    static failed_subtypes: RefCell<Option<Vec<FailedSubtype>>> = RefCell::new(None);
+
+    // Subtyping goals (expected, then actual) that involved a lookup, and are being checked.
+    // If we see one again while checking it, it's a cycle; assume it holds.
+    static subtype_assumptions: RefCell<Vec<(Clo<Ast>, Clo<Ast>)>> = RefCell::new(vec![]);
+    // Subtyping facts (subtype first) to take as given (see `is_subtype_under`):
+    static hypotheses: RefCell<Vec<(Ast, Ast)>> = RefCell::new(vec![]);
+    // While `prove_subtype` runs: the derivation so far.
+    static proof_in_progress: RefCell<Option<ProofInProgress>> = RefCell::new(None);
+
     // Invariant: `underdetermined_form`s in the HashMap must not form a cycle.
+    // (`check_unif_acyclic` checks this in debug builds.)
     pub static unification: RefCell<HashMap<Name, Clo<Ast>>>
         = RefCell::new(HashMap::<Name, Clo<Ast>>::new());
+    // Bumped whenever `unification` changes (see `unification_changed`):
+    static unification_generation: RefCell<u64> = RefCell::new(0);
+    // Every `id` that `determine` has added to `unification`, in order:
+    static determination_log: RefCell<Vec<Name>> = RefCell::new(vec![]);
     pub static underdetermined_form : Rc<Form> = Rc::new(Form {
         name: n("<underdetermined>"),
+        description: None,
         grammar: Rc::new(form_pat!((named "id", atom))),
         type_compare: Both(
             // pre-match handle s the negative case; we need to do the positive case manually:
@@ -226,12 +587,93 @@ thread_local! {
                 })
             }),
             NotWalked),
-        synth_type:   Both(NotWalked, NotWalked),
+        // (e.g. unannotated lambda parameters have underdetermined types)
+        synth_type:   Both(LiteralLike, NotWalked),
         eval:         Both(NotWalked, NotWalked),
         quasiquote:   Both(NotWalked, NotWalked)
     })
 }
 
+/// How `Nat`, `Int`, and `Float` relate to each other under subtyping.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NumericTower {
+    /// They are unrelated.
+    Strict,
+    /// `Nat <: Int <: Float`
+    Widening,
+    /// Each type is a subtype of the types after it (types not mentioned are unrelated).
+    Custom(Vec<Name>),
+}
+
+impl NumericTower {
+    fn chain(&self) -> Vec<Name> {
+        match *self {
+            NumericTower::Strict => vec![],
+            NumericTower::Widening => vec![n("Nat"), n("Int"), n("Float")],
+            NumericTower::Custom(ref chain) => chain.clone(),
+        }
+    }
+
+    pub fn is_subtype(&self, sub: Name, sup: Name) -> bool {
+        let chain = self.chain();
+        let pos = |nm: Name| chain.iter().position(|c| *c == nm);
+        match (pos(sub), pos(sup)) {
+            (Some(sub_pos), Some(sup_pos)) => sub_pos <= sup_pos,
+            _ => sub == sup,
+        }
+    }
+}
+
+/// Choices about which types are subtypes of which (beyond the structural rules).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubtypePolicy {
+    pub numeric_tower: NumericTower,
+    /// If unset, structs only match if they have exactly the same fields.
+    pub struct_width_subtyping: bool,
+    /// If set, `nominal_mu_type`s are only subtypes if they have the same name
+    ///  (rather than if their structure matches).
+    pub nominal_subtyping: bool,
+    /// If set, types that differ only in how their repeated parts are grouped are equal
+    ///  (see `Ast::semantically_eq`).
+    pub semantic_type_equality: bool,
+}
+
+impl Default for SubtypePolicy {
+    fn default() -> SubtypePolicy {
+        SubtypePolicy {
+            numeric_tower: NumericTower::Strict,
+            struct_width_subtyping: true,
+            nominal_subtyping: false,
+            semantic_type_equality: false,
+        }
+    }
+}
+
+thread_local! {
+    // The policy of the subtyping check in progress (see `with_subtype_policy`).
+    static subtype_policy: RefCell<SubtypePolicy> = RefCell::new(SubtypePolicy::default());
+}
+
+/// Run `f` (presumably some type checking), subtyping according to `policy`.
+/// (For entry points like `must_subtype_with`; the walks can't pass the policy along themselves.)
+pub fn with_subtype_policy<T>(policy: &SubtypePolicy, f: impl FnOnce() -> T) -> T {
+    with_scoped(&subtype_policy, policy.clone(), f)
+}
+
+/// Look at the policy of the subtyping check in progress.
+pub fn subtype_policy_says<T>(f: impl FnOnce(&SubtypePolicy) -> T) -> T {
+    subtype_policy.with(|sp| f(&sp.borrow()))
+}
+
+/// Are `lhs` and `rhs` (already canonicalized) literally the same type?
+pub fn same_type(lhs: &Ast, rhs: &Ast) -> bool {
+    if subtype_policy_says(|sp| sp.semantic_type_equality) {
+        lhs.semantically_eq(rhs)
+    } else {
+        lhs == rhs
+    }
+}
+
 custom_derive! {
     #[derive(Copy, Clone, Debug, Reifiable)]
     pub struct Canonicalize {}
@@ -263,7 +705,9 @@ impl WalkMode for Canonicalize {
             // If it's protected, stop:
             Some(t) if &VariableReference(n) == t => Ok(t.clone()),
             Some(t) => canonicalize(t, cnc.env.clone()),
-            None => Ok(VariableReference(n)), // TODO why can this happen?
+            // TODO why can this happen?
+            None if strict_type_vars.with(|s| *s.borrow()) => Err(TyErr::UnboundTypeVar(n)),
+            None => Ok(VariableReference(n)),
         }
     }
 
@@ -283,10 +727,24 @@ fn splice_ddd(
         match context_elts[0].destructure(ddd_form.clone()) {
             None => {} // False alarm; just a normal single repetition
             Some(sub_parts) => {
-                match sub_parts.get_leaf_or_panic(&n("body")).destructure(ddd_form) {
-                    Some(_) => icp!("TODO: count up nestings of :::[]:::"),
-                    None => return Ok(None), // :::[]::: is a subtype of :::[]:::
+                if sub_parts.get_leaf_or_panic(&n("body")).destructure(ddd_form).is_some() {
+                    icp!("TODO: count up nestings of :::[]:::")
                 }
+                // `:::[S]:::` is a subtype of `:::[T]:::` if `S` is a subtype of `T`
+                //  (and they repeat the same number of times).
+                // Walking the two `dotdotdot_type`s quasi-literally compares them element-wise,
+                //  but it doesn't insist on the drivers lining up, so check that here:
+                let sub_drivers: Vec<Ast> =
+                    sub_parts.get_rep_leaf_or_panic(n("driver")).into_iter().cloned().collect();
+                let drivers = ddd_parts.get_rep_term(n("driver"));
+                let correspond = sub_drivers.len() == drivers.len()
+                    && sub_drivers.iter().zip(drivers.iter()).all(|(sub_driver, driver)| {
+                        walk::<Subtype>(driver, &ddd_parts.with_context(sub_driver.clone())).is_ok()
+                    });
+                if !correspond {
+                    return Err(TyErr::DriverMismatch(sub_drivers, drivers));
+                }
+                return Ok(None);
             }
         }
     }
@@ -329,11 +787,11 @@ fn splice_ddd(
                 let mut undet_components = vec![];
                 undet_components
                     .resize_with(expected_len, || Subtype::underspecified(n("ddd_bit")));
-                unif.borrow_mut().insert(undet_parts.get_leaf_or_panic(&n("id")).to_name(), Clo {
-                    it: ast!({"Type" "tuple" :
-                            "component" => (,seq undet_components.clone()) }),
-                    env: ddd_parts.env.clone(),
-                });
+                let id = undet_parts.get_leaf_or_panic(&n("id")).to_name();
+                let tuple =
+                    ast!({"Type" "tuple" : "component" => (,seq undet_components.clone()) });
+                note_determination(id, &tuple);
+                determine(unif, id, Clo { it: tuple, env: ddd_parts.env.clone() });
                 for i in 0..expected_len {
                     envs_with_walked_drivers[i] =
                         envs_with_walked_drivers[i].set(name, undet_components[i].clone());
@@ -363,7 +821,17 @@ impl WalkMode for Subtype {
 
     fn underspecified(name: Name) -> Ast {
         underdetermined_form.with(|u_f| {
-            let new_name = Name::gensym(&format!("{}⚁", name));
+            let idx = underspecified_count.with(|count| count.replace_with(|c| *c + 1));
+            let spelling = match underspecified_renderer.with(|r| r.borrow().clone()) {
+                Some(renderer) => renderer(name, idx),
+                None => format!("{}⚁", name),
+            };
+            let new_name = Name::gensym(&spelling);
+            underspecified_ids.with(|ids| {
+                if let Some(ref mut ids) = *ids.borrow_mut() {
+                    ids.push(new_name)
+                }
+            });
 
             ast!({ u_f.clone() ; "id" => (, Atom(new_name))})
         })
@@ -413,6 +881,13 @@ impl crate::walk_mode::NegativeWalkMode for Subtype {
     /// Push through all variable references and underdeterminednesses on both sides,
     ///  returning types that are ready to compare, or `None` if they're definitionally equal
     fn pre_match(lhs_ty: Ast, rhs_ty: Ast, env: &Assoc<Name, Ast>) -> Option<(Clo<Ast>, Clo<Ast>)> {
+        // (Remember that the expected type, `lhs_ty`, is the supertype.)
+        if hypotheses.with(|h| h.borrow().iter().any(|(sub, sup)| sub == &rhs_ty && sup == &lhs_ty))
+        {
+            proof_leaf("hypothesis", &rhs_ty, &lhs_ty, vec![]);
+            return None;
+        }
+
         let u_f = underdetermined_form.with(|u_f| u_f.clone());
 
         let (res_lhs, res_rhs) = unification.with(|unif| {
@@ -431,128 +906,983 @@ impl crate::walk_mode::NegativeWalkMode for Subtype {
 
             match (lhs_name, rhs_name) {
                 // They are the same underdetermined type; nothing to do:
-                (Ok(l), Ok(r)) if l == r => None,
+                (Ok(l), Ok(r)) if l == r => {
+                    proof_leaf("refl", &rhs.it, &lhs.it, vec![]);
+                    None
+                }
                 // Make a determination (possibly just merging two underdetermined types):
-                (Ok(l), _) => {
-                    unif.borrow_mut().insert(l, rhs);
+                (Ok(l), _) if !occurs_rec(l, rhs.clone(), &unif.borrow()) => {
+                    proof_leaf("determine", &rhs.it, &lhs.it, vec![(l, rhs.it.clone())]);
+                    determine(unif, l, rhs);
                     None
                 }
-                (_, Ok(r)) => {
-                    unif.borrow_mut().insert(r, lhs);
+                (_, Ok(r)) if !occurs_rec(r, lhs.clone(), &unif.borrow()) => {
+                    proof_leaf("determine", &rhs.it, &lhs.it, vec![(r, lhs.it.clone())]);
+                    determine(unif, r, lhs);
                     None
                 }
+                // The underdetermined type would have to contain itself.
+                // Underdetermined types can't be walked, so put it in the context to get an error:
+                (Ok(_), _) => Some((rhs, lhs)),
                 // They are (potentially) different.
-                _ => Some((lhs, rhs)),
+                _ => {
+                    let rhs = instantiate_sub(&lhs, rhs, unif);
+                    Some((lhs, rhs))
+                }
             }
         })?;
 
+        proof_in_progress.with(|pip| {
+            if let Some(ref mut pip) = *pip.borrow_mut() {
+                pip.goal = Some((res_rhs.it.clone(), res_lhs.it.clone()));
+            }
+        });
         Some((res_lhs, res_rhs))
     }
 
+    /// This is the Amber rule, generalized from `mu` to any recursion through the environment:
+    ///  while checking a goal that involves a lookup, assume that it holds.
+    fn around_match(
+        lhs_ty: &Ast,
+        rhs_ty: &Ast,
+        env: &Assoc<Name, Ast>,
+        walk_it: impl FnOnce() -> Result<Assoc<Name, Ast>, TyErr>,
+    ) -> Result<Assoc<Name, Ast>, TyErr> {
+        let lhs = Clo { it: lhs_ty.clone(), env: env.clone() };
+        let rhs = Clo { it: rhs_ty.clone(), env: env.clone() };
+        let goal = (lhs.clone(), rhs.clone());
+        if subtype_assumptions.with(|sa| sa.borrow().contains(&goal)) {
+            proof_leaf("assumption", rhs_ty, lhs_ty, vec![]);
+            return Ok(Assoc::new());
+        }
+        // Cycles can only happen when we look something up (or expand a `type_apply`):
+        let expands = unification.with(|unif| {
+            resolve(lhs.clone(), &unif.borrow()) != lhs
+                || resolve(rhs.clone(), &unif.borrow()) != rhs
+        });
+        if !expands {
+            return walk_it();
+        }
+        subtype_assumptions.with(|sa| sa.borrow_mut().push(goal));
+        let res = walk_it();
+        subtype_assumptions.with(|sa| sa.borrow_mut().pop());
+        res
+    }
+
     // TODO: should unbound variable references ever be walked at all? Maybe it should panic?
 }
 
-pub fn canonicalize(t: &Ast, env: Assoc<Name, Ast>) -> Result<Ast, TyErr> {
-    walk::<Canonicalize>(t, &LazyWalkReses::<Canonicalize>::new_wrapper(env))
+/// `∀ X. **[⋯ :::[X >> ⋯]::: ⋯]**` can be a subtype of a tuple, by picking `X` to fit.
+/// (This is the positive-direction dual of `splice_ddd`.)
+/// Other `∀`s on the subtype side are left alone;
+///  in general, only the supertype gets specialized.
+fn instantiate_sub(
+    expd: &Clo<Ast>,
+    got: Clo<Ast>,
+    unif: &RefCell<HashMap<Name, Clo<Ast>>>,
+) -> Clo<Ast> {
+    let tuple_form = find_core_form("Type", "tuple");
+    let ddd_form = find_core_form("Type", "dotdotdot_type");
+    let expd_len = match expd.it.destructure(tuple_form.clone()) {
+        Some(expd_parts) => expd_parts.get_rep_leaf_or_panic(n("component")).len(),
+        None => return got,
+    };
+    let forall_parts = match got.it.destructure(find_core_form("Type", "forall_type")) {
+        Some(forall_parts) => forall_parts,
+        None => return got,
+    };
+    let body = crate::core_forms::strip_ee(forall_parts.get_leaf_or_panic(&n("body")));
+    let got_components = match body.destructure(tuple_form.clone()) {
+        Some(tuple_parts) => tuple_parts
+            .get_rep_leaf_or_panic(n("component"))
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>(),
+        None => return got,
+    };
+    let ddd_positions: Vec<usize> = (0..got_components.len())
+        .filter(|&i| got_components[i].destructure(ddd_form.clone()).is_some())
+        .collect();
+    // (More than one `:::[⋯]:::` would be ambiguous.)
+    let (ddd_pos, ddd_len) = match ddd_positions[..] {
+        [pos] if expd_len + 1 >= got_components.len() => (pos, expd_len + 1 - got_components.len()),
+        _ => return got,
+    };
+    let ddd_parts = got_components[ddd_pos].destructure(ddd_form).unwrap();
+
+    let mut env = got.env.clone();
+    for param in forall_parts.get_rep_leaf_or_panic(n("param")) {
+        env = env.set(param.to_name(), Subtype::underspecified(param.to_name()));
+    }
+
+    // Each driver needs to be a tuple of the right length; force underdetermined ones to be.
+    // (Check them all before determining anything, so that giving up leaves no trace.)
+    let mut driver_components = vec![];
+    let mut to_determine = vec![];
+    for driver in ddd_parts.get_rep_leaf_or_panic(n("driver")) {
+        let resolved = resolve(Clo { it: driver.clone(), env: env.clone() }, &unif.borrow());
+        let components: Vec<Ast> = if let Some(parts) = resolved.it.destructure(tuple_form.clone())
+        {
+            parts.get_rep_leaf_or_panic(n("component")).into_iter().cloned().collect()
+        } else if let Some(udet_parts) =
+            resolved.it.destructure(underdetermined_form.with(|u_f| u_f.clone()))
+        {
+            let mut components = vec![];
+            components.resize_with(ddd_len, || Subtype::underspecified(n("ddd_bit")));
+            let id = udet_parts.get_leaf_or_panic(&n("id")).to_name();
+            to_determine
+                .push((id, ast!({"Type" "tuple" : "component" => (,seq components.clone())})));
+            components
+        } else {
+            return got; // Let it fail normally
+        };
+        if components.len() != ddd_len {
+            return got;
+        }
+        driver_components.push((driver.vr_to_name(), components));
+    }
+
+    for (id, tuple) in to_determine {
+        note_determination(id, &tuple);
+        determine(unif, id, Clo { it: tuple, env: env.clone() });
+    }
+    let mut expansions = vec![Assoc::new(); ddd_len];
+    for (driver, components) in driver_components {
+        for (expansion, component) in expansions.iter_mut().zip(components) {
+            *expansion = expansion.set(driver, component);
+        }
+    }
+
+    let ddd_body = ddd_parts.get_leaf_or_panic(&n("body"));
+    let mut new_components = got_components[..ddd_pos].to_vec();
+    for expansion in &expansions {
+        new_components.push(crate::alpha::substitute(ddd_body, expansion));
+    }
+    new_components.extend_from_slice(&got_components[ddd_pos + 1..]);
+    Clo { it: ast!({"Type" "tuple" : "component" => (,seq new_components)}), env }
 }
 
-// `sub` must be a subtype of `sup`. (Note that `sub` becomes the context element!)
-pub fn is_subtype(
-    sub: &Ast,
-    sup: &Ast,
-    parts: &LazyWalkReses<crate::ty::SynthTy>,
-) -> Result<Assoc<Name, Ast>, TyErr> {
-    walk::<Subtype>(sup, &parts.switch_mode::<Subtype>().with_context(sub.clone()))
+/// Run `f`, spelling fresh underdetermined types with `renderer`, for the sake of error messages.
+/// It gets the name being underspecified and a count (which starts at 0 for this `f`).
+/// By default, `a` becomes `a⚁`, and prints as `¿a⚁?` until it is resolved.
+pub fn with_underspecified_renderer<T>(
+    renderer: impl Fn(Name, usize) -> String + 'static,
+    f: impl FnOnce() -> T,
+) -> T {
+    let _renderer = Scoped::set(&underspecified_renderer, Some(Rc::new(renderer)));
+    with_scoped(&underspecified_count, 0, f)
 }
 
-// `sub` must be a subtype of `sup`. (Note that `sub` becomes the context element!)
-// Only use this in tests or at the top level; this discards any non-phase-0-environments!
-pub fn must_subtype(
-    sub: &Ast,
-    sup: &Ast,
-    env: Assoc<Name, Ast>,
-) -> Result<Assoc<Name, Ast>, TyErr> {
-    // TODO: I think we should be canonicalizing first...
-    // TODO: they might need different environments?
-    let lwr_env = &LazyWalkReses::<Subtype>::new_wrapper(env).with_context(sub.clone());
+/// Run `f`, and also report the `id`s of the underdetermined types made during it
+///  that are still undetermined at the end (in order).
+/// After inference, these are the types that couldn't be inferred.
+pub fn with_unsolved_variables<T>(f: impl FnOnce() -> T) -> (T, Vec<Name>) {
+    let ids = Scoped::set(&underspecified_ids, Some(vec![]));
+    let res = f();
+    let ids = ids.restore().unwrap();
+    (res, unification.with(|unif| unsolved_among(ids, &unif.borrow())))
+}
 
-    walk::<Subtype>(sup, lwr_env)
+fn unsolved_among(ids: Vec<Name>, unif: &HashMap<Name, Clo<Ast>>) -> Vec<Name> {
+    ids.into_iter().filter(|id| !unif.contains_key(id)).collect()
 }
 
-// TODO: I think we need to route some other things (especially in macros.rs) through this...
-pub fn must_equal(lhs: &Ast, rhs: &Ast, env: Assoc<Name, Ast>) -> Result<(), TyErr> {
-    let lwr_env = &LazyWalkReses::new_wrapper(env);
-    if walk::<Canonicalize>(lhs, lwr_env) == walk::<Canonicalize>(rhs, lwr_env) {
-        Ok(())
+/// Run `f`, treating unbound type variables as errors during canonicalization.
+/// (Normally, they are left alone, which can lead to confusing `Mismatch`es later.)
+pub fn with_strict_type_vars<T>(f: impl FnOnce() -> T) -> T {
+    with_scoped(&strict_type_vars, true, f)
+}
+
+/// How to print an underdetermined type that hasn't been resolved.
+pub fn render_unresolved(id: Name) -> String {
+    if underspecified_renderer.with(|r| r.borrow().is_some()) {
+        format!("{}", id)
     } else {
-        Err(TyErr::Mismatch(lhs.clone(), rhs.clone()))
+        format!("¿{}?", id)
     }
 }
 
-#[test]
-fn basic_subtyping() {
-    use crate::{ty::TyErr::*, util::assoc::Assoc};
+pub fn canonicalize(t: &Ast, env: Assoc<Name, Ast>) -> Result<Ast, TyErr> {
+    walk::<Canonicalize>(t, &LazyWalkReses::<Canonicalize>::new_wrapper(env))
+}
 
-    let mt_ty_env = Assoc::new();
-    let int_ty = ast!({ "Type" "Int" : });
-    let nat_ty = ast!({ "Type" "Nat" : });
-    let float_ty = ast!({ "Type" "Float" : });
+/// Like `canonicalize`, but moving `forall`s (outside of parameters) according to `placement`.
+pub fn canonicalize_placing(
+    t: &Ast,
+    env: Assoc<Name, Ast>,
+    placement: QuantifierPlacement,
+) -> Result<Ast, TyErr> {
+    let res = canonicalize(t, env)?;
+    Ok(match placement {
+        QuantifierPlacement::AsWritten => res,
+        QuantifierPlacement::Prenex => to_prenex(&res),
+        QuantifierPlacement::Innermost => to_innermost(&res),
+    })
+}
 
-    assert_m!(must_subtype(&int_ty, &int_ty, mt_ty_env.clone()), Ok(_));
+/// Where `canonicalize_placing` should put `forall`s.
+/// Either way, a `forall` in the parameter of a function stays put:
+///  `[(∀X. X) -> Int]` (which needs an argument that's everything) is different from
+///  `∀X. [X -> Int]` (which takes anything).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantifierPlacement {
+    AsWritten,
+    /// As far out as possible: `[Int -> ∀X. [X -> X]]` becomes `∀X. [Int -> [X -> X]]`.
+    Prenex,
+    /// As far in as possible: `∀X. [Int -> [X -> X]]` becomes `[Int -> ∀X. [X -> X]]`.
+    Innermost,
+}
 
-    assert_eq!(
-        must_subtype(&float_ty, &int_ty, mt_ty_env.clone()),
-        Err(Mismatch(float_ty.clone(), int_ty.clone()))
+fn forall_ty(params: Vec<Name>, body: Ast) -> Ast {
+    if params.is_empty() {
+        return body;
+    }
+    ast!({"Type" "forall_type" : "param" => (,seq params.into_iter().map(Atom).collect::<Vec<_>>()),
+        "body" => (import [* [forall "param"]] (, body))})
+}
+
+/// The parameters and body of `t`, if it's a `forall`, with the parameters freshened
+///  (so that the body can be moved under other binders).
+fn fresh_forall_parts(t: &Ast) -> Option<(Vec<Name>, Ast)> {
+    let parts = t.destructure(find_core_form("Type", "forall_type"))?;
+    let params: Vec<Name> =
+        parts.get_rep_leaf_or_panic(n("param")).iter().map(|p| p.to_name()).collect();
+    let fresh: Vec<Name> = params.iter().map(|p| p.freshen()).collect();
+    let renaming = params.into_iter().zip(fresh.iter().map(|f| VariableReference(*f))).collect();
+    let body = crate::alpha::substitute(
+        crate::core_forms::strip_ee(parts.get_leaf_or_panic(&n("body"))),
+        &renaming,
     );
+    Some((fresh, body))
+}
 
-    let id_fn_ty = ast!({ "Type" "forall_type" :
-        "param" => ["t"],
-        "body" => (import [* [forall "param"]]
-            { "Type" "fn" : "param" => [ (vr "t") ], "ret" => (vr "t") })});
+/// Lift `forall`s out of function return types, and merge nested `forall`s.
+fn to_prenex(t: &Ast) -> Ast {
+    if let Some((params, body)) = fresh_forall_parts(t) {
+        return match fresh_forall_parts(&to_prenex(&body)) {
+            Some((inner_params, inner_body)) => {
+                forall_ty([params, inner_params].concat(), inner_body)
+            }
+            None => forall_ty(params, to_prenex(&body)),
+        };
+    }
+    if let Some(parts) = t.destructure(find_core_form("Type", "fn")) {
+        let fn_params: Vec<Ast> =
+            parts.get_rep_leaf_or_panic(n("param")).into_iter().map(to_prenex).collect();
+        let ret = to_prenex(parts.get_leaf_or_panic(&n("ret")));
+        return match fresh_forall_parts(&ret) {
+            Some((params, ret)) => forall_ty(params, Ast::fn_ty(fn_params, ret)),
+            None => Ast::fn_ty(fn_params, ret),
+        };
+    }
+    t.with_children(t.children().into_iter().map(to_prenex).collect())
+}
 
-    let int_to_int_fn_ty = ast!({ "Type" "fn" :
-         "param" => [(, int_ty.clone())],
-         "ret" => (, int_ty.clone())});
+/// Push `forall`s into function return types, as long as the parameters don't mention them.
+fn to_innermost(t: &Ast) -> Ast {
+    if let Some((params, body)) = fresh_forall_parts(t) {
+        let body = to_innermost(&body);
+        let fn_parts = match body.destructure(find_core_form("Type", "fn")) {
+            Some(fn_parts) => fn_parts,
+            None => return forall_ty(params, body),
+        };
+        let fn_params: Vec<Ast> =
+            fn_parts.get_rep_leaf_or_panic(n("param")).into_iter().cloned().collect();
+        let (stay, go): (Vec<Name>, Vec<Name>) = params
+            .into_iter()
+            .partition(|p| fn_params.iter().any(|fp| occurs(*p, fp, &Assoc::new())));
+        let ret = to_innermost(&forall_ty(go, fn_parts.get_leaf_or_panic(&n("ret")).clone()));
+        return forall_ty(stay, Ast::fn_ty(fn_params, ret));
+    }
+    t.with_children(t.children().into_iter().map(to_innermost).collect())
+}
 
-    assert_m!(must_subtype(&int_to_int_fn_ty, &int_to_int_fn_ty, mt_ty_env.clone()), Ok(_));
+/// Run a subtyping check, and add to its result every determination it made:
+///  each underdetermined type that it resolved is mapped (by `id`) to its canonicalized value.
+/// (Anything added to `determination_log` while it runs was determined by `check`.)
+fn with_determinations(
+    env: &Assoc<Name, Ast>,
+    check: impl FnOnce() -> Result<Assoc<Name, Ast>, TyErr>,
+) -> Result<Assoc<Name, Ast>, TyErr> {
+    let already_logged = determination_log.with(|log| log.borrow().len());
 
-    assert_m!(must_subtype(&id_fn_ty, &id_fn_ty, mt_ty_env.clone()), Ok(_));
+    let res = check();
+    let recording_failures = failed_subtypes.with(|fs| fs.borrow().is_some());
+    if res.is_err() && !recording_failures {
+        return res;
+    }
 
-    // actually subtype interestingly!
-    assert_m!(must_subtype(&int_to_int_fn_ty, &id_fn_ty, mt_ty_env.clone()), Ok(_));
+    let mut newly_determined: Vec<Name> =
+        determination_log.with(|log| log.borrow()[already_logged..].to_vec());
+    newly_determined.sort_by_key(|id| id.sp());
+    newly_determined.dedup();
+    let u_f = underdetermined_form.with(|u_f| u_f.clone());
+    let determinations = newly_determined.into_iter().map(|id| {
+        let udet = ast!({ u_f.clone() ; "id" => (, Atom(id))});
+        // If it was merged with something still underdetermined, we can only go so far:
+        let determination = canonicalize(&udet, env.clone()).unwrap_or_else(|_| {
+            unification.with(|unif| resolve(Clo { it: udet, env: env.clone() }, &unif.borrow()).it)
+        });
+        (id, determination)
+    });
+    match res {
+        Ok(res) => {
+            Ok(determinations.fold(res, |res, (id, determination)| res.set(id, determination)))
+        }
+        Err(err) => {
+            let failure =
+                FailedSubtype { err: err.clone(), determinations: determinations.collect() };
+            failed_subtypes.with(|fs| fs.borrow_mut().as_mut().unwrap().push(failure));
+            Err(err)
+        }
+    }
+}
 
-    // TODO: this error spits out generated names to the user without context ) :
-    assert_m!(must_subtype(&id_fn_ty, &int_to_int_fn_ty, mt_ty_env.clone()), Err(Mismatch(_, _)));
+/// A subtyping check that failed, and what it had determined before it did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedSubtype {
+    pub err: TyErr,
+    /// The `id`s of underdetermined types, and what they were determined to be.
+    pub determinations: Vec<(Name, Ast)>,
+}
 
-    let parametric_ty_env = assoc_n!(
-        "some_int" => ast!( { "Type" "Int" : }),
-        "convert_to_nat" => ast!({ "Type" "forall_type" :
-            "param" => ["t"],
-            "body" => (import [* [forall "param"]]
-                { "Type" "fn" :
-                    "param" => [ (vr "t") ],
-                    "ret" => (, nat_ty.clone() ) })}),
-        "identity" => id_fn_ty.clone(),
-        "int_to_int" => int_to_int_fn_ty.clone());
+impl std::fmt::Display for FailedSubtype {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.err)?;
+        for (id, determination) in &self.determinations {
+            write!(
+                f,
+                "\n  (having determined {} to be `{}`)",
+                render_unresolved(*id),
+                determination
+            )?;
+        }
+        Ok(())
+    }
+}
 
-    assert_m!(
-        must_subtype(&ast!((vr "int_to_int")), &ast!((vr "identity")), parametric_ty_env.clone()),
-        Ok(_)
-    );
+/// Run `f`, and also report every subtyping check that failed during it
+///  (including ones that were recovered from), for debugging.
+pub fn with_failed_subtypes<T>(f: impl FnOnce() -> T) -> (T, Vec<FailedSubtype>) {
+    let failures = Scoped::set(&failed_subtypes, Some(vec![]));
+    let res = f();
+    (res, failures.restore().unwrap())
+}
 
-    assert_m!(
-        must_subtype(&ast!((vr "identity")), &ast!((vr "int_to_int")), parametric_ty_env.clone()),
-        Err(Mismatch(_, _))
-    );
+/// Anything that modifies `unification` (or swaps it out) must call this afterwards,
+///  so that `resolve` doesn't use stale cached results.
+fn unification_changed() { unification_generation.with(|g| *g.borrow_mut() += 1) }
 
-    fn incomplete_fn_ty() -> Ast {
-        // A function, so we get a fresh underspecified type each time.
-        ast!({ "Type" "fn" :
-            "param" => [ { "Type" "Int" : } ],
-            "ret" => (, Subtype::underspecified(n("<return_type>")) )})
+/// Give the underdetermined type `id` a value in `unif` (which is `unification`).
+fn determine(unif: &RefCell<HashMap<Name, Clo<Ast>>>, id: Name, value: Clo<Ast>) {
+    unif.borrow_mut().insert(id, value);
+    determination_log.with(|log| log.borrow_mut().push(id));
+    unification_changed();
+    check_unif_acyclic(&unif.borrow());
+}
+
+/// Run `f`, remembering the results of `resolve` until it's done (unless already doing so).
+pub fn with_resolve_cache<T>(f: impl FnOnce() -> T) -> T {
+    if resolve_cache.with(|rc| rc.borrow().is_some()) {
+        return f();
     }
+    with_scoped(&resolve_cache, Some(ResolveCache::default()), f)
+}
 
-    assert_m!(must_subtype(&incomplete_fn_ty(), &int_to_int_fn_ty, mt_ty_env.clone()), Ok(_));
+/// `sub` must be a subtype of `sup`. (Note that `sub` becomes the context element!)
+/// On success, the result maps the `id` of every underdetermined type
+///  that the check determined to its final value.
+pub fn is_subtype(
+    sub: &Ast,
+    sup: &Ast,
+    parts: &LazyWalkReses<crate::ty::SynthTy>,
+) -> Result<Assoc<Name, Ast>, TyErr> {
+    // Every type is a subtype of itself, and checking that can't determine anything:
+    if sub == sup {
+        return Ok(Assoc::new());
+    }
+    with_resolve_cache(|| {
+        with_determinations(&parts.env, || {
+            walk::<Subtype>(sup, &parts.switch_mode::<Subtype>().with_context(sub.clone()))
+        })
+    })
+}
+
+/// A set of determinations for underdetermined types, to be shared by a sequence of checks.
+/// (Normally, everything goes into the thread-local `unification`.)
+#[derive(Default)]
+pub struct UnifCtx {
+    unif: HashMap<Name, Clo<Ast>>,
+    /// The underdetermined types made while in this context
+    ids: Vec<Name>,
+}
+
+impl UnifCtx {
+    pub fn new() -> UnifCtx { UnifCtx::default() }
+
+    /// A context that starts out with everything determined so far
+    ///  (but whose own determinations don't affect `unification`).
+    pub fn snapshot() -> UnifCtx {
+        UnifCtx { unif: unification.with(|unif| unif.borrow().clone()), ids: vec![] }
+    }
+
+    /// Run `f` with this context standing in for `unification` (and `with_unsolved_variables`).
+    fn enter<T>(&mut self, f: impl FnOnce() -> T) -> T {
+        let unif = Scoped::set(&unification, std::mem::take(&mut self.unif));
+        unification_changed();
+        let log = Scoped::set(&determination_log, vec![]);
+        let ids = Scoped::set(&underspecified_ids, Some(std::mem::take(&mut self.ids)));
+        let res = f();
+        self.ids = ids.restore().unwrap();
+        log.restore();
+        self.unif = unif.restore();
+        unification_changed();
+        res
+    }
+
+    /// The `id`s of the underdetermined types made in this context
+    ///  that it hasn't determined (in order).
+    pub fn unsolved_variables(&self) -> Vec<Name> { unsolved_among(self.ids.clone(), &self.unif) }
+
+    /// What the underdetermined type `id` has been determined to be (so far), if anything.
+    pub fn solution(&mut self, id: Name) -> Option<Ast> {
+        let clo = self.unif.get(&id)?.clone();
+        self.enter(|| canonicalize(&clo.it, clo.env).ok())
+    }
+}
+
+/// Like `is_subtype`, but determinations accumulate in `ctx`.
+pub fn is_subtype_in(
+    ctx: &mut UnifCtx,
+    sub: &Ast,
+    sup: &Ast,
+    parts: &LazyWalkReses<crate::ty::SynthTy>,
+) -> Result<Assoc<Name, Ast>, TyErr> {
+    ctx.enter(|| is_subtype(sub, sup, parts))
+}
+
+/// `sub` must be a subtype of `sup`. (Note that `sub` becomes the context element!)
+/// On success, the result maps the `id` of every underdetermined type
+///  that the check determined to its final value.
+/// Only use this in tests or at the top level; this discards any non-phase-0-environments!
+pub fn must_subtype(
+    sub: &Ast,
+    sup: &Ast,
+    env: Assoc<Name, Ast>,
+) -> Result<Assoc<Name, Ast>, TyErr> {
+    if sub == sup {
+        return Ok(Assoc::new()); // Reflexivity (see `is_subtype`)
+    }
+    // TODO: I think we should be canonicalizing first...
+    // TODO: they might need different environments?
+    let lwr_env = &LazyWalkReses::<Subtype>::new_wrapper(env.clone()).with_context(sub.clone());
+
+    with_resolve_cache(|| with_determinations(&env, || walk::<Subtype>(sup, lwr_env)))
+}
+
+/// Like `must_subtype`, but subtyping according to `policy`.
+pub fn must_subtype_with(
+    policy: &SubtypePolicy,
+    sub: &Ast,
+    sup: &Ast,
+    env: Assoc<Name, Ast>,
+) -> Result<Assoc<Name, Ast>, TyErr> {
+    with_subtype_policy(policy, || must_subtype(sub, sup, env))
+}
+
+/// Like `must_subtype`, but each `(sub, sup)` in `assumptions` is taken to hold
+///  (as an axiom, whenever the check reaches a comparison of exactly those two types).
+pub fn is_subtype_under(
+    assumptions: &[(Ast, Ast)],
+    sub: &Ast,
+    sup: &Ast,
+    env: Assoc<Name, Ast>,
+) -> Result<Assoc<Name, Ast>, TyErr> {
+    let mut all_hypotheses = hypotheses.with(|h| h.borrow().clone());
+    all_hypotheses.extend_from_slice(assumptions);
+    with_scoped(&hypotheses, all_hypotheses, || must_subtype(sub, sup, env))
+}
+
+/// A derivation of `sub <: sup`: which rule applies, and derivations of its premises.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtypeProof {
+    /// The name of the type form whose subtyping rule applies. Steps that don't walk a node are:
+    ///  `determine` (an underdetermined type is given a value),
+    ///  `refl` (both sides are identical, or the same underdetermined type),
+    ///  `hypothesis` (given to `is_subtype_under`), and
+    ///  `assumption` (the goal is already being proved further up; this is the Amber rule).
+    pub rule: Name,
+    pub sub: Ast,
+    pub sup: Ast,
+    /// The underdetermined types that this step gave values to.
+    pub determinations: Vec<(Name, Ast)>,
+    pub premises: Vec<SubtypeProof>,
+}
+
+struct ProofInProgress {
+    /// The innermost step last; the outermost is a placeholder whose premises are the result.
+    steps: Vec<SubtypeProof>,
+    /// The (sub, sup) that `pre_match` prepared for the next node walked.
+    goal: Option<(Ast, Ast)>,
+    /// For each node being walked: `None` if it has a step in `steps`,
+    ///  or, if it had no goal (so what it proves belongs to the enclosing step),
+    ///  how many premises the enclosing step had when it started.
+    nodes: Vec<Option<usize>>,
+}
+
+fn proof_step(rule: &str, sub: &Ast, sup: &Ast) -> SubtypeProof {
+    SubtypeProof {
+        rule: n(rule),
+        sub: sub.clone(),
+        sup: sup.clone(),
+        determinations: vec![],
+        premises: vec![],
+    }
+}
+
+/// Record a step that doesn't walk a node (if a proof is being recorded).
+fn proof_leaf(rule: &str, sub: &Ast, sup: &Ast, determinations: Vec<(Name, Ast)>) {
+    proof_in_progress.with(|pip| {
+        if let Some(ref mut pip) = *pip.borrow_mut() {
+            let leaf = SubtypeProof { determinations, ..proof_step(rule, sub, sup) };
+            pip.steps.last_mut().unwrap().premises.push(leaf)
+        }
+    })
+}
+
+/// Record that the current step gave `id` a value (if a proof is being recorded).
+fn note_determination(id: Name, t: &Ast) {
+    proof_in_progress.with(|pip| {
+        if let Some(ref mut pip) = *pip.borrow_mut() {
+            pip.steps.last_mut().unwrap().determinations.push((id, t.clone()))
+        }
+    })
+}
+
+/// Like `must_subtype`, but on success, produces a derivation of `sub <: sup`.
+pub fn prove_subtype(sub: &Ast, sup: &Ast, env: Assoc<Name, Ast>) -> Result<SubtypeProof, TyErr> {
+    use crate::ast_walk::{with_walk_hooks, WalkHooks};
+
+    let start =
+        ProofInProgress { steps: vec![proof_step("refl", sub, sup)], goal: None, nodes: vec![] };
+    let proof = Scoped::set(&proof_in_progress, Some(start));
+    let hooks = WalkHooks::<Subtype> {
+        before_node: Some(Rc::new(|f: &Form, _: &Assoc<Name, Ast>| {
+            proof_in_progress.with(|pip| {
+                let mut pip = pip.borrow_mut();
+                let pip = pip.as_mut().unwrap();
+                match pip.goal.take() {
+                    Some((sub, sup)) => {
+                        pip.steps.push(proof_step(&f.name.orig_sp(), &sub, &sup));
+                        pip.nodes.push(None)
+                    }
+                    None => {
+                        let premise_count = pip.steps.last().unwrap().premises.len();
+                        pip.nodes.push(Some(premise_count))
+                    }
+                }
+            })
+        })),
+        after_node: Some(Rc::new(|_: &Form, _: &Assoc<Name, Ast>, succeeded: bool| {
+            proof_in_progress.with(|pip| {
+                let mut pip = pip.borrow_mut();
+                let pip = pip.as_mut().unwrap();
+                match pip.nodes.pop().unwrap() {
+                    None => {
+                        let step = pip.steps.pop().unwrap();
+                        if succeeded {
+                            pip.steps.last_mut().unwrap().premises.push(step)
+                        }
+                    }
+                    Some(premise_count) => {
+                        if !succeeded {
+                            pip.steps.last_mut().unwrap().premises.truncate(premise_count)
+                        }
+                    }
+                }
+            })
+        })),
+    };
+    let res = with_walk_hooks(hooks, || must_subtype(sub, sup, env));
+    let mut proof = proof.restore().unwrap();
+    res?;
+
+    // If nothing was walked, `sub` and `sup` are identical:
+    let mut top = proof.steps.pop().unwrap();
+    Ok(top.premises.pop().unwrap_or(top))
+}
+
+/// The least upper bound of `a` and `b`. For now, that has to be one of them.
+/// An uninhabited type (an `enum` with no arms, like the type of a computation that diverges)
+///  joins with anything, since no value of it will ever show up.
+pub fn join(a: &Ast, b: &Ast, env: Assoc<Name, Ast>) -> Result<Ast, TyErr> {
+    if !is_inhabited(a, &env) {
+        return Ok(b.clone());
+    }
+    if !is_inhabited(b, &env) {
+        return Ok(a.clone());
+    }
+    if must_subtype(a, b, env.clone()).is_ok() {
+        Ok(b.clone())
+    } else if must_subtype(b, a, env).is_ok() {
+        Ok(a.clone())
+    } else {
+        Err(TyErr::Mismatch(a.clone(), b.clone()))
+    }
+}
+
+/// Does `t` have any values? An `enum` with no arms (i.e. `⊥`) doesn't,
+///  and neither does a `struct` or tuple with an uninhabited component.
+/// Recursive types are inhabited if they can bottom out (e.g. `IntList`, via `Nil`).
+/// Type variables that aren't defined are assumed to be inhabited.
+pub fn is_inhabited(t: &Ast, env: &Assoc<Name, Ast>) -> bool {
+    unification.with(|unif| is_inhabited_rec(t, env, &unif.borrow(), &[]))
+}
+
+// `recurring` holds the names (`mu`-bound or defined in `env`)
+//  that we're inside the definitions of;
+//  a value can't be built out of them until the rest of the definition provides one.
+fn is_inhabited_rec(
+    t: &Ast,
+    env: &Assoc<Name, Ast>,
+    unif: &HashMap<Name, Clo<Ast>>,
+    recurring: &[Name],
+) -> bool {
+    let is_recurring = |t: &Ast| match t {
+        VariableReference(vr) => recurring.contains(vr),
+        _ => false,
+    };
+    if is_recurring(t) {
+        return false;
+    }
+    let (t, recurring) = &expand_definition(t, env, unif, recurring);
+    let all_inhabited =
+        |tys: &[&Ast]| tys.iter().all(|t| is_inhabited_rec(t, env, unif, recurring));
+
+    if let Some(enum_parts) = t.destructure(find_core_form("Type", "enum")) {
+        enum_parts
+            .march_all(&[n("name")])
+            .iter()
+            .any(|arm| all_inhabited(&arm.get_rep_leaf_or_panic(n("component"))))
+    } else if let Some(struct_parts) = t.destructure(find_core_form("Type", "struct")) {
+        all_inhabited(&struct_parts.get_rep_leaf_or_panic(n("component")))
+    } else if let Some(tuple_parts) = t.destructure(find_core_form("Type", "tuple")) {
+        all_inhabited(&tuple_parts.get_rep_leaf_or_panic(n("component")))
+    } else if let Some(mu_parts) = t.destructure(find_core_form("Type", "mu_type")) {
+        let mut recurring = recurring.clone();
+        for param in mu_parts.get_rep_leaf_or_panic(n("param")) {
+            recurring.push(crate::core_forms::strip_ee(param).vr_to_name());
+        }
+        let body = crate::core_forms::strip_ee(mu_parts.get_leaf_or_panic(&n("body")));
+        is_inhabited_rec(body, env, unif, &recurring)
+    } else if let Some(tapp_parts) = t.destructure(find_core_form("Type", "type_apply")) {
+        // `resolve` couldn't expand it, so it's something like `X<Int>` underneath `mu X. ...`
+        !is_recurring(tapp_parts.get_leaf_or_panic(&n("type_rator")))
+    } else {
+        !is_recurring(t)
+    }
+}
+
+/// Resolve `t`, adding its name to `recurring` if it's a variable with a definition.
+/// (Otherwise, something like `A = **[A]**` would be expanded forever.)
+fn expand_definition(
+    t: &Ast,
+    env: &Assoc<Name, Ast>,
+    unif: &HashMap<Name, Clo<Ast>>,
+    recurring: &[Name],
+) -> (Ast, Vec<Name>) {
+    let resolved = resolve(Clo { it: t.clone(), env: env.clone() }, unif).it;
+    let mut recurring = recurring.to_vec();
+    match *t {
+        VariableReference(vr) if resolved != *t => recurring.push(vr),
+        _ => {}
+    }
+    (resolved, recurring)
+}
+
+/// How big is `t`? This counts the `Node`s and variable references in it,
+///  after expanding type definitions and applications (like subtyping does).
+/// A recursive type's references to itself (via `mu` or a definition) count as 1,
+///  so the result is always finite.
+pub fn type_size(t: &Ast, env: &Assoc<Name, Ast>) -> usize {
+    unification.with(|unif| type_size_rec(t, env, &unif.borrow(), &[]))
+}
+
+// `recurring` holds the names (`mu`-bound or defined in `env`)
+//  that we're inside the definitions of.
+fn type_size_rec(
+    t: &Ast,
+    env: &Assoc<Name, Ast>,
+    unif: &HashMap<Name, Clo<Ast>>,
+    recurring: &[Name],
+) -> usize {
+    match *t {
+        VariableReference(vr) if recurring.contains(&vr) => return 1,
+        _ => {}
+    }
+    let (t, recurring) = &expand_definition(t, env, unif, recurring);
+
+    if let Some(mu_parts) = t.destructure(find_core_form("Type", "mu_type")) {
+        let mut recurring = recurring.clone();
+        for param in mu_parts.get_rep_leaf_or_panic(n("param")) {
+            recurring.push(crate::core_forms::strip_ee(param).vr_to_name());
+        }
+        let body = crate::core_forms::strip_ee(mu_parts.get_leaf_or_panic(&n("body")));
+        return 1 + type_size_rec(body, env, unif, &recurring);
+    }
+    match *t {
+        Node(_, ref parts, _, _) => {
+            1 + parts.map_reduce(&|p| type_size_rec(p, env, unif, recurring), &|l, r| l + r, 0)
+        }
+        VariableReference(_) => 1,
+        ExtendEnv(ref body, _)
+        | ExtendEnvPhaseless(ref body, _)
+        | QuoteMore(ref body, _)
+        | QuoteLess(ref body, _) => type_size_rec(body, env, unif, recurring),
+        Trivial | Atom(_) => 0,
+        Shape(_) | IncompleteNode(_) => icp!("{} isn't a type", t),
+    }
+}
+
+/// A conversion that the type checker can insert, where subtyping doesn't apply.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Coercion {
+    /// From `*[a: T]*` to `T`
+    UnwrapSingleField(Name),
+    /// From `T` to `*[a: T]*`
+    WrapSingleField(Name),
+    /// From `**[T]**` to `T`
+    UnwrapSingleton,
+    /// From `T` to `**[T]**`
+    WrapSingleton,
+    /// From a numeric type to a wider one (e.g. `Nat` to `Float`); a no-op at runtime
+    Widen,
+}
+
+impl Coercion {
+    pub fn apply(&self, v: Value) -> Value {
+        match *self {
+            Coercion::UnwrapSingleField(name) => unwrap_single_field(name, v),
+            Coercion::WrapSingleField(name) => wrap_single_field(name, v),
+            Coercion::UnwrapSingleton => match v {
+                Value::Sequence(ref parts) if parts.len() == 1 => (*parts[0]).clone(),
+                _ => icp!("{} is not a singleton tuple", v),
+            },
+            Coercion::WrapSingleton => Value::Sequence(vec![Rc::new(v)]),
+            Coercion::Widen => v,
+        }
+    }
+
+    /// The parts of a `coerce` expression that describe this coercion:
+    ///  its kind, and the field it involves (if any).
+    pub fn to_parts(&self) -> (Name, Option<Name>) {
+        match *self {
+            Coercion::UnwrapSingleField(name) => (n("unwrap_single_field"), Some(name)),
+            Coercion::WrapSingleField(name) => (n("wrap_single_field"), Some(name)),
+            Coercion::UnwrapSingleton => (n("unwrap_singleton"), None),
+            Coercion::WrapSingleton => (n("wrap_singleton"), None),
+            Coercion::Widen => (n("widen"), None),
+        }
+    }
+
+    pub fn from_parts(kind: Name, field: Option<Name>) -> Coercion {
+        match (kind.sp().as_str(), field) {
+            ("unwrap_single_field", Some(name)) => Coercion::UnwrapSingleField(name),
+            ("wrap_single_field", Some(name)) => Coercion::WrapSingleField(name),
+            ("unwrap_singleton", None) => Coercion::UnwrapSingleton,
+            ("wrap_singleton", None) => Coercion::WrapSingleton,
+            ("widen", None) => Coercion::Widen,
+            _ => icp!("unknown coercion {} {:?}", kind, field),
+        }
+    }
+}
+
+pub fn unwrap_single_field(name: Name, v: Value) -> Value {
+    match v {
+        Value::Struct(ref fields) => fields.find_or_panic(&name).clone(),
+        _ => icp!("{} is not a struct", v),
+    }
+}
+
+pub fn wrap_single_field(name: Name, v: Value) -> Value { Value::Struct(Assoc::new().set(name, v)) }
+
+/// Can a `from` be converted into a `to` by wrapping or unwrapping a single-field struct
+///  or a one-component tuple, or by widening a number (as in `NumericTower::Widening`)?
+/// (This is never implicit; `from` is never a subtype of `to`.)
+pub fn coerce(from: &Ast, to: &Ast, env: Assoc<Name, Ast>) -> Option<Coercion> {
+    if let (Some(from_num), Some(to_num)) = (numeric_type(from, &env), numeric_type(to, &env)) {
+        if NumericTower::Widening.is_subtype(from_num, to_num) {
+            return Some(Coercion::Widen);
+        }
+    }
+    if let Some((field, component)) = single_component(from, &env) {
+        if must_subtype(&component, to, env.clone()).is_ok() {
+            return Some(match field {
+                Some(name) => Coercion::UnwrapSingleField(name),
+                None => Coercion::UnwrapSingleton,
+            });
+        }
+    }
+    if let Some((field, component)) = single_component(to, &env) {
+        if must_subtype(from, &component, env).is_ok() {
+            return Some(match field {
+                Some(name) => Coercion::WrapSingleField(name),
+                None => Coercion::WrapSingleton,
+            });
+        }
+    }
+    None
+}
+
+/// The name of `t`, if it's `Nat`, `Int`, or `Float`.
+fn numeric_type(t: &Ast, env: &Assoc<Name, Ast>) -> Option<Name> {
+    match canonicalize(t, env.clone()).ok()? {
+        Node(ref f, _, _, _) if NumericTower::Widening.chain().contains(&f.name) => Some(f.name),
+        _ => None,
+    }
+}
+
+/// The only component of a single-field struct (and the field name) or a one-component tuple.
+fn single_component(t: &Ast, env: &Assoc<Name, Ast>) -> Option<(Option<Name>, Ast)> {
+    let t = canonicalize(t, env.clone()).ok()?;
+    if let Some(parts) = t.destructure(find_core_form("Type", "struct")) {
+        if parts.get_leaf(n("rest")).is_some() {
+            return None; // The row might have more fields
+        }
+        match (
+            &parts.get_rep_leaf_or_panic(n("component_name"))[..],
+            &parts.get_rep_leaf_or_panic(n("component"))[..],
+        ) {
+            ([name], [component]) => Some((Some(name.to_name()), (*component).clone())),
+            _ => None,
+        }
+    } else if let Some(parts) = t.destructure(find_core_form("Type", "tuple")) {
+        match &parts.get_rep_leaf_or_panic(n("component"))[..] {
+            [component] => Some((None, (*component).clone())),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// Like `must_equal`, but determinations accumulate in `ctx`.
+pub fn must_equal_in(
+    ctx: &mut UnifCtx,
+    lhs: &Ast,
+    rhs: &Ast,
+    env: Assoc<Name, Ast>,
+) -> Result<(), TyErr> {
+    ctx.enter(|| must_equal(lhs, rhs, env))
+}
+
+// TODO: I think we need to route some other things (especially in macros.rs) through this...
+pub fn must_equal(lhs: &Ast, rhs: &Ast, env: Assoc<Name, Ast>) -> Result<(), TyErr> {
+    use crate::alpha::alpha_normalize;
+    let lwr_env = &LazyWalkReses::new_wrapper(env);
+    // Canonicalization freshens binders (e.g. in `forall`), so compare up to alpha-equivalence:
+    let same = match (walk::<Canonicalize>(lhs, lwr_env), walk::<Canonicalize>(rhs, lwr_env)) {
+        (Ok(lhs), Ok(rhs)) => same_type(&alpha_normalize(&lhs), &alpha_normalize(&rhs)),
+        (lhs, rhs) => lhs == rhs,
+    };
+    if same {
+        Ok(())
+    } else {
+        Err(TyErr::Mismatch(lhs.clone(), rhs.clone()))
+    }
+}
+
+#[test]
+fn basic_subtyping() {
+    use crate::{ty::TyErr::*, util::assoc::Assoc};
+
+    let mt_ty_env = Assoc::new();
+    let int_ty = ast!({ "Type" "Int" : });
+    let nat_ty = ast!({ "Type" "Nat" : });
+    let float_ty = ast!({ "Type" "Float" : });
+
+    assert_m!(must_subtype(&int_ty, &int_ty, mt_ty_env.clone()), Ok(_));
+
+    assert_eq!(
+        must_subtype(&float_ty, &int_ty, mt_ty_env.clone()),
+        Err(Mismatch(float_ty.clone(), int_ty.clone()))
+    );
+
+    let id_fn_ty = ast!({ "Type" "forall_type" :
+        "param" => ["t"],
+        "body" => (import [* [forall "param"]]
+            { "Type" "fn" : "param" => [ (vr "t") ], "ret" => (vr "t") })});
+
+    let int_to_int_fn_ty = ast!({ "Type" "fn" :
+         "param" => [(, int_ty.clone())],
+         "ret" => (, int_ty.clone())});
+
+    assert_m!(must_subtype(&int_to_int_fn_ty, &int_to_int_fn_ty, mt_ty_env.clone()), Ok(_));
+
+    assert_m!(must_subtype(&id_fn_ty, &id_fn_ty, mt_ty_env.clone()), Ok(_));
+
+    // actually subtype interestingly!
+    assert_m!(must_subtype(&int_to_int_fn_ty, &id_fn_ty, mt_ty_env.clone()), Ok(_));
+
+    // TODO: this error spits out generated names to the user without context ) :
+    assert_m!(must_subtype(&id_fn_ty, &int_to_int_fn_ty, mt_ty_env.clone()), Err(Mismatch(_, _)));
+
+    let parametric_ty_env = assoc_n!(
+        "some_int" => ast!( { "Type" "Int" : }),
+        "convert_to_nat" => ast!({ "Type" "forall_type" :
+            "param" => ["t"],
+            "body" => (import [* [forall "param"]]
+                { "Type" "fn" :
+                    "param" => [ (vr "t") ],
+                    "ret" => (, nat_ty.clone() ) })}),
+        "identity" => id_fn_ty.clone(),
+        "int_to_int" => int_to_int_fn_ty.clone());
+
+    assert_m!(
+        must_subtype(&ast!((vr "int_to_int")), &ast!((vr "identity")), parametric_ty_env.clone()),
+        Ok(_)
+    );
+
+    assert_m!(
+        must_subtype(&ast!((vr "identity")), &ast!((vr "int_to_int")), parametric_ty_env.clone()),
+        Err(Mismatch(_, _))
+    );
+
+    fn incomplete_fn_ty() -> Ast {
+        // A function, so we get a fresh underspecified type each time.
+        ast!({ "Type" "fn" :
+            "param" => [ { "Type" "Int" : } ],
+            "ret" => (, Subtype::underspecified(n("<return_type>")) )})
+    }
+
+    assert_m!(must_subtype(&incomplete_fn_ty(), &int_to_int_fn_ty, mt_ty_env.clone()), Ok(_));
+
+    // The result says what the underdetermined type turned out to be:
+    fn udet_id_of_ret(fn_ty: &Ast) -> Name {
+        fn_ty
+            .destructure(find_core_form("Type", "fn"))
+            .unwrap()
+            .get_leaf_or_panic(&n("ret"))
+            .destructure(underdetermined_form.with(|u_f| u_f.clone()))
+            .unwrap()
+            .get_leaf_or_panic(&n("id"))
+            .to_name()
+    }
+    let ((incomplete, res), unsolved) = with_unsolved_variables(|| {
+        let incomplete = incomplete_fn_ty();
+        let res = must_subtype(&incomplete, &int_to_int_fn_ty, mt_ty_env.clone());
+        (incomplete, res)
+    });
+    let ret_id = udet_id_of_ret(&incomplete);
+    assert_eq!(res.map(|res| res.find(&ret_id).cloned()), Ok(Some(int_ty.clone())));
+    assert_eq!(unsolved, vec![]);
+
+    // ...and a lone one can't be inferred:
+    let (lone, unsolved) = with_unsolved_variables(incomplete_fn_ty);
+    assert_eq!(unsolved, vec![udet_id_of_ret(&lone)]);
+    // (Nothing is recorded outside of `with_unsolved_variables`.)
+    incomplete_fn_ty();
+    assert_eq!(underspecified_ids.with(|ids| ids.borrow().clone()), None);
 
     assert_m!(must_subtype(&incomplete_fn_ty(), &id_fn_ty, mt_ty_env.clone()), Ok(_));
 
@@ -605,6 +1935,46 @@ fn misc_subtyping_problems() {
     assert_m!(must_subtype(&int_list_ty, &int_list_ty, ty_env.clone()), Ok(_));
     assert_m!(must_subtype(&int_list_ty, &bool_list_ty, ty_env.clone()), Err(_));
 
+    // Nominal types:
+    let list_of = |name: &str, nominal: bool| {
+        let int_list = ast!({ "Type" "mu_type" :
+            "param" => [(import [prot "param"] (vr name))],
+            "body" => (import [* [prot "param"]] { "Type" "enum" :
+                "name" => [@"c" "Nil", "Cons"],
+                "component" => [@"c" [], [{"Type" "Int" :}, (vr name) ]]})});
+        match (int_list, nominal) {
+            (Node(f, mut parts, export, span), true) => {
+                parts.add_leaf(n("nominal"), Atom(n("nominal")));
+                Node(f, parts, export, span)
+            }
+            (int_list, _) => int_list,
+        }
+    };
+    let nominal_env = assoc_n!(
+        "IntList" => list_of("IntList", false),
+        "MyList" => list_of("MyList", false),
+        "NomIntList" => list_of("NomIntList", true),
+        "NomMyList" => list_of("NomMyList", true));
+    let nominal_check = |sub: &str, sup: &str| {
+        let nominal = SubtypePolicy { nominal_subtyping: true, ..SubtypePolicy::default() };
+        must_subtype_with(&nominal, &ast!((vr sub)), &ast!((vr sup)), nominal_env.clone())
+    };
+    assert_eq!(
+        crate::ty::from_portable_string(
+            "nominal_mu_type NomIntList . { +[Nil]+ +[Cons Int NomIntList]+ }"
+        ),
+        Ok(list_of("NomIntList", true))
+    );
+    assert_m!(nominal_check("IntList", "MyList"), Ok(_));
+    assert_m!(nominal_check("NomIntList", "NomIntList"), Ok(_));
+    assert_m!(nominal_check("NomIntList", "NomMyList"), Err(TyErr::Mismatch(_, _)));
+    assert_m!(nominal_check("IntList", "NomIntList"), Err(TyErr::Mismatch(_, _)));
+    // ...unless nominal subtyping is off:
+    assert_m!(
+        must_subtype(&ast!((vr "NomIntList")), &ast!((vr "NomMyList")), nominal_env.clone()),
+        Ok(_)
+    );
+
     // Don't walk `Atom`s!
     let basic_enum = ast!({"Type" "enum" :
         "name" => [@"arm" "Aa", "Bb"],
@@ -715,6 +2085,39 @@ fn misc_subtyping_problems() {
     );
 }
 
+#[test]
+fn recursive_type_printing() {
+    let int_list_with = |rec: Ast| {
+        ast!({ "Type" "mu_type" :
+            "param" => [(import [prot "param"] (vr "IntList"))],
+            "body" => (import [* [prot "param"]] { "Type" "enum" :
+                "name" => [@"c" "Nil", "Cons"],
+                "component" => [@"c" [], [{"Type" "Int" :}, (, rec) ]]})})
+    };
+    let int_list_ty = int_list_with(ast!((vr "IntList")));
+    let printed = format!("{}", int_list_ty);
+    assert!(printed.starts_with("μIntList. "));
+    assert_eq!(printed.matches('μ').count(), 1);
+    assert!(printed.contains("Int IntList"));
+
+    // Unfolded in place, the inner copy is just a reference to the outer one:
+    let unfolded = format!("{}", int_list_with(int_list_ty.clone()));
+    assert!(unfolded.starts_with("μIntList. "));
+    assert_eq!(unfolded.matches('μ').count(), 1);
+    assert!(unfolded.contains("Int IntList"));
+
+    // A different `mu` with the same parameter name isn't a back-reference:
+    let shadowing = int_list_with(ast!({ "Type" "mu_type" :
+        "param" => [(import [prot "param"] (vr "IntList"))],
+        "body" => (import [* [prot "param"]] {"Type" "tuple" : "component" => [(vr "IntList")]})}));
+    assert_eq!(format!("{}", shadowing).matches('μ').count(), 2);
+
+    // Portable printing still produces something that parses:
+    let portable = crate::ty::to_portable_string(&int_list_ty);
+    assert!(portable.starts_with("mu_type"));
+    assert_m!(crate::ty::from_portable_string(&portable), Ok(_));
+}
+
 #[test]
 fn struct_subtyping() {
     // Trivial struct subtying:
@@ -732,7 +2135,7 @@ fn struct_subtyping() {
     );
 
     // Add a component:
-    assert_m!(
+    let add_a_component = || {
         must_subtype(
             &ast!( { "Type" "struct" :
             "component_name" => [@"c" "a", "b"],
@@ -740,10 +2143,17 @@ fn struct_subtyping() {
             &ast!( { "Type" "struct" :
             "component_name" => [@"c" "a", "b", "c"],
             "component" => [@"c" {"Type" "Int" :}, {"Type" "Nat" :}, {"Type" "Float" :}]}),
-            Assoc::new()
-        ),
-        Ok(_)
+            Assoc::new(),
+        )
+    };
+    assert_m!(add_a_component(), Ok(_));
+    // ...unless structs have to match exactly:
+    let exact = SubtypePolicy { struct_width_subtyping: false, ..SubtypePolicy::default() };
+    assert_m!(
+        with_subtype_policy(&exact, add_a_component),
+        Err(TyErr::NonexistentStructField(_, _))
     );
+    assert_m!(with_subtype_policy(&SubtypePolicy::default(), add_a_component), Ok(_));
 
     // Reorder components:
     assert_m!(
@@ -774,6 +2184,69 @@ fn struct_subtyping() {
     );
 }
 
+#[test]
+fn struct_row_variables() {
+    let rho = Subtype::underspecified(n("ρ"));
+    let rho_id = rho
+        .destructure(underdetermined_form.with(|u_f| u_f.clone()))
+        .unwrap()
+        .get_leaf_or_panic(&n("id"))
+        .to_name();
+    let a_int_rho = ast!({ "Type" "struct" :
+        "component_name" => [@"c" "a"], "component" => [@"c" {"Type" "Int" :}], "rest" => (, rho)});
+
+    let determined = must_subtype(
+        &ast!({ "Type" "struct" :
+            "component_name" => [@"c" "a", "b"],
+            "component" => [@"c" {"Type" "Int" :}, {"Type" "Nat" :}]}),
+        &a_int_rho,
+        Assoc::new(),
+    );
+    assert_eq!(
+        determined.map(|d| d.find(&rho_id).cloned()),
+        Ok(Some(ast!({ "Type" "struct" :
+            "component_name" => [@"c" "b"], "component" => [@"c" {"Type" "Nat" :}]})))
+    );
+
+    // The row doesn't relax the fields that are mentioned:
+    assert_m!(
+        must_subtype(
+            &ast!({ "Type" "struct" :
+                "component_name" => [@"c" "a", "b"],
+                "component" => [@"c" {"Type" "Float" :}, {"Type" "Nat" :}]}),
+            &a_int_rho,
+            Assoc::new()
+        ),
+        Err(_)
+    );
+    // An open row can't stand in for a closed one:
+    assert_m!(
+        must_subtype(
+            &a_int_rho,
+            &ast!({ "Type" "struct" :
+                "component_name" => [@"c" "a"], "component" => [@"c" {"Type" "Int" :}]}),
+            Assoc::new()
+        ),
+        Err(_)
+    );
+}
+
+#[test]
+fn canonicalize_agrees_with_synth_ty() {
+    use crate::{ast_walk::walk_both, ty::SynthTy};
+
+    let env = assoc_n!("T" => uty!({Int :}), "Protected" => ast!((vr "Protected")));
+    for t in &[
+        uty!({fn : [T] {Nat :}}),
+        uty!({tuple : [T ; Protected]}),
+        ast!({"Type" "struct" :
+            "component_name" => [@"c" "a", "b"], "component" => [@"c" (vr "T"), (vr "Protected")]}),
+    ] {
+        let (canon, synth) = walk_both::<Canonicalize, SynthTy>(t, env.clone());
+        assert_eq!(Some(canon.unwrap()), synth.ok());
+    }
+}
+
 #[test]
 fn subtype_different_mus() {
     // testing the Amber rule:
@@ -808,9 +2281,12 @@ fn subtype_dotdotdot_type() {
 
     assert_m!(must_subtype(&threeple, &dddple, Assoc::new()), Ok(_));
 
-    // TODO #15: this panics in mbe.rs; it ought to error instead (might not still be true)
-    // assert_m!(must_subtype(&dddple, &threeple, assoc_n!("T" => Subtype::underspecified(n("-")))),
-    //     Err(_));
+    // Pick `T` to be `**[Int Float Nat]**`:
+    assert_m!(must_subtype(&dddple, &threeple, Assoc::new()), Ok(_));
+    assert_m!(
+        must_subtype(&dddple, &threeple, assoc_n!("T" => Subtype::underspecified(n("-")))),
+        Ok(_)
+    );
 
     assert_m!(must_subtype(&dddple, &dddple, Assoc::new()), Ok(_));
 
@@ -823,6 +2299,42 @@ fn subtype_dotdotdot_type() {
     assert_m!(must_subtype(&expr_threeple, &dddple, Assoc::new()), Ok(_));
 
     assert_m!(must_subtype(&expr_threeple, &expr_dddple, Assoc::new()), Ok(_));
+
+    assert_m!(must_subtype(&expr_dddple, &expr_threeple, Assoc::new()), Ok(_));
+    // There's no `T` that makes the components `Expr`s:
+    assert_m!(must_subtype(&expr_dddple, &threeple, Assoc::new()), Err(_));
+    let int_and_dddple = uty!({forall_type : [T] {tuple : [{Int :}; {dotdotdot_type : [T] T}]}});
+    assert_m!(must_subtype(&int_and_dddple, &threeple, Assoc::new()), Ok(_));
+    assert_m!(must_subtype(&int_and_dddple, &uty!({tuple : [{Nat :}]}), Assoc::new()), Err(_));
+    // A driver that can't be a tuple means giving up, without determining the other drivers:
+    let env = assoc_n!("I" => uty!({Int :}));
+    let badly_driven = uty!({forall_type : [T] {tuple : [{dotdotdot_type : [T; I] {Int :}}]}});
+    let determinations = unification.with(|unif| unif.borrow().len());
+    assert_m!(must_subtype(&badly_driven, &threeple, env), Err(_));
+    assert_eq!(unification.with(|unif| unif.borrow().len()), determinations);
+
+    // Two repetitions compare element-wise:
+    let nat_dddple = uty!({forall_type : [T] {tuple : [{dotdotdot_type : [T] {Nat :}}]}});
+    let int_dddple = uty!({forall_type : [T] {tuple : [{dotdotdot_type : [T] {Int :}}]}});
+    let widening = SubtypePolicy { numeric_tower: NumericTower::Widening, ..Default::default() };
+    assert_m!(must_subtype_with(&widening, &nat_dddple, &int_dddple, Assoc::new()), Ok(_));
+    assert_m!(must_subtype_with(&widening, &int_dddple, &nat_dddple, Assoc::new()), Err(_));
+    // ...but only if they're driven the same way:
+    let int_dddple_2 = uty!({forall_type : [T; U] {tuple : [{dotdotdot_type : [T; U] {Int :}}]}});
+    assert_m!(
+        must_subtype(&int_dddple, &int_dddple_2, Assoc::new()),
+        Err(TyErr::DriverMismatch(_, _))
+    );
+    let env = assoc_n!("T" => ast!((vr "T")), "U" => ast!((vr "U")));
+    let driven_by = |driver: &str| {
+        ast!({"Type" "tuple" : "component" => [{"Type" "dotdotdot_type" :
+            "driver" => [(vr driver)], "body" => {"Type" "Int" :}}]})
+    };
+    assert_m!(must_subtype(&driven_by("T"), &driven_by("T"), env.clone()), Ok(_));
+    assert_m!(
+        must_subtype(&driven_by("T"), &driven_by("U"), env.clone()),
+        Err(TyErr::DriverMismatch(_, _))
+    );
 }
 
 #[test]
@@ -869,3 +2381,724 @@ fn basic_resolve() {
                           "type_rator" => (vr "List"), "arg" => [(,ud0.clone())]} ]]})})
     );
 }
+
+#[test]
+fn resolve_caching() {
+    let id_ty = uty!({forall_type : [T] {fn : [T] T}});
+    let t_env = assoc_n!("Id" => id_ty);
+    let tapp = Clo { it: uty!({type_apply : Id [{Int :}]}), env: t_env };
+    let resolved = |t: &Clo<Ast>| unification.with(|unif| resolve(t.clone(), &unif.borrow()).it);
+
+    let expansions_for = || {
+        let before = resolve_expansions.with(|re| *re.borrow());
+        for _ in 0..10 {
+            assert_eq!(resolved(&tapp), uty!({fn : [{Int :}] {Int :}}));
+        }
+        resolve_expansions.with(|re| *re.borrow()) - before
+    };
+
+    assert_eq!(expansions_for(), 10);
+    assert_eq!(with_resolve_cache(expansions_for), 1);
+
+    // Changing `unification` invalidates the cache:
+    with_resolve_cache(|| {
+        assert_eq!(expansions_for(), 1);
+        unification.with(|unif| {
+            unif.borrow_mut().insert(n("irrelevant"), Clo { it: uty!({Nat :}), env: Assoc::new() })
+        });
+        unification_changed();
+        assert_eq!(expansions_for(), 1);
+    });
+
+    // ...and so does swapping it out, even for a map of the same size at the same address:
+    let u_f = underdetermined_form.with(|u_f| u_f.clone());
+    let udet = Clo { it: ast!({ u_f ; "id" => (, Atom(n("a")))}), env: Assoc::new() };
+    let ctx_with = |t: Ast| UnifCtx {
+        unif: vec![(n("a"), Clo { it: t, env: Assoc::new() })].into_iter().collect(),
+        ids: vec![],
+    };
+    let (mut int_ctx, mut nat_ctx) = (ctx_with(uty!({Int :})), ctx_with(uty!({Nat :})));
+    with_resolve_cache(|| {
+        assert_eq!(int_ctx.enter(|| resolved(&udet)), uty!({Int :}));
+        assert_eq!(nat_ctx.enter(|| resolved(&udet)), uty!({Nat :}));
+    });
+}
+
+#[test]
+fn occurs_check() {
+    let int_ty = ast!({ "Type" "Int" : });
+    let list_ty = ast!( { "Type" "forall_type" :
+        "param" => ["Datum"],
+        "body" => (import [* [forall "param"]] { "Type" "mu_type" :
+            "param" => [(import [prot "param"] (vr "List"))],
+            "body" => (import [* [prot "param"]] { "Type" "enum" :
+                "name" => [@"c" "Nil", "Cons"],
+                "component" => [@"c" [], [(vr "Datum"), {"Type" "type_apply" :
+                    "type_rator" => (vr "List"),
+                    "arg" => [(vr "Datum")]} ]]})})});
+    let env = assoc_n!("List" => list_ty.clone(), "IntAlias" => int_ty.clone());
+
+    let list_of_x = ast!({"Type" "type_apply" : "type_rator" => (vr "List"), "arg" => [(vr "X")]});
+    assert!(occurs(n("X"), &list_of_x, &env));
+    assert!(!occurs(n("Datum"), &list_of_x, &env)); // (bound by the `forall`)
+    assert!(!occurs(n("X"), &int_ty, &env));
+    assert!(!occurs(n("X"), &ast!((vr "IntAlias")), &env));
+
+    // `X` is behind a `mu` that binds a different name:
+    let mu_y = ast!({ "Type" "mu_type" :
+        "param" => [(import [prot "param"] (vr "Y"))],
+        "body" => (import [* [prot "param"]] { "Type" "tuple" :
+            "component" => [(vr "X"), (vr "Y")]})});
+    assert!(occurs(n("X"), &mu_y, &env));
+    assert!(!occurs(n("Y"), &mu_y, &env));
+
+    // Underdetermined types are found through `unification`:
+    let udet = Subtype::underspecified(n("occurs"));
+    let udet_id = udet
+        .destructure(underdetermined_form.with(|u_f| u_f.clone()))
+        .unwrap()
+        .get_leaf_or_panic(&n("id"))
+        .to_name();
+    let tuple_of_udet = ast!({"Type" "tuple" : "component" => [(, udet.clone())]});
+    assert!(occurs(udet_id, &tuple_of_udet, &env));
+    assert!(!occurs(udet_id, &int_ty, &env));
+
+    // ...so it can't be made to contain itself:
+    assert_m!(must_subtype(&tuple_of_udet, &udet, env.clone()), Err(TyErr::Mismatch(_, _)));
+    assert_m!(must_subtype(&udet, &tuple_of_udet, env.clone()), Err(TyErr::Mismatch(_, _)));
+    assert_m!(must_subtype(&udet, &int_ty, env.clone()), Ok(_));
+    assert!(!occurs(udet_id, &tuple_of_udet, &env)); // it's `Int` now
+}
+
+#[test]
+fn numeric_tower_subtyping() {
+    let int_ty = ast!({ "Type" "Int" : });
+    let nat_ty = ast!({ "Type" "Nat" : });
+    let float_ty = ast!({ "Type" "Float" : });
+    let mt_ty_env = Assoc::new();
+
+    let tower = |numeric_tower| SubtypePolicy { numeric_tower, ..SubtypePolicy::default() };
+    let (strict, widening) = (tower(NumericTower::Strict), tower(NumericTower::Widening));
+    let reversed = tower(NumericTower::Custom(vec![n("Float"), n("Int")]));
+    let int_to_float = |p| must_subtype_with(p, &int_ty, &float_ty, mt_ty_env.clone());
+    let float_to_int = |p| must_subtype_with(p, &float_ty, &int_ty, mt_ty_env.clone());
+
+    assert_m!(int_to_float(&strict), Err(TyErr::Mismatch(_, _)));
+    assert_m!(int_to_float(&widening), Ok(_));
+    assert_m!(float_to_int(&widening), Err(TyErr::Mismatch(_, _)));
+    assert_m!(float_to_int(&reversed), Ok(_));
+    assert_m!(int_to_float(&reversed), Err(TyErr::Mismatch(_, _)));
+
+    // Transitive, and it reaches inside other types:
+    let nat_fn =
+        ast!({ "Type" "fn" : "param" => [(, float_ty.clone())], "ret" => (, nat_ty.clone())});
+    let float_fn =
+        ast!({ "Type" "fn" : "param" => [(, nat_ty.clone())], "ret" => (, float_ty.clone())});
+    assert_m!(must_subtype_with(&widening, &nat_fn, &float_fn, mt_ty_env.clone()), Ok(_));
+    assert_m!(
+        must_subtype_with(&widening, &float_fn, &nat_fn, mt_ty_env.clone()),
+        Err(TyErr::Mismatch(_, _))
+    );
+
+    // The default is strict:
+    assert_m!(must_subtype(&int_ty, &float_ty, mt_ty_env.clone()), Err(TyErr::Mismatch(_, _)));
+}
+
+#[test]
+fn reordered_components_are_equal() {
+    let mt_ty_env = Assoc::new();
+    let xy = ast!({"Type" "struct" :
+        "component_name" => [@"c" "x", "y"], "component" => [@"c" {"Type" "Int" :}, {"Type" "Nat" :}]});
+    let yx = ast!({"Type" "struct" :
+        "component_name" => [@"c" "y", "x"], "component" => [@"c" {"Type" "Nat" :}, {"Type" "Int" :}]});
+    let yx_wrong = ast!({"Type" "struct" :
+        "component_name" => [@"c" "y", "x"], "component" => [@"c" {"Type" "Int" :}, {"Type" "Nat" :}]});
+
+    assert_ne!(xy, yx);
+    assert_eq!(must_equal(&xy, &yx, mt_ty_env.clone()), Ok(()));
+    assert_m!(must_equal(&xy, &yx_wrong, mt_ty_env.clone()), Err(_));
+    assert_eq!(canonicalize(&xy, mt_ty_env.clone()), canonicalize(&yx, mt_ty_env.clone()));
+
+    // Reordering subtyping still works:
+    assert_m!(must_subtype(&xy, &yx, mt_ty_env.clone()), Ok(_));
+
+    let ab = ast!({"Type" "enum" :
+        "name" => [@"arm" "A", "B"], "component" => [@"arm" [{"Type" "Int" :}], []]});
+    let ba = ast!({"Type" "enum" :
+        "name" => [@"arm" "B", "A"], "component" => [@"arm" [], [{"Type" "Int" :}]]});
+    let ba_wrong = ast!({"Type" "enum" :
+        "name" => [@"arm" "B", "A"], "component" => [@"arm" [{"Type" "Int" :}], []]});
+    // The same goes for `enum` arms:
+    assert_ne!(ab, ba);
+    assert_eq!(must_equal(&ab, &ba, mt_ty_env.clone()), Ok(()));
+    assert_m!(must_equal(&ab, &ba_wrong, mt_ty_env.clone()), Err(_));
+    assert_eq!(canonicalize(&ab, mt_ty_env.clone()), canonicalize(&ba, mt_ty_env.clone()));
+    assert_m!(must_subtype(&ab, &ba, mt_ty_env.clone()), Ok(_));
+}
+
+#[test]
+fn custom_underspecified_rendering() {
+    let fresh = || <Subtype as WalkMode>::underspecified(n("a"));
+
+    assert_eq!(format!("{}", fresh()), "¿a⚁?");
+    with_underspecified_renderer(
+        |_, idx| format!("?{}", idx),
+        || {
+            assert_eq!(format!("{}", fresh()), "?0");
+            assert_eq!(format!("{}", fresh()), "?1");
+
+            // Error messages use the custom spelling:
+            let fn_ty = uty!({fn : [{Int :}] (, fresh())});
+            let err = must_subtype(&uty!({Int :}), &fn_ty, Assoc::new()).unwrap_err();
+            assert!(format!("{}", err).contains("?2"));
+        },
+    );
+}
+
+#[test]
+fn strict_type_vars_must_be_bound() {
+    let env = assoc_n!("T" => uty!({Int :}));
+    let bound = uty!({fn : [T] {Int :}});
+    let unbound = uty!({fn : [Unbound] {Int :}});
+
+    // Leniently, the unbound name is left alone:
+    assert_eq!(canonicalize(&unbound, env.clone()), Ok(unbound.clone()));
+
+    with_strict_type_vars(|| {
+        assert_eq!(canonicalize(&bound, env.clone()), Ok(uty!({fn : [{Int :}] {Int :}})));
+        assert_eq!(canonicalize(&unbound, env.clone()), Err(TyErr::UnboundTypeVar(n("Unbound"))));
+    });
+
+    assert_eq!(canonicalize(&unbound, env), Ok(unbound.clone()));
+}
+
+#[test]
+fn single_component_coercions() {
+    let mt_ty_env = Assoc::new();
+    let int_ty = uty!({Int :});
+    let a_int = ast!({"Type" "struct" : "component_name" => [@"c" "a"], "component" => [@"c" (, int_ty.clone())]});
+    let ab_int = ast!({"Type" "struct" :
+        "component_name" => [@"c" "a", "b"], "component" => [@"c" (, int_ty.clone()), (, int_ty.clone())]});
+    let int_1ple = uty!({tuple : [{Int :}]});
+
+    assert_eq!(
+        coerce(&a_int, &int_ty, mt_ty_env.clone()),
+        Some(Coercion::UnwrapSingleField(n("a")))
+    );
+    assert_eq!(coerce(&int_ty, &a_int, mt_ty_env.clone()), Some(Coercion::WrapSingleField(n("a"))));
+    assert_eq!(coerce(&int_1ple, &int_ty, mt_ty_env.clone()), Some(Coercion::UnwrapSingleton));
+    assert_eq!(coerce(&int_ty, &int_1ple, mt_ty_env.clone()), Some(Coercion::WrapSingleton));
+
+    // ...but these aren't subtypes:
+    assert_m!(must_subtype(&a_int, &int_ty, mt_ty_env.clone()), Err(_));
+
+    assert_eq!(coerce(&ab_int, &int_ty, mt_ty_env.clone()), None);
+    assert_eq!(coerce(&int_ty, &ab_int, mt_ty_env.clone()), None);
+    assert_eq!(coerce(&a_int, &uty!({Ident :}), mt_ty_env.clone()), None);
+
+    // Numbers only widen:
+    assert_eq!(coerce(&uty!({Nat :}), &uty!({Float :}), mt_ty_env.clone()), Some(Coercion::Widen));
+    assert_eq!(coerce(&uty!({Float :}), &int_ty, mt_ty_env.clone()), None);
+
+    let wrapped = Coercion::WrapSingleField(n("a")).apply(val!(i 5));
+    assert_eq!(wrapped, val!(struct "a" => (i 5)));
+    assert_eq!(Coercion::UnwrapSingleField(n("a")).apply(wrapped), val!(i 5));
+    assert_eq!(
+        Coercion::UnwrapSingleton.apply(Coercion::WrapSingleton.apply(val!(i 5))),
+        val!(i 5)
+    );
+}
+
+#[test]
+fn applying_unification() {
+    let undet = Subtype::underspecified(n("a"));
+    let undet_id = match undet {
+        Node(_, ref parts, _, _) => parts.get_leaf_or_panic(&n("id")).to_name(),
+        _ => panic!(),
+    };
+    let other_undet = Subtype::underspecified(n("b"));
+    let other_undet_id = match other_undet {
+        Node(_, ref parts, _, _) => parts.get_leaf_or_panic(&n("id")).to_name(),
+        _ => panic!(),
+    };
+    let mut unif = HashMap::new();
+    // `b` is determined to be `a`, which is determined to be `Int`:
+    unif.insert(undet_id, Clo { it: uty!({Int :}), env: Assoc::new() });
+    unif.insert(other_undet_id, Clo { it: undet.clone(), env: Assoc::new() });
+
+    let fn_ty = uty!({fn : [(, undet.clone()); {Nat :}] (, other_undet.clone())});
+    let env = Assoc::new();
+    assert_eq!(apply_unification(&fn_ty, &env, &unif), uty!({fn : [{Int :}; {Nat :}] {Int :}}));
+
+    let still_undet = Subtype::underspecified(n("c"));
+    let still_undet_id = match still_undet {
+        Node(_, ref parts, _, _) => parts.get_leaf_or_panic(&n("id")).to_name(),
+        _ => panic!(),
+    };
+    let tuple_ty = uty!({tuple : [(, undet); (, still_undet.clone())]});
+    let still_undet_copy = still_undet.clone();
+    assert_eq!(
+        apply_unification(&tuple_ty, &env, &unif),
+        uty!({tuple : [{Int :}; (, still_undet_copy)]})
+    );
+
+    // A determination means what it meant where it was made:
+    let point_env = assoc_n!("Point" => uty!({tuple : [{Int :}; {Int :}]}), "T" => ast!((vr "T")));
+    unif.insert(still_undet_id, Clo { it: ast!((vr "Point")), env: point_env.clone() });
+    let t_or_point = uty!({tuple : [T; (, still_undet)]});
+    assert_eq!(
+        apply_unification(&t_or_point, &env, &unif),
+        uty!({tuple : [T; {tuple : [{Int :}; {Int :}]}]})
+    );
+    // ...but names that mean the same thing in both places are left alone:
+    assert_eq!(apply_unification(&t_or_point, &point_env, &unif), uty!({tuple : [T; Point]}));
+}
+
+#[test]
+fn core_form_lookups_are_cached() {
+    use crate::core_forms::core_form_lookups;
+
+    let expr_threeple = uty!({tuple : [{type_apply : (prim Expr) [{Int :}]};
+                                       {type_apply : (prim Expr) [{Float :}]};
+                                       {type_apply : (prim Expr) [{Nat :}]}]});
+    let expr_dddple = uty!(
+        {forall_type : [T] {tuple : [{dotdotdot_type : [T] {type_apply : (prim Expr) [T]}}]}});
+    let check = || must_subtype(&expr_dddple, &expr_threeple, Assoc::new());
+
+    assert_m!(check(), Ok(_));
+    core_form_lookups.with(|cfl| *cfl.borrow_mut() = (0, 0));
+    assert_m!(check(), Ok(_));
+    let (lookups, searches) = core_form_lookups.with(|cfl| *cfl.borrow());
+    // Forms are looked up all the time, but each only needs to be found once:
+    assert!(lookups > 10);
+    assert_eq!(searches, 0);
+}
+
+#[test]
+fn cyclic_subtyping_terminates() {
+    // These types are recursive through the environment, rather than via `mu`:
+    let env = assoc_n!(
+        "A" => ast!({"Type" "fn" : "param" => [(vr "A")], "ret" => {"Type" "Int" :}}),
+        "B" => ast!({"Type" "fn" : "param" => [(vr "B")], "ret" => {"Type" "Int" :}}),
+        "C" => ast!({"Type" "fn" : "param" => [(vr "C")], "ret" => {"Type" "Float" :}}));
+
+    assert_m!(must_subtype(&ast!((vr "A")), &ast!((vr "B")), env.clone()), Ok(_));
+    assert_m!(must_subtype(&ast!((vr "B")), &ast!((vr "A")), env.clone()), Ok(_));
+    assert_m!(must_subtype(&ast!((vr "A")), &ast!((vr "C")), env.clone()), Err(_));
+    // Assumptions don't leak from one check to the next:
+    assert_m!(must_subtype(&ast!((vr "A")), &ast!((vr "C")), env.clone()), Err(_));
+
+    // An assumption is about types in a particular environment:
+    let (a, c) = (ast!((vr "A")), ast!((vr "C")));
+    let other_env = assoc_n!("A" => ast!({"Type" "Int" :}), "C" => ast!({"Type" "Float" :}));
+    let assumed = (Clo { it: c.clone(), env: env.clone() }, Clo { it: a.clone(), env: env });
+    subtype_assumptions.with(|sa| sa.borrow_mut().push(assumed));
+    assert_m!(must_subtype(&a, &c, other_env), Err(_));
+    subtype_assumptions.with(|sa| sa.borrow_mut().pop());
+}
+
+#[test]
+fn shared_unification_scope() {
+    let parts = LazyWalkReses::<crate::ty::SynthTy>::new_wrapper(Assoc::new());
+    let udet_id = |t: &Ast| {
+        t.destructure(underdetermined_form.with(|u_f| u_f.clone()))
+            .unwrap()
+            .get_leaf_or_panic(&n("id"))
+            .to_name()
+    };
+    let t = Subtype::underspecified(n("t"));
+    let u = Subtype::underspecified(n("u"));
+    let int_ty = ast!({ "Type" "Int" : });
+
+    let mut ctx = UnifCtx::new();
+    assert_m!(is_subtype_in(&mut ctx, &t, &u, &parts), Ok(_));
+    // Neither check alone says what `t` is...
+    assert_eq!(ctx.solution(udet_id(&t)), None);
+    assert_m!(is_subtype_in(&mut ctx, &u, &int_ty, &parts), Ok(_));
+    // ...but together they do:
+    assert_eq!(ctx.solution(udet_id(&t)), Some(int_ty.clone()));
+
+    // None of this leaked into the thread-local scope:
+    unification.with(|unif| assert!(!unif.borrow().contains_key(&udet_id(&t))));
+    assert_m!(must_equal_in(&mut ctx, &t, &int_ty, Assoc::new()), Ok(()));
+
+    // It keeps track of the underdetermined types made in it:
+    let v = ctx.enter(|| Subtype::underspecified(n("v")));
+    assert_eq!(ctx.unsolved_variables(), vec![udet_id(&v)]);
+    assert_m!(is_subtype_in(&mut ctx, &v, &int_ty, &parts), Ok(_));
+    assert_eq!(ctx.unsolved_variables(), vec![]);
+}
+
+#[test]
+fn inhabitedness() {
+    let bottom = ast!({"Type" "enum" : "name" => [], "component" => []});
+    let int_ty = ast!({"Type" "Int" :});
+    let int_list_ty = ast!( { "Type" "mu_type" :
+        "param" => [(import [prot "param"] (vr "IntList"))],
+        "body" => (import [* [prot "param"]] { "Type" "enum" :
+            "name" => [@"c" "Nil", "Cons"],
+            "component" => [@"c" [], [{"Type" "Int":}, (vr "IntList") ]]})});
+    // A list with no end:
+    let stream_ty = ast!( { "Type" "mu_type" :
+        "param" => [(import [prot "param"] (vr "IntList"))],
+        "body" => (import [* [prot "param"]] { "Type" "enum" :
+            "name" => [@"c" "Cons"],
+            "component" => [@"c" [{"Type" "Int":}, (vr "IntList") ]]})});
+    let env = assoc_n!("IntList" => int_list_ty.clone(), "Bot" => bottom.clone());
+
+    assert!(!is_inhabited(&bottom, &env));
+    assert!(!is_inhabited(&ast!((vr "Bot")), &env));
+    assert!(is_inhabited(&int_ty, &env));
+    assert!(is_inhabited(&ast!((vr "T")), &env)); // We don't know anything about `T`
+    assert!(is_inhabited(&int_list_ty, &env));
+    assert!(is_inhabited(&ast!((vr "IntList")), &env));
+    assert!(!is_inhabited(&stream_ty, &env));
+    // The same, but by definition instead of by `mu`:
+    let env = env.set(n("A"), ast!({"Type" "tuple" : "component" => [(vr "A")]}));
+    assert!(!is_inhabited(&ast!((vr "A")), &env));
+
+    assert!(is_inhabited(
+        &ast!({"Type" "struct" :
+            "component_name" => [@"c" "a", "b"],
+            "component" => [@"c" (, int_ty.clone()), (vr "IntList")]}),
+        &env
+    ));
+    assert!(!is_inhabited(
+        &ast!({"Type" "struct" :
+            "component_name" => [@"c" "a", "b"],
+            "component" => [@"c" (, int_ty.clone()), (vr "Bot")]}),
+        &env
+    ));
+    // An enum only needs one arm:
+    assert!(is_inhabited(
+        &ast!({"Type" "enum" :
+            "name" => [@"c" "Yes", "No"],
+            "component" => [@"c" [], [(vr "Bot")]]}),
+        &env
+    ));
+}
+
+#[test]
+fn type_level_conditionals() {
+    let type_if = |lhs: Ast, rhs: Ast| {
+        ast!({"Type" "type_if" : "lhs" => (, lhs), "rhs" => (, rhs),
+            "then" => {"Type" "Nat" :}, "else" => {"Type" "Float" :}})
+    };
+    let int_ty = ast!({"Type" "Int" :});
+    let env = assoc_n!("I" => int_ty.clone());
+
+    let true_if = type_if(int_ty.clone(), ast!((vr "I")));
+    assert_eq!(canonicalize(&true_if, env.clone()), Ok(ast!({"Type" "Nat" :})));
+    let false_if = type_if(int_ty.clone(), ast!({"Type" "Float" :}));
+    assert_eq!(canonicalize(&false_if, env.clone()), Ok(ast!({"Type" "Float" :})));
+
+    // Subtyping sees through them, on either side:
+    assert_m!(must_subtype(&ast!({"Type" "Nat" :}), &true_if, env.clone()), Ok(_));
+    assert_m!(must_subtype(&ast!({"Type" "Nat" :}), &false_if, env.clone()), Err(_));
+    assert_m!(must_subtype(&false_if, &ast!({"Type" "Float" :}), env.clone()), Ok(_));
+    assert_m!(must_subtype(&true_if, &ast!({"Type" "Float" :}), env.clone()), Err(_));
+
+    // Comparing with an abstract type is stuck, not false:
+    let env = env.set(n("A"), ast!((vr "A")));
+    let stuck_if = type_if(int_ty.clone(), ast!((vr "A")));
+    assert_eq!(canonicalize(&stuck_if, env.clone()), Ok(stuck_if.clone()));
+    assert_m!(must_subtype(&stuck_if, &stuck_if, env.clone()), Ok(_));
+    assert_m!(must_subtype(&stuck_if, &ast!({"Type" "Float" :}), env.clone()), Err(_));
+}
+
+#[test]
+fn type_sizes() {
+    let int_ty = ast!({"Type" "Int" :});
+    let int_list_ty = ast!( { "Type" "mu_type" :
+        "param" => [(import [prot "param"] (vr "IntList"))],
+        "body" => (import [* [prot "param"]] { "Type" "enum" :
+            "name" => [@"c" "Nil", "Cons"],
+            "component" => [@"c" [], [{"Type" "Int":}, (vr "IntList") ]]})});
+    let env = assoc_n!("IntList" => int_list_ty.clone(), "I" => int_ty.clone());
+
+    assert_eq!(type_size(&int_ty, &env), 1);
+    // `mu`, `enum`, `Int`, and the back-edge:
+    assert_eq!(type_size(&int_list_ty, &env), 4);
+    // Definitions are expanded:
+    assert_eq!(type_size(&ast!((vr "IntList")), &env), 4);
+    assert_eq!(
+        type_size(&ast!({"Type" "fn" : "param" => [(vr "I"), (vr "I")], "ret" => (vr "T")}), &env),
+        4
+    );
+    // A definition's references to itself are back-edges, too (`fn`, `Int`, and `A`):
+    let env = env.set(n("A"), ast!({"Type" "fn" : "param" => [(vr "A")], "ret" => (vr "I")}));
+    assert_eq!(type_size(&ast!((vr "A")), &env), 3);
+}
+
+#[test]
+fn failed_subtype_diagnostics() {
+    let udet = Subtype::underspecified(n("a"));
+    let udet_id = udet.node_parts().get_leaf_or_panic(&n("id")).to_name();
+    let int_ty = ast!({"Type" "Int" :});
+    let float_ty = ast!({"Type" "Float" :});
+
+    // Whichever of `param` and `ret` gets checked first determines `udet`; the other fails:
+    let (res, failures) = with_failed_subtypes(|| {
+        must_subtype(
+            &Ast::fn_ty(vec![int_ty.clone()], float_ty.clone()),
+            &Ast::fn_ty(vec![udet.clone()], udet.clone()),
+            Assoc::new(),
+        )
+    });
+    assert_m!(res, Err(TyErr::Mismatch(_, _)));
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].determinations.len(), 1);
+    let (id, ref determination) = failures[0].determinations[0];
+    assert_eq!(id, udet_id);
+    assert!(determination == &int_ty || determination == &float_ty);
+    assert!(format!("{}", failures[0]).contains(&render_unresolved(udet_id)));
+
+    // Nothing is recorded outside of `with_failed_subtypes`:
+    assert_eq!(with_failed_subtypes(|| ()).1, vec![]);
+}
+
+#[test]
+fn quantifier_placement_normal_forms() {
+    use crate::alpha::alpha_normalize;
+    let int_ty = ast!({"Type" "Int" :});
+    let forall = |param: &str, body: Ast| {
+        ast!({"Type" "forall_type" : "param" => [(, Atom(n(param)))],
+            "body" => (import [* [forall "param"]] (, body))})
+    };
+    let x_to_x = Ast::fn_ty(vec![ast!((vr "X"))], ast!((vr "X")));
+    // (Canonicalization freshens binders, so compare alpha-normalized types.)
+    let placed = |placement: QuantifierPlacement, t: &Ast| {
+        canonicalize_placing(t, Assoc::new(), placement).map(|t| alpha_normalize(&t))
+    };
+    let in_prenex = |t: &Ast| placed(QuantifierPlacement::Prenex, t);
+    let innermost = |t: &Ast| placed(QuantifierPlacement::Innermost, t);
+
+    // A `forall` in a parameter can't move:
+    let takes_anything = Ast::fn_ty(vec![forall("X", ast!((vr "X")))], int_ty.clone());
+    assert_eq!(in_prenex(&takes_anything), Ok(alpha_normalize(&takes_anything)));
+    assert_eq!(innermost(&takes_anything), Ok(alpha_normalize(&takes_anything)));
+
+    // ...but one in a return type can:
+    let inner = Ast::fn_ty(vec![int_ty.clone()], forall("X", x_to_x.clone()));
+    let outer = forall("X", Ast::fn_ty(vec![int_ty.clone()], x_to_x.clone()));
+    assert_eq!(in_prenex(&inner), Ok(alpha_normalize(&outer)));
+    assert_eq!(innermost(&outer), Ok(alpha_normalize(&inner)));
+    // (They have the same instances:)
+    let nat_ty = ast!({"Type" "Nat" :});
+    let nat_to_nat =
+        Ast::fn_ty(vec![int_ty.clone()], Ast::fn_ty(vec![nat_ty.clone()], nat_ty.clone()));
+    let nat_to_int = Ast::fn_ty(vec![int_ty.clone()], Ast::fn_ty(vec![nat_ty], int_ty.clone()));
+    for t in &[inner.clone(), outer.clone(), in_prenex(&inner).unwrap()] {
+        assert_m!(must_subtype(&nat_to_nat, t, Assoc::new()), Ok(_));
+        assert_m!(must_subtype(&nat_to_int, t, Assoc::new()), Err(_));
+    }
+
+    // Nested `forall`s merge, and only go as far in as they can:
+    let nested = forall("Y", forall("X", Ast::fn_ty(vec![ast!((vr "Y"))], x_to_x.clone())));
+    let merged = ast!({"Type" "forall_type" : "param" => ["Y", "X"],
+        "body" => (import [* [forall "param"]] (, Ast::fn_ty(vec![ast!((vr "Y"))], x_to_x.clone())))});
+    assert_eq!(in_prenex(&nested), Ok(alpha_normalize(&merged)));
+    let pushed = forall("Y", Ast::fn_ty(vec![ast!((vr "Y"))], forall("X", x_to_x.clone())));
+    assert_eq!(innermost(&merged), Ok(alpha_normalize(&pushed)));
+
+    // It's opt-in:
+    assert_eq!(placed(QuantifierPlacement::AsWritten, &inner), Ok(alpha_normalize(&inner)));
+}
+
+/// Reflexive checks don't need to walk the types at all, however deep they are.
+/// Run with `cargo test reflexive_subtype_benchmark -- --ignored`.
+#[test]
+#[ignore]
+fn reflexive_subtype_benchmark() {
+    let quantified = crate::ast::deeply_nested_type(200);
+    let returning = |leaf: Ast| (0..50).fold(leaf, |t, _| Ast::fn_ty(vec![], t));
+    let nat_ret = returning(ast!({"Type" "Nat" :}));
+    let int_ret = returning(ast!({"Type" "Int" :}));
+
+    let time = |check: &dyn Fn() -> Result<Assoc<Name, Ast>, TyErr>| {
+        let start = std::time::Instant::now();
+        for _ in 0..100 {
+            assert_m!(std::hint::black_box(check()), Ok(_));
+        }
+        start.elapsed()
+    };
+    let reflexive_forall = time(&|| must_subtype(&quantified, &quantified, Assoc::new()));
+    let reflexive_fn = time(&|| must_subtype(&int_ret, &int_ret.clone(), Assoc::new()));
+    // For comparison, a check that has to go all the way down:
+    let widening_fn = time(&|| {
+        let widening =
+            SubtypePolicy { numeric_tower: NumericTower::Widening, ..Default::default() };
+        must_subtype_with(&widening, &nat_ret, &int_ret, Assoc::new())
+    });
+    assert!(reflexive_fn < widening_fn);
+    assert!(reflexive_forall < widening_fn);
+}
+
+#[test]
+fn subtyping_under_hypotheses() {
+    // Abstract types:
+    let env = assoc_n!("X" => ast!((vr "X")), "Y" => ast!((vr "Y")));
+    let (x, y) = (ast!((vr "X")), ast!((vr "Y")));
+    let x_sub_y = [(x.clone(), y.clone())];
+
+    assert_m!(must_subtype(&x, &y, env.clone()), Err(_));
+    assert_m!(is_subtype_under(&x_sub_y, &x, &y, env.clone()), Ok(_));
+    // Only in that direction:
+    assert_m!(is_subtype_under(&x_sub_y, &y, &x, env.clone()), Err(_));
+
+    // Deeper down (and in contravariant position, too):
+    let y_to_x = Ast::fn_ty(vec![y.clone()], x.clone());
+    let x_to_y = Ast::fn_ty(vec![x.clone()], y.clone());
+    assert_m!(must_subtype(&y_to_x, &x_to_y, env.clone()), Err(_));
+    assert_m!(is_subtype_under(&x_sub_y, &y_to_x, &x_to_y, env.clone()), Ok(_));
+    assert_m!(is_subtype_under(&x_sub_y, &x_to_y, &y_to_x, env.clone()), Err(_));
+
+    // The hypotheses don't outlive the check:
+    assert_m!(must_subtype(&x, &y, env), Err(_));
+}
+
+#[test]
+fn reparameterized_subtyping() {
+    // Either = ∀ L R. μ Either. { +[Left L]+ +[Right R]+ +[Both L R Either<L R>]+ }
+    let either_ty = ast!({ "Type" "forall_type" :
+        "param" => ["L", "R"],
+        "body" => (import [* [forall "param"]] { "Type" "mu_type" :
+            "param" => [(import [prot "param"] (vr "Either"))],
+            "body" => (import [* [prot "param"]] { "Type" "enum" :
+                "name" => [@"c" "Left", "Right", "Both"],
+                "component" => [@"c" [(vr "L")], [(vr "R")],
+                    [(vr "L"), (vr "R"), {"Type" "type_apply" :
+                        "type_rator" => (vr "Either"), "arg" => [(vr "L"), (vr "R")]}]]})})});
+    // Flipped = ∀ A B. Either<B A>
+    let flipped_ty = ast!({ "Type" "forall_type" :
+        "param" => ["A", "B"],
+        "body" => (import [* [forall "param"]] {"Type" "type_apply" :
+            "type_rator" => (vr "Either"), "arg" => [(vr "B"), (vr "A")]})});
+    // Both = ∀ T. Either<T T> (with a different number of parameters)
+    let both_ty = ast!({ "Type" "forall_type" :
+        "param" => ["T"],
+        "body" => (import [* [forall "param"]] {"Type" "type_apply" :
+            "type_rator" => (vr "Either"), "arg" => [(vr "T"), (vr "T")]})});
+    let env = assoc_n!("Either" => either_ty, "Flipped" => flipped_ty, "Both" => both_ty);
+    let applied = |rator: &str, args: Vec<Ast>| ast!({"Type" "type_apply" : "type_rator" => (vr rator), "arg" => (,seq args)});
+    let forall_xy = |body: Ast| {
+        ast!({ "Type" "forall_type" : "param" => ["X", "Y"],
+            "body" => (import [* [forall "param"]] (, body))})
+    };
+    let (x, y) = (ast!((vr "X")), ast!((vr "Y")));
+    let (int, float) = (ast!({"Type" "Int" :}), ast!({"Type" "Float" :}));
+
+    // Swapping the parameters twice gets back where we started:
+    assert_m!(
+        must_subtype(
+            &ast!((vr "Either")),
+            &forall_xy(applied("Flipped", vec![y.clone(), x.clone()])),
+            env.clone()
+        ),
+        Ok(_)
+    );
+    assert_m!(
+        must_subtype(
+            &ast!((vr "Flipped")),
+            &forall_xy(applied("Either", vec![y.clone(), x.clone()])),
+            env.clone()
+        ),
+        Ok(_)
+    );
+    assert_m!(
+        must_subtype(
+            &applied("Flipped", vec![int.clone(), float.clone()]),
+            &applied("Either", vec![float.clone(), int.clone()]),
+            env.clone()
+        ),
+        Ok(_)
+    );
+    // ...but swapping them just once doesn't:
+    assert_m!(
+        must_subtype(
+            &applied("Flipped", vec![int.clone(), float.clone()]),
+            &applied("Either", vec![int.clone(), float.clone()]),
+            env.clone()
+        ),
+        Err(_)
+    );
+
+    // Parameters can be merged, too (but not split apart):
+    assert_m!(must_subtype(&ast!((vr "Both")), &ast!((vr "Either")), env.clone()), Ok(_));
+    assert_m!(must_subtype(&ast!((vr "Both")), &ast!((vr "Flipped")), env.clone()), Ok(_));
+    assert_m!(must_subtype(&ast!((vr "Either")), &ast!((vr "Both")), env.clone()), Err(_));
+    assert_m!(
+        must_subtype(
+            &applied("Both", vec![int.clone()]),
+            &applied("Flipped", vec![int.clone(), int.clone()]),
+            env.clone()
+        ),
+        Ok(_)
+    );
+    assert_m!(
+        must_subtype(
+            &applied("Both", vec![int.clone()]),
+            &applied("Either", vec![int, float]),
+            env
+        ),
+        Err(_)
+    );
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "cycle in unification")]
+fn unification_cycles_are_caught() {
+    let udet_id = |t: &Ast| match t {
+        Node(_, ref parts, _, _) => parts.get_leaf_or_panic(&n("id")).to_name(),
+        _ => panic!(),
+    };
+    let (a, b) = (Subtype::underspecified(n("a")), Subtype::underspecified(n("b")));
+    let mut unif = HashMap::new();
+    // Chains are fine...
+    unif.insert(udet_id(&a), Clo { it: uty!({fn : [(, b.clone())] {Int :}}), env: Assoc::new() });
+    unif.insert(udet_id(&b), Clo { it: uty!({Nat :}), env: Assoc::new() });
+    check_unif_acyclic(&unif);
+    // ...but loops aren't:
+    unif.insert(udet_id(&b), Clo { it: uty!({tuple : [(, a); {Nat :}]}), env: Assoc::new() });
+    check_unif_acyclic(&unif);
+}
+
+#[test]
+fn subtyping_proofs() {
+    let int_ty = ast!({ "Type" "Int" : });
+    let id_fn_ty = ast!({ "Type" "forall_type" :
+        "param" => ["t"],
+        "body" => (import [* [forall "param"]]
+            { "Type" "fn" : "param" => [ (vr "t") ], "ret" => (vr "t") })});
+    let int_to_int_fn_ty = ast!({ "Type" "fn" :
+         "param" => [(, int_ty.clone())],
+         "ret" => (, int_ty.clone())});
+
+    let proof = prove_subtype(&int_to_int_fn_ty, &id_fn_ty, Assoc::new()).unwrap();
+    // Instantiate the `∀`...
+    assert_eq!(proof.rule, n("forall_type"));
+    assert_eq!((&proof.sub, &proof.sup), (&int_to_int_fn_ty, &id_fn_ty));
+    // ...then compare the functions...
+    assert_eq!(proof.premises.len(), 1);
+    let fn_step = &proof.premises[0];
+    assert_eq!(fn_step.rule, n("fn"));
+    let rules: Vec<Name> = fn_step.premises.iter().map(|step| step.rule).collect();
+    assert_eq!(rules, vec![n("determine"), n("Int")]);
+    // ...where the parameter determines `t`, and the return type then checks out:
+    let (param_step, ret_step) = (&fn_step.premises[0], &fn_step.premises[1]);
+    assert_eq!(param_step.determinations.len(), 1);
+    assert_eq!(param_step.determinations[0].1, int_ty);
+    assert_eq!((&ret_step.sub, &ret_step.sup), (&int_ty, &int_ty));
+    // Every step says what it proves:
+    fn all_steps(proof: &SubtypeProof) -> Vec<&SubtypeProof> {
+        let mut res = vec![proof];
+        res.extend(proof.premises.iter().flat_map(all_steps));
+        res
+    }
+    assert!(all_steps(&proof).iter().all(|step| step.sub != Trivial && step.sup != Trivial));
+
+    // Identical types don't need walking:
+    assert_eq!(prove_subtype(&int_ty, &int_ty, Assoc::new()).map(|p| p.rule), Ok(n("refl")));
+    assert_m!(prove_subtype(&id_fn_ty, &int_to_int_fn_ty, Assoc::new()), Err(_));
+}
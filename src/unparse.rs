@@ -7,6 +7,12 @@ use crate::{
     name::*,
     util::mbe::EnvMBE,
 };
+use std::cell::RefCell;
+
+thread_local! {
+    // The `mu_type`s that we're in the middle of printing:
+    static mus_being_printed: RefCell<Vec<Ast>> = RefCell::new(vec![]);
+}
 
 fn node_names_mentioned(pat: &FormPat) -> Vec<Name> {
     match *pat {
@@ -15,7 +21,7 @@ fn node_names_mentioned(pat: &FormPat) -> Vec<Name> {
             res.push(n);
             res
         }
-        Scope(_, _) => vec![],
+        Scope(_, _) | PrattExpr(_, _, _) => vec![],
         Pick(_, _) => vec![],
         Star(ref body)
         | Plus(ref body)
@@ -26,7 +32,8 @@ fn node_names_mentioned(pat: &FormPat) -> Vec<Name> {
         | QuoteDeepen(ref body, _)
         | QuoteEscape(ref body, _)
         | Common(ref body)
-        | Reserved(ref body, _) => node_names_mentioned(&*body),
+        | Reserved(ref body, _)
+        | Satisfy(ref body, _) => node_names_mentioned(&*body),
         Seq(ref sub_pats) | Alt(ref sub_pats) => {
             let mut res = vec![];
             for pat in sub_pats {
@@ -39,15 +46,69 @@ fn node_names_mentioned(pat: &FormPat) -> Vec<Name> {
             res.append(&mut node_names_mentioned(&*rhs));
             res
         }
-        Anyways(_) | Impossible | Scan(_) | Call(_) | SynImport(_, _, _) => vec![],
+        Switch(ref cases, ref default) => {
+            let mut res = vec![];
+            for (_, case) in cases {
+                res.append(&mut node_names_mentioned(case));
+            }
+            res.append(&mut node_names_mentioned(default));
+            res
+        }
+        Anyways(_)
+        | Impossible
+        | Scan(_)
+        | AnyGroup(_)
+        | Custom(_)
+        | SkipTo(_)
+        | RestOfInput
+        | Call(_)
+        | SynImport(_, _, _) => vec![],
     }
 }
 
+/// Print `mu` as `μList. {...}`. Inside of it, a copy of it (say, from unfolding it in place)
+///  is printed as just `List`, so unfolded types don't print as an ever-growing blob.
+fn unparse_mu(mu: &Ast) -> String {
+    let parts = mu.node_parts();
+    let params: Vec<String> = parts
+        .get_rep_leaf_or_panic(n("param"))
+        .into_iter()
+        .map(|p| format!("{}", crate::core_forms::strip_ee(p)))
+        .collect();
+    if mus_being_printed.with(|mbp| mbp.borrow().iter().any(|outer| is_copy_of(mu, outer))) {
+        return params.join(" ");
+    }
+    let nominal = if parts.get_leaf(n("nominal")).is_some() { "nominal " } else { "" };
+    mus_being_printed.with(|mbp| mbp.borrow_mut().push(mu.clone()));
+    let body = format!("{}", crate::core_forms::strip_ee(parts.get_leaf_or_panic(&n("body"))));
+    mus_being_printed.with(|mbp| mbp.borrow_mut().pop());
+    format!("{}μ{}. {}", nominal, params.join(" "), body)
+}
+
+/// Is the `mu_type` `inner` the same as `outer`, or `outer` unfolded once?
+/// (Another `mu` that merely has the same parameter names is a different type.)
+fn is_copy_of(inner: &Ast, outer: &Ast) -> bool {
+    use crate::core_forms::strip_ee;
+    if inner == outer {
+        return true;
+    }
+    let (inner_parts, outer_parts) = (inner.node_parts(), outer.node_parts());
+    let params = inner_parts.get_rep_leaf_or_panic(n("param"));
+    if params != outer_parts.get_rep_leaf_or_panic(n("param")) {
+        return false;
+    }
+    let unfolding: crate::util::assoc::Assoc<Name, Ast> =
+        params.iter().map(|p| (strip_ee(p).vr_to_name(), inner.clone())).collect();
+    let unfolded =
+        crate::alpha::substitute(strip_ee(inner_parts.get_leaf_or_panic(&n("body"))), &unfolding);
+    &unfolded == strip_ee(outer_parts.get_leaf_or_panic(&n("body")))
+}
+
 pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv) -> String {
     // HACK: handle underdetermined forms
     let undet = crate::ty_compare::underdetermined_form.with(|u| u.clone());
     match *actl {
-        Node(ref form, ref body, _) if form == &undet => {
+        Node(ref form, ref body, _, _) if form == &undet => {
             return crate::ty_compare::unification.with(|unif| {
                 let var = body.get_leaf_or_panic(&n("id")).to_name();
                 let looked_up = unif.borrow().get(&var).cloned();
@@ -56,10 +117,14 @@ pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv)
                     Some(ref clo) => {
                         format!("{} in some environment", clo.it /* , {:#?} clo.env */)
                     }
-                    None => format!("¿{}?", var),
+                    None => crate::ty_compare::render_unresolved(var),
                 }
             });
         }
+        // HACK: print `mu_type`s compactly (unless they need to be parsed back in)
+        Node(ref form, _, _, _) if form.name == n("mu_type") && !printing_portably() => {
+            return unparse_mu(actl);
+        }
         _ => {}
     }
 
@@ -74,6 +139,16 @@ pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv)
         (&Literal(_, n), _) => n.print(),
         (&Scan(_), &Atom(n)) => n.print(),
         (&Scan(_), _) => "".to_string(), // HACK for `Alt`
+        (&AnyGroup(d), &Atom(n)) => format!("{}{}{}", d.open(), n.print(), d.close()),
+        (&AnyGroup(_), _) => "".to_string(),
+        (&Custom(_), &Atom(n)) => n.print(),
+        (&Custom(_), _) => "".to_string(), // HACK for `Alt`
+        (&SkipTo(_), &Node(_, ref parts, _, _)) => {
+            format!("{}", parts.get_leaf_or_panic(&n("text")))
+        }
+        (&SkipTo(_), _) => "".to_string(),
+        (&RestOfInput, &Atom(n)) => n.print(),
+        (&RestOfInput, _) => "".to_string(),
         (&VarRef(ref sub_form), &VariableReference(n)) => {
             unparse_mbe(&*sub_form, &Atom(n), context, s)
         }
@@ -107,7 +182,7 @@ pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv)
             // HACK: certain forms don't live in the syntax environment,
             //  but "belong" under an `Alt`, so just assume forms know their grammar:
             if any_scopes {
-                if let &Node(ref form_actual, ref body, _) = actl {
+                if let &Node(ref form_actual, ref body, _, _) = actl {
                     return unparse_mbe(&*form_actual.grammar, actl, body, s);
                 }
             }
@@ -117,6 +192,16 @@ pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv)
         (&Biased(ref lhs, ref rhs), _) => {
             format!("{}{}", unparse_mbe(lhs, actl, context, s), unparse_mbe(rhs, actl, context, s))
         }
+        (&Switch(ref cases, ref default), _) => {
+            // Like `Alt`: the first case that has anything to say is the one that was parsed.
+            for (_, case) in cases {
+                let sub_res = unparse_mbe(case, actl, context, s);
+                if !sub_res.is_empty() {
+                    return sub_res;
+                }
+            }
+            unparse_mbe(default, actl, context, s)
+        }
         (&Star(ref sub_pat), _) | (&Plus(ref sub_pat), _) => {
             let mut first = true;
             let mut res = String::new();
@@ -129,7 +214,7 @@ pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv)
             }
             res
         }
-        (&Scope(ref form, _), &Node(ref form_actual, ref body, _)) => {
+        (&Scope(ref form, _), &Node(ref form_actual, ref body, _, _)) => {
             if form == form_actual {
                 unparse_mbe(&*form.grammar, actl, body, s)
             } else {
@@ -137,6 +222,11 @@ pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv)
             }
         }
         (&Scope(_, _), _) => "".to_string(), // Non-match
+        (PrattExpr(_, _, form), Node(form_actual, body, _, _)) if form == form_actual => {
+            unparse_mbe(&form.grammar, actl, body, s)
+        }
+        // Just an operand:
+        (PrattExpr(body, _, _), _) => unparse_mbe(body, actl, &EnvMBE::new(), s),
         (&Pick(ref body, _), _) | (&Common(ref body), _) => unparse_mbe(&*body, actl, context, s),
         (&NameImport(ref body, _), &ExtendEnv(ref actl_body, _)) => {
             unparse_mbe(&*body, &*actl_body, context, s)
@@ -154,11 +244,13 @@ pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv)
             unparse_mbe(&*body, &*actl_body, context, s)
         }
         (&QuoteEscape(_, _), _) => format!("[Missing ql]{:#?}", actl),
-        (&SynImport(ref _lhs_grammar, ref _rhs, _), &Node(_, _, _)) => {
+        (&SynImport(ref _lhs_grammar, ref _rhs, _), &Node(_, _, _, _)) => {
             // TODO: I think we need to store the LHS or the new SynEnv to make this pretty.
             format!("?synax import? {:#?} ?si?", actl)
         }
         (&SynImport(_, _, _), _) => "".to_string(),
-        (&Reserved(ref body, _), _) => unparse_mbe(body, actl, context, s),
+        (&Reserved(ref body, _), _) | (&Satisfy(ref body, _), _) => {
+            unparse_mbe(body, actl, context, s)
+        }
     }
 }
@@ -36,6 +36,21 @@ impl<K: Eq + Hash + Clone, V: Clone + PartialEq> PartialEq for Assoc<K, V> {
 
 impl<K: Eq + Hash + Clone, V: Clone + Eq> Eq for Assoc<K, V> {}
 
+// Independent of iteration order, to agree with `PartialEq`
+impl<K: Eq + Hash + Clone, V: Clone + Hash> Hash for Assoc<K, V> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        use std::hash::Hasher;
+        let mut combined: u64 = 0;
+        for (k, v) in self.iter_pairs() {
+            let mut entry_hasher = std::collections::hash_map::DefaultHasher::new();
+            k.hash(&mut entry_hasher);
+            v.hash(&mut entry_hasher);
+            combined = combined.wrapping_add(entry_hasher.finish());
+        }
+        combined.hash(state)
+    }
+}
+
 impl<K: Eq + Hash + Clone, V: Clone> Default for Assoc<K, V> {
     fn default() -> Self { Self::new() }
 }
@@ -166,6 +181,23 @@ impl<K: Eq + Hash + Clone, V: Clone> Assoc<K, V> {
         )
     }
 
+    /// Every key in either `self` or `other`, with what each one maps it to (if anything).
+    pub fn zip<'assoc, OtherV: Clone>(
+        &'assoc self,
+        other: &'assoc Assoc<K, OtherV>,
+    ) -> impl Iterator<Item = (K, Option<V>, Option<OtherV>)> + 'assoc {
+        let in_self = self
+            .hamt
+            .iter()
+            .map(move |(k, v)| (k.clone(), Some(v.clone()), other.find(k).cloned()));
+        let only_in_other = other
+            .hamt
+            .iter()
+            .filter(move |(k, _)| self.find(k).is_none())
+            .map(|(k, v)| (k.clone(), None, Some(v.clone())));
+        in_self.chain(only_in_other)
+    }
+
     pub fn find_value<'assoc, 'f>(&'assoc self, target: &'f V) -> Option<&'assoc K>
     where V: PartialEq {
         self.hamt.iter().find(|(_, v)| v == &target).map(|(k, _)| k)
@@ -210,6 +242,45 @@ impl<K: Eq + Hash + Clone, V: Clone> Assoc<K, V> {
     }
 }
 
+/// Later pairs shadow earlier ones, as with repeated `set`s.
+impl<K: Eq + Hash + Clone, V: Clone> std::iter::FromIterator<(K, V)> for Assoc<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut res = Assoc::new();
+        for (k, v) in iter {
+            res.mut_set(k, v);
+        }
+        res
+    }
+}
+
+/// Only the visible (unshadowed) pairs, in no particular order.
+impl<'assoc, K: Eq + Hash + Clone, V: Clone> IntoIterator for &'assoc Assoc<K, V> {
+    type Item = (&'assoc K, &'assoc V);
+    type IntoIter = im_rc::hashmap::Iter<'assoc, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter { self.iter_pairs() }
+}
+
+/// Serialized as a map of just the visible pairs.
+impl<K: Eq + Hash + Clone + serde::Serialize, V: Clone + serde::Serialize> serde::Serialize
+    for Assoc<K, V>
+{
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_map(self.iter_pairs())
+    }
+}
+
+impl<'de, K, V> serde::Deserialize<'de> for Assoc<K, V>
+where
+    K: Eq + Hash + Clone + serde::Deserialize<'de>,
+    V: Clone + serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let pairs = std::collections::HashMap::<K, V>::deserialize(d)?;
+        Ok(pairs.into_iter().collect())
+    }
+}
+
 impl<K: Eq + Hash + Clone, V: Clone, E: Clone> Assoc<K, Result<V, E>> {
     pub fn lift_result(self) -> Result<Assoc<K, V>, E> {
         let mut oks = vec![];
@@ -220,6 +291,47 @@ impl<K: Eq + Hash + Clone, V: Clone, E: Clone> Assoc<K, Result<V, E>> {
     }
 }
 
+/// An imperative symbol table, for clients that would rather not thread `Assoc`s around.
+/// Each scope is an `Assoc` extending the one outside it, so entering and exiting are O(1).
+#[derive(Clone)]
+pub struct ScopedEnv<K, V>
+where K: Eq + Hash + Clone
+{
+    scopes: Vec<Assoc<K, V>>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Default for ScopedEnv<K, V> {
+    fn default() -> Self { Self::new() }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> ScopedEnv<K, V> {
+    pub fn new() -> Self { Self::from_assoc(Assoc::new()) }
+
+    /// Start with the definitions in `outermost` (which can't be exited).
+    pub fn from_assoc(outermost: Assoc<K, V>) -> Self { ScopedEnv { scopes: vec![outermost] } }
+
+    pub fn enter_scope(&mut self) {
+        let inner = self.current().clone();
+        self.scopes.push(inner);
+    }
+
+    /// Forget everything defined since the matching `enter_scope`.
+    pub fn exit_scope(&mut self) {
+        if self.scopes.len() == 1 {
+            icp!("`exit_scope` without `enter_scope`");
+        }
+        self.scopes.pop();
+    }
+
+    /// Define `k` in the innermost scope (shadowing any outer definition).
+    pub fn define(&mut self, k: K, v: V) { self.scopes.last_mut().unwrap().mut_set(k, v) }
+
+    pub fn lookup(&self, k: &K) -> Option<&V> { self.current().find(k) }
+
+    /// Everything visible right now.
+    pub fn current(&self) -> &Assoc<K, V> { self.scopes.last().unwrap() }
+}
+
 #[test]
 fn basic_assoc() {
     let mt: Assoc<i32, i32> = Assoc::new();
@@ -291,6 +403,54 @@ fn assoc_equality() {
     // assert!(mt.almost_ptr_eq(&Assoc::new()));
 }
 
+#[test]
+fn assoc_hashing() {
+    use std::hash::Hasher;
+    fn hash_of(a: &Assoc<i32, i32>) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        a.hash(&mut hasher);
+        hasher.finish()
+    }
+    let mt: Assoc<i32, i32> = Assoc::new();
+
+    // Only the final mapping matters, not how it got built:
+    let forwards = mt.set(1, 10).set(2, 20).set(3, 30);
+    let backwards = mt.set(3, 30).set(2, 20).set(1, 10);
+    let overridden = mt.set(2, 0).set(1, 10).set(3, 30).set(4, 40).set(2, 20).unset(&4);
+    assert_eq!(forwards, backwards);
+    assert_eq!(forwards, overridden);
+    assert_eq!(hash_of(&forwards), hash_of(&backwards));
+    assert_eq!(hash_of(&forwards), hash_of(&overridden));
+
+    assert!(forwards != forwards.set(2, 21));
+    assert!(hash_of(&forwards) != hash_of(&forwards.set(2, 21)));
+
+    // So they work as keys:
+    let mut cache = std::collections::HashMap::new();
+    cache.insert(forwards, "cached");
+    assert_eq!(cache.get(&overridden), Some(&"cached"));
+}
+
+#[test]
+fn assoc_iteration() {
+    let a: Assoc<i32, i32> = vec![(5, 6), (6, 7), (5, 500)].into_iter().collect();
+    assert_eq!(a, Assoc::new().set(5, 6).set(6, 7).set(5, 500));
+    assert_eq!(a.find(&5), Some(&500));
+
+    let mut pairs: Vec<(i32, i32)> = (&a).into_iter().map(|(k, v)| (*k, *v)).collect();
+    pairs.sort();
+    assert_eq!(pairs, vec![(5, 500), (6, 7)]);
+
+    let mut total = 0;
+    for (k, v) in &a {
+        total += k + v;
+    }
+    assert_eq!(total, 518);
+
+    let empty: Assoc<i32, i32> = std::iter::empty().collect();
+    assert!(empty.empty());
+}
+
 #[test]
 fn assoc_r_and_r_roundtrip() {
     use num::BigInt;
@@ -311,6 +471,25 @@ fn assoc_map() {
     assert_eq!(a1.map_with(&a2, &|a, b| a + b), assoc_n!("x" => 0, "y" => 0, "z" => 0));
 }
 
+#[test]
+fn assoc_zip() {
+    use crate::name::{n, Name};
+
+    let a1 = assoc_n!("x" => 1, "y" => 2);
+    let a2 = assoc_n!("y" => "two", "z" => "three");
+
+    let mut zipped: Vec<_> = a1.zip(&a2).collect();
+    zipped.sort_by_key(|entry| entry.0);
+    assert_eq!(zipped, vec![
+        (n("x"), Some(1), None),
+        (n("y"), Some(2), Some("two")),
+        (n("z"), None, Some("three"))
+    ]);
+
+    let mt: Assoc<Name, i32> = Assoc::new();
+    assert_eq!(mt.zip(&mt).count(), 0);
+}
+
 #[test]
 fn assoc_reduce() {
     let a1 = assoc_n!("x" => 1, "y" => 2, "z" => 3);
@@ -319,3 +498,23 @@ fn assoc_reduce() {
     let a1 = assoc_n!("x" => 1, "y" => 2, "z" => 3);
     assert_eq!(a1.reduce(&|key, a, b| if key.is("y") { b } else { a + b }, 0), 4);
 }
+
+#[test]
+fn scoped_env() {
+    use crate::name::n;
+    let mut env = ScopedEnv::from_assoc(assoc_n!("x" => 1));
+    env.enter_scope();
+    env.define(n("y"), 2);
+    env.define(n("x"), 10);
+    assert_eq!(env.lookup(&n("x")), Some(&10));
+    assert_eq!(env.lookup(&n("y")), Some(&2));
+
+    env.enter_scope();
+    assert_eq!(env.lookup(&n("y")), Some(&2));
+    env.exit_scope();
+
+    env.exit_scope();
+    assert_eq!(env.lookup(&n("x")), Some(&1));
+    assert_eq!(env.lookup(&n("y")), None);
+    assert_eq!(env.current(), &assoc_n!("x" => 1));
+}
@@ -3,7 +3,7 @@ use std::fmt::{Debug, Display, Formatter, Result};
 custom_derive! {
     #[derive(Reifiable, Clone, PartialEq)]
     pub struct Spanned<T> {
-        pub loc: crate::ast::Ast, // TODO: implement spans!
+        pub loc: crate::ast::Ast, // Parsed `Ast`s know their `Span`s
         pub body: T
     }
 }
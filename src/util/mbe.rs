@@ -69,7 +69,7 @@ use std::{fmt, rc::Rc};
 ///   in which the marched values are not repeated (or one layer less repeated).
 /// Marching multiple repeated values at once
 ///  is only permitted if they were constructed to repeat the same number of times.
-#[derive(Eq, Clone, Default)]
+#[derive(Eq, Clone, Default, serde::Serialize, serde::Deserialize)]
 // `Clone` needs to traverse the whole `Vec` ):
 pub struct EnvMBE<T: Clone> {
     /// Non-repeated values
@@ -159,6 +159,21 @@ impl<T: PartialEq + Clone> PartialEq for EnvMBE<T> {
     }
 }
 
+// Coarser than `PartialEq`: doesn't look at which repeats are named what.
+impl<T: std::hash::Hash + Clone> std::hash::Hash for EnvMBE<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.leaves.hash(state);
+        self.repeats.hash(state);
+    }
+}
+
+impl<T: PartialEq + Clone> EnvMBE<T> {
+    /// Like `==`, but regardless of how the repetitions are grouped (see `semantically_eq_by`).
+    pub fn semantically_eq(&self, other: &EnvMBE<T>) -> bool {
+        self.semantically_eq_by(other, &|l, r| l == r)
+    }
+}
+
 impl<T: Clone + fmt::Debug> fmt::Debug for EnvMBE<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.leaves.empty() && self.repeats.is_empty() {
@@ -315,6 +330,19 @@ impl<T: Clone> EnvMBE<T> {
         result
     }
 
+    /// Reorder the repetition that `driving_name` is in, according to `key`.
+    /// (Does nothing if `driving_name` isn't repeated.)
+    pub fn sort_rep_by_key<K: Ord, F>(&self, driving_name: Name, mut key: F) -> EnvMBE<T>
+    where F: FnMut(&EnvMBE<T>) -> K {
+        let mut res = self.clone();
+        if let Some(&Some(loc)) = self.leaf_locations.find(&driving_name) {
+            let mut rep = (*self.repeats[loc]).clone();
+            rep.sort_by_key(|sub| key(sub));
+            res.repeats[loc] = Rc::new(rep);
+        }
+        res
+    }
+
     /// Get a non-repeated thing in the enviornment
     pub fn get_leaf(&self, n: Name) -> Option<&T> { self.leaves.find(&n) }
 
@@ -344,6 +372,23 @@ impl<T: Clone> EnvMBE<T> {
     /// Extend with a non-repeated thing
     pub fn add_leaf(&mut self, n: Name, v: T) { self.leaves = self.leaves.set(n, v); }
 
+    /// Replace the leaf `n` (inside the repetitions picked by `rep_idxs`, outermost first)
+    ///  with what `f` makes of it. `None` if there's no such leaf or `f` returns `None`.
+    pub fn update_leaf_at<F>(&self, n: Name, rep_idxs: &[usize], f: F) -> Option<EnvMBE<T>>
+    where F: FnOnce(&T) -> Option<T> {
+        let mut res = self.clone();
+        match rep_idxs.split_first() {
+            None => res.add_leaf(n, f(self.leaves.find(&n)?)?),
+            Some((&idx, rest)) => {
+                let loc = (*self.leaf_locations.find(&n)?)?;
+                let mut rep = (*self.repeats[loc]).clone();
+                rep[idx] = rep.get(idx)?.update_leaf_at(n, rest, f)?;
+                res.repeats[loc] = Rc::new(rep);
+            }
+        }
+        Some(res)
+    }
+
     pub fn add_named_repeat(&mut self, n: Name, sub: Vec<EnvMBE<T>>) {
         if sub.is_empty() {
             return;
@@ -455,6 +500,103 @@ impl<T: Clone> EnvMBE<T> {
         }
     }
 
+    /// Like `named_map`, but `f` also gets the repetition indices of each leaf
+    ///  (outermost first, as in `leaves_at`).
+    pub fn map_at<NewT: Clone, F>(&self, f: &mut F) -> EnvMBE<NewT>
+    where F: FnMut(Name, &[usize], &T) -> NewT {
+        self.map_at_under(&mut vec![], f)
+    }
+
+    fn map_at_under<NewT: Clone, F>(&self, rep_idxs: &mut Vec<usize>, f: &mut F) -> EnvMBE<NewT>
+    where F: FnMut(Name, &[usize], &T) -> NewT {
+        let leaves =
+            self.leaves.keyed_map_borrow_f(&mut |n: &Name, leaf: &T| f(*n, rep_idxs, leaf));
+        let mut repeats = vec![];
+        for rc_vec_mbe in &self.repeats {
+            let mut rep = vec![];
+            for (idx, mbe) in rc_vec_mbe.iter().enumerate() {
+                rep_idxs.push(idx);
+                rep.push(mbe.map_at_under(rep_idxs, f));
+                rep_idxs.pop();
+            }
+            repeats.push(Rc::new(rep));
+        }
+        EnvMBE {
+            leaves: leaves,
+            repeats: repeats,
+            leaf_locations: self.leaf_locations.clone(),
+            named_repeats: self.named_repeats.clone(),
+        }
+    }
+
+    /// Do `self` and `other` have the same leaves (according to `eq`),
+    ///  repeated the same way, regardless of how the repetitions are grouped or named?
+    /// (`==` is sensitive to that; e.g. two names repeated in lockstep are different
+    ///  from the same two names repeated the same number of times separately.)
+    pub fn semantically_eq_by(&self, other: &EnvMBE<T>, eq: &dyn Fn(&T, &T) -> bool) -> bool {
+        let leaf_names = |mbe: &EnvMBE<T>| -> std::collections::HashSet<Name> {
+            let located = mbe.leaf_locations.iter_pairs().filter(|(_, loc)| loc.is_some());
+            mbe.leaves.iter_keys().cloned().chain(located.map(|(n, _)| *n)).collect()
+        };
+        let names = leaf_names(self);
+        names == leaf_names(other)
+            && names.into_iter().all(|n| self.leaf_semantically_eq(other, n, eq))
+    }
+
+    fn leaf_semantically_eq(
+        &self,
+        other: &EnvMBE<T>,
+        n: Name,
+        eq: &dyn Fn(&T, &T) -> bool,
+    ) -> bool {
+        let rep = |mbe: &EnvMBE<T>| match mbe.leaf_locations.find(&n) {
+            Some(&Some(loc)) => Some(mbe.repeats[loc].clone()),
+            _ => None,
+        };
+        match (self.leaves.find(&n), other.leaves.find(&n)) {
+            (Some(l), Some(r)) => eq(l, r),
+            (None, None) => match (rep(self), rep(other)) {
+                (None, None) => true,
+                (Some(l), Some(r)) => {
+                    l.len() == r.len()
+                        && l.iter().zip(r.iter()).all(|(l, r)| l.leaf_semantically_eq(r, n, eq))
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// All the leaves, in the order that `map` (and `named_map`) visits them.
+    pub fn all_leaves(&self) -> Vec<&T> {
+        let mut res: Vec<&T> = self.leaves.iter_values().collect();
+        for rc_vec_mbe in &self.repeats {
+            for mbe in rc_vec_mbe.iter() {
+                res.append(&mut mbe.all_leaves());
+            }
+        }
+        res
+    }
+
+    /// Every leaf, with its name and the repetition indices (outermost first) that lead to it
+    ///  (as `update_leaf_at` takes them).
+    pub fn leaves_at(&self) -> Vec<(Name, Vec<usize>, &T)> {
+        let mut res: Vec<(Name, Vec<usize>, &T)> =
+            self.leaves.iter_pairs().map(|(n, leaf)| (*n, vec![], leaf)).collect();
+        for (loc, rc_vec_mbe) in self.repeats.iter().enumerate() {
+            for (idx, mbe) in rc_vec_mbe.iter().enumerate() {
+                for (n, mut rep_idxs, leaf) in mbe.leaves_at() {
+                    // Skip obsolete repetitions:
+                    if *self.leaf_locations.find(&n).unwrap_or(&None) == Some(loc) {
+                        rep_idxs.insert(0, idx);
+                        res.push((n, rep_idxs, leaf));
+                    }
+                }
+            }
+        }
+        res
+    }
+
     pub fn map_reduce<NewT: Clone>(
         &self,
         f: &dyn Fn(&T) -> NewT,
@@ -945,6 +1087,38 @@ fn basic_mbe() {
     assert_eq!(output, vec![(Some(0), Some(11)), (Some(0), Some(12)), (Some(0), Some(13))]);
 }
 
+#[test]
+fn semantic_equality() {
+    let leaves = |name: &str, vals: &[i32]| -> Vec<EnvMBE<i32>> {
+        vals.iter().map(|v| EnvMBE::new_from_leaves(Assoc::new().set(n(name), *v))).collect()
+    };
+
+    // `a` and `b` repeated in lockstep...
+    let mut together = EnvMBE::new();
+    together.add_leaf(n("c"), 0);
+    together.add_anon_repeat(
+        leaves("a", &[1, 2]).iter().zip(leaves("b", &[3, 4])).map(|(a, b)| a.merge(&b)).collect(),
+    );
+    // ...vs. separately:
+    let mut apart = EnvMBE::new();
+    apart.add_named_repeat(n("b"), leaves("b", &[3, 4]));
+    apart.add_anon_repeat(leaves("a", &[1, 2]));
+    apart.add_leaf(n("c"), 0);
+
+    assert!(together != apart);
+    assert!(together.semantically_eq(&apart));
+    assert!(apart.semantically_eq(&together));
+
+    let mut different = apart.clone();
+    different.add_anon_repeat(leaves("d", &[5]));
+    assert!(!together.semantically_eq(&different));
+    let mut different = EnvMBE::new();
+    different.add_named_repeat(n("b"), leaves("b", &[3, 4]));
+    different.add_anon_repeat(leaves("a", &[1, 5]));
+    different.add_leaf(n("c"), 0);
+    assert!(!together.semantically_eq(&different));
+}
+
 #[test]
 fn splice_healing() {
     use crate::ast::Ast;
@@ -2,4 +2,5 @@ pub mod assoc;
 pub mod asterism;
 pub mod err;
 pub mod mbe;
+pub mod scoped;
 pub mod sky;
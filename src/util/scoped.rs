@@ -0,0 +1,63 @@
+// Temporarily overriding thread-local state.
+
+use std::{cell::RefCell, thread::LocalKey};
+
+/// Puts a value into a thread-local `RefCell`, and puts the old one back when dropped
+///  (even if that's because a panic is unwinding through it).
+pub struct Scoped<T: 'static> {
+    key: &'static LocalKey<RefCell<T>>,
+    old: Option<T>,
+}
+
+impl<T: 'static> Scoped<T> {
+    pub fn set(key: &'static LocalKey<RefCell<T>>, value: T) -> Scoped<T> {
+        Scoped { key, old: Some(key.with(|k| k.replace(value))) }
+    }
+
+    /// Put the old value back now, and return the one being replaced
+    ///  (for state that accumulates something while it's in place).
+    pub fn restore(mut self) -> T {
+        let old = self.old.take().unwrap();
+        self.key.with(|k| k.replace(old))
+    }
+}
+
+impl<T: 'static> Drop for Scoped<T> {
+    fn drop(&mut self) {
+        if let Some(old) = self.old.take() {
+            // (If the thread is shutting down, there's nothing to restore.)
+            let _ = self.key.try_with(|k| *k.borrow_mut() = old);
+        }
+    }
+}
+
+/// Run `f` with the thread-local `key` set to `value`.
+pub fn with_scoped<T: 'static, R>(
+    key: &'static LocalKey<RefCell<T>>,
+    value: T,
+    f: impl FnOnce() -> R,
+) -> R {
+    let _scoped = Scoped::set(key, value);
+    f()
+}
+
+#[test]
+fn scoped_restores() {
+    thread_local! {
+        static setting: RefCell<u32> = RefCell::new(0);
+    }
+    let get = || setting.with(|s| *s.borrow());
+
+    assert_eq!(with_scoped(&setting, 1, || with_scoped(&setting, 2, get) + get()), 3);
+    assert_eq!(get(), 0);
+
+    let scoped = Scoped::set(&setting, 5);
+    setting.with(|s| *s.borrow_mut() += 1);
+    assert_eq!(scoped.restore(), 6);
+    assert_eq!(get(), 0);
+
+    // ...even when `f` panics:
+    let res = std::panic::catch_unwind(|| with_scoped(&setting, 7, || panic!("oops")));
+    assert!(res.is_err());
+    assert_eq!(get(), 0);
+}
@@ -31,7 +31,7 @@ type Res<Mode> = Result<<<Mode as WalkMode>::D as Dir>::Out, <Mode as WalkMode>:
 ///  and negative walks can actually use it
 ///   -- the special value they traverse is stored in the environment with a special name --
 ///  but they conceptually are mostly relying on the special value.
-pub trait WalkMode: Debug + Copy + Clone + Reifiable {
+pub trait WalkMode: Debug + Copy + Clone + Reifiable + 'static {
     /// The object type for the environment to walk in.
     type Elt: Clone + Debug + Reifiable + WalkElt;
 
@@ -70,6 +70,10 @@ pub trait WalkMode: Debug + Copy + Clone + Reifiable {
     fn get_walk_rule(form: &Form) -> WalkRule<Self>
     where Self: Sized;
 
+    /// Called just before the walk rule for a `Node` of `form` runs.
+    /// (`get_walk_rule` can also be called just to inspect the rule, so it's not the place for this.)
+    fn entering_node(_form: &Form) {}
+
     /// Should the walker extend the environment based on imports?
     /// Only `QQ` and `Expand` have this as false; it's not 100% clear what's special about them.
     /// (This evolved over time; it used to be false for `Eval`, because of lambda).
@@ -145,6 +149,13 @@ where Self: std::marker::Sized
     ///  and and mode-specific leaf-processing
     fn pre_walk(node: Ast, cnc: LazyWalkReses<Self::Mode>) -> (Ast, LazyWalkReses<Self::Mode>);
 
+    /// Walk `node` by calling `walk_it` (which starts with `pre_walk`).
+    fn around_walk(
+        node: &Ast,
+        cnc: &LazyWalkReses<Self::Mode>,
+        walk_it: impl FnOnce() -> Res<Self::Mode>,
+    ) -> Res<Self::Mode>;
+
     fn walk_quasi_literally(node: Ast, cnc: &LazyWalkReses<Self::Mode>) -> Res<Self::Mode>;
 
     /// Look up variable in the environment!
@@ -181,6 +192,14 @@ impl<Mode: WalkMode<D = Self>> Dir for Positive<Mode> {
         (freshen(&node), cnc) // No-op
     }
 
+    fn around_walk(
+        _: &Ast,
+        _: &LazyWalkReses<Self::Mode>,
+        walk_it: impl FnOnce() -> Res<Self::Mode>,
+    ) -> Res<Self::Mode> {
+        walk_it()
+    }
+
     /// Turn `a` from an `Ast` into an `Elt` using `::from_ast()`... except:
     ///  * any `Node` it has might be unquotation or dotdotdoting, which escape quotation
     ///  * any `ExtendEnv[Phaseless]` still extends the environment
@@ -189,7 +208,7 @@ impl<Mode: WalkMode<D = Self>> Dir for Positive<Mode> {
         // TODO: this needs to handle splicing, like the negative w_q_l does.
         // (Wait, in what way does it not?!?)
         match a {
-            Node(f, parts, exports) => {
+            Node(f, parts, exports, span) => {
                 // TODO: This can probably be simplified:
                 //  We need something that's like `map`,
                 //   but that exposes the "current marchedness" of the `EnvMBE`/`Sky`,
@@ -204,7 +223,7 @@ impl<Mode: WalkMode<D = Self>> Dir for Positive<Mode> {
                             match *p {
                                 // Yes, `walk`, not `w_q_l`;
                                 //  the mode is in charge of figuring things out.
-                                Node(_, _, _)
+                                Node(_, _, _, _)
                                 | VariableReference(_)
                                 | ExtendEnv(_, _)
                                 | ExtendEnvPhaseless(_, _) => walk(p, cnc_m),
@@ -227,7 +246,7 @@ impl<Mode: WalkMode<D = Self>> Dir for Positive<Mode> {
                 // TODO: it should be a type error (or at least an obvious runtime error)
                 // to put a splice (i.e. a `...[]...`) somewhere it can't be healed.
 
-                Ok(<Self::Mode as WalkMode>::Elt::from_ast(&Node(f, walked, exports)))
+                Ok(<Self::Mode as WalkMode>::Elt::from_ast(&Node(f, walked, exports, span)))
             }
             orig => {
                 // TODO #40: This mess is to push `Shape` down past a wrapper (i.e. `ExtendEnv`),
@@ -290,6 +309,18 @@ impl<Mode: WalkMode<D = Self> + NegativeWalkMode> Dir for Negative<Mode> {
     type Out = Assoc<Name, <Self::Mode as WalkMode>::Elt>;
     type Mode = Mode;
 
+    fn around_walk(
+        node: &Ast,
+        cnc: &LazyWalkReses<Self::Mode>,
+        walk_it: impl FnOnce() -> Res<Self::Mode>,
+    ) -> Res<Self::Mode> {
+        if !<Self::Mode as NegativeWalkMode>::needs_pre_match() {
+            return walk_it();
+        }
+        let node_elt = <Self::Mode as WalkMode>::Elt::from_ast(node);
+        Mode::around_match(&node_elt, cnc.context_elt(), &cnc.env, walk_it)
+    }
+
     fn pre_walk(node: Ast, cnc: LazyWalkReses<Self::Mode>) -> (Ast, LazyWalkReses<Self::Mode>) {
         if !<Self::Mode as NegativeWalkMode>::needs_pre_match() {
             return (freshen(&node), cnc);
@@ -331,7 +362,7 @@ impl<Mode: WalkMode<D = Self> + NegativeWalkMode> Dir for Negative<Mode> {
         cnc.parts.map_reduce_with(
             &parts_actual,
             &|model: &Rc<LazilyWalkedTerm<Mode>>, actual: &Ast| match model.term {
-                Node(_, _, _)
+                Node(_, _, _, _)
                 | VariableReference(_)
                 | ExtendEnv(_, _)
                 | ExtendEnvPhaseless(_, _) => {
@@ -401,6 +432,17 @@ pub trait NegativeWalkMode: WalkMode {
         Some((Clo { it: expected, env: env.clone() }, Clo { it: got, env: env.clone() }))
     }
 
+    /// Match `expected` against `got` by calling `walk_it` (which starts with `pre_match`).
+    /// By default, that's all, but it's a chance to keep track of which matches are in progress.
+    fn around_match(
+        _expected: &Self::Elt,
+        _got: &Self::Elt,
+        _env: &Assoc<Name, Self::Elt>,
+        walk_it: impl FnOnce() -> Res<Self>,
+    ) -> Res<Self> {
+        walk_it()
+    }
+
     /// Match the context element against the current node.
     /// Note that this should come after `pre_match`,
     ///  so any remaining variables will be not be resolved.
@@ -415,7 +457,9 @@ pub trait NegativeWalkMode: WalkMode {
         // break apart the node, and walk it element-wise
         match (expected, got) {
             // `pre_walk` has already freshened for us
-            (&Node(ref f, _, _), &Node(ref f_actual, ref parts_actual, _)) if *f == *f_actual => {
+            (&Node(ref f, _, _, _), &Node(ref f_actual, ref parts_actual, _, _))
+                if *f == *f_actual =>
+            {
                 Ok(parts_actual.clone())
             }
             _ => {